@@ -0,0 +1,21 @@
+// Make the version of hubuum_client available to the `version` command.
+fn main() {
+    println!("cargo:rerun-if-changed=Cargo.lock");
+
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let mut lines = lock.lines();
+    let mut version = "unknown".to_string();
+    while let Some(line) = lines.next() {
+        if line.trim() == r#"name = "hubuum_client""# {
+            if let Some(v) = lines
+                .next()
+                .and_then(|l| l.trim().strip_prefix("version = "))
+            {
+                version = v.trim_matches('"').to_string();
+            }
+            break;
+        }
+    }
+
+    println!("cargo:rustc-env=HUBUUM_CLIENT_VERSION={}", version);
+}
@@ -13,6 +13,21 @@ struct FieldOpts {
     required: Option<bool>,
     flag: Option<bool>,
     autocomplete: Option<syn::Path>,
+    values: Option<String>,
+    path: Option<bool>,
+    conflicts_with: Option<String>,
+    requires: Option<String>,
+    default: Option<String>,
+    secret: Option<bool>,
+    context: Option<bool>,
+}
+
+#[derive(FromField, Default)]
+#[darling(default, attributes(positional))]
+struct PositionalOpts {
+    index: usize,
+    help: Option<String>,
+    required: Option<bool>,
 }
 
 #[derive(Debug)]
@@ -20,6 +35,8 @@ struct CommandInfo {
     about: Option<String>,
     long_about: Option<String>,
     examples: Option<String>,
+    mutating: bool,
+    cancellable: bool,
 }
 
 impl Parse for CommandInfo {
@@ -28,6 +45,8 @@ impl Parse for CommandInfo {
             about: None,
             long_about: None,
             examples: None,
+            mutating: false,
+            cancellable: false,
         };
 
         while !input.is_empty() {
@@ -39,6 +58,8 @@ impl Parse for CommandInfo {
                 "about" => info.about = Some(value.value()),
                 "long_about" => info.long_about = Some(value.value()),
                 "examples" => info.examples = Some(value.value()),
+                "mutating" => info.mutating = value.value() == "true",
+                "cancellable" => info.cancellable = value.value() == "true",
                 _ => return Err(input.error("Unknown field in command_info")),
             }
 
@@ -51,7 +72,7 @@ impl Parse for CommandInfo {
     }
 }
 
-#[proc_macro_derive(CliCommand, attributes(option, command_info))]
+#[proc_macro_derive(CliCommand, attributes(option, positional, command_info))]
 pub fn derive_cli_command(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = &input.ident;
@@ -59,7 +80,7 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
     let command_info = input.attrs.iter()
         .find(|attr| attr.path().is_ident("command_info"))
         .map(|attr| attr.parse_args::<CommandInfo>().expect("Failed to parse command_info"))
-        .unwrap_or_else(|| CommandInfo { about: None, long_about: None, examples: None });
+        .unwrap_or_else(|| CommandInfo { about: None, long_about: None, examples: None, mutating: false, cancellable: false });
 
     let fields = match input.data {
         Data::Struct(ref data) => {
@@ -71,6 +92,26 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
         _ => panic!("CliCommand can only be derived for structs"),
     };
 
+    // Commands with their own --format option, like the graph commands, do not get the
+    // output format option.
+    let has_format_option = fields.named.iter().any(|f| {
+        FieldOpts::from_field(f).unwrap_or_default().long.as_deref() == Some("format")
+    });
+
+    // The names `conflicts_with` and `requires` may refer to, including the options every
+    // command gets.
+    let mut option_names: Vec<String> = fields.named.iter()
+        .map(|f| f.ident.as_ref().unwrap().to_string())
+        .collect();
+    option_names.extend(["help", "format", "yaml", "copy", "verbose", "quiet"].map(String::from));
+
+    // The verbosity options only get their short names if the command does not use them.
+    let short_taken = |short: &str| fields.named.iter().any(|f| {
+        FieldOpts::from_field(f).unwrap_or_default().short.as_deref() == Some(short)
+    });
+    let verbose_short = if short_taken("v") { quote! { None } } else { quote! { Some("-v".to_string()) } };
+    let quiet_short = if short_taken("q") { quote! { None } } else { quote! { Some("-q".to_string()) } };
+
     let mut options: Vec<_> = fields.named.iter().map(|f| {
         let opts = FieldOpts::from_field(f).unwrap_or_default();
         let field_name = f.ident.as_ref().unwrap();
@@ -91,17 +132,36 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
             _ => false,
         };
 
-        let required = if is_optional {
+        // Options with a default are never missing.
+        let required = if is_optional || opts.default.is_some() {
             quote! { false }
         } else {
             opts.required.map(|r| quote! { #r }).unwrap_or(quote! { true })
         };
 
         let flag = opts.flag.map(|f| quote! { #f }).unwrap_or(quote! { false });
+        let path = opts.path.map(|p| quote! { #p }).unwrap_or(quote! { false });
+        let secret = opts.secret.map(|s| quote! { #s }).unwrap_or(quote! { false });
+        let context = opts.context.map(|c| quote! { #c }).unwrap_or(quote! { false });
 
         let autocomplete_fn = opts.autocomplete.as_ref().map(|fn_path| {
             quote! { Some(#fn_path as fn(&crate::commandlist::CommandList, &str, &[String]) -> Vec<String>) }
         }).unwrap_or(quote! { None });
+        let completes = opts.autocomplete.as_ref()
+            .and_then(|fn_path| fn_path.segments.last())
+            .map(|segment| segment.ident.to_string())
+            .map(|name| quote! { Some(#name.to_string()) })
+            .unwrap_or(quote! { None });
+
+        let default = opts.default.as_ref().map(|d| quote! { Some(#d.to_string()) }).unwrap_or(quote! { None });
+        let values = split_list(opts.values.as_deref());
+        let conflicts_with = split_list(opts.conflicts_with.as_deref());
+        let requires = split_list(opts.requires.as_deref());
+        for other in conflicts_with.iter().chain(requires.iter()) {
+            if !option_names.contains(other) {
+                panic!("Option '{}' refers to unknown option '{}'", field_name, other);
+            }
+        }
 
         quote! {
             CliOption {
@@ -114,6 +174,14 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
                 required: #required,
                 flag: #flag,
                 autocomplete: #autocomplete_fn,
+                completes: #completes,
+                values: vec![#(#values.to_string()),*],
+                path: #path,
+                secret: #secret,
+                context: #context,
+                conflicts_with: vec![#(#conflicts_with.to_string()),*],
+                requires: vec![#(#requires.to_string()),*],
+                default: #default,
             }
         }
     }).collect();
@@ -129,6 +197,126 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
             required: false,
             flag: true,
             autocomplete: None,
+            completes: None,
+            values: Vec::new(),
+            path: false,
+            secret: false,
+            context: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            default: None,
+        }
+    });
+
+    if !has_format_option {
+        options.push(quote! {
+            CliOption {
+                name: "format".to_string(),
+                short: None,
+                long: Some("--format".to_string()),
+                help: "Output format (text, json, jsonl, yaml)".to_string(),
+                field_type_help: "string".to_string(),
+                field_type: std::any::TypeId::of::<String>(),
+                required: false,
+                flag: false,
+                autocomplete: None,
+                completes: None,
+                values: vec!["text".to_string(), "json".to_string(), "jsonl".to_string(), "yaml".to_string()],
+                path: false,
+            secret: false,
+            context: false,
+                conflicts_with: Vec::new(),
+                requires: Vec::new(),
+                default: None,
+            }
+        });
+    }
+
+    options.push(quote! {
+        CliOption {
+            name: "yaml".to_string(),
+            short: None,
+            long: Some("--yaml".to_string()),
+            help: "Output as YAML, same as --format yaml".to_string(),
+            field_type_help: "bool".to_string(),
+            field_type: std::any::TypeId::of::<bool>(),
+            required: false,
+            flag: true,
+            autocomplete: None,
+            completes: None,
+            values: Vec::new(),
+            path: false,
+            secret: false,
+            context: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            default: None,
+        }
+    });
+
+    options.push(quote! {
+        CliOption {
+            name: "copy".to_string(),
+            short: None,
+            long: Some("--copy".to_string()),
+            help: "Copy the output to the clipboard".to_string(),
+            field_type_help: "bool".to_string(),
+            field_type: std::any::TypeId::of::<bool>(),
+            required: false,
+            flag: true,
+            autocomplete: None,
+            completes: None,
+            values: Vec::new(),
+            path: false,
+            secret: false,
+            context: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            default: None,
+        }
+    });
+
+    options.push(quote! {
+        CliOption {
+            name: "verbose".to_string(),
+            short: #verbose_short,
+            long: Some("--verbose".to_string()),
+            help: "Show more diagnostics, -vv for even more".to_string(),
+            field_type_help: "bool".to_string(),
+            field_type: std::any::TypeId::of::<bool>(),
+            required: false,
+            flag: true,
+            autocomplete: None,
+            completes: None,
+            values: Vec::new(),
+            path: false,
+            secret: false,
+            context: false,
+            conflicts_with: vec!["quiet".to_string()],
+            requires: Vec::new(),
+            default: None,
+        }
+    });
+
+    options.push(quote! {
+        CliOption {
+            name: "quiet".to_string(),
+            short: #quiet_short,
+            long: Some("--quiet".to_string()),
+            help: "Only show the output and errors".to_string(),
+            field_type_help: "bool".to_string(),
+            field_type: std::any::TypeId::of::<bool>(),
+            required: false,
+            flag: true,
+            autocomplete: None,
+            completes: None,
+            values: Vec::new(),
+            path: false,
+            secret: false,
+            context: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            default: None,
         }
     });
 
@@ -138,6 +326,13 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
         let opts = FieldOpts::from_field(f).unwrap_or_default();
         let short_opt = opts.short.as_ref().map(|s| s.to_string());
         let long_opt = opts.long.as_ref().map(|l| l.to_string());
+
+        let key_matches = match (&short_opt, &long_opt) {
+            (Some(short), Some(long)) => quote! { key == #short || key == #long },
+            (Some(short), None) => quote! { key == #short },
+            (None, Some(long)) => quote! { key == #long },
+            (None, None) => quote! { false },
+        };
     
         let is_optional = match &f.ty {
             syn::Type::Path(type_path) => {
@@ -149,41 +344,113 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
         };
     
         let is_flag = opts.flag.unwrap_or(false);
+        let expand = expand_home(&opts);
     
         if is_flag {
             if is_optional {
                 quote! {
-                    if key == #short_opt || key == #long_opt {
+                    if #key_matches {
                         obj.#field_name = Some(true);
                     }
                 }
             } else {
                 quote! {
-                    if key == #short_opt || key == #long_opt {
+                    if #key_matches {
                         obj.#field_name = true;
                     }
                 }
             }
         } else {
+            let parsed = parse_value(&opts, field_type, quote! { key }, quote! { value });
             if is_optional {
                 quote! {
-                    if key == #short_opt || key == #long_opt {
-                        obj.#field_name = Some(value.parse().map_err(|_| AppError::ParseError(format!("Option '{}' has value '{}' (expected type: {})", key, value, stringify!(#field_type).to_string().to_lowercase().replace(" ", ""))))?);
+                    if #key_matches {
+                        #expand
+                        obj.#field_name = Some(#parsed);
                     }
                 }
             } else {
                 quote! {
-                    if key == #short_opt || key == #long_opt {
-                        obj.#field_name = value.parse().map_err(|_| AppError::ParseError(format!("Option '{}' has value '{}' (expected type: {})", key, value, stringify!(#field_type).to_string().to_lowercase().replace(" ", ""))))?;
+                    if #key_matches {
+                        #expand
+                        obj.#field_name = #parsed;
                     }
                 }
             }
         }
     }).collect();
     
+    // Fields that can also be given as positional arguments, e.g. `object info MyObject`.
+    let positional_fields: Vec<_> = fields
+        .named
+        .iter()
+        .filter(|f| f.attrs.iter().any(|attr| attr.path().is_ident("positional")))
+        .map(|f| {
+            let opts = PositionalOpts::from_field(f).expect("Failed to parse positional");
+            (f, opts)
+        })
+        .collect();
+
+    let positionals: Vec<_> = positional_fields.iter().map(|(f, opts)| {
+        let field_name = f.ident.as_ref().unwrap();
+        let index = opts.index;
+        let help = opts.help.clone().unwrap_or_default();
+        let required = opts.required.unwrap_or(true);
+        quote! {
+            crate::commands::CliPositional {
+                name: stringify!(#field_name).to_string(),
+                index: #index,
+                help: #help.to_string(),
+                required: #required,
+            }
+        }
+    }).collect();
+
+    // Defaults are set before anything given on the command line.
+    let default_setters: Vec<_> = fields.named.iter().filter_map(|f| {
+        let opts = FieldOpts::from_field(f).unwrap_or_default();
+        let default = opts.default.clone()?;
+        let field_name = f.ident.as_ref().unwrap();
+        let option = opts.long.clone().unwrap_or_else(|| field_name.to_string());
+        let parsed = parse_value(&opts, &f.ty, quote! { #option }, quote! { #default });
+        if is_option_type(&f.ty) {
+            Some(quote! { obj.#field_name = Some(#parsed); })
+        } else {
+            Some(quote! { obj.#field_name = #parsed; })
+        }
+    }).collect();
+
+    // Positionals are set first, so that the option overrides them if both are given.
+    let positional_setters: Vec<_> = positional_fields.iter().map(|(f, opts)| {
+        let field_name = f.ident.as_ref().unwrap();
+        let field_type = &f.ty;
+        let index = opts.index;
+        let expand = expand_home(&FieldOpts::from_field(f).unwrap_or_default());
+        let parsed = quote! {
+            value.parse().map_err(|_| AppError::ParseError(format!("Argument {} has value '{}' (expected type: {})", #index + 1, value, stringify!(#field_type).to_string().to_lowercase().replace(" ", ""))))?
+        };
+        if is_option_type(field_type) {
+            quote! {
+                if let Some(value) = tokens.get_positionals().get(#index) {
+                    #expand
+                    obj.#field_name = Some(#parsed);
+                }
+            }
+        } else {
+            quote! {
+                if let Some(value) = tokens.get_positionals().get(#index) {
+                    #expand
+                    obj.#field_name = #parsed;
+                }
+            }
+        }
+    }).collect();
+
     let cmd_about = prepare_option_string(&command_info.about);
     let cmd_long_about = prepare_option_string(&command_info.long_about);
     let cmd_examples = prepare_option_string(&command_info.examples);
+    let cmd_mutating = command_info.mutating;
+    let cmd_cancellable = command_info.cancellable;
 
     let expanded = quote! {
         impl CliCommandInfo for #name {
@@ -208,6 +475,20 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
             fn examples(&self) -> Option<String> {
                 #cmd_examples
             }
+
+            fn is_mutating(&self) -> bool {
+                #cmd_mutating
+            }
+
+            fn is_cancellable(&self) -> bool {
+                #cmd_cancellable
+            }
+
+            fn positionals(&self) -> Vec<crate::commands::CliPositional> {
+                vec![
+                    #(#positionals),*
+                ]
+            }
         }
 
         impl #name {
@@ -215,6 +496,9 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
                 let mut obj = Self::default();
                 obj.validate(tokens)?;
 
+                #(#default_setters)*
+                #(#positional_setters)*
+
                 for (key, value) in tokens.get_options() {
                     #(#field_setters)*
                 }
@@ -228,6 +512,54 @@ pub fn derive_cli_command(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+// Parse `value`, given to the option `key`, into the type of the field. Options with a fixed
+// set of values list them when the value is not one of them.
+fn parse_value(
+    opts: &FieldOpts,
+    field_type: &syn::Type,
+    key: proc_macro2::TokenStream,
+    value: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let values = split_list(opts.values.as_deref());
+    if values.is_empty() {
+        quote! {
+            #value.parse().map_err(|_| AppError::ParseError(format!("Option '{}' has value '{}' (expected type: {})", #key, #value, stringify!(#field_type).to_string().to_lowercase().replace(" ", ""))))?
+        }
+    } else {
+        let expected = values.join(", ");
+        quote! {
+            #value.parse().map_err(|_| AppError::ParseError(format!("Option '{}' has value '{}' (expected one of: {})", #key, #value, #expected)))?
+        }
+    }
+}
+
+// Paths get a leading `~` expanded to the home directory, as the shell would, since the
+// file completion offers it.
+fn expand_home(opts: &FieldOpts) -> proc_macro2::TokenStream {
+    if opts.path.unwrap_or(false) {
+        quote! { let value = &crate::files::expand_home(value); }
+    } else {
+        quote! {}
+    }
+}
+
+fn split_list(list: Option<&str>) -> Vec<String> {
+    list.map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+fn is_option_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|seg| seg.ident == "Option")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
 // Helper function to prepare Option<String> values
 fn prepare_option_string(opt: &Option<String>) -> proc_macro2::TokenStream {
     match opt {
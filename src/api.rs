@@ -0,0 +1,496 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use std::str::FromStr;
+
+use hubuum_client::{ApiError, Authenticated, BaseUrl, QueryFilter, SyncClient, Unauthenticated};
+use log::{debug, warn};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::config::{get_config, AppConfig};
+use crate::errors::AppError;
+use crate::http_trace::{record_exchange, redact_body, HttpExchange};
+use crate::i18n::tr_args;
+use crate::interrupt::abandoned;
+use crate::output::add_warning;
+use crate::response_cache::{cached_get, purge_response_cache};
+use crate::timing::{endpoint_name, record_timing, TimingKind};
+
+/// The base URL of the server, e.g. `https://localhost:8080`.
+pub fn base_url(config: &AppConfig) -> String {
+    format!(
+        "{}://{}:{}",
+        config.server.protocol, config.server.hostname, config.server.port
+    )
+}
+
+/// A client for the configured server, not yet logged in. Its requests use the
+/// certificate validation and timeouts of the configuration, like our own requests.
+pub fn new_client(config: &AppConfig) -> Result<SyncClient<Unauthenticated>, AppError> {
+    let baseurl = BaseUrl::from_str(&base_url(config))?;
+    Ok(SyncClient::new_with_http_client(
+        baseurl,
+        http_client(config)?,
+    ))
+}
+
+/// GET an API path (e.g. `/api/v1/iam/users/1/groups`) that the client library does not
+/// wrap, authenticated with the token of the client. Always asks the server.
+pub fn get_json(client: &SyncClient<Authenticated>, path: &str) -> Result<Value, AppError> {
+    request(client, Method::GET, path, None)
+}
+
+/// Like [`get_json`], but the response is cached on disk for `cache.time` seconds. Only
+/// for read-mostly endpoints, like the server version, where a stale answer does no harm.
+pub fn get_json_cached(client: &SyncClient<Authenticated>, path: &str) -> Result<Value, AppError> {
+    cached_get(path, || get_json(client, path))
+}
+
+/// POST to an API path that the client library does not wrap. As this changes something
+/// on the server, the cached responses are forgotten.
+pub fn post_json(
+    client: &SyncClient<Authenticated>,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<Value, AppError> {
+    let response = request(client, Method::POST, path, body);
+    if let Err(err) = purge_response_cache() {
+        warn!("Failed to purge the response cache: {}", err);
+    }
+    response
+}
+
+const HEALTH_PATH: &str = "/api/v0/meta/health";
+const NEXT_CURSOR_HEADER: &str = "X-Next-Cursor";
+const TOTAL_COUNT_HEADER: &str = "X-Total-Count";
+const VERSION_PATH: &str = "/api/v0/meta/version";
+
+/// A page of a listing, with the cursor to ask for the next page. `next_cursor` is `None`
+/// on the last page.
+#[derive(Debug, Clone)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// GET a page of at most `limit` entities from a listing path (e.g. `/api/v1/classes/1/`),
+/// starting at `cursor`, or at the first entity if `None`. `filters` are sent as the
+/// client library would send them, as `key__operator=value`. Retried like other GETs.
+pub fn get_page<T: DeserializeOwned>(
+    client: &SyncClient<Authenticated>,
+    path: &str,
+    filters: &[QueryFilter],
+    limit: usize,
+    cursor: Option<&str>,
+) -> Result<Page<T>, AppError> {
+    let mut query = filters
+        .iter()
+        .map(|f| (format!("{}__{}", f.key, f.operator), f.value.clone()))
+        .collect::<Vec<_>>();
+    query.push(("limit".to_string(), limit.to_string()));
+    if let Some(cursor) = cursor {
+        query.push(("cursor".to_string(), cursor.to_string()));
+    }
+
+    let response = request_with_query(client, Method::GET, path, &query, None)?;
+    let items = match response.body {
+        Value::Null => Vec::new(),
+        body => serde_json::from_value(body)?,
+    };
+    Ok(Page {
+        items,
+        next_cursor: response.next_cursor.filter(|cursor| !cursor.is_empty()),
+    })
+}
+
+/// The version of the server and the API versions it serves.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ServerVersion {
+    pub version: Option<String>,
+    pub api_versions: Vec<String>,
+}
+
+impl ServerVersion {
+    /// A warning if the server does not serve the API version we are configured to use.
+    /// Servers that don't list their API versions are assumed to be compatible.
+    pub fn compatibility_warning(&self, api_version: &str) -> Option<String> {
+        if self.api_versions.is_empty() || self.api_versions.iter().any(|v| v == api_version) {
+            return None;
+        }
+        Some(format!(
+            "The server serves API {}, but the client uses {}",
+            self.api_versions.join(", "),
+            api_version
+        ))
+    }
+}
+
+/// Check that the server is healthy, returning the round-trip time.
+pub fn ping(client: &SyncClient<Authenticated>) -> Result<Duration, AppError> {
+    let start = Instant::now();
+    request(client, Method::GET, HEALTH_PATH, None)?;
+    Ok(start.elapsed())
+}
+
+/// Ask the server for its version.
+pub fn server_version(client: &SyncClient<Authenticated>) -> Result<ServerVersion, AppError> {
+    let response = get_json_cached(client, VERSION_PATH)?;
+
+    let api_versions = match response.get("api_versions").or(response.get("api_version")) {
+        Some(Value::Array(versions)) => versions
+            .iter()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect(),
+        Some(Value::String(version)) => vec![version.clone()],
+        _ => Vec::new(),
+    };
+
+    Ok(ServerVersion {
+        version: response
+            .get("version")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        api_versions,
+    })
+}
+
+// Set once the server has answered a HEAD without a total count, so we stop asking.
+static HEAD_COUNT_UNSUPPORTED: AtomicBool = AtomicBool::new(false);
+
+/// The number of entities behind an API path, from the `X-Total-Count` header of a HEAD
+/// request. `None` if the server does not send the header, in which case the caller has to
+/// list the entities and count them.
+pub fn head_count(
+    client: &SyncClient<Authenticated>,
+    path: &str,
+) -> Result<Option<usize>, AppError> {
+    if HEAD_COUNT_UNSUPPORTED.load(Ordering::Relaxed) {
+        return Ok(None);
+    }
+
+    let config = get_config()?;
+    let url = format!("{}{}", base_url(&config), path);
+    let mut exchange = HttpExchange::new("HEAD", &url, None);
+    let start = Instant::now();
+    let response = http_client(&config)?
+        .head(&url)
+        .bearer_auth(client.get_token())
+        .send();
+    exchange.elapsed_ms = start.elapsed().as_millis();
+    match &response {
+        Ok(response) => exchange.status = Some(response.status().as_u16()),
+        Err(err) => exchange.error = Some(err.to_string()),
+    }
+    record_exchange(exchange);
+    let response = response.map_err(|e| AppError::HttpError(e.to_string()))?;
+
+    let count = response
+        .headers()
+        .get(TOTAL_COUNT_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok());
+    if response.status().is_success() && count.is_none() {
+        debug!(
+            "The server sends no {} header, counting by listing",
+            TOTAL_COUNT_HEADER
+        );
+        HEAD_COUNT_UNSUPPORTED.store(true, Ordering::Relaxed);
+    }
+    Ok(count)
+}
+
+/// Run an idempotent request, retrying it with exponential backoff as configured in the
+/// `http` section while `is_transient` says the failure may go away by itself. The error
+/// of the last attempt is returned together with the number of attempts made.
+pub fn with_retries<T, E, F, P>(mut f: F, is_transient: P) -> Result<T, (u32, E)>
+where
+    F: FnMut() -> Result<T, E>,
+    P: Fn(&E) -> bool,
+    E: std::fmt::Display,
+{
+    let config = get_config()
+        .map(|c| c.http)
+        .unwrap_or_else(|_| AppConfig::default().http);
+    let mut backoff = Duration::from_millis(config.retry_backoff);
+    let mut attempt = 1;
+
+    loop {
+        match f() {
+            Ok(result) => return Ok(result),
+            // A command left running after Ctrl-C gives up at once.
+            Err(err) if attempt <= config.retries && is_transient(&err) && !abandoned() => {
+                warn!(
+                    "Attempt {} failed ({}), retrying in {}ms",
+                    attempt,
+                    err,
+                    backoff.as_millis()
+                );
+                std::thread::sleep(backoff);
+                backoff = (backoff * 2).min(Duration::from_millis(config.retry_backoff_max));
+                attempt += 1;
+            }
+            Err(err) => return Err((attempt, err)),
+        }
+    }
+}
+
+/// Whether an error from the client library is worth retrying, i.e. the server failed.
+pub fn is_transient_api_error(err: &ApiError) -> bool {
+    match err {
+        ApiError::HttpWithBody { status, .. } => status.to_string().starts_with('5'),
+        _ => false,
+    }
+}
+
+/// Retry an idempotent call to the client library, see [`with_retries`]. When retrying
+/// does not help, a warning says how many attempts were made.
+pub fn retry_api<T, F>(f: F) -> Result<T, ApiError>
+where
+    F: FnMut() -> Result<T, ApiError>,
+{
+    with_retries(f, is_transient_api_error).map_err(|(attempts, err)| {
+        if attempts > 1 {
+            let _ = add_warning(tr_args(
+                "Giving up after {attempts} attempts",
+                &[("attempts", &attempts)],
+            ));
+        }
+        err
+    })
+}
+
+/// An HTTP client for the requests the client library does not wrap, with the configured
+/// timeouts.
+fn http_client(config: &AppConfig) -> Result<reqwest::blocking::Client, AppError> {
+    reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(!config.server.ssl_validation)
+        .connect_timeout(Duration::from_secs(config.http.connect_timeout))
+        .timeout(Duration::from_secs(config.http.request_timeout))
+        .build()
+        .map_err(|e| AppError::HttpError(e.to_string()))
+}
+
+// Failures worth retrying: no connection, a timeout or a failing server.
+enum RequestError {
+    Transient(String),
+    Permanent(AppError),
+}
+
+impl std::fmt::Display for RequestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestError::Transient(message) => write!(f, "{}", message),
+            RequestError::Permanent(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+fn request(
+    client: &SyncClient<Authenticated>,
+    method: Method,
+    path: &str,
+    body: Option<&Value>,
+) -> Result<Value, AppError> {
+    request_with_query(client, method, path, &[], body).map(|response| response.body)
+}
+
+// A response body with the cursor of the next page, if the server sent one.
+struct Response {
+    body: Value,
+    next_cursor: Option<String>,
+}
+
+fn request_with_query(
+    client: &SyncClient<Authenticated>,
+    method: Method,
+    path: &str,
+    query: &[(String, String)],
+    body: Option<&Value>,
+) -> Result<Response, AppError> {
+    // Only GETs are safe to send again.
+    if method != Method::GET {
+        return request_once(client, method, path, query, body).map_err(|err| match err {
+            RequestError::Transient(message) => AppError::HttpError(message),
+            RequestError::Permanent(err) => err,
+        });
+    }
+
+    with_retries(
+        || request_once(client, method.clone(), path, query, body),
+        |err| matches!(err, RequestError::Transient(_)),
+    )
+    .map_err(|(attempts, err)| match err {
+        RequestError::Transient(message) if attempts > 1 => AppError::HttpError(format!(
+            "{} ({})",
+            message,
+            tr_args(
+                "Giving up after {attempts} attempts",
+                &[("attempts", &attempts)]
+            )
+        )),
+        RequestError::Transient(message) => AppError::HttpError(message),
+        RequestError::Permanent(err) => err,
+    })
+}
+
+fn request_once(
+    client: &SyncClient<Authenticated>,
+    method: Method,
+    path: &str,
+    query: &[(String, String)],
+    body: Option<&Value>,
+) -> Result<Response, RequestError> {
+    let config = get_config().map_err(RequestError::Permanent)?;
+    let http = http_client(&config).map_err(RequestError::Permanent)?;
+    let url = format!("{}{}", base_url(&config), path);
+    let body = body.map(Value::to_string);
+
+    let mut request = http
+        .request(method.clone(), &url)
+        .bearer_auth(client.get_token())
+        .query(query);
+    if let Some(body) = &body {
+        request = request
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.clone());
+    }
+
+    let mut exchange = HttpExchange::new(method.as_str(), &url, body.as_deref());
+    let start = Instant::now();
+    let response = request.send().and_then(|response| {
+        let status = response.status();
+        let next_cursor = response
+            .headers()
+            .get(NEXT_CURSOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok((status, next_cursor, response.text()?))
+    });
+    exchange.elapsed_ms = start.elapsed().as_millis();
+    record_timing(
+        TimingKind::Endpoint,
+        &endpoint_name(method.as_str(), path),
+        start.elapsed(),
+    );
+    match &response {
+        Ok((status, _, body)) => {
+            exchange.status = Some(status.as_u16());
+            exchange.response_body = redact_body(body);
+        }
+        Err(err) => exchange.error = Some(err.to_string()),
+    }
+    record_exchange(exchange);
+
+    let (status, next_cursor, body) = response.map_err(|e| {
+        if e.is_connect() || e.is_timeout() || e.is_body() || e.is_decode() {
+            RequestError::Transient(e.to_string())
+        } else {
+            RequestError::Permanent(AppError::HttpError(e.to_string()))
+        }
+    })?;
+    if status.is_server_error() {
+        return Err(RequestError::Transient(format!("{}: {}", status, body)));
+    }
+    if !status.is_success() {
+        return Err(RequestError::Permanent(AppError::HttpError(format!(
+            "{}: {}",
+            status, body
+        ))));
+    }
+
+    let body = if body.trim().is_empty() {
+        Value::Null
+    } else {
+        serde_json::from_str(&body).map_err(|e| RequestError::Permanent(e.into()))?
+    };
+    Ok(Response { body, next_cursor })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::set_config;
+
+    // Retry quickly, and as often as the defaults say.
+    fn fast_retries() -> u32 {
+        let mut config = AppConfig::default();
+        config.http.retry_backoff = 1;
+        config.http.retry_backoff_max = 2;
+        let retries = config.http.retries;
+        set_config(config).unwrap();
+        retries
+    }
+
+    fn transient(err: &&str) -> bool {
+        err.starts_with("503")
+    }
+
+    #[test]
+    fn test_with_retries_returns_the_first_success() {
+        fast_retries();
+        let mut calls = 0;
+        let result = with_retries(
+            || {
+                calls += 1;
+                Ok::<_, &str>(calls)
+            },
+            transient,
+        );
+        assert_eq!(result.ok(), Some(1));
+        assert_eq!(calls, 1);
+    }
+
+    #[test]
+    fn test_with_retries_retries_transient_failures() {
+        fast_retries();
+        let mut calls = 0;
+        let result = with_retries(
+            || {
+                calls += 1;
+                if calls < 3 {
+                    Err("503 Service Unavailable")
+                } else {
+                    Ok(calls)
+                }
+            },
+            transient,
+        );
+        assert_eq!(result.ok(), Some(3));
+        assert_eq!(calls, 3);
+    }
+
+    #[test]
+    fn test_with_retries_gives_up_after_the_configured_retries() {
+        let retries = fast_retries();
+        let mut calls = 0;
+        let result = with_retries(
+            || {
+                calls += 1;
+                Err::<(), _>("503 Service Unavailable")
+            },
+            transient,
+        );
+        let (attempts, err) = result.unwrap_err();
+        assert_eq!(attempts, retries + 1);
+        assert_eq!(calls, retries + 1);
+        assert_eq!(err, "503 Service Unavailable");
+    }
+
+    #[test]
+    fn test_with_retries_does_not_retry_permanent_failures() {
+        fast_retries();
+        let mut calls = 0;
+        let result = with_retries(
+            || {
+                calls += 1;
+                Err::<(), _>("404 Not Found")
+            },
+            transient,
+        );
+        assert_eq!(result.unwrap_err().0, 1);
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,115 @@
+use std::io::{BufRead, Write};
+use std::path::Path;
+
+use chrono::{Local, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+
+use crate::config::get_config;
+use crate::errors::AppError;
+use crate::files::{get_audit_file, rotate_file, rotated_path};
+use crate::history::{mutating_command, redact_secrets};
+
+// The audit file is rotated when it grows past this size, keeping this many old files as
+// audit.jsonl.1 (the newest) to audit.jsonl.N.
+const AUDIT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+const AUDIT_KEEP: usize = 5;
+
+/// A command that changed something on the server, as recorded in the audit log.
+///
+/// The audit log is append-only and only holds mutating commands, unlike the command log
+/// which holds everything that was typed.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuditEntry {
+    pub at: NaiveDateTime,
+    pub user: String,
+    pub server: String,
+    pub command: String,
+    pub target: Option<String>,
+    pub line: String,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+fn rotate(path: &Path) -> Result<(), AppError> {
+    if std::fs::metadata(path)?.len() < AUDIT_MAX_SIZE {
+        return Ok(());
+    }
+    rotate_file(path, AUDIT_KEEP)?;
+    std::fs::File::create(path)?;
+    Ok(())
+}
+
+// What the command worked on: the name given as the first positional or with --name,
+// prefixed by the class if one was given.
+fn target(parts: &[String], command_len: usize) -> Option<String> {
+    let value = |short: &str, long: &str| {
+        parts
+            .iter()
+            .position(|p| p == short || p == long)
+            .and_then(|i| parts.get(i + 1))
+            .cloned()
+    };
+
+    let name = parts
+        .get(command_len)
+        .filter(|part| !part.starts_with('-'))
+        .cloned()
+        .or_else(|| value("-n", "--name"))?;
+
+    Some(match value("-c", "--class") {
+        Some(class) => format!("{}/{}", class, name),
+        None => name,
+    })
+}
+
+/// Append a command to the audit log if it changes something on the server.
+pub fn record_audit(line: &str, error: Option<&AppError>) -> Result<(), AppError> {
+    let Some(command) = mutating_command(line) else {
+        return Ok(());
+    };
+    let parts = shlex::split(line).unwrap_or_default();
+    let config = get_config()?;
+
+    let entry = AuditEntry {
+        at: Local::now().naive_local(),
+        user: config.server.username,
+        server: config.server.hostname,
+        // Object commands may be given without `object` when a class is in use.
+        target: target(
+            &parts,
+            command.len() - usize::from(!parts.starts_with(&command)),
+        ),
+        command: command.join(" "),
+        line: redact_secrets(line.trim()),
+        success: error.is_none(),
+        error: error.map(|err| err.to_string()),
+    };
+
+    let path = get_audit_file()?;
+    rotate(&path)?;
+    let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Read the audit log, including the rotated files, oldest first. Lines that can not be
+/// parsed are skipped.
+pub fn audit_log() -> Result<Vec<AuditEntry>, AppError> {
+    let path = get_audit_file()?;
+    let files = (1..=AUDIT_KEEP)
+        .rev()
+        .map(|n| rotated_path(&path, n))
+        .filter(|file| file.exists())
+        .chain(std::iter::once(path.clone()));
+
+    let mut entries = Vec::new();
+    for file in files {
+        let file = std::fs::File::open(file)?;
+        for line in std::io::BufReader::new(file).lines() {
+            if let Ok(entry) = serde_json::from_str(&line?) {
+                entries.push(entry);
+            }
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,235 @@
+use hubuum_client::{Authenticated, Credentials, SyncClient, Token, Unauthenticated};
+use log::debug;
+
+use crate::config::AuthConfig;
+use crate::errors::AppError;
+use crate::files;
+use crate::i18n::tr_args;
+use crate::interrupt::while_prompting;
+use crate::models::TokenEntry;
+use crate::output::{add_warning, flush_output};
+use crate::progress::stop_spinner;
+
+/// A way of logging in to the server.
+///
+/// Providers are tried in the order given by `auth.providers` in the configuration,
+/// until one of them logs in.
+pub trait AuthProvider {
+    /// The name used for the provider in the configuration.
+    fn name(&self) -> &'static str;
+
+    /// Log in as `username` on `hostname`. Returns `Ok(None)` if the provider has no
+    /// credentials to offer, so the next provider should be tried.
+    fn login(
+        &self,
+        client: &SyncClient<Unauthenticated>,
+        username: &str,
+        hostname: &str,
+    ) -> Result<Option<SyncClient<Authenticated>>, AppError>;
+
+    /// Whether the token from a successful login should be saved to the token file.
+    fn saves_token(&self) -> bool {
+        true
+    }
+}
+
+/// Reuse a token saved by an earlier login.
+pub struct TokenFileProvider;
+
+impl AuthProvider for TokenFileProvider {
+    fn name(&self) -> &'static str {
+        "token"
+    }
+
+    fn login(
+        &self,
+        client: &SyncClient<Unauthenticated>,
+        username: &str,
+        hostname: &str,
+    ) -> Result<Option<SyncClient<Authenticated>>, AppError> {
+        let Some(token) = files::get_token_from_tokenfile(hostname, username)? else {
+            return Ok(None);
+        };
+
+        debug!("Found existing token, testing validity...");
+        Ok(Some(client.clone().login_with_token(Token { token })?))
+    }
+
+    fn saves_token(&self) -> bool {
+        false
+    }
+}
+
+/// Prompt the user for their password.
+pub struct PasswordProvider;
+
+impl AuthProvider for PasswordProvider {
+    fn name(&self) -> &'static str {
+        "password"
+    }
+
+    fn login(
+        &self,
+        client: &SyncClient<Unauthenticated>,
+        username: &str,
+        hostname: &str,
+    ) -> Result<Option<SyncClient<Authenticated>>, AppError> {
+        stop_spinner();
+        let password = while_prompting(|| {
+            rpassword::prompt_password(tr_args(
+                "Password for {username} @ {hostname}: ",
+                &[("username", &username), ("hostname", &hostname)],
+            ))
+        })?;
+        password_login(client, username, password)
+    }
+}
+
+/// Read the password from the system keyring, stored under `auth.keyring_service` with
+/// `username@hostname` as the user.
+pub struct KeyringProvider {
+    service: String,
+}
+
+impl AuthProvider for KeyringProvider {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn login(
+        &self,
+        client: &SyncClient<Unauthenticated>,
+        username: &str,
+        hostname: &str,
+    ) -> Result<Option<SyncClient<Authenticated>>, AppError> {
+        let user = format!("{}@{}", username, hostname);
+        let entry = keyring::Entry::new(&self.service, &user)
+            .map_err(|e| AppError::AuthError(e.to_string()))?;
+
+        match entry.get_password() {
+            Ok(password) => password_login(client, username, password),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(err) => Err(AppError::AuthError(err.to_string())),
+        }
+    }
+}
+
+/// Run an external command (`auth.command`) that prints the password on stdout. The
+/// command gets the username and hostname in `HUBUUM_USERNAME` and `HUBUUM_HOSTNAME`.
+pub struct CommandProvider {
+    command: String,
+}
+
+impl AuthProvider for CommandProvider {
+    fn name(&self) -> &'static str {
+        "command"
+    }
+
+    fn login(
+        &self,
+        client: &SyncClient<Unauthenticated>,
+        username: &str,
+        hostname: &str,
+    ) -> Result<Option<SyncClient<Authenticated>>, AppError> {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("HUBUUM_USERNAME", username)
+            .env("HUBUUM_HOSTNAME", hostname)
+            .output()?;
+
+        if !output.status.success() {
+            return Err(AppError::AuthError(format!(
+                "'{}' failed: {}",
+                self.command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+
+        let password = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if password.is_empty() {
+            return Ok(None);
+        }
+        password_login(client, username, password)
+    }
+}
+
+fn password_login(
+    client: &SyncClient<Unauthenticated>,
+    username: &str,
+    password: String,
+) -> Result<Option<SyncClient<Authenticated>>, AppError> {
+    let client = client
+        .clone()
+        .login(Credentials::new(username.to_string(), password))?;
+    Ok(Some(client))
+}
+
+/// Create the providers listed in the configuration, in order.
+pub fn providers_from_config(config: &AuthConfig) -> Result<Vec<Box<dyn AuthProvider>>, AppError> {
+    config
+        .providers
+        .iter()
+        .map(|name| -> Result<Box<dyn AuthProvider>, AppError> {
+            match name.as_str() {
+                "token" => Ok(Box::new(TokenFileProvider)),
+                "password" => Ok(Box::new(PasswordProvider)),
+                "keyring" => Ok(Box::new(KeyringProvider {
+                    service: config.keyring_service.clone(),
+                })),
+                "command" => match &config.command {
+                    Some(command) => Ok(Box::new(CommandProvider {
+                        command: command.clone(),
+                    })),
+                    None => Err(AppError::ConfigError(
+                        "auth.command must be set to use the command provider".to_string(),
+                    )),
+                },
+                other => Err(AppError::ConfigError(format!(
+                    "Unknown authentication provider '{}'",
+                    other
+                ))),
+            }
+        })
+        .collect()
+}
+
+/// Log in using the first provider that succeeds. Failing providers are reported as
+/// warnings before moving on to the next one.
+pub fn login(
+    client: SyncClient<Unauthenticated>,
+    username: &str,
+    hostname: &str,
+    providers: &[Box<dyn AuthProvider>],
+) -> Result<SyncClient<Authenticated>, AppError> {
+    for provider in providers {
+        debug!("Trying authentication provider {}", provider.name());
+        let client = match provider.login(&client, username, hostname) {
+            Ok(Some(client)) => client,
+            Ok(None) => continue,
+            Err(err) => {
+                add_warning(tr_args(
+                    "Login with {provider} failed: {error}",
+                    &[("provider", &provider.name()), ("error", &err)],
+                ))?;
+                flush_output()?;
+                continue;
+            }
+        };
+
+        if provider.saves_token() {
+            debug!("Logged in successfully, saving token...");
+            files::write_token_to_tokenfile(TokenEntry {
+                hostname: hostname.to_string(),
+                username: username.to_string(),
+                token: client.get_token().to_string(),
+                issued_at: Some(chrono::Utc::now().naive_utc()),
+            })?;
+        }
+        return Ok(client);
+    }
+
+    Err(AppError::AuthError(
+        "None of the authentication providers could log in".to_string(),
+    ))
+}
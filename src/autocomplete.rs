@@ -1,8 +1,166 @@
-use log::{trace, warn};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use hubuum_client::FilterOperator;
+use hubuum_client::{Authenticated, SyncClient};
+use log::{trace, warn};
+use once_cell::sync::Lazy;
 
 use crate::commandlist::CommandList;
+use crate::config::get_config;
+use crate::files::expand_home;
+use crate::fuzzy::{fuzzy_enabled, fuzzy_filter};
+use crate::session;
+
+// The shortest time between background refreshes of the completion cache, in seconds.
+const PREFETCH_MIN_INTERVAL: u64 = 30;
+
+// Names fetched for completion, keyed by what was fetched (e.g. `classes` or
+// `objects:Host`), so completing does not hit the server on every keystroke.
+static COMPLETION_CACHE: Lazy<Mutex<HashMap<String, (Instant, Vec<String>)>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Empty the completion cache.
+pub fn clear_cache() {
+    if let Ok(mut cache) = COMPLETION_CACHE.lock() {
+        cache.clear();
+    }
+}
+
+/// The number of name lists in the completion cache.
+pub fn cache_entries() -> usize {
+    COMPLETION_CACHE
+        .lock()
+        .map(|cache| cache.len())
+        .unwrap_or(0)
+}
+
+// The names for `key`, from the cache if they were fetched within `cache.time` seconds.
+// Failures are logged and give no names, as completion has nowhere to report them.
+fn cached_names<F, E>(key: &str, fetch: F) -> Vec<String>
+where
+    F: FnOnce() -> Result<Vec<String>, E>,
+    E: std::fmt::Display,
+{
+    let (ttl, disabled) = get_config()
+        .map(|config| (Duration::from_secs(config.cache.time), config.cache.disable))
+        .unwrap_or((Duration::ZERO, true));
+
+    if !disabled {
+        if let Ok(cache) = COMPLETION_CACHE.lock() {
+            if let Some((at, names)) = cache.get(key) {
+                if at.elapsed() < ttl {
+                    trace!("Completion cache hit for {}", key);
+                    return names.clone();
+                }
+            }
+        }
+    }
+
+    fetch_names(key, fetch, !disabled)
+}
+
+fn fetch_names<F, E>(key: &str, fetch: F, store: bool) -> Vec<String>
+where
+    F: FnOnce() -> Result<Vec<String>, E>,
+    E: std::fmt::Display,
+{
+    let names = match fetch() {
+        Ok(names) => names,
+        Err(err) => {
+            warn!("Failed to fetch {} for autocomplete: {}", key, err);
+            return Vec::new();
+        }
+    };
+
+    if store {
+        if let Ok(mut cache) = COMPLETION_CACHE.lock() {
+            cache.insert(key.to_string(), (Instant::now(), names.clone()));
+        }
+    }
+    names
+}
+
+fn api_completion_disabled() -> bool {
+    get_config().is_ok_and(|config| config.completion.disable_api_related)
+}
+
+// The client to complete with, unless completions from the server are disabled.
+fn api_client(cmdlist: &CommandList) -> Option<Arc<SyncClient<Authenticated>>> {
+    if api_completion_disabled() {
+        return None;
+    }
+    cmdlist.client()
+}
+
+fn prefetch(client: &SyncClient<Authenticated>) {
+    fetch_names(
+        "classes",
+        || {
+            client
+                .classes()
+                .find()
+                .execute()
+                .map(|classes| classes.into_iter().map(|c| c.name).collect())
+        },
+        true,
+    );
+    fetch_names(
+        "namespaces",
+        || {
+            client
+                .namespaces()
+                .find()
+                .execute()
+                .map(|namespaces| namespaces.into_iter().map(|n| n.name).collect())
+        },
+        true,
+    );
+}
+
+/// Fetch class and namespace names into the completion cache on a background thread, now
+/// and whenever they are about to expire, so the first Tab does not wait for the server.
+/// Does nothing if server completions or the cache are disabled.
+pub fn start_prefetch() {
+    static STARTED: AtomicBool = AtomicBool::new(false);
+    if STARTED.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    std::thread::spawn(|| loop {
+        let config = get_config().ok();
+        let enabled = config
+            .as_ref()
+            .is_some_and(|c| !c.completion.disable_api_related && !c.cache.disable);
+        if enabled {
+            if let Some(client) = session::client() {
+                trace!("Prefetching completion data");
+                prefetch(&client);
+            }
+        }
+
+        // Refresh a little before the cached names expire.
+        let ttl = config.map_or(PREFETCH_MIN_INTERVAL, |c| c.cache.time);
+        std::thread::sleep(Duration::from_secs(
+            (ttl * 9 / 10).max(PREFETCH_MIN_INTERVAL),
+        ));
+    });
+}
+
+// The names matching what has been typed so far, by prefix or fuzzily.
+fn matching(prefix: &str, names: Vec<String>) -> Vec<String> {
+    if prefix.is_empty() {
+        return names;
+    }
+    if fuzzy_enabled() {
+        return fuzzy_filter(prefix, names);
+    }
+    names
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect()
+}
 
 pub fn bool(_cmdlist: &CommandList, _prefix: &str, _parts: &[String]) -> Vec<String> {
     vec!["true".to_string(), "false".to_string()]
@@ -10,44 +168,98 @@ pub fn bool(_cmdlist: &CommandList, _prefix: &str, _parts: &[String]) -> Vec<Str
 
 pub fn classes(cmdlist: &CommandList, prefix: &str, _parts: &[String]) -> Vec<String> {
     trace!("Autocompleting classes with prefix: {}", prefix);
-    let mut cmd = cmdlist.client().classes().find();
-
-    if !prefix.is_empty() {
-        cmd = cmd.add_filter(
-            "name",
-            FilterOperator::StartsWith { is_negated: false },
-            prefix,
-        );
-    }
-    match cmd.execute() {
-        Ok(classes) => classes.into_iter().map(|c| c.name).collect(),
+    let Some(client) = api_client(cmdlist) else {
+        return Vec::new();
+    };
+
+    let names = cached_names("classes", || {
+        client
+            .classes()
+            .find()
+            .execute()
+            .map(|classes| classes.into_iter().map(|c| c.name).collect())
+    });
+    matching(prefix, names)
+}
+
+pub fn class_templates(_cmdlist: &CommandList, prefix: &str, _parts: &[String]) -> Vec<String> {
+    trace!("Autocompleting class templates with prefix: {}", prefix);
+    match crate::templates::class_templates() {
+        Ok(templates) => templates
+            .into_iter()
+            .map(|t| t.name)
+            .filter(|name| name.starts_with(prefix))
+            .collect(),
         Err(_) => {
-            warn!("Failed to fetch classes for autocomplete");
+            warn!("Failed to read class templates for autocomplete");
             Vec::new()
         }
     }
 }
 
-pub fn namespaces(cmdlist: &CommandList, prefix: &str, _parts: &[String]) -> Vec<String> {
-    trace!("Autocompleting namespaces with prefix: {}", prefix);
-    let mut cmd = cmdlist.client().namespaces().find();
-
-    if !prefix.is_empty() {
-        cmd = cmd.add_filter(
-            "name",
-            FilterOperator::StartsWith { is_negated: false },
-            prefix,
-        );
-    }
-    match cmd.execute() {
-        Ok(namespaces) => namespaces.into_iter().map(|c| c.name).collect(),
+pub fn object_templates(_cmdlist: &CommandList, prefix: &str, _parts: &[String]) -> Vec<String> {
+    trace!("Autocompleting object templates with prefix: {}", prefix);
+    match crate::templates::object_templates() {
+        Ok(templates) => templates
+            .into_iter()
+            .map(|t| t.name)
+            .filter(|name| name.starts_with(prefix))
+            .collect(),
         Err(_) => {
-            warn!("Failed to fetch namespaces for autocomplete");
+            warn!("Failed to read object templates for autocomplete");
             Vec::new()
         }
     }
 }
 
+pub fn namespaces(cmdlist: &CommandList, prefix: &str, _parts: &[String]) -> Vec<String> {
+    trace!("Autocompleting namespaces with prefix: {}", prefix);
+    let Some(client) = api_client(cmdlist) else {
+        return Vec::new();
+    };
+
+    let names = cached_names("namespaces", || {
+        client
+            .namespaces()
+            .find()
+            .execute()
+            .map(|namespaces| namespaces.into_iter().map(|n| n.name).collect())
+    });
+    matching(prefix, names)
+}
+
+pub fn users(cmdlist: &CommandList, prefix: &str, _parts: &[String]) -> Vec<String> {
+    trace!("Autocompleting users with prefix: {}", prefix);
+    let Some(client) = api_client(cmdlist) else {
+        return Vec::new();
+    };
+
+    let names = cached_names("users", || {
+        client
+            .users()
+            .find()
+            .execute()
+            .map(|users| users.into_iter().map(|u| u.username).collect())
+    });
+    matching(prefix, names)
+}
+
+pub fn groups(cmdlist: &CommandList, prefix: &str, _parts: &[String]) -> Vec<String> {
+    trace!("Autocompleting groups with prefix: {}", prefix);
+    let Some(client) = api_client(cmdlist) else {
+        return Vec::new();
+    };
+
+    let names = cached_names("groups", || {
+        client
+            .groups()
+            .find()
+            .execute()
+            .map(|groups| groups.into_iter().map(|g| g.groupname).collect())
+    });
+    matching(prefix, names)
+}
+
 fn objects_from_class_source(
     cmdlist: &CommandList,
     prefix: &str,
@@ -64,37 +276,23 @@ fn objects_from_class_source(
         None => return Vec::new(),
     };
 
-    let class = match cmdlist
-        .client()
-        .classes()
-        .find()
-        .add_filter_name_exact(classname)
-        .execute_expecting_single_result()
-    {
-        Ok(ret) => ret,
-        Err(_) => {
-            warn!("Failed to fetch class from {} autocomplete", source);
-            return Vec::new();
-        }
+    let Some(client) = api_client(cmdlist) else {
+        return Vec::new();
     };
 
-    let mut cmd = cmdlist.client().objects(class.id).find();
-
-    if !prefix.is_empty() {
-        cmd = cmd.add_filter(
-            "name",
-            FilterOperator::StartsWith { is_negated: false },
-            prefix,
-        );
-    }
-
-    match cmd.execute() {
-        Ok(objects) => objects.into_iter().map(|c| c.name).collect(),
-        Err(_) => {
-            warn!("Failed to fetch objects for autocomplete");
-            Vec::new()
-        }
-    }
+    let names = cached_names(&format!("objects:{}", classname), || {
+        let class = client
+            .classes()
+            .find()
+            .add_filter_name_exact(classname.clone())
+            .execute_expecting_single_result()?;
+        client
+            .objects(class.id)
+            .find()
+            .execute()
+            .map(|objects| objects.into_iter().map(|o| o.name).collect())
+    });
+    matching(prefix, names)
 }
 
 pub fn objects_from_class(cmdlist: &CommandList, prefix: &str, parts: &[String]) -> Vec<String> {
@@ -112,3 +310,131 @@ pub fn objects_from_class_from(
 pub fn objects_from_class_to(cmdlist: &CommandList, prefix: &str, parts: &[String]) -> Vec<String> {
     objects_from_class_source(cmdlist, prefix, parts, "--class_to")
 }
+
+// The value following any of `options` in the parts of the line.
+fn option_value(parts: &[String], options: &[&str]) -> Option<String> {
+    parts
+        .windows(2)
+        .find(|w| options.contains(&w[0].as_str()))
+        .map(|w| w[1].clone())
+}
+
+// JSONPath expressions for every key and array element in the data, e.g. `$.os`,
+// `$.os.family` and `$.disks[0]`, plus `[*]` for arrays.
+fn json_paths(path: &str, value: &serde_json::Value, out: &mut Vec<String>) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map {
+                let is_identifier =
+                    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                let child = if is_identifier {
+                    format!("{}.{}", path, key)
+                } else {
+                    format!("{}['{}']", path, key.replace('\'', "\\'"))
+                };
+                out.push(child.clone());
+                json_paths(&child, value, out);
+            }
+        }
+        serde_json::Value::Array(items) if !items.is_empty() => {
+            out.push(format!("{}[*]", path));
+            for (i, value) in items.iter().enumerate() {
+                let child = format!("{}[{}]", path, i);
+                out.push(child.clone());
+                json_paths(&child, value, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Complete `--path` from the keys in the data of the object being shown. The object is
+/// given by `--name` (or the first positional argument) and `--class` (or the class in use).
+pub fn data_paths(cmdlist: &CommandList, prefix: &str, parts: &[String]) -> Vec<String> {
+    trace!("Autocompleting data paths with prefix: {}", prefix);
+    let Some(client) = api_client(cmdlist) else {
+        return Vec::new();
+    };
+
+    let Some(classname) =
+        option_value(parts, &["--class", "-c"]).or_else(|| session::context().class)
+    else {
+        return Vec::new();
+    };
+    let name = option_value(parts, &["--name", "-n"]).or_else(|| {
+        // The first argument after the command that is neither an option nor its value.
+        let command = parts.iter().position(|p| p == "info")?;
+        let mut rest = parts[command + 1..].iter();
+        while let Some(part) = rest.next() {
+            if part.starts_with('-') {
+                rest.next();
+            } else {
+                return Some(part.clone());
+            }
+        }
+        None
+    });
+    let Some(name) = name else {
+        return Vec::new();
+    };
+
+    let paths = cached_names(&format!("paths:{}/{}", classname, name), || {
+        let class = client
+            .classes()
+            .find()
+            .add_filter_name_exact(classname.clone())
+            .execute_expecting_single_result()?;
+        let object = client
+            .objects(class.id)
+            .find()
+            .add_filter_name_exact(name.clone())
+            .execute_expecting_single_result()?;
+
+        let mut paths = Vec::new();
+        if let Some(data) = &object.data {
+            json_paths("$", data, &mut paths);
+        }
+        Ok::<_, hubuum_client::ApiError>(paths)
+    });
+    matching(prefix, paths)
+}
+
+/// Complete a local file path. Directories get a trailing `/` so completion can continue
+/// into them, and hidden entries are only offered once a `.` has been typed.
+pub fn file_paths(prefix: &str) -> Vec<String> {
+    let (dir, file) = match prefix.rfind('/') {
+        Some(i) => (&prefix[..=i], &prefix[i + 1..]),
+        None => ("", prefix),
+    };
+
+    let read_from = match dir {
+        "" => std::path::PathBuf::from("."),
+        dir => std::path::PathBuf::from(expand_home(dir)),
+    };
+
+    let entries = match std::fs::read_dir(&read_from) {
+        Ok(entries) => entries,
+        Err(err) => {
+            trace!(
+                "Unable to read {} for completion: {}",
+                read_from.display(),
+                err
+            );
+            return Vec::new();
+        }
+    };
+
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(file) || (name.starts_with('.') && !file.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+            Some(format!("{}{}{}", dir, name, if is_dir { "/" } else { "" }))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
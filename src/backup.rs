@@ -0,0 +1,609 @@
+// Backups of a server: groups, namespaces with the permissions of groups on them, classes and
+// objects, as JSON files in a gzipped tar archive. Entities refer to each other by name, so a
+// backup can be restored on another server where the ids differ.
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::Read;
+use std::str::FromStr;
+
+use chrono::{NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use hubuum_client::{
+    Authenticated, ClassPatch, ClassPost, GroupPost, NamespacePatch, NamespacePost, ObjectPatch,
+    ObjectPost, SyncClient,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::api::{get_json, post_json, retry_api};
+use crate::config::get_config;
+use crate::errors::AppError;
+use crate::manifest::{ClassSpec, NamespaceSpec, ObjectSpec};
+use crate::snapshots::snapshot_before;
+
+/// The version of the archive format. Backups from newer versions are refused.
+pub const BACKUP_VERSION: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BackupInfo {
+    pub version: u32,
+    pub server: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GroupSpec {
+    pub groupname: String,
+    pub description: String,
+}
+
+/// The permissions a group has on a namespace, by name, e.g. `ReadCollection`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PermissionSpec {
+    pub namespace: String,
+    pub group: String,
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupArchive {
+    pub info: BackupInfo,
+    pub groups: Vec<GroupSpec>,
+    pub namespaces: Vec<NamespaceSpec>,
+    pub permissions: Vec<PermissionSpec>,
+    pub classes: Vec<ClassSpec>,
+    pub objects: Vec<ObjectSpec>,
+}
+
+// Permission entries from the server have a flag per permission, e.g.
+// `has_read_namespace` or `has_create_class_relation`, while permissions are granted by
+// names such as `ReadCollection` and `CreateClassRelation`.
+pub fn permission_names(entry: &Value) -> Vec<String> {
+    let Some(fields) = entry.as_object() else {
+        return Vec::new();
+    };
+    fields
+        .iter()
+        .filter(|(_, value)| value.as_bool() == Some(true))
+        .filter_map(|(key, _)| key.strip_prefix("has_"))
+        .map(|flag| {
+            flag.split('_')
+                .map(|word| match word {
+                    "namespace" => "Collection".to_string(),
+                    word => {
+                        let mut chars = word.chars();
+                        chars
+                            .next()
+                            .map(|c| c.to_uppercase().chain(chars).collect())
+                            .unwrap_or_default()
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect()
+}
+
+impl BackupArchive {
+    /// Read everything the user can see on the server.
+    pub fn take(client: &SyncClient<Authenticated>) -> Result<Self, AppError> {
+        let groups = retry_api(|| client.groups().find().execute())?;
+        let group_names = groups
+            .iter()
+            .map(|g| (g.id, g.groupname.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let mut namespaces = Vec::new();
+        let mut permissions = Vec::new();
+        let namespace_list = retry_api(|| client.namespaces().find().execute())?;
+        for namespace in &namespace_list {
+            let entries = get_json(
+                client,
+                &format!("/api/v1/namespaces/{}/permissions", namespace.id),
+            )?;
+            let mut owner = None;
+            for entry in entries.as_array().into_iter().flatten() {
+                let Some(group) = entry
+                    .get("group_id")
+                    .and_then(Value::as_i64)
+                    .and_then(|id| group_names.get(&(id as i32)))
+                else {
+                    continue;
+                };
+                let names = permission_names(entry);
+                // A group that can delegate the namespace can own it when it is restored.
+                if owner.is_none() && names.iter().any(|n| n == "DelegateCollection") {
+                    owner = Some(group.clone());
+                }
+                permissions.push(PermissionSpec {
+                    namespace: namespace.name.clone(),
+                    group: group.clone(),
+                    permissions: names,
+                });
+            }
+            namespaces.push(NamespaceSpec {
+                name: namespace.name.clone(),
+                description: Some(namespace.description.clone()),
+                owner,
+            });
+        }
+        let namespace_names = namespace_list
+            .iter()
+            .map(|n| (n.id, n.name.clone()))
+            .collect::<HashMap<_, _>>();
+
+        let class_list = retry_api(|| client.classes().find().execute())?;
+        let mut objects = Vec::new();
+        for class in &class_list {
+            for object in retry_api(|| client.objects(class.id).find().execute())? {
+                objects.push(ObjectSpec {
+                    name: object.name,
+                    class: class.name.clone(),
+                    namespace: namespace_names
+                        .get(&object.namespace_id)
+                        .cloned()
+                        .unwrap_or_default(),
+                    description: Some(object.description),
+                    data: object.data,
+                });
+            }
+        }
+
+        Ok(BackupArchive {
+            info: BackupInfo {
+                version: BACKUP_VERSION,
+                server: get_config()?.server.hostname,
+                created_at: Utc::now().naive_utc(),
+            },
+            groups: groups
+                .into_iter()
+                .map(|g| GroupSpec {
+                    groupname: g.groupname,
+                    description: g.description,
+                })
+                .collect(),
+            namespaces,
+            permissions,
+            classes: class_list
+                .into_iter()
+                .map(|c| ClassSpec {
+                    name: c.name,
+                    namespace: c.namespace.name,
+                    description: Some(c.description),
+                    schema: c.json_schema,
+                    validate: c.validate_schema,
+                })
+                .collect(),
+            objects,
+        })
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), AppError> {
+        let encoder = GzEncoder::new(File::create(path)?, Compression::default());
+        let mut archive = tar::Builder::new(encoder);
+        let files = [
+            ("info.json", serde_json::to_vec_pretty(&self.info)?),
+            ("groups.json", serde_json::to_vec_pretty(&self.groups)?),
+            (
+                "namespaces.json",
+                serde_json::to_vec_pretty(&self.namespaces)?,
+            ),
+            (
+                "permissions.json",
+                serde_json::to_vec_pretty(&self.permissions)?,
+            ),
+            ("classes.json", serde_json::to_vec_pretty(&self.classes)?),
+            ("objects.json", serde_json::to_vec_pretty(&self.objects)?),
+        ];
+        for (name, content) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o600);
+            header.set_mtime(self.info.created_at.and_utc().timestamp() as u64);
+            header.set_cksum();
+            archive.append_data(&mut header, name, content.as_slice())?;
+        }
+        archive.into_inner()?.finish()?;
+        Ok(())
+    }
+
+    pub fn read(path: &str) -> Result<Self, AppError> {
+        let mut archive = tar::Archive::new(GzDecoder::new(File::open(path)?));
+        let mut files = HashMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let name = entry.path()?.to_string_lossy().to_string();
+            let mut content = String::new();
+            entry.read_to_string(&mut content)?;
+            files.insert(name, content);
+        }
+        let file = |name: &str| {
+            files.get(name).ok_or_else(|| {
+                AppError::ParseError(format!("{}: not a backup, {} is missing", path, name))
+            })
+        };
+
+        let info: BackupInfo = serde_json::from_str(file("info.json")?)?;
+        if info.version > BACKUP_VERSION {
+            return Err(AppError::ParseError(format!(
+                "{}: backup version {} is newer than the supported version {}",
+                path, info.version, BACKUP_VERSION
+            )));
+        }
+
+        Ok(BackupArchive {
+            info,
+            groups: serde_json::from_str(file("groups.json")?)?,
+            namespaces: serde_json::from_str(file("namespaces.json")?)?,
+            permissions: serde_json::from_str(file("permissions.json")?)?,
+            classes: serde_json::from_str(file("classes.json")?)?,
+            objects: serde_json::from_str(file("objects.json")?)?,
+        })
+    }
+
+    /// The number of entities in the backup, as counted by `restore`.
+    pub fn entity_count(&self) -> usize {
+        self.groups.len() + self.namespaces.len() + self.classes.len() + self.objects.len()
+    }
+}
+
+/// What `restore` does with an entity that already exists with the same name.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Conflict {
+    /// Keep the existing entity.
+    #[default]
+    Skip,
+    /// Update the existing entity to match the backup.
+    Overwrite,
+    /// Restore the entity under a new name, e.g. `web01-restored`.
+    Rename,
+}
+
+impl FromStr for Conflict {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "skip" => Ok(Conflict::Skip),
+            "overwrite" => Ok(Conflict::Overwrite),
+            "rename" => Ok(Conflict::Rename),
+            _ => Err(AppError::ParseError(format!(
+                "Invalid conflict strategy '{}', expected skip, overwrite or rename",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct RestoreSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failures: Vec<String>,
+}
+
+// The first of `name-restored`, `name-restored-2`, ... that is not taken.
+fn unique_name(name: &str, taken: &HashSet<String>) -> String {
+    let mut candidate = format!("{}-restored", name);
+    let mut n = 1;
+    while taken.contains(&candidate) {
+        n += 1;
+        candidate = format!("{}-restored-{}", name, n);
+    }
+    candidate
+}
+
+// What to do with one entity from the backup.
+enum Action {
+    Create(String),
+    Update(i32),
+    Skip(i32),
+}
+
+fn action(
+    conflict: Conflict,
+    name: &str,
+    existing: &HashMap<String, i32>,
+    taken: &HashSet<String>,
+) -> Action {
+    match (existing.get(name), conflict) {
+        (None, _) => Action::Create(name.to_string()),
+        (Some(id), Conflict::Skip) => Action::Skip(*id),
+        (Some(id), Conflict::Overwrite) => Action::Update(*id),
+        (Some(_), Conflict::Rename) => Action::Create(unique_name(name, taken)),
+    }
+}
+
+/// Replay a backup against the server: groups, namespaces and the permissions on them,
+/// classes and then objects. Groups are matched by name and only created if missing.
+/// Namespaces missing on the server are owned by the group that owned them when the backup
+/// was taken, or by `owner`. `done` is called after each entity.
+pub fn restore<F>(
+    client: &SyncClient<Authenticated>,
+    backup: &BackupArchive,
+    conflict: Conflict,
+    owner: Option<&str>,
+    mut done: F,
+) -> Result<RestoreSummary, AppError>
+where
+    F: FnMut(),
+{
+    let mut summary = RestoreSummary::default();
+
+    let mut groups = client
+        .groups()
+        .find()
+        .execute()?
+        .into_iter()
+        .map(|g| (g.groupname, g.id))
+        .collect::<HashMap<_, _>>();
+    for spec in &backup.groups {
+        if groups.contains_key(&spec.groupname) {
+            summary.skipped += 1;
+        } else {
+            match client.groups().create(GroupPost {
+                groupname: spec.groupname.clone(),
+                description: spec.description.clone(),
+            }) {
+                Ok(group) => {
+                    groups.insert(group.groupname, group.id);
+                    summary.created += 1;
+                }
+                Err(err) => summary
+                    .failures
+                    .push(format!("group {}: {}", spec.groupname, err)),
+            }
+        }
+        done();
+    }
+
+    // Namespaces and classes by their name in the backup, as they may be renamed.
+    let existing = client
+        .namespaces()
+        .find()
+        .execute()?
+        .into_iter()
+        .map(|n| (n.name, n.id))
+        .collect::<HashMap<_, _>>();
+    let mut taken = existing.keys().cloned().collect::<HashSet<_>>();
+    let mut namespaces = HashMap::new();
+    // Permissions are only granted on namespaces the restore created or overwrote.
+    let mut restored_namespaces = HashSet::new();
+    for spec in &backup.namespaces {
+        let result = match action(conflict, &spec.name, &existing, &taken) {
+            Action::Skip(id) => {
+                summary.skipped += 1;
+                Ok(id)
+            }
+            Action::Update(id) => client
+                .namespaces()
+                .update(
+                    id,
+                    NamespacePatch {
+                        description: spec.description.clone(),
+                        ..Default::default()
+                    },
+                )
+                .map(|_| {
+                    summary.updated += 1;
+                    restored_namespaces.insert(spec.name.clone());
+                    id
+                })
+                .map_err(AppError::from),
+            Action::Create(name) => {
+                let group_id = spec
+                    .owner
+                    .as_deref()
+                    .or(owner)
+                    .and_then(|group| groups.get(group))
+                    .copied()
+                    .ok_or_else(|| AppError::MissingOptions(vec!["owner".to_string()]));
+                group_id
+                    .and_then(|group_id| {
+                        client
+                            .namespaces()
+                            .create(NamespacePost {
+                                name: name.clone(),
+                                description: spec.description.clone().unwrap_or_default(),
+                                group_id,
+                            })
+                            .map_err(AppError::from)
+                    })
+                    .map(|namespace| {
+                        summary.created += 1;
+                        taken.insert(name);
+                        restored_namespaces.insert(spec.name.clone());
+                        namespace.id
+                    })
+            }
+        };
+        match result {
+            Ok(id) => {
+                namespaces.insert(spec.name.clone(), id);
+            }
+            Err(err) => summary
+                .failures
+                .push(format!("namespace {}: {}", spec.name, err)),
+        }
+        done();
+    }
+
+    for spec in &backup.permissions {
+        if !restored_namespaces.contains(&spec.namespace) || spec.permissions.is_empty() {
+            continue;
+        }
+        let (Some(namespace_id), Some(group_id)) =
+            (namespaces.get(&spec.namespace), groups.get(&spec.group))
+        else {
+            continue;
+        };
+        if let Err(err) = post_json(
+            client,
+            &format!(
+                "/api/v1/namespaces/{}/permissions/group/{}",
+                namespace_id, group_id
+            ),
+            Some(&json!(spec.permissions)),
+        ) {
+            summary.failures.push(format!(
+                "permissions of {} on {}: {}",
+                spec.group, spec.namespace, err
+            ));
+        }
+    }
+
+    let existing = client
+        .classes()
+        .find()
+        .execute()?
+        .into_iter()
+        .map(|c| (c.name, c.id))
+        .collect::<HashMap<_, _>>();
+    let mut taken = existing.keys().cloned().collect::<HashSet<_>>();
+    let mut classes = HashMap::new();
+    // Objects are only compared with the objects of classes that existed already.
+    let mut existing_objects = HashMap::new();
+    for spec in &backup.classes {
+        let namespace_id = namespaces.get(&spec.namespace).copied();
+        let result = match (
+            action(conflict, &spec.name, &existing, &taken),
+            namespace_id,
+        ) {
+            (_, None) => Err(AppError::EntityNotFound(format!(
+                "namespace {}",
+                spec.namespace
+            ))),
+            (Action::Skip(id), _) => {
+                summary.skipped += 1;
+                Ok((id, true))
+            }
+            (Action::Update(id), Some(namespace_id)) => client
+                .classes()
+                .update(
+                    id,
+                    ClassPatch {
+                        namespace_id: Some(namespace_id),
+                        description: spec.description.clone(),
+                        json_schema: spec.schema.clone(),
+                        validate_schema: spec.validate,
+                        ..Default::default()
+                    },
+                )
+                .map(|_| {
+                    summary.updated += 1;
+                    (id, true)
+                })
+                .map_err(AppError::from),
+            (Action::Create(name), Some(namespace_id)) => client
+                .classes()
+                .create(ClassPost {
+                    name: name.clone(),
+                    namespace_id,
+                    description: spec.description.clone().unwrap_or_default(),
+                    json_schema: spec.schema.clone(),
+                    validate_schema: spec.validate,
+                })
+                .map(|class| {
+                    summary.created += 1;
+                    taken.insert(name);
+                    (class.id, false)
+                })
+                .map_err(AppError::from),
+        };
+        match result {
+            Ok((id, existed)) => {
+                classes.insert(spec.name.clone(), id);
+                if existed {
+                    let objects = client
+                        .objects(id)
+                        .find()
+                        .execute()?
+                        .into_iter()
+                        .map(|o| (o.name, o.id))
+                        .collect::<HashMap<_, _>>();
+                    existing_objects.insert(id, objects);
+                }
+            }
+            Err(err) => summary
+                .failures
+                .push(format!("class {}: {}", spec.name, err)),
+        }
+        done();
+    }
+
+    for spec in &backup.objects {
+        let result = restore_object(
+            client,
+            spec,
+            conflict,
+            &classes,
+            &namespaces,
+            &mut existing_objects,
+            &mut summary,
+        );
+        if let Err(err) = result {
+            summary
+                .failures
+                .push(format!("object {}/{}: {}", spec.class, spec.name, err));
+        }
+        done();
+    }
+
+    Ok(summary)
+}
+
+fn restore_object(
+    client: &SyncClient<Authenticated>,
+    spec: &ObjectSpec,
+    conflict: Conflict,
+    classes: &HashMap<String, i32>,
+    namespaces: &HashMap<String, i32>,
+    existing_objects: &mut HashMap<i32, HashMap<String, i32>>,
+    summary: &mut RestoreSummary,
+) -> Result<(), AppError> {
+    let class_id = *classes
+        .get(&spec.class)
+        .ok_or_else(|| AppError::EntityNotFound(format!("class {}", spec.class)))?;
+    let namespace_id = *namespaces
+        .get(&spec.namespace)
+        .ok_or_else(|| AppError::EntityNotFound(format!("namespace {}", spec.namespace)))?;
+    let existing = existing_objects.entry(class_id).or_default();
+    let taken = existing.keys().cloned().collect::<HashSet<_>>();
+
+    match action(conflict, &spec.name, existing, &taken) {
+        Action::Skip(_) => summary.skipped += 1,
+        Action::Update(id) => {
+            let object = client
+                .objects(class_id)
+                .find()
+                .add_filter_id(id)
+                .execute_expecting_single_result()?;
+            snapshot_before(client, &spec.class, &object, "restore")?;
+            client.objects(class_id).update(
+                id,
+                ObjectPatch {
+                    namespace_id: Some(namespace_id),
+                    description: spec.description.clone(),
+                    data: spec.data.clone(),
+                    ..Default::default()
+                },
+            )?;
+            summary.updated += 1;
+        }
+        Action::Create(name) => {
+            let object = client.objects(class_id).create(ObjectPost {
+                name: name.clone(),
+                hubuum_class_id: class_id,
+                namespace_id,
+                description: spec.description.clone().unwrap_or_default(),
+                data: spec.data.clone(),
+            })?;
+            existing.insert(name, object.id);
+            summary.created += 1;
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,7 @@
 // src/cli.rs
+use crate::completions::SHELLS;
 use crate::config::AppConfig;
-use clap::{Arg, ArgMatches, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use std::{path::PathBuf, process::exit};
 
 pub fn build_cli() -> Command {
@@ -11,6 +12,12 @@ pub fn build_cli() -> Command {
                 .value_name("FILE")
                 .help("Specify a custom configuration file"),
         )
+        .arg(
+            Arg::new("check_config")
+                .long("check-config")
+                .action(ArgAction::SetTrue)
+                .help("Check the configuration files and exit"),
+        )
         .arg(
             Arg::new("hostname")
                 .long("hostname")
@@ -67,6 +74,18 @@ pub fn build_cli() -> Command {
                 .value_name("BOOL")
                 .help("Disable API-related completions"),
         )
+        .arg(
+            Arg::new("log_file")
+                .long("log-file")
+                .value_name("FILE")
+                .help("Log to this file instead of the data directory"),
+        )
+        .arg(
+            Arg::new("offline")
+                .long("offline")
+                .action(ArgAction::SetTrue)
+                .help("Do not contact the server, show the output saved from earlier sessions"),
+        )
         .arg(
             Arg::new("command")
                 .long("command")
@@ -79,6 +98,13 @@ pub fn build_cli() -> Command {
                 .value_name("FILE")
                 .help("Run commands from a file and exit"),
         )
+        .arg(
+            Arg::new("completions")
+                .long("completions")
+                .value_name("SHELL")
+                .value_parser(SHELLS)
+                .help("Print a completion script for the shell and exit"),
+        )
 }
 
 pub fn get_cli_config_path(matches: &ArgMatches) -> Option<PathBuf> {
@@ -123,6 +149,9 @@ pub fn update_config_from_cli(config: &mut AppConfig, matches: &ArgMatches) {
             config.cache.disable = cache_disable;
         }
     }
+    if let Some(log_file) = matches.get_one::<String>("log_file") {
+        config.log.file = Some(log_file.to_string());
+    }
     if let Some(disable_api_completion) = matches.get_one::<String>("completion_disable_api") {
         if let Ok(completion_disable_api) = disable_api_completion.parse() {
             config.completion.disable_api_related = completion_disable_api;
@@ -0,0 +1,21 @@
+use arboard::Clipboard;
+
+use crate::errors::AppError;
+
+fn clipboard() -> Result<Clipboard, AppError> {
+    Clipboard::new().map_err(|e| AppError::ClipboardError(e.to_string()))
+}
+
+/// Put text on the system clipboard.
+pub fn copy_to_clipboard(text: &str) -> Result<(), AppError> {
+    clipboard()?
+        .set_text(text)
+        .map_err(|e| AppError::ClipboardError(e.to_string()))
+}
+
+/// The text on the system clipboard, for `paste://` option values.
+pub fn paste_from_clipboard() -> Result<String, AppError> {
+    clipboard()?
+        .get_text()
+        .map_err(|e| AppError::ClipboardError(e.to_string()))
+}
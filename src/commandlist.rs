@@ -1,9 +1,10 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::sync::Arc;
 
 use hubuum_client::{Authenticated, SyncClient};
-use rustyline::highlight::Highlighter;
+use rustyline::highlight::{CmdKind, Highlighter};
 use rustyline::{hint::Hinter, validate::Validator, Helper};
 
 // use colored::Colorize;
@@ -11,12 +12,17 @@ use log::{debug, trace};
 use rustyline::completion::{Completer, Pair};
 use rustyline::Context;
 
+use crate::autocomplete::file_paths;
 use crate::commands::{CliCommand, CliOption};
+use crate::fuzzy::{fuzzy_enabled, fuzzy_filter};
+use crate::highlight::highlight_line;
+use crate::session;
 
+#[derive(Default)]
 pub struct CommandList {
     commands: HashMap<String, Box<dyn CliCommand>>,
     scopes: HashMap<String, CommandList>,
-    client: Arc<SyncClient<Authenticated>>,
+    default_command: Option<String>,
 }
 
 impl Display for CommandList {
@@ -34,16 +40,17 @@ impl Display for CommandList {
 }
 
 impl CommandList {
-    pub fn new(client: Arc<SyncClient<Authenticated>>) -> Self {
+    pub fn new() -> Self {
         CommandList {
             commands: HashMap::new(),
             scopes: HashMap::new(),
-            client,
+            default_command: None,
         }
     }
 
-    pub fn client(&self) -> Arc<SyncClient<Authenticated>> {
-        self.client.clone()
+    /// The client of the session, if logged in.
+    pub fn client(&self) -> Option<Arc<SyncClient<Authenticated>>> {
+        session::client()
     }
 
     pub fn add_command<T: CliCommand + 'static>(&mut self, name: &str, command: T) -> &mut Self {
@@ -52,11 +59,24 @@ impl CommandList {
         self
     }
 
+    /// Run the command `name` when the scope is given without a command, e.g. `history`.
+    pub fn set_default_command(&mut self, name: &str) -> &mut Self {
+        self.default_command = Some(name.to_string());
+        self
+    }
+
+    #[allow(clippy::borrowed_box)]
+    pub fn get_default_command(&self) -> Option<&Box<dyn CliCommand>> {
+        self.default_command
+            .as_ref()
+            .and_then(|name| self.commands.get(name))
+    }
+
     pub fn add_scope(&mut self, name: &str) -> &mut CommandList {
         debug!("Adding scope: {}", name);
         self.scopes
             .entry(name.to_string())
-            .or_insert_with(|| CommandList::new(self.client.clone()))
+            .or_insert_with(CommandList::new)
     }
 
     #[allow(clippy::borrowed_box)]
@@ -70,6 +90,15 @@ impl CommandList {
         self.scopes.get(name)
     }
 
+    /// The names of the commands and scopes directly in this scope.
+    pub fn names(&self) -> Vec<String> {
+        self.commands
+            .keys()
+            .chain(self.scopes.keys())
+            .cloned()
+            .collect()
+    }
+
     pub fn get_completions(&self, prefix: &str) -> Vec<Pair> {
         let mut commands_completions: Vec<Pair> = self
             .commands
@@ -95,40 +124,91 @@ impl CommandList {
         commands_completions
     }
 
-    fn generate_tree(&self, prefix: &str, is_last: bool) -> String {
+    fn generate_tree(&self, prefix: &str) -> String {
         let mut result = String::new();
-        let indent = if prefix.is_empty() { "" } else { "  " };
-        let branch = if is_last { "└─ " } else { "├─ " };
 
-        // Add commands
-        let command_count = self.commands.len();
-        for (i, command_name) in self.commands.keys().enumerate() {
-            let line = format!("{}{}{}{}\n", prefix, indent, branch, command_name);
+        let mut commands = self.commands.iter().collect::<Vec<_>>();
+        commands.sort_by(|(a, _), (b, _)| a.cmp(b));
+        let mut scopes = self.scopes.iter().collect::<Vec<_>>();
+        scopes.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        let total = commands.len() + scopes.len();
+        let width = commands
+            .iter()
+            .map(|(name, _)| name.chars().count())
+            .max()
+            .unwrap_or(0);
+        let branch = |i: usize| if i + 1 == total { "└─ " } else { "├─ " };
+
+        // Add commands, with what they do
+        for (i, (name, command)) in commands.iter().enumerate() {
+            let line = match command.about() {
+                Some(about) => format!("{}{}{:width$}  {}", prefix, branch(i), name, about),
+                None => format!("{}{}{}", prefix, branch(i), name),
+            };
             result.push_str(&line);
-            if i < command_count - 1 || !self.scopes.is_empty() {
-                result.push_str(&format!("{}{}│\n", prefix, indent));
-            }
+            result.push('\n');
         }
 
         // Add scopes
-        let scope_count = self.scopes.len();
-        for (i, (scope_name, scope)) in self.scopes.iter().enumerate() {
-            let line = format!("{}{}{}{}\n", prefix, indent, branch, scope_name);
-            result.push_str(&line);
-            let new_prefix = format!(
-                "{}{}{}",
-                prefix,
-                indent,
-                if i == scope_count - 1 { " " } else { "│" }
-            );
-            result.push_str(&scope.generate_tree(&new_prefix, i == scope_count - 1));
+        for (i, (name, scope)) in scopes.iter().enumerate() {
+            let i = commands.len() + i;
+            result.push_str(&format!("{}{}{}\n", prefix, branch(i), name));
+            let continuation = if i + 1 == total { "   " } else { "│  " };
+            result.push_str(&scope.generate_tree(&format!("{}{}", prefix, continuation)));
         }
 
         result
     }
 
+    /// All commands with their full names, e.g. `object list`, sorted by name.
+    pub fn all_commands(&self) -> Vec<(String, &dyn CliCommand)> {
+        let mut commands = self
+            .commands
+            .iter()
+            .map(|(name, command)| (name.clone(), command.as_ref()))
+            .collect::<Vec<_>>();
+        for (name, scope) in &self.scopes {
+            commands.extend(
+                scope
+                    .all_commands()
+                    .into_iter()
+                    .map(|(path, command)| (format!("{} {}", name, path), command)),
+            );
+        }
+        commands.sort_by(|(a, _), (b, _)| a.cmp(b));
+        commands
+    }
+
+    /// The full names of all commands, e.g. `object list`.
+    pub fn command_paths(&self) -> Vec<String> {
+        self.all_commands()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// The commands whose name or help mentions `keyword`, ignoring case.
+    pub fn search(&self, keyword: &str) -> Vec<(String, &dyn CliCommand)> {
+        let keyword = keyword.to_lowercase();
+        self.all_commands()
+            .into_iter()
+            .filter(|(path, command)| {
+                let mut texts = vec![path.clone()];
+                texts.extend(command.about());
+                texts.extend(command.long_about());
+                texts.extend(command.examples());
+                texts.extend(command.options().into_iter().map(|opt| opt.help));
+                texts
+                    .iter()
+                    .any(|text| text.to_lowercase().contains(&keyword))
+            })
+            .collect()
+    }
+
+    /// The command tree, with what each command does.
     pub fn show_tree(&self) -> String {
-        self.generate_tree("", true).to_string()
+        self.generate_tree("")
     }
 }
 
@@ -136,8 +216,24 @@ impl Validator for CommandList {}
 impl Validator for &CommandList {}
 impl Helper for CommandList {}
 impl Helper for &CommandList {}
-impl Highlighter for CommandList {}
-impl Highlighter for &CommandList {}
+impl Highlighter for CommandList {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight_line(self, line))
+    }
+
+    fn highlight_char(&self, _line: &str, _pos: usize, _kind: CmdKind) -> bool {
+        true
+    }
+}
+impl Highlighter for &CommandList {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        CommandList::highlight(self, line, pos)
+    }
+
+    fn highlight_char(&self, line: &str, pos: usize, kind: CmdKind) -> bool {
+        CommandList::highlight_char(self, line, pos, kind)
+    }
+}
 impl Hinter for CommandList {
     type Hint = String;
     fn hint(&self, _line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> {
@@ -193,12 +289,14 @@ impl Completer for CommandList {
 
         let mut current_scope = self;
         let mut command = None;
+        let mut command_index = 0;
 
-        for part in &parts {
+        for (i, part) in parts.iter().enumerate() {
             if let Some(scope) = current_scope.get_scope(part) {
                 current_scope = scope;
             } else if let Some(cmd) = current_scope.get_command(part) {
                 command = Some(cmd);
+                command_index = i;
                 break;
             } else {
                 // Invalid part, stop completion
@@ -206,97 +304,78 @@ impl Completer for CommandList {
             }
         }
 
-        if let Some(command) = command {
-            let options = command.options();
-            let options_seen = options_seen(&parts, &options);
-            trace!("Options seen: {:?}", options_seen);
-
-            let last_token = word;
-
-            // Determine if the last token is an option or a value
-            if let Some(current_token) = parts.iter().rev().nth(0) {
-                trace!("Current token: {}", current_token);
-                if current_token.starts_with('-') {
-                    trace!("Current token is an option");
-                    let opt_def = option_definiton(&options, current_token);
-
-                    if let Some(opt_def) = opt_def {
-                        trace!("Current option is known with definition: {:?}", opt_def);
-                        if opt_def.flag {
-                            suggest_options(&options, &options_seen, last_token, &mut completions);
-                        } else {
-                            suggest_from_autocomplete(
-                                &self,
-                                &opt_def,
-                                &last_token,
-                                &parts,
-                                &mut completions,
-                            )
-                        }
-                    } else {
-                        // Previous token is not a recognized option
-                        suggest_options(&options, &options_seen, last_token, &mut completions);
-                    }
+        // A command name that is still being typed is completed as a name.
+        let typing_command = !word.is_empty() && command_index + 1 == parts.len();
+
+        // Any value can be read from a file, so `file://` is completed wherever it appears.
+        if let Some(path) = word.strip_prefix("file://") {
+            completions.extend(file_paths(path).into_iter().map(|path| Pair {
+                display: path.clone(),
+                replacement: format!("file://{}", path),
+            }));
+            return Ok((start, completions));
+        }
+
+        match command {
+            Some(command) if !typing_command => {
+                let options = command.options();
+                let options_seen = options_seen(&parts, &options);
+                trace!("Options seen: {:?}", options_seen);
+
+                // The tokens before the word being completed. If the last of them is an
+                // option taking a value, the word is that value.
+                let completed = if word.is_empty() {
+                    &parts[..]
                 } else {
-                    trace!("Testing previous token");
-
-                    if let Some(prev_token) = parts.iter().rev().nth(1) {
-                        if prev_token.starts_with('-') {
-                            trace!("Previous token is an option, expanding completions for option");
-                            let opt_def = option_definiton(&options, &prev_token);
-
-                            if let Some(opt_def) = opt_def {
-                                trace!("Previous option is known with definition: {:?}", opt_def);
-                                if opt_def.flag {
-                                    // Option is a flag, does not expect a value
-                                    suggest_options(
-                                        &options,
-                                        &options_seen,
-                                        last_token,
-                                        &mut completions,
-                                    );
-                                } else {
-                                    trace!("Option is not a flag");
-                                    if let Some(autocomplete_fn) = opt_def.autocomplete {
-                                        trace!("Option has autocomplete function");
-                                        let suggestions =
-                                            autocomplete_fn(&self, last_token, &parts);
-
-                                        if suggestions.contains(&current_token.to_string()) {
-                                            // The previous token matches one of the suggestions, so move on to the rest of the options.
-                                            suggest_options(
-                                                &options,
-                                                &options_seen,
-                                                last_token,
-                                                &mut completions,
-                                            );
-                                        } else {
-                                            completions.extend(suggestions.into_iter().map(|s| {
-                                                Pair {
-                                                    display: s.clone(),
-                                                    replacement: s,
-                                                }
-                                            }));
-                                        }
-                                    } else {
-                                        trace!("Option lacks autocomplete function");
-                                    }
-                                }
-                            } else {
-                                // Previous token is not a recognized option
-                                // Do nothing
-                            }
-                        } else {
-                            suggest_options(&options, &options_seen, last_token, &mut completions);
-                        }
-                    } else {
-                        // Previous token is not an option, suggest options
-                        suggest_options(&options, &options_seen, last_token, &mut completions);
+                    &parts[..parts.len() - 1]
+                };
+                let value_for = completed
+                    .last()
+                    .filter(|token| token.starts_with('-'))
+                    .and_then(|token| option_definiton(&options, token))
+                    .filter(|opt| !opt.flag);
+
+                // Otherwise, a word not starting with a dash may be a positional argument.
+                let positional_for = || {
+                    let index = positionals_given(&completed[command_index + 1..], &options);
+                    let positional = command
+                        .positionals()
+                        .into_iter()
+                        .find(|p| p.index == index && !options_seen.contains(&p.name))?;
+                    options.iter().find(|opt| opt.name == positional.name)
+                };
+
+                match value_for {
+                    Some(opt_def) => {
+                        trace!("Completing value for option: {:?}", opt_def);
+                        suggest_values(self, opt_def, word, &parts, &mut completions);
                     }
+                    None => match positional_for().filter(|_| !word.starts_with('-')) {
+                        Some(opt_def) => {
+                            trace!("Completing positional for option: {:?}", opt_def);
+                            suggest_values(self, opt_def, word, &parts, &mut completions);
+                        }
+                        None => suggest_options(&options, &options_seen, word, &mut completions),
+                    },
+                }
+            }
+            _ => {
+                completions.extend(current_scope.get_completions(word));
+
+                // Fall back to matching the whole line against every command, so `ob li`
+                // or `obli` complete to `object list`.
+                if completions.is_empty() && command.is_none() && fuzzy_enabled() {
+                    let query = parts.concat();
+                    let fuzzy = fuzzy_filter(&query, self.command_paths())
+                        .into_iter()
+                        .map(|path| Pair {
+                            display: path.clone(),
+                            replacement: format!("{} ", path),
+                        })
+                        .collect();
+                    return Ok((0, fuzzy));
                 }
             }
-        } else {
-            completions.extend(current_scope.get_completions(word));
         }
 
         trace!("Completions: {:?}", display_pairs(&completions));
@@ -319,6 +398,23 @@ fn options_seen(parts: &[String], options: &[CliOption]) -> Vec<String> {
         .collect()
 }
 
+/// The number of positional arguments among the tokens after the command, skipping options
+/// and their values.
+fn positionals_given(tokens: &[String], options: &[CliOption]) -> usize {
+    let mut count = 0;
+    let mut tokens = tokens.iter();
+    while let Some(token) = tokens.next() {
+        if token.starts_with('-') && token.parse::<f64>().is_err() {
+            if option_definiton(options, token).is_some_and(|opt| !opt.flag) {
+                tokens.next();
+            }
+        } else {
+            count += 1;
+        }
+    }
+    count
+}
+
 /// Find the definition of an option by its long or short name
 fn option_definiton<'a>(option_defs: &'a [CliOption], token: &str) -> Option<&'a CliOption> {
     option_defs
@@ -326,6 +422,41 @@ fn option_definiton<'a>(option_defs: &'a [CliOption], token: &str) -> Option<&'a
         .find(|opt| opt.long.as_deref() == Some(token) || opt.short.as_deref() == Some(token))
 }
 
+/// Suggest values for an option: local paths if it takes a file, its fixed set of values if
+/// it has one, otherwise what its autocomplete function returns. Options with none of these
+/// take free text.
+fn suggest_values(
+    cmdlist: &CommandList,
+    opt_def: &CliOption,
+    last_token: &str,
+    tokens: &[String],
+    completions: &mut Vec<Pair>,
+) {
+    if opt_def.path {
+        completions.extend(file_paths(last_token).into_iter().map(|path| Pair {
+            display: path.clone(),
+            replacement: path,
+        }));
+        return;
+    }
+
+    if opt_def.values.is_empty() {
+        suggest_from_autocomplete(cmdlist, opt_def, last_token, tokens, completions);
+        return;
+    }
+
+    completions.extend(
+        opt_def
+            .values
+            .iter()
+            .filter(|value| value.starts_with(last_token))
+            .map(|value| Pair {
+                display: value.clone(),
+                replacement: value.clone(),
+            }),
+    );
+}
+
 /// Suggest completions for an option based on its autocomplete function
 fn suggest_from_autocomplete(
     cmdlist: &CommandList,
@@ -0,0 +1,80 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::audit::audit_log;
+use crate::errors::AppError;
+use crate::history::parse_since;
+use crate::models::OutputFormat;
+use crate::output::{append_json, append_line, get_format};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show the audit log",
+    long_about = "Show the commands that changed something on the server, with who ran them against which server, when, and whether they succeeded. The audit log is kept separate from the command history and the debug log.",
+    examples = r#"--since '1 day ago'
+--last 10 --failed"#
+)]
+pub struct AuditShow {
+    #[option(
+        short = "s",
+        long = "since",
+        help = "Only show commands run after this time, e.g. '1 hour ago'"
+    )]
+    pub since: Option<String>,
+    #[option(short = "l", long = "last", help = "Only show the last N commands")]
+    pub last: Option<usize>,
+    #[option(
+        long = "failed",
+        help = "Only show commands that failed",
+        flag = "true"
+    )]
+    pub failed: Option<bool>,
+}
+
+impl CliCommand for AuditShow {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let since = new.since.as_deref().map(parse_since).transpose()?;
+
+        let mut entries = audit_log()?
+            .into_iter()
+            .filter(|e| since.is_none_or(|since| e.at >= since))
+            .filter(|e| new.failed.is_none() || !e.success)
+            .collect::<Vec<_>>();
+        if let Some(last) = new.last {
+            entries.drain(..entries.len().saturating_sub(last));
+        }
+
+        match get_format()? {
+            OutputFormat::Text => {
+                for entry in entries {
+                    append_line(format!(
+                        "{}  {}@{}  {}  {}",
+                        entry.at.format("%Y-%m-%d %H:%M:%S"),
+                        entry.user,
+                        entry.server,
+                        if entry.success { "✓" } else { "✗" },
+                        entry.line
+                    ))?;
+                    if let Some(error) = entry.error {
+                        append_line(format!("    {}", error))?;
+                    }
+                }
+                Ok(())
+            }
+            _ => append_json(&entries),
+        }
+    }
+}
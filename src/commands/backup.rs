@@ -0,0 +1,112 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::autocomplete::groups;
+use crate::backup::{restore, BackupArchive, Conflict};
+use crate::errors::AppError;
+use crate::i18n::tr_args;
+use crate::output::{add_error, append_line};
+use crate::progress::{finish_progress, progress_bar};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Back up the server",
+    long_about = "Save the groups, namespaces with the permissions groups have on them, classes with their schemas, and objects you can see to a versioned archive (a gzipped tar of JSON files).",
+    examples = r#"--output backup.tar.gz"#
+)]
+pub struct Backup {
+    #[option(
+        short = "o",
+        long = "output",
+        help = "File to write the backup to",
+        path = "true"
+    )]
+    pub output: String,
+}
+
+impl CliCommand for Backup {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let backup = BackupArchive::take(client)?;
+        backup.write(&new.output)?;
+
+        append_line(tr_args(
+            "Saved {count} entities to {file}",
+            &[("count", &backup.entity_count()), ("file", &new.output)],
+        ))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Restore a backup",
+    long_about = "Recreate the groups, namespaces, permissions, classes and objects of a backup on the server. Entities are matched by name. --conflict decides what happens to those that exist already: skip keeps them (the default), overwrite updates them to match the backup, and rename restores the backup under a new name, e.g. web01-restored. Existing groups are always kept. Namespaces are created with the group that could delegate them when the backup was taken, or with --owner.",
+    examples = r#"--file backup.tar.gz
+--file backup.tar.gz --conflict overwrite
+--file backup.tar.gz --conflict rename --owner admins"#,
+    mutating = "true"
+)]
+pub struct Restore {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "Backup file to restore",
+        path = "true"
+    )]
+    pub file: String,
+    #[option(
+        short = "c",
+        long = "conflict",
+        help = "What to do with entities that exist",
+        values = "skip,overwrite,rename",
+        default = "skip"
+    )]
+    pub conflict: Conflict,
+    #[option(
+        short = "o",
+        long = "owner",
+        help = "Group owning the namespaces that are created, if the backup has none",
+        autocomplete = "groups"
+    )]
+    pub owner: Option<String>,
+}
+
+impl CliCommand for Restore {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let backup = BackupArchive::read(&new.file)?;
+
+        let progress = progress_bar(backup.entity_count(), "Restoring");
+        let summary = restore(client, &backup, new.conflict, new.owner.as_deref(), || {
+            progress.inc(1)
+        })?;
+        finish_progress(&progress);
+
+        for failure in &summary.failures {
+            add_error(failure)?;
+        }
+        append_line(tr_args(
+            "Restored the backup of {server} from {time}: {created} created, {updated} updated, {skipped} skipped, {failed} failed",
+            &[
+                ("server", &backup.info.server),
+                ("time", &backup.info.created_at.format("%Y-%m-%d %H:%M:%S")),
+                ("created", &summary.created),
+                ("updated", &summary.updated),
+                ("skipped", &summary.skipped),
+                ("failed", &summary.failures.len()),
+            ],
+        ))
+    }
+}
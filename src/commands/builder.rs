@@ -1,31 +1,67 @@
-use std::sync::Arc;
-
-use hubuum_client::{Authenticated, SyncClient};
-
 use crate::commandlist::CommandList;
 use crate::commands;
 
-pub fn build_repl_commands(client: Arc<SyncClient<Authenticated>>) -> CommandList {
-    let mut cli = CommandList::new(client);
+pub fn build_repl_commands() -> CommandList {
+    let mut cli = CommandList::new();
 
     add_class_commands(&mut cli);
     add_namespace_commands(&mut cli);
     add_user_commands(&mut cli);
     add_group_commands(&mut cli);
+    add_permissions_commands(&mut cli);
     add_object_commands(&mut cli);
     add_relation_commands(&mut cli);
+    add_notify_commands(&mut cli);
+    add_sandbox_commands(&mut cli);
+    add_history_commands(&mut cli);
+    add_record_commands(&mut cli);
+    add_audit_commands(&mut cli);
+    add_token_commands(&mut cli);
+    add_server_commands(&mut cli);
+    add_config_commands(&mut cli);
+    add_cache_commands(&mut cli);
+    add_debug_commands(&mut cli);
+    add_trace_commands(&mut cli);
+    add_stats_commands(&mut cli);
+    add_export_commands(&mut cli);
 
+    cli.add_command("search", commands::Search::default());
+    cli.add_command("apply", commands::ManifestApply::default());
+    cli.add_command("plan", commands::ManifestPlan::default());
+    cli.add_command("backup", commands::Backup::default());
+    cli.add_command("restore", commands::Restore::default());
+    cli.add_command("whoami", commands::Whoami::default());
+    cli.add_command("passwd", commands::Passwd::default());
+    cli.add_command("login", commands::Login::default());
+    cli.add_command("logout", commands::Logout::default());
+    cli.add_command("ping", commands::Ping::default());
+    cli.add_command("version", commands::Version::default());
+    cli.add_command("use", commands::Use::default());
     cli.add_command("help", commands::Help::default());
+    cli.add_command("completions", commands::Completions::default());
 
     cli
 }
 
 fn add_class_commands(cli: &mut CommandList) {
-    cli.add_scope("class")
+    let class = cli.add_scope("class");
+    class
         .add_command("create", commands::ClassNew::default())
         .add_command("list", commands::ClassList::default())
         .add_command("delete", commands::ClassDelete::default())
-        .add_command("info", commands::ClassInfo::default());
+        .add_command("info", commands::ClassInfo::default())
+        .add_command("graph", commands::ClassGraph::default())
+        .add_command("scaffold", commands::ClassScaffold::default())
+        .add_command("migrate-data", commands::ClassMigrateData::default());
+
+    class
+        .add_scope("template")
+        .add_command("list", commands::ClassTemplateList::default())
+        .add_command("apply", commands::ClassTemplateApply::default());
+
+    class
+        .add_scope("schema")
+        .add_command("diff", commands::ClassSchemaDiff::default());
 }
 
 fn add_namespace_commands(cli: &mut CommandList) {
@@ -33,7 +69,10 @@ fn add_namespace_commands(cli: &mut CommandList) {
         .add_command("create", commands::NamespaceNew::default())
         .add_command("list", commands::NamespaceList::default())
         .add_command("delete", commands::NamespaceDelete::default())
-        .add_command("info", commands::NamespaceInfo::default());
+        .add_command("modify", commands::NamespaceModify::default())
+        .add_command("move", commands::NamespaceMove::default())
+        .add_command("info", commands::NamespaceInfo::default())
+        .add_command("stats", commands::NamespaceStats::default());
 }
 
 fn add_user_commands(cli: &mut CommandList) {
@@ -41,6 +80,7 @@ fn add_user_commands(cli: &mut CommandList) {
         .add_command("create", commands::UserNew::default())
         .add_command("list", commands::UserList::default())
         .add_command("delete", commands::UserDelete::default())
+        .add_command("modify", commands::UserModify::default())
         .add_command("info", commands::UserInfo::default());
 }
 
@@ -50,13 +90,41 @@ fn add_group_commands(cli: &mut CommandList) {
         .add_command("list", commands::GroupList::default());
 }
 
+fn add_permissions_commands(cli: &mut CommandList) {
+    cli.add_scope("permissions")
+        .add_command("show", commands::PermissionsShow::default());
+}
+
 fn add_object_commands(cli: &mut CommandList) {
-    cli.add_scope("object")
+    let object = cli.add_scope("object");
+    object
         .add_command("create", commands::ObjectNew::default())
         .add_command("list", commands::ObjectList::default())
+        .add_command("count", commands::ObjectCount::default())
         .add_command("delete", commands::ObjectDelete::default())
+        .add_command("purge", commands::ObjectPurge::default())
         .add_command("modify", commands::ObjectModify::default())
-        .add_command("info", commands::ObjectInfo::default());
+        .add_command("info", commands::ObjectInfo::default())
+        .add_command("history", commands::ObjectHistory::default())
+        .add_command("graph", commands::ObjectGraph::default())
+        .add_command("import", commands::ObjectImport::default())
+        .add_command("snapshot", commands::ObjectTakeSnapshot::default())
+        .add_command("restore", commands::ObjectRestoreSnapshot::default())
+        .add_command(
+            "restore-snapshot",
+            commands::ObjectRestoreSnapshot::default(),
+        );
+
+    object
+        .add_scope("template")
+        .add_command("save", commands::ObjectTemplateSave::default())
+        .add_command("list", commands::ObjectTemplateList::default());
+
+    object
+        .add_scope("data")
+        .add_command("get", commands::ObjectDataGet::default())
+        .add_command("set", commands::ObjectDataSet::default())
+        .add_command("unset", commands::ObjectDataUnset::default());
 }
 
 fn add_relation_commands(cli: &mut CommandList) {
@@ -64,5 +132,97 @@ fn add_relation_commands(cli: &mut CommandList) {
         .add_command("create", commands::RelationNew::default())
         .add_command("list", commands::RelationList::default())
         .add_command("delete", commands::RelationDelete::default())
-        .add_command("info", commands::RelationInfo::default());
+        .add_command("info", commands::RelationInfo::default())
+        .add_command("import", commands::RelationImport::default());
+}
+
+fn add_notify_commands(cli: &mut CommandList) {
+    cli.add_scope("notify")
+        .add_command("on", commands::NotifyOn::default())
+        .add_command("off", commands::NotifyOff::default())
+        .add_command("list", commands::NotifyList::default());
+}
+
+fn add_sandbox_commands(cli: &mut CommandList) {
+    cli.add_scope("sandbox")
+        .add_command("start", commands::SandboxStart::default())
+        .add_command("destroy", commands::SandboxDestroy::default())
+        .add_command("status", commands::SandboxStatus::default());
+}
+
+fn add_history_commands(cli: &mut CommandList) {
+    cli.add_scope("history")
+        .add_command("list", commands::HistoryList::default())
+        .add_command("search", commands::HistorySearch::default())
+        .add_command("export", commands::HistoryExport::default())
+        .set_default_command("list");
+}
+
+fn add_record_commands(cli: &mut CommandList) {
+    cli.add_scope("record")
+        .add_command("start", commands::RecordStart::default())
+        .add_command("stop", commands::RecordStop::default())
+        .add_command("status", commands::RecordStatus::default());
+}
+
+fn add_audit_commands(cli: &mut CommandList) {
+    cli.add_scope("audit")
+        .add_command("show", commands::AuditShow::default())
+        .set_default_command("show");
+}
+
+fn add_token_commands(cli: &mut CommandList) {
+    cli.add_scope("token")
+        .add_command("list", commands::TokenList::default())
+        .add_command("revoke", commands::TokenRevoke::default());
+}
+
+fn add_server_commands(cli: &mut CommandList) {
+    cli.add_scope("server")
+        .add_command("info", commands::ServerInfo::default())
+        .add_command("stats", commands::Stats::default());
+}
+
+fn add_config_commands(cli: &mut CommandList) {
+    cli.add_scope("config")
+        .add_command("show", commands::ConfigShow::default())
+        .add_command("get", commands::ConfigGet::default())
+        .add_command("set", commands::ConfigSet::default())
+        .add_command("validate", commands::ConfigValidate::default())
+        .add_command("migrate", commands::ConfigMigrate::default());
+}
+
+fn add_cache_commands(cli: &mut CommandList) {
+    cli.add_scope("cache")
+        .add_command("clear", commands::CacheClear::default())
+        .add_command("stats", commands::CacheStats::default())
+        .add_command("purge", commands::CachePurge::default());
+}
+
+fn add_debug_commands(cli: &mut CommandList) {
+    cli.add_scope("debug")
+        .add_command("on", commands::DebugOn::default())
+        .add_command("off", commands::DebugOff::default())
+        .add_command("status", commands::DebugStatus::default())
+        .set_default_command("status");
+}
+
+fn add_trace_commands(cli: &mut CommandList) {
+    cli.add_scope("trace")
+        .add_command("last", commands::TraceLast::default())
+        .set_default_command("last");
+}
+
+fn add_stats_commands(cli: &mut CommandList) {
+    cli.add_scope("stats")
+        .add_command("server", commands::Stats::default())
+        .add_command("timing", commands::StatsTiming::default())
+        .set_default_command("server");
+}
+
+fn add_export_commands(cli: &mut CommandList) {
+    cli.add_scope("export").add_command(
+        "ansible-inventory",
+        commands::ExportAnsibleInventory::default(),
+    );
 }
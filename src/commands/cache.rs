@@ -0,0 +1,99 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::autocomplete::{cache_entries, clear_cache};
+use crate::config::get_config;
+use crate::errors::AppError;
+use crate::formatting::{FormattedCacheStats, OutputFormatterWithPadding};
+use crate::i18n::{tr, tr_args};
+use crate::lookup_cache::{clear_lookup_cache, lookup_cache_stats};
+use crate::output::append_line;
+use crate::response_cache::{disk_cache_usage, purge_response_cache};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Clear the cache",
+    long_about = "Forget the names cached for completion and the classes and namespaces cached by name, so they are fetched from the server again. Use it to see changes made by others at once, rather than after cache.time seconds."
+)]
+pub struct CacheClear {}
+
+impl CliCommand for CacheClear {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        clear_cache();
+        clear_lookup_cache();
+        append_line(tr("Cache cleared"))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show cache statistics",
+    long_about = "Show how many classes and namespaces are cached by name, how often the caches were used, how many name lists are cached for completion, and how much of cache.size the responses cached on disk use. Entries are kept for cache.time seconds, and commands that change classes or namespaces forget the affected entries. Changes made by other clients or sessions are not seen until the entries expire or cache clear is run, e.g. a renamed class is still found by its old name, and a deleted one fails when it is used."
+)]
+pub struct CacheStats {}
+
+impl CliCommand for CacheStats {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let config = get_config()?;
+        let usage = disk_cache_usage()?;
+        FormattedCacheStats {
+            enabled: !config.cache.disable,
+            ttl_seconds: config.cache.time,
+            lookups: lookup_cache_stats(),
+            completion_entries: cache_entries(),
+            disk_entries: usage.entries,
+            disk_bytes: usage.bytes,
+        }
+        .format(15)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Remove the responses cached on disk",
+    long_about = "Remove the server responses cached on disk, so they are fetched again. The cache is kept between sessions and shrunk to cache.size bytes by removing the least recently used responses."
+)]
+pub struct CachePurge {}
+
+impl CliCommand for CachePurge {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let count = purge_response_cache()?;
+        append_line(tr_args(
+            "Removed {count} cached responses",
+            &[("count", &count)],
+        ))?;
+        Ok(())
+    }
+}
@@ -1,30 +1,49 @@
+use std::collections::BTreeMap;
+
 use cli_command_derive::CliCommand;
 use hubuum_client::{
-    Authenticated, Class, ClassPost, FilterOperator, IntoResourceFilter, QueryFilter, SyncClient,
+    Authenticated, Class, ClassPost, FilterOperator, IntoResourceFilter, ObjectPatch, QueryFilter,
+    SyncClient,
 };
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use super::shared::find_class_by_name;
+use super::shared::{
+    count_objects, count_objects_in_classes, find_class_by_name, repeated_values, sort_entities,
+    DataTransform, ListFilter,
+};
 use super::CliCommand;
 use super::{CliCommandInfo, CliOption};
 
+use crate::api::retry_api;
 use crate::autocomplete::{bool, classes, namespaces};
+use crate::backup::BackupArchive;
 use crate::commands::shared::find_namespace_by_name;
 use crate::errors::AppError;
-use crate::formatting::{OutputFormatter, OutputFormatterWithPadding};
-use crate::output::append_key_value;
+use crate::formatting::{
+    format_count, FormattedClassWithCount, OutputFormatter, OutputFormatterWithPadding,
+};
+use crate::i18n::{tr, tr_args};
+use crate::interrupt::take_interrupt;
+use crate::models::OutputFormat;
+use crate::output::{
+    add_error, add_warning, append_json, append_key_value, append_line, append_yaml, flush_output,
+    get_format,
+};
+use crate::plan::{confirm, Plan};
+use crate::progress::{finish_progress, progress_bar};
+use crate::schema::{diff_schemas, schema_properties, validate_data, SchemaDiff};
+use crate::snapshots::snapshot_before;
 use crate::tokenizer::CommandTokenizer;
 
-trait GetClassname {
-    fn classname(&self) -> Option<String>;
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
 #[command_info(
     about = "Create a new class",
     long_about = "Create a new class with the specified properties.",
     examples = r#"-n MyClass -N namespace_1 -d "My class description"
---name MyClass --namespace namespace_1 --description 'My class' --schema '{\"type\": \"object\"}'"#
+--name MyClass --namespace namespace_1 --description 'My class' --schema '{\"type\": \"object\"}'
+--interactive"#,
+    mutating = "true"
 )]
 pub struct ClassNew {
     #[option(short = "n", long = "name", help = "Name of the class")]
@@ -33,7 +52,8 @@ pub struct ClassNew {
         short = "N",
         long = "namespace",
         help = "Namespace name",
-        autocomplete = "namespaces"
+        autocomplete = "namespaces",
+        context = "true"
     )]
     pub namespace: String,
     #[option(short = "d", long = "description", help = "Description of the class")]
@@ -44,9 +64,17 @@ pub struct ClassNew {
         short = "v",
         long = "validate",
         help = "Validate against schema, requires schema to be set",
-        autocomplete = "bool"
+        autocomplete = "bool",
+        requires = "json_schema"
     )]
     pub validate_schema: Option<bool>,
+    #[option(
+        short = "i",
+        long = "interactive",
+        help = "Ask for the options that were not given",
+        flag = "true"
+    )]
+    pub interactive: Option<bool>,
 }
 
 impl CliCommand for ClassNew {
@@ -87,14 +115,10 @@ impl IntoResourceFilter<Class> for &ClassInfo {
     }
 }
 
-impl GetClassname for &ClassInfo {
-    fn classname(&self) -> Option<String> {
-        self.name.clone()
-    }
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct ClassInfo {
+    #[positional(index = 0, help = "Name of the class")]
     #[option(
         short = "n",
         long = "name",
@@ -110,23 +134,26 @@ impl CliCommand for ClassInfo {
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut query = self.new_from_tokens(tokens)?;
-        query.name = classname_or_pos(&query, tokens, 0)?;
+        let query = self.new_from_tokens(tokens)?;
         let class = find_class_by_name(client, &query.name.unwrap())?;
 
         class.format(15)?;
 
-        // This will hopefully be a head request in the future
-        let objects = client.objects(class.id).find().execute()?;
+        if get_format()? != OutputFormat::Text {
+            return Ok(());
+        }
+
         // Not sure why we need to reduce the padding, should be checked.
-        append_key_value("Objects", objects.len(), 14)?;
+        append_key_value(tr("Objects"), count_objects(client, class.id)?, 14)?;
 
         Ok(())
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(mutating = "true")]
 pub struct ClassDelete {
+    #[positional(index = 0, help = "Name of the class")]
     #[option(
         short = "n",
         long = "name",
@@ -142,8 +169,7 @@ impl CliCommand for ClassDelete {
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut query = self.new_from_tokens(tokens)?;
-        query.name = classname_or_pos(&query, tokens, 0)?;
+        let query = self.new_from_tokens(tokens)?;
 
         let class = find_class_by_name(client, &query.name.unwrap())?;
         client.classes().delete(class.id)?;
@@ -152,31 +178,8 @@ impl CliCommand for ClassDelete {
     }
 }
 
-impl GetClassname for &ClassDelete {
-    fn classname(&self) -> Option<String> {
-        self.name.clone()
-    }
-}
-
-fn classname_or_pos<U>(
-    query: U,
-    tokens: &CommandTokenizer,
-    pos: usize,
-) -> Result<Option<String>, AppError>
-where
-    U: GetClassname,
-{
-    let pos0 = tokens.get_positionals().get(pos);
-    if query.classname().is_none() {
-        if pos0.is_none() {
-            return Err(AppError::MissingOptions(vec!["name".to_string()]));
-        }
-        return Ok(pos0.cloned());
-    };
-    Ok(query.classname().clone())
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct ClassList {
     #[option(
         short = "n",
@@ -187,6 +190,37 @@ pub struct ClassList {
     pub name: Option<String>,
     #[option(short = "d", long = "description", help = "Description of the class")]
     pub description: Option<String>,
+    #[option(
+        long = "count",
+        help = "Only show the number of matches",
+        flag = "true"
+    )]
+    pub count: Option<bool>,
+    #[option(
+        short = "s",
+        long = "sort",
+        help = "Sort by comma-separated fields, e.g. name, or objects with --with-counts"
+    )]
+    pub sort: Option<String>,
+    #[option(
+        short = "r",
+        long = "reverse",
+        help = "Reverse the sort order",
+        flag = "true"
+    )]
+    pub reverse: Option<bool>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression, e.g. 'name__startswith=web,data__env=prod'"
+    )]
+    pub filter: Option<String>,
+    #[option(
+        long = "with-counts",
+        help = "Show the number of objects in each class",
+        flag = "true"
+    )]
+    pub with_counts: Option<bool>,
 }
 
 impl IntoResourceFilter<Class> for &ClassList {
@@ -217,8 +251,322 @@ impl CliCommand for ClassList {
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
         let new = self.new_from_tokens(tokens)?;
-        let classes = client.classes().filter(&new)?;
+        let filter = ListFilter::new(&new, new.filter.as_deref())?;
+        let classes = retry_api(|| client.classes().filter(&filter))?;
+
+        if new.count.is_some() {
+            return format_count(classes.len());
+        }
+
+        if new.with_counts.is_some() {
+            let counts = count_objects_in_classes(
+                client,
+                &classes.iter().map(|class| class.id).collect::<Vec<_>>(),
+            )?;
+            let classes = classes
+                .iter()
+                .map(|class| FormattedClassWithCount::new(class, counts[&class.id]))
+                .collect::<Vec<_>>();
+            // Sorted after counting, so `--sort objects` works.
+            let classes = sort_entities(classes, new.sort.as_deref(), new.reverse.is_some())?;
+            return classes.format();
+        }
+
+        let classes = sort_entities(classes, new.sort.as_deref(), new.reverse.is_some())?;
+
         classes.format()?;
         Ok(())
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Compare the schema of a class with a file",
+    long_about = "Compare the JSON schema of a class with a schema in a file, and list the properties that are added, removed or changed in the file. The file may hold the schema itself, the JSON of class info, a manifest or a backup archive. With --old the file is taken to be the earlier schema, e.g. from a previous export. With --check-objects, the objects of the class that the new schema would reject are listed as well.",
+    examples = r#"Host -f host-schema.json
+-n Host -f manifest.yaml --check-objects
+Host -f backup.tar.gz --old"#
+)]
+pub struct ClassSchemaDiff {
+    #[positional(index = 0, help = "Name of the class")]
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the class",
+        autocomplete = "classes"
+    )]
+    pub name: String,
+    #[option(
+        short = "f",
+        long = "file",
+        help = "File with the schema to compare with",
+        path = "true"
+    )]
+    pub file: String,
+    #[option(
+        short = "o",
+        long = "old",
+        help = "The file holds the old schema rather than the new one",
+        flag = "true"
+    )]
+    pub old: Option<bool>,
+    #[option(
+        short = "C",
+        long = "check-objects",
+        help = "List the objects that fail validation under the new schema",
+        flag = "true"
+    )]
+    pub check_objects: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct SchemaComparison {
+    #[serde(flatten)]
+    diff: SchemaDiff,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    invalid_objects: Option<BTreeMap<String, Vec<String>>>,
+}
+
+impl CliCommand for ClassSchemaDiff {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let class = find_class_by_name(client, &query.name)?;
+        let current = class.json_schema.clone().unwrap_or(Value::Null);
+        let file = read_class_schema(&query.file, &class.name)?;
+
+        let (before, after) = match query.old.unwrap_or(false) {
+            true => (file, current),
+            false => (current, file),
+        };
+        let diff = diff_schemas(&before, &after);
+
+        let invalid_objects = match query.check_objects.unwrap_or(false) {
+            true => Some(
+                client
+                    .objects(class.id)
+                    .find()
+                    .execute()?
+                    .into_iter()
+                    .map(|object| {
+                        let data = object.data.unwrap_or(Value::Null);
+                        (object.name, validate_data(&after, &data))
+                    })
+                    .filter(|(_, problems)| !problems.is_empty())
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+            false => None,
+        };
+
+        let comparison = SchemaComparison {
+            diff,
+            invalid_objects,
+        };
+        match get_format()? {
+            OutputFormat::Text => {}
+            OutputFormat::Yaml => return append_yaml(&comparison),
+            _ => return append_json(&comparison),
+        }
+
+        if comparison.diff.is_empty() {
+            append_line(tr("The schemas have the same properties"))?;
+        }
+        let properties = schema_properties(&after);
+        for name in &comparison.diff.added {
+            let label = properties
+                .iter()
+                .find(|property| &property.name == name)
+                .map(|property| property.type_label())
+                .unwrap_or_default();
+            append_line(format!("+ {} [{}]", name, label))?;
+        }
+        for name in &comparison.diff.removed {
+            append_line(format!("- {}", name))?;
+        }
+        for change in &comparison.diff.changed {
+            append_line(format!(
+                "~ {}: {}",
+                change.property,
+                change.changes.join(", ")
+            ))?;
+        }
+
+        if let Some(invalid) = &comparison.invalid_objects {
+            append_line("")?;
+            append_line(tr_args(
+                "{count} objects would fail validation",
+                &[("count", &invalid.len())],
+            ))?;
+            for (name, problems) in invalid {
+                append_line(format!("  {}", name))?;
+                for problem in problems {
+                    append_line(format!("    {}", problem))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// The schema of a class in a file: a backup archive, a manifest, the JSON of class info,
+// or the schema itself.
+fn read_class_schema(path: &str, class: &str) -> Result<Value, AppError> {
+    let not_found = || AppError::EntityNotFound(format!("{} in {}", class, path));
+    if path.ends_with(".tar.gz") || path.ends_with(".tgz") {
+        return BackupArchive::read(path)?
+            .classes
+            .into_iter()
+            .find(|spec| spec.name == class)
+            .map(|spec| spec.schema.unwrap_or(Value::Null))
+            .ok_or_else(not_found);
+    }
+
+    let content = std::fs::read_to_string(path)?;
+    let document: Value = serde_yaml::from_str(&content)
+        .map_err(|e| AppError::ParseError(format!("{}: {}", path, e)))?;
+    if let Some(classes) = document.get("classes").and_then(Value::as_array) {
+        return classes
+            .iter()
+            .find(|spec| spec.get("name").and_then(Value::as_str) == Some(class))
+            .map(|spec| spec.get("schema").cloned().unwrap_or(Value::Null))
+            .ok_or_else(not_found);
+    }
+    match document.get("json_schema") {
+        Some(schema) => Ok(schema.clone()),
+        None => Ok(document),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Transform the data of every object in a class",
+    long_about = "Apply a sequence of transformations to the data of every object in a class. A transformation is target=source to copy a value, target=value to set a JSON value, or remove(path) to remove a key, applied in the order given. If the class has a schema, objects whose new data would fail validation are left unchanged and reported. With --dry-run the changes are shown without making them.",
+    examples = r#"-n Host -t 'address=ip' -t 'remove(ip)' --dry-run
+-n Host -t 'location.rack=rack' -t 'remove(rack)' -t 'status="active"' --yes"#,
+    mutating = "true"
+)]
+pub struct ClassMigrateData {
+    #[positional(index = 0, help = "Name of the class")]
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the class",
+        autocomplete = "classes"
+    )]
+    pub name: String,
+    #[option(
+        short = "t",
+        long = "transform",
+        help = "Transformation to apply, may be repeated"
+    )]
+    pub transform: Option<String>,
+    #[option(
+        long = "dry-run",
+        help = "Show the changes without making them",
+        flag = "true"
+    )]
+    pub dry_run: Option<bool>,
+    #[option(
+        short = "y",
+        long = "yes",
+        help = "Change the objects without asking",
+        flag = "true"
+    )]
+    pub yes: Option<bool>,
+}
+
+impl CliCommand for ClassMigrateData {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let transforms = repeated_values(tokens, &["t", "transform"], &query.transform)
+            .iter()
+            .map(|transform| transform.parse::<DataTransform>())
+            .collect::<Result<Vec<_>, _>>()?;
+        if transforms.is_empty() {
+            return Err(AppError::MissingOptions(vec!["transform".to_string()]));
+        }
+
+        let class = find_class_by_name(client, &query.name)?;
+        let mut plan = Plan::new();
+        let mut changes = Vec::new();
+        for object in retry_api(|| client.objects(class.id).find().execute())? {
+            let before = object.data.clone().unwrap_or(Value::Null);
+            let mut after = before.clone();
+            for transform in &transforms {
+                transform.apply(&mut after)?;
+            }
+            if after == before {
+                continue;
+            }
+
+            if let Some(schema) = &class.json_schema {
+                let problems = validate_data(schema, &after);
+                if !problems.is_empty() {
+                    add_warning(format!("{}: {}", object.name, problems.join("; ")))?;
+                    continue;
+                }
+            }
+            plan.update("object", &object.name, &before, &after)?;
+            changes.push((object, after));
+        }
+
+        if plan.is_empty() {
+            append_line(tr("Nothing to migrate"))?;
+            return Ok(());
+        }
+        if query.dry_run.is_some() {
+            return plan.format(0);
+        }
+        if query.yes.is_none() {
+            plan.format(0)?;
+            flush_output()?;
+            if !confirm(&tr_args(
+                "Change the data of {count} objects?",
+                &[("count", &changes.len())],
+            ))? {
+                append_line(tr("Cancelled"))?;
+                return Err(AppError::Quiet);
+            }
+        }
+
+        let total = changes.len();
+        let mut migrated = 0;
+        let progress = progress_bar(total, "Migrating");
+        for (object, data) in changes {
+            if take_interrupt() {
+                break;
+            }
+            let result = snapshot_before(client, &class.name, &object, "migrate").and_then(|_| {
+                client
+                    .objects(class.id)
+                    .update(
+                        object.id,
+                        ObjectPatch {
+                            data: Some(data),
+                            ..Default::default()
+                        },
+                    )
+                    .map_err(AppError::from)
+            });
+            match result {
+                Ok(_) => migrated += 1,
+                Err(err) => add_error(format!("{}: {}", object.name, err))?,
+            }
+            progress.inc(1);
+        }
+        finish_progress(&progress);
+
+        append_line(tr_args(
+            "Migrated {migrated} of {total} objects",
+            &[("migrated", &migrated), ("total", &total)],
+        ))?;
+        Ok(())
+    }
+}
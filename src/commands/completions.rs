@@ -0,0 +1,48 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{build_repl_commands, CliCommand, CliCommandInfo, CliOption};
+
+use crate::cli::build_cli;
+use crate::completions::completion_script;
+use crate::errors::AppError;
+use crate::output::append_line;
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Print a shell completion script",
+    long_about = "Print a completion script for bash, zsh or fish, completing the command line arguments and the commands given to --command. The same script is printed by starting the CLI with --completions.",
+    examples = r#"bash
+--shell zsh"#
+)]
+pub struct Completions {
+    #[positional(index = 0, help = "Shell to complete for")]
+    #[option(
+        short = "s",
+        long = "shell",
+        help = "Shell to complete for",
+        values = "bash,zsh,fish"
+    )]
+    pub shell: Option<String>,
+}
+
+impl CliCommand for Completions {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let shell = new.shell.unwrap_or_default();
+
+        let script = completion_script(&shell, &build_cli(), &build_repl_commands())?;
+        append_line(script.trim_end())?;
+        Ok(())
+    }
+}
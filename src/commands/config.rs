@@ -0,0 +1,239 @@
+use std::path::PathBuf;
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::shared::Commafy;
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::config::{
+    get_config, get_config_value, get_user_config_path, save_config_value, set_config,
+    set_config_value,
+};
+use crate::config_check::{config_files, migrate_file, validate_file, Severity};
+use crate::errors::AppError;
+use crate::formatting::OutputFormatterWithPadding;
+use crate::i18n::{set_language, tr_args};
+use crate::models::OutputFormat;
+use crate::output::{add_error, add_warning, append_json, append_line, get_format};
+use crate::tokenizer::CommandTokenizer;
+
+// The configuration commands work on the local configuration, so they are all usable
+// without being logged in.
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show the configuration",
+    long_about = "Show the configuration in use, including settings from the command line and the environment. The fetch bearer token and header values are shown as ***."
+)]
+pub struct ConfigShow {}
+
+impl CliCommand for ConfigShow {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        get_config()?.redacted().format(32)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show a configuration value",
+    long_about = "Show the value of a single configuration key, given as section.key. Secrets are shown as ***, as by config show.",
+    examples = r#"server.port
+--key cache.time"#
+)]
+pub struct ConfigGet {
+    #[positional(index = 0, help = "Key to show")]
+    #[option(short = "k", long = "key", help = "Key to show, e.g. server.port")]
+    pub key: Option<String>,
+}
+
+impl CliCommand for ConfigGet {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let key = new.key.unwrap_or_default();
+
+        let value = get_config_value(&get_config()?.redacted(), &key)?;
+        match get_format()? {
+            OutputFormat::Text => match value {
+                serde_json::Value::String(value) => append_line(value)?,
+                value => append_line(value.to_string())?,
+            },
+            _ => append_json(&value)?,
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Change a configuration value",
+    long_about = "Change a configuration value for this session, and with --save also in the configuration file of the user. Lists are given comma-separated. Server settings are used by the next login.",
+    examples = r#"cache.time 600
+display.language nb --save
+--key auth.providers --value keyring,password"#
+)]
+pub struct ConfigSet {
+    #[positional(index = 0, help = "Key to change")]
+    #[option(short = "k", long = "key", help = "Key to change, e.g. cache.time")]
+    pub key: Option<String>,
+    #[positional(index = 1, help = "The new value")]
+    #[option(short = "v", long = "value", help = "The new value")]
+    pub value: Option<String>,
+    #[option(
+        short = "s",
+        long = "save",
+        help = "Also save the value to the configuration file",
+        flag = "true"
+    )]
+    pub save: Option<bool>,
+}
+
+impl CliCommand for ConfigSet {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let key = new.key.unwrap_or_default();
+        let value = new.value.unwrap_or_default();
+
+        let (config, new_value) = set_config_value(&get_config()?, &key, &value)?;
+        if key == "display.language" {
+            set_language(&config.display.language)?;
+        }
+        let shown = get_config_value(&config.redacted(), &key)?;
+        set_config(config)?;
+        append_line(tr_args(
+            "Set {key} to {value}",
+            &[("key", &key), ("value", &shown)],
+        ))?;
+
+        if new.save.is_some() {
+            let path = save_config_value(&key, &new_value)?;
+            append_line(tr_args("Saved to {file}", &[("file", &path.display())]))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Check the configuration files",
+    long_about = "Check the configuration files for syntax errors, unknown keys, values of the wrong type and deprecated settings. The same check is run by starting the CLI with --check-config.",
+    examples = r#"--file ./hubuum.toml"#
+)]
+pub struct ConfigValidate {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "File to check, instead of the system and user configuration",
+        path = "true"
+    )]
+    pub file: Option<String>,
+}
+
+impl CliCommand for ConfigValidate {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let files = match new.file {
+            Some(file) => vec![PathBuf::from(file)],
+            None => config_files(None),
+        };
+
+        let mut count = 0;
+        for file in &files {
+            for problem in validate_file(file)? {
+                count += 1;
+                match problem.severity {
+                    Severity::Warning => add_warning(problem)?,
+                    Severity::Error => add_error(problem)?,
+                }
+            }
+        }
+
+        if count == 0 {
+            append_line(tr_args(
+                "No problems found in {files}",
+                &[("files", &files.iter().map(|f| f.display()).commafy())],
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Update deprecated settings in a configuration file",
+    long_about = "Rewrite deprecated settings to their current names. The original file is kept as a .bak file next to it.",
+    examples = r#"--file /etc/hubuum_cli/config.toml"#
+)]
+pub struct ConfigMigrate {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "File to migrate (default is the user configuration)",
+        path = "true"
+    )]
+    pub file: Option<String>,
+}
+
+impl CliCommand for ConfigMigrate {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let file = new
+            .file
+            .map(PathBuf::from)
+            .unwrap_or_else(get_user_config_path);
+
+        let renamed = migrate_file(&file)?;
+        if renamed.is_empty() {
+            append_line(tr_args(
+                "Nothing to migrate in {file}",
+                &[("file", &file.display())],
+            ))?;
+        }
+        for (old, new) in renamed {
+            append_line(format!("{} -> {}", old, new))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,81 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::shared::{find_class_by_name, find_namespace_by_name};
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::autocomplete::{classes, namespaces};
+use crate::errors::AppError;
+use crate::i18n::tr;
+use crate::output::append_key_value;
+use crate::session::{self, Context};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Enter a class or namespace",
+    long_about = "Make commands default to a class or namespace. With a class in use, object commands can be run without the 'object' prefix and --class, e.g. 'list' or 'info web01'. Without arguments the current context is shown.",
+    examples = r#"class Host
+namespace prod
+--clear"#
+)]
+pub struct Use {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class to use",
+        autocomplete = "classes"
+    )]
+    pub class: Option<String>,
+    #[option(
+        short = "n",
+        long = "namespace",
+        help = "Namespace to use",
+        autocomplete = "namespaces"
+    )]
+    pub namespace: Option<String>,
+    #[option(long = "clear", help = "Leave the class and namespace", flag = "true")]
+    pub clear: Option<bool>,
+}
+
+impl CliCommand for Use {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let mut new = self.new_from_tokens(tokens)?;
+
+        if new.clear.is_some() {
+            return session::set_context(Context::default());
+        }
+
+        match tokens.get_positionals() {
+            [] => {}
+            [kind, name] if kind == "class" => new.class = Some(name.clone()),
+            [kind, name] if kind == "namespace" => new.namespace = Some(name.clone()),
+            _ => {
+                return Err(AppError::ParseError(
+                    "Expected 'use class <name>' or 'use namespace <name>'".to_string(),
+                ))
+            }
+        }
+
+        let mut context = session::context();
+        if new.class.is_none() && new.namespace.is_none() {
+            append_key_value(tr("Class"), context.class.unwrap_or_default(), 10)?;
+            append_key_value(tr("Namespace"), context.namespace.unwrap_or_default(), 10)?;
+            return Ok(());
+        }
+
+        // Look the entities up, so typos are caught here rather than by every later command.
+        if let Some(class) = new.class {
+            context.class = Some(find_class_by_name(client, &class)?.name);
+        }
+        if let Some(namespace) = new.namespace {
+            context.namespace = Some(find_namespace_by_name(client, &namespace)?.name);
+        }
+        session::set_context(context)
+    }
+}
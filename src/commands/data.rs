@@ -0,0 +1,246 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, ObjectPatch, SyncClient};
+use jqesque::Jqesque;
+use jsonpath_rust::{JsonPath, JsonPathValue};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::shared::{find_class_by_name, find_object_by_name, prettify_slice_path, DataPath};
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::autocomplete::{classes, data_paths, objects_from_class};
+use crate::errors::AppError;
+use crate::formatting::format_data;
+use crate::snapshots::snapshot_before;
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show the data of an object",
+    long_about = "Show the data of an object, or the part of it matched by a JSONPath. A single string is printed without quotes, which makes the command useful in scripts. Fails if the path matches nothing.",
+    examples = r#"-c Host -n web01
+-c Host -n web01 --path $.ip
+-c Host -n web01 -p '$.interfaces[*].mac' --format json"#
+)]
+pub struct ObjectDataGet {
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: String,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "p",
+        long = "path",
+        help = "JSONPath of the data to show",
+        autocomplete = "data_paths"
+    )]
+    pub path: Option<String>,
+}
+
+impl CliCommand for ObjectDataGet {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let class = find_class_by_name(client, &query.class)?;
+        let object = find_object_by_name(client, class.id, &query.name)?;
+        let data = object.data.unwrap_or(Value::Null);
+
+        let Some(jsonpath) = &query.path else {
+            return format_data(&data);
+        };
+        let path = jsonpath
+            .parse::<JsonPath>()
+            .map_err(|e| AppError::JsonPathError(e.to_string()))?;
+
+        let mut matches = path
+            .find_slice(&data)
+            .into_iter()
+            .filter_map(|slice| match slice {
+                JsonPathValue::Slice(value, path) => {
+                    Some((prettify_slice_path(&path), value.clone()))
+                }
+                JsonPathValue::NewValue(value) => Some(("Generated".to_string(), value)),
+                JsonPathValue::NoValue => None,
+            })
+            .collect::<Vec<_>>();
+
+        match matches.len() {
+            0 => Err(AppError::EntityNotFound(format!(
+                "{} in the data of {}",
+                jsonpath, object.name
+            ))),
+            1 => format_data(&matches.remove(0).1),
+            _ => format_data(&Value::Object(matches.into_iter().collect())),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Set values in the data of an object",
+    long_about = "Set one or more values in the data of an object, given as key.path=value like --data for object modify. Keys that do not exist are created, and everything else in the data is left as it is.",
+    examples = r#"-c Host -n web01 ip=10.0.0.1
+-c Host -n web01 location.rack=B12 location.unit=4
+-c Host -n web01 --set ip=10.0.0.1 --set netmask=24
+-c Host -n web01 'tags[0]=web'"#,
+    mutating = "true"
+)]
+pub struct ObjectDataSet {
+    #[positional(index = 0, help = "Value to set as key.path=value, more may follow")]
+    #[option(
+        short = "s",
+        long = "set",
+        help = "Value to set as key.path=value, may be repeated"
+    )]
+    pub assignment: String,
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: String,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+}
+
+impl CliCommand for ObjectDataSet {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let assignments = values(tokens, "s", "set", &query.assignment)
+            .iter()
+            .map(|assignment| assignment.parse::<Jqesque>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        update_data(client, &query.class, &query.name, |data| {
+            for assignment in &assignments {
+                assignment.apply_to(data)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Remove keys from the data of an object",
+    long_about = "Remove one or more keys or array elements from the data of an object. Fails without changing anything if a path does not exist.",
+    examples = r#"-c Host -n web01 ip
+-c Host -n web01 location.rack 'tags[2]'"#,
+    mutating = "true"
+)]
+pub struct ObjectDataUnset {
+    #[positional(index = 0, help = "Path of the key to remove, more may follow")]
+    #[option(
+        short = "p",
+        long = "path",
+        help = "Path of the key to remove, may be repeated",
+        autocomplete = "data_paths"
+    )]
+    pub path: String,
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: String,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+}
+
+impl CliCommand for ObjectDataUnset {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let paths = values(tokens, "p", "path", &query.path)
+            .iter()
+            .map(|path| path.parse::<DataPath>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        update_data(client, &query.class, &query.name, |data| {
+            for path in &paths {
+                if !path.remove(data) {
+                    return Err(AppError::EntityNotFound(format!(
+                        "{} in the data of {}",
+                        path, query.name
+                    )));
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+// The values given with the option, which may be repeated, followed by the positionals.
+// Falls back to the value of the field, which the wizard may have set.
+fn values(tokens: &CommandTokenizer, short: &str, long: &str, value: &str) -> Vec<String> {
+    let values = [short, long]
+        .iter()
+        .flat_map(|key| tokens.get_all_values(key).to_vec())
+        .chain(tokens.get_positionals().iter().cloned())
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        return vec![value.to_string()];
+    }
+    values
+}
+
+// Change the data of an object and save it, showing the data as saved.
+fn update_data<F>(
+    client: &SyncClient<Authenticated>,
+    class: &str,
+    name: &str,
+    change: F,
+) -> Result<(), AppError>
+where
+    F: FnOnce(&mut Value) -> Result<(), AppError>,
+{
+    let class = find_class_by_name(client, class)?;
+    let object = find_object_by_name(client, class.id, name)?;
+
+    let mut data = object.data.clone().unwrap_or(Value::Null);
+    change(&mut data)?;
+
+    snapshot_before(client, &class.name, &object, "modify")?;
+    let result = client.objects(class.id).update(
+        object.id,
+        ObjectPatch {
+            data: Some(data),
+            ..Default::default()
+        },
+    )?;
+    format_data(&result.data.unwrap_or(Value::Null))
+}
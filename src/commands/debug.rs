@@ -0,0 +1,134 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::errors::AppError;
+use crate::i18n::{tr, tr_args};
+use crate::logger::{log_filters, reset_log_filter, set_log_filter};
+use crate::output::append_line;
+use crate::tokenizer::CommandTokenizer;
+
+const DEFAULT_LEVEL: &str = "debug";
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Log more while the CLI runs",
+    long_about = "Change what is written to the log file without restarting, optionally only for some targets (modules), and with --terminal copy the same to the terminal. The level and targets can also be given as positionals. 'debug off' goes back to RUST_LOG.",
+    examples = r#"trace hubuum_client
+--level trace --target hubuum_client --terminal
+--terminal"#
+)]
+pub struct DebugOn {
+    #[option(
+        short = "l",
+        long = "level",
+        help = "Level to log at, defaults to debug",
+        values = "trace,debug,info,warn,error"
+    )]
+    pub level: Option<String>,
+    #[option(
+        short = "t",
+        long = "target",
+        help = "Comma-separated targets to log, e.g. hubuum_client, defaults to all"
+    )]
+    pub target: Option<String>,
+    #[option(
+        long = "terminal",
+        help = "Copy the log to the terminal",
+        flag = "true"
+    )]
+    pub terminal: Option<bool>,
+}
+
+impl CliCommand for DebugOn {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let mut positionals = tokens.get_positionals().iter();
+        let level = new
+            .level
+            .or_else(|| positionals.next().cloned())
+            .unwrap_or_else(|| DEFAULT_LEVEL.to_string());
+
+        let targets = new
+            .target
+            .iter()
+            .flat_map(|targets| targets.split(','))
+            .chain(positionals.map(String::as_str))
+            .map(str::trim)
+            .filter(|target| !target.is_empty())
+            .collect::<Vec<_>>();
+        let filter = if targets.is_empty() {
+            level
+        } else {
+            targets
+                .iter()
+                .map(|target| format!("{}={}", target, level))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+
+        set_log_filter(&filter, new.terminal.is_some())?;
+        append_line(tr_args("Logging {filter}", &[("filter", &filter)]))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Log as at startup",
+    long_about = "Go back to logging what RUST_LOG asks for, and stop copying the log to the terminal."
+)]
+pub struct DebugOff {}
+
+impl CliCommand for DebugOff {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        reset_log_filter()?;
+        let filters = log_filters()?;
+        append_line(tr_args("Logging {filter}", &[("filter", &filters.file)]))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show what is logged",
+    long_about = "Show the log filter in use, and whether the log is copied to the terminal."
+)]
+pub struct DebugStatus {}
+
+impl CliCommand for DebugStatus {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let filters = log_filters()?;
+        append_line(tr_args("Logging {filter}", &[("filter", &filters.file)]))?;
+        if filters.terminal.is_some() {
+            append_line(tr("The log is copied to the terminal"))?;
+        }
+        Ok(())
+    }
+}
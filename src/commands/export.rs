@@ -0,0 +1,219 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, Object, SyncClient};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::shared::{find_class_by_name, parse_filter_expression, FilterClause};
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::autocomplete::{classes, data_paths};
+use crate::errors::AppError;
+use crate::output::{append_json, append_lines};
+use crate::tokenizer::CommandTokenizer;
+
+const UNGROUPED: &str = "ungrouped";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum InventoryFormat {
+    #[default]
+    Json,
+    Ini,
+}
+
+impl std::str::FromStr for InventoryFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(InventoryFormat::Json),
+            "ini" => Ok(InventoryFormat::Ini),
+            _ => Err(AppError::ParseError(format!(
+                "Invalid inventory format '{}', expected json or ini",
+                s
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Export objects as an Ansible inventory",
+    long_about = "Print the objects of a class as an Ansible inventory, with the object names as hosts and the top level keys of their data as host variables. With --group-by, hosts are put in groups named by the value at a data path; hosts without a value, or without --group-by, are ungrouped. The JSON format is what Ansible expects from a dynamic inventory script.",
+    examples = r#"--class Host
+--class Host --group-by data.role
+--class Host --group-by role --filter 'data__env=prod' --format ini"#
+)]
+pub struct ExportAnsibleInventory {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the hosts",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "g",
+        long = "group-by",
+        help = "Data path to group hosts by, e.g. data.role",
+        autocomplete = "data_paths"
+    )]
+    pub group_by: Option<String>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression, e.g. 'name__startswith=web,data__env=prod'"
+    )]
+    pub filter: Option<String>,
+    #[option(
+        long = "format",
+        help = "Inventory format",
+        values = "json,ini",
+        default = "json"
+    )]
+    pub inventory_format: InventoryFormat,
+}
+
+impl CliCommand for ExportAnsibleInventory {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let filters = match &new.filter {
+            Some(filter) => parse_filter_expression(filter)?,
+            None => Vec::new(),
+        };
+        let class = find_class_by_name(client, &new.class)?;
+
+        let mut query = client.objects(class.id).find();
+        for filter in filters.iter().map(FilterClause::query_filter) {
+            query = query.add_filter(&filter.key, filter.operator, &filter.value);
+        }
+        let objects = query.execute()?;
+
+        let inventory = Inventory::new(&objects, new.group_by.as_deref());
+        match new.inventory_format {
+            InventoryFormat::Json => append_json(inventory.to_json()),
+            InventoryFormat::Ini => append_lines(&inventory.to_ini()),
+        }
+    }
+}
+
+/// Hosts by group, and the variables of each host.
+struct Inventory {
+    groups: BTreeMap<String, BTreeSet<String>>,
+    hostvars: BTreeMap<String, Map<String, Value>>,
+}
+
+impl Inventory {
+    fn new(objects: &[Object], group_by: Option<&str>) -> Self {
+        let mut groups = BTreeMap::<String, BTreeSet<String>>::new();
+        let mut hostvars = BTreeMap::new();
+
+        for object in objects {
+            let data = object.data.clone().unwrap_or(Value::Null);
+            let names = group_by
+                .map(|path| group_names(&data, path))
+                .unwrap_or_default();
+            if names.is_empty() {
+                groups
+                    .entry(UNGROUPED.to_string())
+                    .or_default()
+                    .insert(object.name.clone());
+            }
+            for name in names {
+                groups.entry(name).or_default().insert(object.name.clone());
+            }
+
+            let vars = match data {
+                Value::Object(map) => map,
+                _ => Map::new(),
+            };
+            hostvars.insert(object.name.clone(), vars);
+        }
+
+        Inventory { groups, hostvars }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut inventory = Map::new();
+        inventory.insert("_meta".to_string(), json!({ "hostvars": self.hostvars }));
+        inventory.insert(
+            "all".to_string(),
+            json!({ "children": self.groups.keys().collect::<Vec<_>>() }),
+        );
+        for (group, hosts) in &self.groups {
+            inventory.insert(group.clone(), json!({ "hosts": hosts }));
+        }
+        Value::Object(inventory)
+    }
+
+    fn to_ini(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        for (group, hosts) in &self.groups {
+            if !lines.is_empty() {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", group));
+            for host in hosts {
+                let vars = self.hostvars[host]
+                    .iter()
+                    .map(|(key, value)| format!("{}={}", key, ini_value(value)))
+                    .collect::<Vec<_>>();
+                lines.push(
+                    std::iter::once(host.clone())
+                        .chain(vars)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                );
+            }
+        }
+        lines
+    }
+}
+
+// The groups of a host are the values at `path` in its data, where `data.` in front of the
+// path is optional. An array puts the host in one group per element.
+fn group_names(data: &Value, path: &str) -> Vec<String> {
+    let path = path.strip_prefix("data.").unwrap_or(path);
+    let value = data.pointer(&format!("/{}", path.replace('.', "/")));
+    let values = match value {
+        Some(Value::Array(values)) => values.iter().collect::<Vec<_>>(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(value) => vec![value],
+    };
+    values
+        .into_iter()
+        .map(|value| match value {
+            Value::String(s) => group_name(s),
+            value => group_name(&value.to_string()),
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+// Ansible group names may only contain letters, digits and underscores.
+fn group_name(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+// Strings are quoted if they contain whitespace, other values are written as JSON, which
+// Ansible reads as the value of the same type.
+fn ini_value(value: &Value) -> String {
+    match value {
+        Value::String(s) if s.chars().any(char::is_whitespace) => {
+            format!("'{}'", s.replace('\'', "\\'"))
+        }
+        Value::String(s) => s.clone(),
+        Value::Array(_) | Value::Object(_) => format!("'{}'", value),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::{HashMap, HashSet};
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, Class, ClassRelation, Object, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::shared::{find_class_by_name, find_entities_by_ids, find_object_by_name};
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::api::retry_api;
+use crate::autocomplete::{classes, objects_from_class};
+use crate::errors::AppError;
+use crate::graph::{Graph, GraphFormat};
+use crate::output::append_lines;
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Graph the relations between classes",
+    long_about = "Print the classes and the relations between them as a Graphviz (dot) or Mermaid graph, e.g. for documenting the data model.",
+    examples = r#"--format dot
+--format mermaid"#,
+    cancellable = "true"
+)]
+pub struct ClassGraph {
+    #[option(
+        long = "format",
+        help = "Graph format",
+        values = "dot,mermaid",
+        default = "dot"
+    )]
+    pub graph_format: GraphFormat,
+}
+
+impl CliCommand for ClassGraph {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let classes = retry_api(|| client.classes().find().execute())?;
+        let relations = retry_api(|| client.class_relation().find().execute())?;
+
+        let mut graph = Graph::new();
+        for class in &classes {
+            graph.add_node(&class_node(class.id), &class.name);
+        }
+        for relation in &relations {
+            graph.add_edge(
+                &class_node(relation.from_hubuum_class_id),
+                &class_node(relation.to_hubuum_class_id),
+            );
+        }
+
+        append_lines(&graph.render(new.graph_format))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Graph the relations of an object",
+    long_about = "Print an object and the objects related to it, up to --depth relations away, as a Graphviz (dot) or Mermaid graph.",
+    examples = r#"--class Host --name web01
+--class Host --name web01 --depth 1 --format mermaid"#,
+    cancellable = "true"
+)]
+pub struct ObjectGraph {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: String,
+    #[option(
+        short = "d",
+        long = "depth",
+        help = "How many relations away to follow",
+        default = "3"
+    )]
+    pub depth: usize,
+    #[option(
+        long = "format",
+        help = "Graph format",
+        values = "dot,mermaid",
+        default = "dot"
+    )]
+    pub graph_format: GraphFormat,
+}
+
+impl CliCommand for ObjectGraph {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let class = find_class_by_name(client, &new.class)?;
+        let root = find_object_by_name(client, class.id, &new.name)?;
+
+        let classes = retry_api(|| client.classes().find().execute())?
+            .into_iter()
+            .map(|class| (class.id, class))
+            .collect::<HashMap<_, _>>();
+        let class_relations = retry_api(|| client.class_relation().find().execute())?
+            .into_iter()
+            .map(|relation| (relation.id, relation))
+            .collect::<HashMap<_, _>>();
+
+        let mut graph = Graph::new();
+        graph.add_node(&object_node(root.id), &object_label(&root, &classes));
+        let mut seen = HashSet::from([root.id]);
+        let mut frontier = vec![root];
+
+        for _ in 0..new.depth {
+            // The objects found at this depth, by class, as objects are looked up per class.
+            let mut next = HashMap::<i32, Vec<i32>>::new();
+            for object in &frontier {
+                for (other, class_id) in related_objects(client, object, &class_relations)? {
+                    graph.add_edge(&object_node(object.id), &object_node(other));
+                    if seen.insert(other) {
+                        next.entry(class_id).or_default().push(other);
+                    }
+                }
+            }
+
+            frontier = Vec::new();
+            for (class_id, ids) in next {
+                let objects = find_entities_by_ids(&client.objects(class_id), &ids, |id| *id)?;
+                frontier.extend(objects.into_values());
+            }
+            for object in &frontier {
+                graph.add_node(&object_node(object.id), &object_label(object, &classes));
+            }
+            if frontier.is_empty() {
+                break;
+            }
+        }
+
+        append_lines(&graph.render(new.graph_format))
+    }
+}
+
+/// The ids and classes of the objects related to `object`. A relation does not say which of
+/// its classes the other object is in, but it is the one that is not the class of `object`.
+fn related_objects(
+    client: &SyncClient<Authenticated>,
+    object: &Object,
+    class_relations: &HashMap<i32, ClassRelation>,
+) -> Result<Vec<(i32, i32)>, AppError> {
+    let mut related = Vec::new();
+    for filter in ["from_objects", "to_objects"] {
+        let relations = retry_api(|| {
+            client
+                .object_relation()
+                .find()
+                .add_filter_equals(filter, object.id)
+                .execute()
+        })?;
+        for relation in relations {
+            let Some(class_relation) = class_relations.get(&relation.class_relation_id) else {
+                continue;
+            };
+            let other = if relation.from_hubuum_object_id == object.id {
+                relation.to_hubuum_object_id
+            } else {
+                relation.from_hubuum_object_id
+            };
+            let class_id = if class_relation.from_hubuum_class_id == object.hubuum_class_id {
+                class_relation.to_hubuum_class_id
+            } else {
+                class_relation.from_hubuum_class_id
+            };
+            related.push((other, class_id));
+        }
+    }
+    Ok(related)
+}
+
+fn class_node(id: i32) -> String {
+    format!("c{}", id)
+}
+
+fn object_node(id: i32) -> String {
+    format!("o{}", id)
+}
+
+fn object_label(object: &Object, classes: &HashMap<i32, Class>) -> String {
+    match classes.get(&object.hubuum_class_id) {
+        Some(class) => format!("{} ({})", object.name, class.name),
+        None => object.name.clone(),
+    }
+}
@@ -4,14 +4,18 @@ use hubuum_client::{
 };
 use serde::{Deserialize, Serialize};
 
+use super::shared::ListFilter;
 use super::CliCommand;
 use super::{CliCommandInfo, CliOption};
 
+use crate::api::retry_api;
+use crate::autocomplete::groups;
 use crate::errors::AppError;
-use crate::formatting::{OutputFormatter, OutputFormatterWithPadding};
+use crate::formatting::{format_count, OutputFormatter, OutputFormatterWithPadding};
 use crate::tokenizer::CommandTokenizer;
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(mutating = "true")]
 pub struct GroupNew {
     #[option(short = "g", long = "groupname", help = "Name of the group")]
     pub groupname: String,
@@ -44,8 +48,14 @@ impl CliCommand for GroupNew {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct GroupList {
-    #[option(short = "g", long = "groupname", help = "Name of the group")]
+    #[option(
+        short = "g",
+        long = "groupname",
+        help = "Name of the group",
+        autocomplete = "groups"
+    )]
     pub name: String,
     #[option(
         short = "gs",
@@ -61,6 +71,18 @@ pub struct GroupList {
     pub name_endswith: String,
     #[option(short = "d", long = "description", help = "Description of the group")]
     pub description: String,
+    #[option(
+        long = "count",
+        help = "Only show the number of matches",
+        flag = "true"
+    )]
+    pub count: Option<bool>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression, e.g. 'name__startswith=web,data__env=prod'"
+    )]
+    pub filter: Option<String>,
 }
 
 impl IntoResourceFilter<Group> for &GroupList {
@@ -98,7 +120,6 @@ impl IntoResourceFilter<Group> for &GroupList {
                 operator: FilterOperator::IContains { is_negated: false },
             });
         }
-
         filters
     }
 }
@@ -110,7 +131,13 @@ impl CliCommand for GroupList {
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
         let new = self.new_from_tokens(tokens)?;
-        let groups = client.groups().filter(&new)?;
+        let filter = ListFilter::new(&new, new.filter.as_deref())?;
+        let groups = retry_api(|| client.groups().filter(&filter))?;
+
+        if new.count.is_some() {
+            return format_count(groups.len());
+        }
+
         groups.format()?;
 
         Ok(())
@@ -1,9 +1,9 @@
-use std::sync::Arc;
-
 use cli_command_derive::CliCommand;
 use hubuum_client::{Authenticated, SyncClient};
 
 use crate::errors::AppError;
+use crate::i18n::tr_args;
+use crate::output::{add_warning, append_line};
 use crate::tokenizer::CommandTokenizer;
 
 use super::CliCommand;
@@ -11,26 +11,81 @@ use super::{CliCommandInfo, CliOption};
 
 #[allow(dead_code)]
 #[derive(Debug, Default, CliCommand)]
+#[command_info(
+    about = "Show the available commands",
+    long_about = "Show the command tree with what each command does. Give a scope to only show the commands in it, or a command to show its help. With --search, list the commands whose help mentions a keyword.",
+    examples = r#"object
+class create
+--search schema"#
+)]
 pub struct Help {
     #[option(short = "t", long = "tree", help = "Command tree", flag = "true")]
     pub tree: Option<bool>,
+    #[option(
+        short = "s",
+        long = "search",
+        help = "Find commands whose help mentions this keyword"
+    )]
+    pub search: Option<String>,
 }
 
 impl CliCommand for Help {
     fn execute(
         &self,
-        client: &SyncClient<Authenticated>,
+        _client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let options = tokens.get_options();
-        if options.get("tree").is_some() {
-            println!(
-                "{}\n",
-                crate::commands::build_repl_commands(Arc::new(client.clone())).show_tree()
-            );
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let commands = crate::commands::build_repl_commands();
+
+        if let Some(keyword) = new.search {
+            let found = commands.search(&keyword);
+            if found.is_empty() {
+                return add_warning(tr_args(
+                    "No commands mention '{keyword}'",
+                    &[("keyword", &keyword)],
+                ));
+            }
+            let width = found
+                .iter()
+                .map(|(path, _)| path.chars().count())
+                .max()
+                .unwrap_or(0);
+            for (path, command) in found {
+                append_line(format!(
+                    "{:width$}  {}",
+                    path,
+                    command.about().unwrap_or_default()
+                ))?;
+            }
             return Ok(());
         }
 
+        // Walk the positionals down the tree, e.g. `help object` or `help object list`.
+        let mut scope = &commands;
+        let mut context = Vec::new();
+        for part in tokens.get_positionals() {
+            if let Some(inner) = scope.get_scope(part) {
+                context.push(part.clone());
+                scope = inner;
+            } else if let Some(command) = scope.get_command(part) {
+                return command.help(part, &context);
+            } else {
+                return Err(AppError::CommandNotFound(format!(
+                    "Command not found: {}",
+                    part
+                )));
+            }
+        }
+
+        if !context.is_empty() {
+            append_line(context.join(" "))?;
+        }
+        append_line(scope.show_tree().trim_end())?;
         Ok(())
     }
 }
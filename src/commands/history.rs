@@ -0,0 +1,191 @@
+use std::io::Write;
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::errors::AppError;
+use crate::history::{command_log, parse_since, readline_history};
+use crate::i18n::tr_args;
+use crate::models::OutputFormat;
+use crate::output::{append_json, append_line, get_format};
+use crate::tokenizer::CommandTokenizer;
+
+// How many commands `history` shows by default.
+
+#[derive(Debug, Serialize, Clone)]
+struct HistoryEntry {
+    index: usize,
+    line: String,
+}
+
+// Show history entries, numbered so they can be run again with `!<index>`.
+fn show_history(entries: Vec<HistoryEntry>) -> Result<(), AppError> {
+    match get_format()? {
+        OutputFormat::Text => {
+            let width = entries
+                .last()
+                .map_or(1, |entry| entry.index.to_string().len());
+            for entry in entries {
+                append_line(format!("{:>width$}  {}", entry.index, entry.line))?;
+            }
+            Ok(())
+        }
+        _ => append_json(&entries),
+    }
+}
+
+fn numbered_history() -> Result<Vec<HistoryEntry>, AppError> {
+    Ok(readline_history()?
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| HistoryEntry { index: i + 1, line })
+        .collect())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show recent commands",
+    long_about = "Show the most recent commands, numbered. Run a command again with !<number>, the previous command with !! and the last command starting with some text with !<text>.",
+    examples = r#"--last 50"#
+)]
+pub struct HistoryList {
+    #[option(
+        short = "l",
+        long = "last",
+        help = "Number of commands to show",
+        default = "25"
+    )]
+    pub last: usize,
+}
+
+impl CliCommand for HistoryList {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let mut entries = numbered_history()?;
+        entries.drain(..entries.len().saturating_sub(new.last));
+        show_history(entries)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Search the command history",
+    long_about = "Show the commands in the history containing a term, ignoring case, numbered so they can be run again with !<number>.",
+    examples = r#"schema
+--term 'object create'"#
+)]
+pub struct HistorySearch {
+    #[option(short = "t", long = "term", help = "Text to search for")]
+    pub term: Option<String>,
+}
+
+impl CliCommand for HistorySearch {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let term = new
+            .term
+            .or_else(|| {
+                let positionals = tokens.get_positionals();
+                (!positionals.is_empty()).then(|| positionals.join(" "))
+            })
+            .ok_or_else(|| AppError::MissingOptions(vec!["term".to_string()]))?
+            .to_lowercase();
+
+        let entries = numbered_history()?
+            .into_iter()
+            .filter(|entry| entry.line.to_lowercase().contains(&term))
+            .collect();
+        show_history(entries)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Export command history as a script",
+    long_about = "Write commands from the history to a file that can be run again with --source, turning an interactive session into a repeatable change.",
+    examples = r#"--since '1 hour ago' --file change.hub
+--since 2024-05-01 --file change.hub --successful --mutating"#
+)]
+pub struct HistoryExport {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "File to write the script to",
+        path = "true"
+    )]
+    pub file: String,
+    #[option(
+        short = "s",
+        long = "since",
+        help = "Only export commands run after this time, e.g. '1 hour ago'"
+    )]
+    pub since: Option<String>,
+    #[option(
+        long = "successful",
+        help = "Only export commands that succeeded",
+        flag = "true"
+    )]
+    pub successful: Option<bool>,
+    #[option(
+        long = "mutating",
+        help = "Only export commands that change something on the server",
+        flag = "true"
+    )]
+    pub mutating: Option<bool>,
+}
+
+impl CliCommand for HistoryExport {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let since = new.since.as_deref().map(parse_since).transpose()?;
+
+        let entries = command_log()?
+            .into_iter()
+            .filter(|e| since.is_none_or(|since| e.at >= since))
+            .filter(|e| new.successful.is_none() || e.success)
+            .filter(|e| new.mutating.is_none() || e.is_mutating())
+            // Exporting the history is not part of the change.
+            .filter(|e| !e.line.starts_with("history"))
+            .collect::<Vec<_>>();
+
+        let mut file = std::fs::File::create(&new.file)?;
+        writeln!(
+            file,
+            "# Exported from hubuum-cli at {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        )?;
+        for entry in &entries {
+            writeln!(file, "# {}", entry.at.format("%Y-%m-%d %H:%M:%S"))?;
+            writeln!(file, "{}", entry.line)?;
+        }
+
+        append_line(tr_args(
+            "Exported {count} commands to {file}",
+            &[("count", &entries.len()), ("file", &new.file)],
+        ))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::api::{new_client, post_json};
+use crate::auth::{self, AuthProvider, PasswordProvider};
+use crate::autocomplete::clear_cache;
+use crate::config::{get_config, set_config};
+use crate::errors::AppError;
+use crate::files::remove_tokens_from_tokenfile;
+use crate::i18n::{tr, tr_args};
+use crate::lookup_cache::clear_lookup_cache;
+use crate::offline;
+use crate::output::{add_warning, append_line};
+use crate::session;
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Log in as another user or to another server",
+    long_about = "Log in without restarting the CLI. The password is prompted for (or read by the configured providers), and all following commands use the new login.",
+    examples = r#"-u admin
+admin --hostname hubuum.example.com --port 443"#
+)]
+pub struct Login {
+    #[option(short = "u", long = "username", help = "Username to log in as")]
+    pub username: Option<String>,
+    #[option(short = "H", long = "hostname", help = "Server to log in to")]
+    pub hostname: Option<String>,
+    #[option(short = "p", long = "port", help = "Port of the server")]
+    pub port: Option<u16>,
+}
+
+impl CliCommand for Login {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let mut config = get_config()?;
+
+        if let Some(username) = new
+            .username
+            .or_else(|| tokens.get_positionals().first().cloned())
+        {
+            config.server.username = username;
+        }
+        if let Some(hostname) = new.hostname {
+            config.server.hostname = hostname;
+        }
+        if let Some(port) = new.port {
+            config.server.port = port;
+        }
+
+        // Logging in again is about getting a fresh login, so saved tokens are skipped.
+        let mut providers: Vec<Box<dyn AuthProvider>> = auth::providers_from_config(&config.auth)?
+            .into_iter()
+            .filter(|provider| provider.name() != "token")
+            .collect();
+        if providers.is_empty() {
+            providers.push(Box::new(PasswordProvider));
+        }
+
+        let client = auth::login(
+            new_client(&config)?,
+            &config.server.username,
+            &config.server.hostname,
+            &providers,
+        )?;
+
+        // Names and entities cached earlier may not be visible to the new user.
+        clear_cache();
+        clear_lookup_cache();
+        session::set_client(client)?;
+        offline::set_offline(false);
+        append_line(tr_args(
+            "Logged in as {username} @ {hostname}",
+            &[
+                ("username", &config.server.username),
+                ("hostname", &config.server.hostname),
+            ],
+        ))?;
+        set_config(config)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Log out",
+    long_about = "Revoke the token of the current login and remove it from the token file. Use 'login' to log in again."
+)]
+pub struct Logout {}
+
+impl CliCommand for Logout {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let token = client.get_token().to_string();
+
+        if let Err(err) = post_json(client, "/api/v0/auth/logout", None) {
+            add_warning(format!("Failed to revoke the token on the server: {}", err))?;
+        }
+        remove_tokens_from_tokenfile(|entry| entry.token == token)?;
+        session::clear_client()?;
+
+        append_line(tr("Logged out"))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::errors::AppError;
+use crate::formatting::OutputFormatterWithPadding;
+use crate::i18n::{tr, tr_args};
+use crate::manifest::{manifest_changes, Manifest};
+use crate::output::{add_error, append_line, flush_output};
+use crate::plan::confirm;
+use crate::progress::{finish_progress, progress_bar};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Make the server match a manifest",
+    long_about = "Read a YAML manifest describing namespaces, classes and objects, show the changes needed for the server to match it, and make them. Fields left out of the manifest are not changed. Namespaces are created with the group given as their owner. With --prune, classes that still have objects are only deleted with --cascade, as their objects are deleted with them. Changes that fail are listed after the rest are made, and the command then fails.",
+    examples = r#"-f manifest.yaml
+--file manifest.yaml --yes
+--file manifest.yaml --prune --cascade"#,
+    mutating = "true"
+)]
+pub struct ManifestApply {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "YAML manifest with namespaces, classes and objects",
+        path = "true"
+    )]
+    pub file: String,
+    #[option(
+        long = "prune",
+        help = "Delete objects in the classes of the manifest, and classes in its namespaces, that it does not list",
+        flag = "true"
+    )]
+    pub prune: Option<bool>,
+    #[option(
+        long = "cascade",
+        help = "Allow --prune to delete classes that have objects, and the objects with them",
+        flag = "true",
+        requires = "prune"
+    )]
+    pub cascade: Option<bool>,
+    #[option(
+        short = "y",
+        long = "yes",
+        help = "Apply the changes without asking",
+        flag = "true"
+    )]
+    pub yes: Option<bool>,
+}
+
+impl CliCommand for ManifestApply {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let manifest = Manifest::from_file(&new.file)?;
+        let changes = manifest_changes(client, &manifest, new.prune.is_some())?;
+
+        if changes.is_empty() {
+            append_line(tr("The server matches the manifest"))?;
+            return Ok(());
+        }
+
+        changes.plan.format(0)?;
+        flush_output()?;
+
+        if changes.cascaded() > 0 && new.cascade.is_none() {
+            return Err(AppError::InvalidOption(tr_args(
+                "Pruning would delete {count} objects along with their classes, add --cascade to allow it",
+                &[("count", &changes.cascaded())],
+            )));
+        }
+
+        if new.yes.is_none() && !confirm(&tr("Apply this plan?"))? {
+            append_line(tr("Plan not applied"))?;
+            return Ok(());
+        }
+
+        let progress = progress_bar(changes.len(), "Applying");
+        let failures = changes.apply(client, || progress.inc(1))?;
+        finish_progress(&progress);
+
+        for failure in &failures {
+            add_error(failure)?;
+        }
+
+        append_line(tr_args(
+            "Made {done} of {total} changes",
+            &[
+                ("done", &(changes.len() - failures.len())),
+                ("total", &changes.len()),
+            ],
+        ))?;
+        if !failures.is_empty() {
+            return Err(AppError::Quiet);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show the changes a manifest would make",
+    long_about = "Read a YAML manifest like apply does, and show the creates, updates (field by field) and, with --prune, deletes needed for the server to match it, without changing anything.",
+    examples = r#"-f manifest.yaml
+--file manifest.yaml --prune"#
+)]
+pub struct ManifestPlan {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "YAML manifest with namespaces, classes and objects",
+        path = "true"
+    )]
+    pub file: String,
+    #[option(
+        long = "prune",
+        help = "Include deletes of the objects and classes the manifest does not list",
+        flag = "true"
+    )]
+    pub prune: Option<bool>,
+}
+
+impl CliCommand for ManifestPlan {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let manifest = Manifest::from_file(&new.file)?;
+        let changes = manifest_changes(client, &manifest, new.prune.is_some())?;
+
+        if changes.is_empty() {
+            append_line(tr("The server matches the manifest"))?;
+            return Ok(());
+        }
+        changes.plan.format(0)
+    }
+}
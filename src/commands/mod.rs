@@ -2,28 +2,77 @@ use hubuum_client::{Authenticated, SyncClient};
 use log::trace;
 use std::any::TypeId;
 
+mod audit;
+mod backup;
 mod builder;
+mod cache;
 mod class;
+mod completions;
+mod config;
+mod context;
+mod data;
+mod debug;
+mod export;
+mod graph;
 mod group;
 mod help;
+mod history;
+mod login;
+mod manifest;
 mod namespace;
+mod notify;
 mod object;
+mod permissions;
+mod record;
 mod relations;
+mod sandbox;
+mod search;
+mod server;
 mod shared;
+mod stats;
+mod template;
+mod token;
+mod trace;
 mod user;
 
 use crate::{output::append_line, CommandList};
 
+pub use self::config::*;
+pub use audit::*;
+pub use backup::*;
 pub use builder::build_repl_commands;
+pub use cache::*;
 pub use class::*;
+pub use completions::*;
+pub use context::*;
+pub use data::*;
+pub use debug::*;
+pub use export::*;
+pub use graph::*;
 pub use group::*;
 #[allow(unused_imports)]
 pub use help::Help;
+pub use history::*;
+pub use login::*;
+pub use manifest::*;
 pub use namespace::*;
+pub use notify::*;
 pub use object::*;
+pub use permissions::*;
+pub use record::*;
 pub use relations::*;
+pub use sandbox::*;
+pub use search::*;
+pub use server::*;
+pub use stats::*;
+pub use template::*;
+pub use token::*;
+pub use trace::*;
 pub use user::*;
 
+use crate::formatting::{hanging, terminal_width, words, wrap_text};
+use crate::fuzzy::closest_matches;
+use crate::i18n::{tr, tr_args};
 use crate::{errors::AppError, tokenizer::CommandTokenizer};
 
 #[allow(dead_code)]
@@ -38,6 +87,23 @@ pub struct CliOption {
     pub field_type_help: String,
     pub required: bool,
     pub autocomplete: Option<fn(&CommandList, &str, &[String]) -> Vec<String>>,
+    /// The name of the autocomplete function, shown in the help of the option.
+    pub completes: Option<String>,
+    /// The values the option accepts, if it only accepts a fixed set.
+    pub values: Vec<String>,
+    /// Whether the option takes a local file path.
+    pub path: bool,
+    /// Whether the value is a secret, like a password, which is never written to disk.
+    pub secret: bool,
+    /// Whether the option defaults to the class or namespace entered with `use`. Only set
+    /// on options that pick or scope entities, never on ones that move an entity.
+    pub context: bool,
+    /// The names of the options this option can not be given together with.
+    pub conflicts_with: Vec<String>,
+    /// The names of the options that must be given with this option.
+    pub requires: Vec<String>,
+    /// The value the option has when it is not given.
+    pub default: Option<String>,
 }
 
 impl CliOption {
@@ -48,6 +114,38 @@ impl CliOption {
     pub fn long_without_dashes(&self) -> Option<String> {
         self.long.as_ref().map(|l| l[2..].to_string())
     }
+
+    /// Whether the option was given, by its short or long name.
+    pub fn is_given(&self, tokens: &CommandTokenizer) -> bool {
+        let options = tokens.get_options();
+        self.short_without_dash()
+            .is_some_and(|short| options.contains_key(&short))
+            || self
+                .long_without_dashes()
+                .is_some_and(|long| options.contains_key(&long))
+    }
+
+    /// The name the option is shown by in messages, the long one if it has one.
+    pub fn display_name(&self) -> String {
+        self.long
+            .clone()
+            .or_else(|| self.short.clone())
+            .unwrap_or_else(|| self.name.clone())
+    }
+}
+
+/// Options every command gets from the derive macro.
+pub const GLOBAL_OPTIONS: [&str; 6] = ["help", "format", "yaml", "copy", "verbose", "quiet"];
+
+/// An argument that can be given without its option, by its position after the command,
+/// e.g. the name in `object info MyObject`.
+#[derive(Debug)]
+pub struct CliPositional {
+    /// The name of the option the argument stands in for.
+    pub name: String,
+    pub index: usize,
+    pub help: String,
+    pub required: bool,
 }
 
 #[allow(dead_code)]
@@ -57,50 +155,215 @@ pub trait CliCommandInfo {
     fn about(&self) -> Option<String>;
     fn long_about(&self) -> Option<String>;
     fn examples(&self) -> Option<String>;
+    /// Whether the command changes something on the server, from `mutating = "true"` in
+    /// its `command_info`. These are audited and invalidate the caches.
+    fn is_mutating(&self) -> bool;
+    /// Whether Ctrl-C may leave the command running in the background and return to the
+    /// prompt at once, from `cancellable = "true"` in its `command_info`. Only for commands
+    /// that change nothing, as the request in flight still finishes.
+    fn is_cancellable(&self) -> bool;
+    fn positionals(&self) -> Vec<CliPositional>;
 }
 
-pub trait CliCommand: CliCommandInfo {
+pub trait CliCommand: CliCommandInfo + Send + Sync {
     fn execute(
         &self,
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError>;
 
+    /// Run the command without being logged in. Only commands that don't need the server,
+    /// like `login` and `help`, implement this.
+    fn execute_unauthenticated(&self, _tokens: &CommandTokenizer) -> Result<(), AppError> {
+        Err(AppError::AuthError(tr(
+            "Not logged in, use 'login' to log in",
+        )))
+    }
+
+    /// Whether `--format` is an option of the command itself rather than the output format.
+    fn has_own_format(&self) -> bool {
+        self.options()
+            .iter()
+            .any(|opt| opt.name != "format" && opt.long.as_deref() == Some("--format"))
+    }
+
     fn validate(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.validate_unknown_options(tokens)?;
         self.validate_not_both_short_and_long_set(tokens)?;
+        self.validate_option_relations(tokens)?;
         self.validate_missing_options(tokens)?;
         self.validate_flag_options(tokens)?;
         Ok(())
     }
 
+    /// Options the command does not have are typos more often than not, so they are
+    /// rejected with the closest options the command does have, and the options it has.
+    fn validate_unknown_options(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let options = self.options();
+        let known = options
+            .iter()
+            .flat_map(|opt| [opt.short.clone(), opt.long.clone()])
+            .flatten()
+            .collect::<Vec<_>>();
+
+        let mut unknown = tokens
+            .get_options()
+            .keys()
+            .filter(|key| {
+                !options.iter().any(|opt| {
+                    opt.short_without_dash().as_deref() == Some(key.as_str())
+                        || opt.long_without_dashes().as_deref() == Some(key.as_str())
+                })
+            })
+            .map(|key| {
+                if key.chars().count() == 1 {
+                    format!("-{}", key)
+                } else {
+                    format!("--{}", key)
+                }
+            })
+            .collect::<Vec<_>>();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort();
+
+        let mut parts = Vec::new();
+        for given in &unknown {
+            let suggestions = closest_matches(given, known.clone());
+            if suggestions.is_empty() {
+                parts.push(given.clone());
+            } else {
+                parts.push(format!(
+                    "{}. {}",
+                    given,
+                    tr_args(
+                        "Did you mean {suggestion}?",
+                        &[("suggestion", &suggestions.join(" or "))]
+                    )
+                ));
+            }
+        }
+
+        let valid = options
+            .iter()
+            .filter_map(|opt| opt.long.clone().or_else(|| opt.short.clone()))
+            .collect::<Vec<_>>();
+        Err(AppError::InvalidOption(format!(
+            "{}\n{}",
+            parts.join("\n"),
+            tr_args(
+                "Valid options: {options}",
+                &[("options", &valid.join(", "))]
+            )
+        )))
+    }
+
+    /// All the required options that were not given, and required positionals given
+    /// neither by position nor by their option, are reported at once.
     fn validate_missing_options(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
-        let tokenpairs = tokens.get_options();
-        let mut missing_options = Vec::new();
+        let options = self.options();
+        let positionals = self.positionals();
 
-        // Check if either opt.short or opt.long is a key in tokenpairs
-        for opt in self.options() {
-            if !opt.required {
+        let mut missing_options = Vec::new();
+        for opt in &options {
+            let positional = positionals.iter().find(|p| p.name == opt.name);
+            let required = opt.required || positional.is_some_and(|p| p.required);
+            if !required || opt.is_given(tokens) {
                 continue;
             }
+            if positional.is_some_and(|p| tokens.get_positionals().len() > p.index) {
+                continue;
+            }
+            trace!("Missing option: {}", opt.name);
+            missing_options.push(opt.name.clone());
+        }
+
+        if !missing_options.is_empty() {
+            return Err(AppError::MissingOptions(missing_options));
+        }
+
+        Ok(())
+    }
+
+    /// A one line synopsis of the command, e.g. `object info <name> --class <class>
+    /// [--data]`, with optional options in brackets. Options that can be given by position
+    /// are shown as positionals.
+    fn usage(&self, command: &str) -> String {
+        self.usage_parts(command).join(" ")
+    }
+
+    /// The parts of the usage line, each option with its value kept together.
+    fn usage_parts(&self, command: &str) -> Vec<String> {
+        let mut positionals = self.positionals();
+        positionals.sort_by_key(|p| p.index);
+
+        let mut parts = vec![command.to_string()];
+        for positional in &positionals {
+            if positional.required {
+                parts.push(format!("<{}>", positional.name));
+            } else {
+                parts.push(format!("[<{}>]", positional.name));
+            }
+        }
 
-            if let Some(short) = &opt.short_without_dash() {
-                if tokenpairs.contains_key(short) {
-                    continue;
+        let options = self
+            .options()
+            .into_iter()
+            .filter(|opt| !GLOBAL_OPTIONS.contains(&opt.name.as_str()))
+            .filter(|opt| !positionals.iter().any(|p| p.name == opt.name));
+        let (required, optional): (Vec<_>, Vec<_>) = options.partition(|opt| opt.required);
+        for opt in required.iter().chain(optional.iter()) {
+            let part = if opt.flag {
+                opt.display_name()
+            } else {
+                format!("{} <{}>", opt.display_name(), opt.name)
+            };
+            if opt.required {
+                parts.push(part);
+            } else {
+                parts.push(format!("[{}]", part));
+            }
+        }
+        parts
+    }
+
+    /// Options given together with an option they conflict with, or without an option
+    /// they require, are all reported at once.
+    fn validate_option_relations(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let options = self.options();
+        let find = |name: &String| options.iter().find(|opt| &opt.name == name);
+
+        let mut problems = Vec::new();
+        for opt in options.iter().filter(|opt| opt.is_given(tokens)) {
+            for other in opt.conflicts_with.iter().filter_map(find) {
+                // A conflict declared on both options is reported once.
+                let reported = other.conflicts_with.contains(&opt.name) && other.name < opt.name;
+                if other.is_given(tokens) && !reported {
+                    problems.push(tr_args(
+                        "{option} can not be used with {other}",
+                        &[
+                            ("option", &opt.display_name()),
+                            ("other", &other.display_name()),
+                        ],
+                    ));
                 }
-                trace!("Short not found: {}", short);
             }
-            if let Some(long) = &opt.long_without_dashes() {
-                if tokenpairs.contains_key(long) {
-                    continue;
+            for other in opt.requires.iter().filter_map(find) {
+                if !other.is_given(tokens) {
+                    problems.push(tr_args(
+                        "{option} requires {other}",
+                        &[
+                            ("option", &opt.display_name()),
+                            ("other", &other.display_name()),
+                        ],
+                    ));
                 }
-                trace!("Long not found: {}", long);
             }
-
-            missing_options.push(opt.name.clone());
         }
 
-        if !missing_options.is_empty() {
-            return Err(AppError::MissingOptions(missing_options))?;
+        if !problems.is_empty() {
+            return Err(AppError::InvalidOption(problems.join("\n")));
         }
 
         Ok(())
@@ -161,19 +424,51 @@ pub trait CliCommand: CliCommandInfo {
     }
 
     fn help(&self, command_name: &String, context: &[String]) -> Result<(), AppError> {
-        let mut help = String::new();
-        let fq_name = format!("\n{} {}", context.join(" "), command_name);
-        if let Some(about) = self.about() {
-            help.push_str(&format!("{} - {} \n\n", fq_name, about));
-        } else {
-            help.push_str(&format!("{}\n\n", fq_name));
+        let width = terminal_width();
+        let command = context
+            .iter()
+            .chain(std::iter::once(command_name))
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut help = vec![String::new()];
+        match self.about() {
+            Some(about) => help.push(format!("{} - {}", command, about)),
+            None => help.push(command.clone()),
         }
+        help.push(String::new());
         if let Some(long_about) = self.long_about() {
-            help.push_str(&format!("{}\n\n", long_about));
+            help.extend(wrap_text(&long_about, width));
+            help.push(String::new());
         }
+
+        let usage = format!("{}: ", tr("Usage"));
+        let parts = self.usage_parts(&command);
+        help.extend(hanging(&usage, &parts, width));
+        help.push(String::new());
+
+        let mut positionals = self.positionals();
+        if !positionals.is_empty() {
+            positionals.sort_by_key(|p| p.index);
+            help.push(format!("{}:", tr("Arguments")));
+            let name_width = positionals.iter().map(|p| p.name.len()).max().unwrap_or(0) + 2;
+            for positional in &positionals {
+                let optional = if positional.required {
+                    ""
+                } else {
+                    " (optional)"
+                };
+                let name = format!("  {:<name_width$} ", format!("<{}>", positional.name));
+                let description = format!("{}{}", positional.help, optional);
+                help.extend(hanging(&name, &words(&description), width));
+            }
+            help.push(String::new());
+        }
+
         let options = self.options();
         if !options.is_empty() {
-            help.push_str("Options:\n");
+            help.push(format!("{}:", tr("Options")));
 
             // Find the maximum width for each column
             let max_short_width = options
@@ -192,7 +487,7 @@ pub trait CliCommand: CliCommandInfo {
                 .max()
                 .unwrap_or(0);
 
-            for opt in self.options() {
+            for opt in &options {
                 let short = opt
                     .short
                     .as_ref()
@@ -201,30 +496,43 @@ pub trait CliCommand: CliCommandInfo {
                     .long
                     .as_ref()
                     .map_or(String::new(), |l| format!("{},", l));
-                let flag = if opt.flag { " (flag)" } else { "" };
-
-                help.push_str(&format!(
-                    "  {:<width_short$} {:<width_long$} {:<width_type$} {}{}\n",
+                let columns = format!(
+                    "  {:<width_short$} {:<width_long$} {:<width_type$} ",
                     short,
                     long,
                     format!("<{}>", opt.field_type_help),
-                    opt.help,
-                    flag,
                     width_short = max_short_width + 3, // +3 for "-x,"
                     width_long = max_long_width + 4,   // +4 for "--xx,"
                     width_type = max_type_width + 2    // +2 for "<>"
-                ));
+                );
+
+                // What the option accepts is listed after what it does.
+                let mut description = opt.help.clone();
+                if let Some(default) = &opt.default {
+                    description.push_str(&format!(" [default: {}]", default));
+                }
+                if !opt.values.is_empty() {
+                    description.push_str(&format!(" [values: {}]", opt.values.join(", ")));
+                }
+                if let Some(completes) = &opt.completes {
+                    description.push_str(&format!(" [completes: {}]", completes));
+                }
+                if opt.flag {
+                    description.push_str(" (flag)");
+                }
+                help.extend(hanging(&columns, &words(&description), width));
             }
-            help.push('\n');
+            help.push(String::new());
         }
-        if let Some(examples) = self.examples() {
-            help.push_str("Examples:\n");
 
+        if let Some(examples) = self.examples() {
+            help.push(format!("{}:", tr("Examples")));
             for line in examples.lines() {
-                help.push_str(&format!("  {} {}\n", fq_name, line));
+                help.push(format!("  {} {}", command, line));
             }
         }
-        for line in help.lines() {
+
+        for line in help {
             append_line(line)?;
         }
         Ok(())
@@ -1,21 +1,36 @@
+use std::collections::HashMap;
+
 use cli_command_derive::CliCommand;
-use hubuum_client::{Authenticated, FilterOperator, NamespacePost, SyncClient};
+use hubuum_client::{
+    Authenticated, ClassPatch, FilterOperator, IntoResourceFilter, Namespace, NamespacePatch,
+    NamespacePost, ObjectPatch, QueryFilter, SyncClient,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 
+use super::shared::{
+    count_objects_per_namespace, find_class_by_name, find_namespace_by_name,
+    parse_filter_expression, FilterClause, ListFilter,
+};
 use super::CliCommand;
 use super::{CliCommandInfo, CliOption};
 
-use crate::autocomplete::namespaces;
+use crate::api::retry_api;
+use crate::autocomplete::{classes, groups, namespaces};
 use crate::errors::AppError;
-use crate::formatting::{OutputFormatter, OutputFormatterWithPadding};
-use crate::output::append_line;
+use crate::formatting::{
+    format_count, FormattedNamespaceStats, OutputFormatter, OutputFormatterWithPadding,
+};
+use crate::i18n::{tr, tr_args};
+use crate::interrupt::take_interrupt;
+use crate::output::{add_error, append_line};
+use crate::plan::Plan;
+use crate::progress::{finish_progress, progress_bar};
+use crate::snapshots::snapshot_before;
 use crate::tokenizer::CommandTokenizer;
 
-trait GetNamespace {
-    fn namespace(&self) -> Option<String>;
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(mutating = "true")]
 pub struct NamespaceNew {
     #[option(short = "n", long = "name", help = "Name of the namespace")]
     pub name: String,
@@ -28,9 +43,17 @@ pub struct NamespaceNew {
     #[option(
         short = "o",
         long = "owner",
-        help = "Name of the group owning namespace"
+        help = "Name of the group owning namespace",
+        autocomplete = "groups"
     )]
     pub owner: String,
+    #[option(
+        short = "i",
+        long = "interactive",
+        help = "Ask for the options that were not given",
+        flag = "true"
+    )]
+    pub interactive: Option<bool>,
 }
 
 impl NamespaceNew {
@@ -67,6 +90,7 @@ impl CliCommand for NamespaceNew {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct NamespaceList {
     #[option(short = "n", long = "name", help = "Name of the namespace")]
     pub name: Option<String>,
@@ -76,6 +100,39 @@ pub struct NamespaceList {
         help = "Description of the namespace"
     )]
     pub description: Option<String>,
+    #[option(
+        long = "count",
+        help = "Only show the number of matches",
+        flag = "true"
+    )]
+    pub count: Option<bool>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression, e.g. 'name__startswith=web,data__env=prod'"
+    )]
+    pub filter: Option<String>,
+}
+
+impl IntoResourceFilter<Namespace> for &NamespaceList {
+    fn into_resource_filter(self) -> Vec<QueryFilter> {
+        let mut filters = vec![];
+        if let Some(name) = &self.name {
+            filters.push(QueryFilter {
+                key: "name".to_string(),
+                value: name.clone(),
+                operator: FilterOperator::Contains { is_negated: false },
+            });
+        }
+        if let Some(description) = &self.description {
+            filters.push(QueryFilter {
+                key: "description".to_string(),
+                value: description.clone(),
+                operator: FilterOperator::Contains { is_negated: false },
+            });
+        }
+        filters
+    }
 }
 
 impl CliCommand for NamespaceList {
@@ -85,28 +142,13 @@ impl CliCommand for NamespaceList {
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
         let new = self.new_from_tokens(tokens)?;
+        let filter = ListFilter::new(&new, new.filter.as_deref())?;
+        let namespaces = retry_api(|| client.namespaces().filter(&filter))?;
 
-        let search = client.namespaces().find();
-
-        let search = match &new.name {
-            Some(name) => search.add_filter(
-                "name",
-                FilterOperator::Contains { is_negated: false },
-                name.clone(),
-            ),
-            None => search,
-        };
-
-        let search = match &new.description {
-            Some(description) => search.add_filter(
-                "description",
-                FilterOperator::Contains { is_negated: false },
-                description.clone(),
-            ),
-            None => search,
-        };
+        if new.count.is_some() {
+            return format_count(namespaces.len());
+        }
 
-        let namespaces = search.execute()?;
         namespaces.format()?;
 
         Ok(())
@@ -114,7 +156,9 @@ impl CliCommand for NamespaceList {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct NamespaceInfo {
+    #[positional(index = 0, help = "Name of the namespace")]
     #[option(
         short = "n",
         long = "name",
@@ -124,25 +168,13 @@ pub struct NamespaceInfo {
     pub name: Option<String>,
 }
 
-impl GetNamespace for &NamespaceInfo {
-    fn namespace(&self) -> Option<String> {
-        self.name.clone()
-    }
-}
-
 impl CliCommand for NamespaceInfo {
     fn execute(
         &self,
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut new = self.new_from_tokens(tokens)?;
-
-        new.name = namespace_or_pos(&new, tokens, 0)?;
-
-        if new.name.is_none() {
-            return Err(AppError::MissingOptions(vec!["namespace".to_string()]));
-        }
+        let new = self.new_from_tokens(tokens)?;
 
         let namespace = client
             .namespaces()
@@ -157,7 +189,9 @@ impl CliCommand for NamespaceInfo {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(mutating = "true")]
 pub struct NamespaceDelete {
+    #[positional(index = 0, help = "Name of the namespace")]
     #[option(
         short = "n",
         long = "name",
@@ -167,25 +201,13 @@ pub struct NamespaceDelete {
     pub name: Option<String>,
 }
 
-impl GetNamespace for &NamespaceDelete {
-    fn namespace(&self) -> Option<String> {
-        self.name.clone()
-    }
-}
-
 impl CliCommand for NamespaceDelete {
     fn execute(
         &self,
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut new = self.new_from_tokens(tokens)?;
-
-        new.name = namespace_or_pos(&new, tokens, 0)?;
-
-        if new.name.is_none() {
-            return Err(AppError::MissingOptions(vec!["namespace".to_string()]));
-        }
+        let new = self.new_from_tokens(tokens)?;
 
         let namespace = client
             .namespaces()
@@ -194,26 +216,316 @@ impl CliCommand for NamespaceDelete {
             .execute_expecting_single_result()?;
 
         client.namespaces().delete(namespace.id)?;
-        append_line(format!("Namespace '{}' deleted", namespace.name))?;
+        append_line(tr_args(
+            "Namespace '{name}' deleted",
+            &[("name", &namespace.name)],
+        ))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Modify a namespace",
+    long_about = "Rename a namespace or change its description.",
+    examples = r#"namespace_1 --rename namespace_2
+--name namespace_1 --description 'Servers in the lab'"#,
+    mutating = "true"
+)]
+pub struct NamespaceModify {
+    #[positional(index = 0, help = "Name of the namespace")]
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the namespace",
+        autocomplete = "namespaces"
+    )]
+    pub name: Option<String>,
+    #[option(short = "r", long = "rename", help = "Rename namespace")]
+    pub rename: Option<String>,
+    #[option(
+        short = "d",
+        long = "description",
+        help = "Description of the namespace"
+    )]
+    pub description: Option<String>,
+}
+
+impl CliCommand for NamespaceModify {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+
+        if new.rename.is_none() && new.description.is_none() {
+            return Err(AppError::InvalidOption(
+                "Nothing to modify, give --rename or --description".to_string(),
+            ));
+        }
+
+        let namespace = find_namespace_by_name(client, &new.name.clone().unwrap())?;
+
+        let mut patch = NamespacePatch::default();
+
+        if let Some(rename) = &new.rename {
+            patch.name = Some(rename.clone());
+        }
+
+        if let Some(description) = &new.description {
+            patch.description = Some(description.clone());
+        }
+
+        let result = client.namespaces().update(namespace.id, patch)?;
+        result.format(15)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show class and object counts per namespace",
+    long_about = "Show the number of classes and objects in each namespace, or in the namespaces matching a name.",
+    examples = r#"--name lab"#
+)]
+pub struct NamespaceStats {
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the namespace",
+        autocomplete = "namespaces"
+    )]
+    pub name: Option<String>,
+}
+
+impl IntoResourceFilter<Namespace> for &NamespaceStats {
+    fn into_resource_filter(self) -> Vec<QueryFilter> {
+        let mut filters = vec![];
+        if let Some(name) = &self.name {
+            filters.push(QueryFilter {
+                key: "name".to_string(),
+                value: name.clone(),
+                operator: FilterOperator::Contains { is_negated: false },
+            });
+        }
+        filters
+    }
+}
+
+impl CliCommand for NamespaceStats {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let namespaces = retry_api(|| client.namespaces().filter(&new))?;
+
+        let classes = retry_api(|| client.classes().find().execute())?;
+        let mut class_counts: HashMap<i32, usize> = HashMap::new();
+        for class in &classes {
+            *class_counts.entry(class.namespace.id).or_default() += 1;
+        }
+        let object_counts = count_objects_per_namespace(
+            client,
+            &classes.iter().map(|class| class.id).collect::<Vec<_>>(),
+        )?;
+
+        let stats = namespaces
+            .iter()
+            .map(|n| {
+                FormattedNamespaceStats::new(
+                    n,
+                    class_counts.get(&n.id).copied().unwrap_or_default(),
+                    object_counts.get(&n.id).copied().unwrap_or_default(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        stats.format()?;
 
         Ok(())
     }
 }
 
-fn namespace_or_pos<U>(
-    query: U,
-    tokens: &CommandTokenizer,
-    pos: usize,
-) -> Result<Option<String>, AppError>
-where
-    U: GetNamespace,
-{
-    let pos0 = tokens.get_positionals().get(pos);
-    if query.namespace().is_none() {
-        if pos0.is_none() {
-            return Err(AppError::MissingOptions(vec!["namespace".to_string()]));
-        }
-        return Ok(pos0.cloned());
-    };
-    Ok(query.namespace().clone())
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Move objects to another namespace",
+    long_about = "Move all objects in a namespace to another namespace, optionally limited to a class or a filter expression, and optionally moving the classes in the namespace as well.",
+    examples = r#"--from lab --to production --dry-run
+--from lab --to production --class Host --filter 'name__startswith=web'
+--from lab --to production --with-classes"#,
+    mutating = "true"
+)]
+pub struct NamespaceMove {
+    #[option(
+        short = "F",
+        long = "from",
+        help = "Namespace to move objects from",
+        autocomplete = "namespaces"
+    )]
+    pub from: String,
+    #[option(
+        short = "T",
+        long = "to",
+        help = "Namespace to move objects to",
+        autocomplete = "namespaces"
+    )]
+    pub to: String,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Only move objects in this class",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: Option<String>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression for the objects, e.g. 'name__startswith=web'"
+    )]
+    pub filter: Option<String>,
+    #[option(
+        long = "with-classes",
+        help = "Also move the classes in the namespace",
+        flag = "true"
+    )]
+    pub with_classes: Option<bool>,
+    #[option(
+        long = "dry-run",
+        help = "Show what would be moved without moving anything",
+        flag = "true"
+    )]
+    pub dry_run: Option<bool>,
+}
+
+impl CliCommand for NamespaceMove {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let clauses = match &new.filter {
+            Some(filter) => parse_filter_expression(filter)?,
+            None => Vec::new(),
+        };
+
+        let from = find_namespace_by_name(client, &new.from)?;
+        let to = find_namespace_by_name(client, &new.to)?;
+
+        let classes = match &new.class {
+            Some(class) => vec![find_class_by_name(client, class)?],
+            None => retry_api(|| client.classes().find().execute())?,
+        };
+
+        let mut plan = Plan::new();
+        let before = json!({ "namespace": from.name });
+        let after = json!({ "namespace": to.name });
+
+        let mut objects = Vec::new();
+        for class in &classes {
+            let mut query = client.objects(class.id).find().add_filter(
+                "namespace_id",
+                FilterOperator::Equals { is_negated: false },
+                from.id.to_string(),
+            );
+            for filter in clauses.iter().map(FilterClause::query_filter) {
+                query = query.add_filter(&filter.key, filter.operator, &filter.value);
+            }
+
+            for object in query.execute()? {
+                let name = format!("{}/{}", class.name, object.name);
+                plan.update("object", &name, &before, &after)?;
+                objects.push((class.id, class.name.clone(), object));
+            }
+        }
+
+        let classes = match new.with_classes {
+            Some(_) => classes
+                .into_iter()
+                .filter(|c| c.namespace.id == from.id)
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+        for class in &classes {
+            plan.update("class", &class.name, &before, &after)?;
+        }
+
+        if plan.is_empty() {
+            append_line(tr("Nothing to move"))?;
+            return Ok(());
+        }
+
+        if new.dry_run.is_some() {
+            plan.format(0)?;
+            return Ok(());
+        }
+
+        let total = objects.len() + classes.len();
+        let mut moved = 0;
+        let mut failures = Vec::new();
+        let progress = progress_bar(total, "Moving");
+
+        for (class_id, class_name, object) in &objects {
+            if take_interrupt() {
+                break;
+            }
+            let patch = ObjectPatch {
+                namespace_id: Some(to.id),
+                ..Default::default()
+            };
+            let result = snapshot_before(client, class_name, object, "move").and_then(|_| {
+                client
+                    .objects(*class_id)
+                    .update(object.id, patch)
+                    .map_err(AppError::from)
+            });
+            match result {
+                Ok(_) => moved += 1,
+                Err(err) => {
+                    failures.push(format!("object {}/{}: {}", class_name, object.name, err))
+                }
+            }
+            progress.inc(1);
+        }
+
+        // Classes are moved last, so their objects are still found above. An interrupted
+        // move leaves them, as some of their objects may not have been moved.
+        let interrupted = moved + failures.len() < objects.len();
+        if !interrupted {
+            for class in &classes {
+                let patch = ClassPatch {
+                    namespace_id: Some(to.id),
+                    ..Default::default()
+                };
+                match client.classes().update(class.id, patch) {
+                    Ok(_) => moved += 1,
+                    Err(err) => failures.push(format!("class {}: {}", class.name, err)),
+                }
+                progress.inc(1);
+            }
+        }
+        finish_progress(&progress);
+
+        for failure in &failures {
+            add_error(failure)?;
+        }
+
+        append_line(tr_args(
+            "Moved {moved} of {total} entities from {from} to {to}",
+            &[
+                ("moved", &moved),
+                ("total", &total),
+                ("from", &from.name),
+                ("to", &to.name),
+            ],
+        ))?;
+
+        Ok(())
+    }
 }
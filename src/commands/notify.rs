@@ -0,0 +1,128 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::shared::find_class_by_name;
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::autocomplete::classes;
+use crate::errors::AppError;
+use crate::i18n::{tr, tr_args};
+use crate::models::HumanDuration;
+use crate::output::{add_warning, append_line};
+use crate::tokenizer::CommandTokenizer;
+use crate::watcher::{poll_interval, unwatch_class, watch_class, watched_classes};
+
+// Accept both `notify on class Host` and `notify on Host`.
+fn class_or_pos(class: &Option<String>, tokens: &CommandTokenizer) -> Result<String, AppError> {
+    if let Some(class) = class {
+        return Ok(class.clone());
+    }
+
+    let positionals = tokens.get_positionals();
+    let name = match positionals.first().map(String::as_str) {
+        Some("class") => positionals.get(1),
+        _ => positionals.first(),
+    };
+    name.cloned()
+        .ok_or_else(|| AppError::MissingOptions(vec!["class".to_string()]))
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Watch a class for changes",
+    long_about = "Periodically poll a class for new, changed and deleted objects, and print a notice between prompts when something changed.",
+    examples = r#"class Host
+--class Host --interval 60"#
+)]
+pub struct NotifyOn {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Name of the class",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: Option<String>,
+    #[option(
+        short = "i",
+        long = "interval",
+        help = "Time between each check, e.g. 30s or 5m (shared by all watches)"
+    )]
+    pub interval: Option<HumanDuration>,
+}
+
+impl CliCommand for NotifyOn {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let name = class_or_pos(&new.class, tokens)?;
+
+        let class = find_class_by_name(client, &name)?;
+        watch_class(client, class, new.interval.map(|i| i.as_secs()))?;
+
+        append_line(tr_args(
+            "Watching class {class} every {interval} seconds",
+            &[("class", &name), ("interval", &poll_interval())],
+        ))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Stop watching a class for changes",
+    examples = r#"class Host"#
+)]
+pub struct NotifyOff {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Name of the class",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: Option<String>,
+}
+
+impl CliCommand for NotifyOff {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let name = class_or_pos(&new.class, tokens)?;
+
+        if !unwatch_class(&name)? {
+            add_warning(tr_args("Class {class} is not watched", &[("class", &name)]))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(about = "List the watched classes")]
+pub struct NotifyList {}
+
+impl CliCommand for NotifyList {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+
+        let classes = watched_classes()?;
+        if classes.is_empty() {
+            append_line(tr("No classes are watched"))?;
+        }
+        for class in classes {
+            append_line(class)?;
+        }
+        Ok(())
+    }
+}
@@ -1,36 +1,61 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+use chrono::{Local, TimeZone, Utc};
 
 use cli_command_derive::CliCommand;
 
 use hubuum_client::{
-    Authenticated, FilterOperator, IntoResourceFilter, Object, ObjectPatch, ObjectPost,
-    QueryFilter, SyncClient,
+    ApiError, Authenticated, Class, FilterOperator, IntoResourceFilter, Object, ObjectPatch,
+    ObjectPost, QueryFilter, SyncClient,
 };
 use jqesque::Jqesque;
 use jsonpath_rust::{JsonPath, JsonPathValue};
 
 use serde::{Deserialize, Serialize};
 
-use super::shared::{find_object_by_name, prettify_slice_path};
+use super::shared::{
+    confirm_glob_matches, count_objects, count_objects_in_classes, diff_data, find_object_by_name,
+    find_objects_by_glob, is_glob, join_thread, prettify_slice_path, DataChange, DataItem,
+    DataPath,
+};
 use super::{CliCommand, CliCommandInfo, CliOption};
 
-use crate::autocomplete::{classes, namespaces, objects_from_class};
-use crate::commands::shared::{find_class_by_name, find_entities_by_ids, find_namespace_by_name};
+use crate::api::{get_page, retry_api};
+use crate::audit::audit_log;
+use crate::autocomplete::{classes, data_paths, namespaces, object_templates, objects_from_class};
+use crate::commands::shared::{
+    find_class_by_name, find_entities_by_ids, find_namespace_by_name, merge_data,
+    parse_where_expression, sort_entities, ColumnMapping, Commafy, DataCondition, ListFilter,
+    MappedRow, MAX_CONCURRENT_QUERIES,
+};
 use crate::errors::AppError;
-use crate::formatting::{FormattedObject, OutputFormatter, OutputFormatterWithPadding};
-use crate::output::{add_warning, append_key_value, append_line};
+use crate::formatting::{
+    format_count, format_object_comparison, FormattedObject, FormattedObjectSnapshot,
+    OutputFormatter, OutputFormatterWithPadding,
+};
+use crate::i18n::{tr, tr_args};
+use crate::interrupt::take_interrupt;
+use crate::models::OutputFormat;
+use crate::output::{
+    add_error, add_warning, append_json, append_key_value, append_line, flush_output, get_format,
+};
+use crate::plan::{confirm, Plan};
+use crate::progress::{finish_progress, progress_bar};
+use crate::schema::prompt_required_data;
+use crate::snapshots::{object_snapshots, save_object_snapshot, snapshot_before, ObjectSnapshot};
+use crate::templates::find_object_template;
 use crate::tokenizer::CommandTokenizer;
 
-trait GetObjectname {
-    fn objectname(&self) -> Option<String>;
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
 #[command_info(
     about = "Create a object class",
-    long_about = "Create a new object in a specific class with the specified properties.",
+    long_about = "Create a new object in a specific class with the specified properties. Without --data, if the class has a JSON schema with required properties, you are offered to enter them one by one, checked against their type in the schema. With --from-template, the description and data come from an object template saved with object template save, with its placeholders filled in, and --data is merged into the data of the template.",
     examples = r#"-n MyObject -c MyClaass -N namespace_1 -d "My object description"
---name MyObject --class MyClass --namespace namespace_1 --description 'My object' --data '{"key": "val"}'"#
+--name MyObject --class MyClass --namespace namespace_1 --description 'My object' --data '{"key": "val"}'
+-c MyClass --interactive
+-n web02 -c Host -N infra --from-template web-server"#,
+    mutating = "true"
 )]
 pub struct ObjectNew {
     #[option(short = "n", long = "name", help = "Name of the object")]
@@ -39,24 +64,44 @@ pub struct ObjectNew {
         short = "c",
         long = "class",
         help = "Name of the class the object belongs to",
-        autocomplete = "classes"
+        autocomplete = "classes",
+        context = "true"
     )]
     pub class: String,
     #[option(
         short = "N",
         long = "namespace",
         help = "Namespace name",
-        autocomplete = "namespaces"
+        autocomplete = "namespaces",
+        context = "true"
     )]
     pub namespace: String,
-    #[option(short = "d", long = "description", help = "Description of the class")]
-    pub description: String,
+    #[option(
+        short = "d",
+        long = "description",
+        help = "Description of the object, required unless given by --from-template"
+    )]
+    pub description: Option<String>,
     #[option(
         short = "D",
         long = "data",
         help = "JSON data for the object the class"
     )]
     pub data: Option<serde_json::Value>,
+    #[option(
+        short = "T",
+        long = "from-template",
+        help = "Take the description and data from an object template",
+        autocomplete = "object_templates"
+    )]
+    pub from_template: Option<String>,
+    #[option(
+        short = "i",
+        long = "interactive",
+        help = "Ask for the options that were not given",
+        flag = "true"
+    )]
+    pub interactive: Option<bool>,
 }
 
 impl CliCommand for ObjectNew {
@@ -69,12 +114,46 @@ impl CliCommand for ObjectNew {
         let namespace = find_namespace_by_name(client, &new.namespace)?;
         let class = find_class_by_name(client, &new.class)?;
 
+        let template = new
+            .from_template
+            .as_deref()
+            .map(find_object_template)
+            .transpose()?;
+        if let Some(template) = template.as_ref().filter(|t| t.class != class.name) {
+            add_warning(format!(
+                "Template '{}' was saved from an object of class {}",
+                template.name, template.class
+            ))?;
+        }
+        let (template_description, template_data) = template
+            .map(|t| t.instantiate(&new.name, &class.name, &namespace.name))
+            .unzip();
+
+        let description = new
+            .description
+            .clone()
+            .or(template_description)
+            .ok_or_else(|| AppError::MissingOptions(vec!["description".to_string()]))?;
+
+        // Data given with --data is merged into the data of the template.
+        let mut data = match (template_data.flatten(), new.data.clone()) {
+            (Some(base), Some(serde_json::Value::Object(data))) => {
+                Some(merge_data(Some(&base), &data))
+            }
+            (base, data) => data.or(base),
+        };
+        if data.is_none() {
+            if let Some(schema) = &class.json_schema {
+                data = prompt_required_data(&class.name, schema, None)?;
+            }
+        }
+
         let result = client.objects(class.id).create(ObjectPost {
             name: new.name.clone(),
             hubuum_class_id: class.id,
             namespace_id: namespace.id,
-            description: new.description.clone(),
-            data: new.data.clone(),
+            description,
+            data,
         })?;
 
         let mut classmap = HashMap::new();
@@ -105,14 +184,18 @@ impl IntoResourceFilter<Object> for &ObjectInfo {
     }
 }
 
-impl GetObjectname for &ObjectInfo {
-    fn objectname(&self) -> Option<String> {
-        self.name.clone()
-    }
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show objects",
+    long_about = "Show an object, or several objects of the same class given as positionals or with --name repeated. Names may be globs, where `*` matches any run of characters and `?` a single character; you are asked before showing more than 20 matches. Several objects are fetched concurrently and shown one after the other, or side by side with --compare.",
+    examples = r#"-c Host web01
+-c Host web01 web02 web03
+-c Host 'web*'
+-c Host --name web01 --name web02 --compare"#,
+    cancellable = "true"
+)]
 pub struct ObjectInfo {
+    #[positional(index = 0, help = "Name of the object, more names may follow")]
     #[option(
         short = "n",
         long = "name",
@@ -124,7 +207,8 @@ pub struct ObjectInfo {
         short = "c",
         long = "class",
         help = "Class of the object",
-        autocomplete = "classes"
+        autocomplete = "classes",
+        context = "true"
     )]
     pub class: String,
     #[option(
@@ -137,9 +221,18 @@ pub struct ObjectInfo {
     #[option(
         short = "p",
         long = "path",
-        help = "Path to display within the data, implies -d"
+        help = "Path to display within the data, implies -d",
+        autocomplete = "data_paths",
+        conflicts_with = "format,yaml"
     )]
     pub jsonpath: Option<String>,
+    #[option(
+        long = "compare",
+        help = "Show several objects side by side, with the fields that differ marked",
+        flag = "true",
+        conflicts_with = "jsonpath"
+    )]
+    pub compare: Option<bool>,
 }
 
 impl CliCommand for ObjectInfo {
@@ -148,107 +241,234 @@ impl CliCommand for ObjectInfo {
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut query = self.new_from_tokens(tokens)?;
-        query.name = objectname_or_pos(&query, tokens, 0)?;
-
+        let query = self.new_from_tokens(tokens)?;
         let class = find_class_by_name(client, &query.class)?;
-        let object = find_object_by_name(client, class.id, &query.name.unwrap())?;
 
-        let namespace = client
-            .namespaces()
-            .find()
-            .add_filter_id(object.namespace_id)
-            .execute_expecting_single_result()?;
+        let names = object_names(&query, tokens);
+        if let [name] = names.as_slice() {
+            if !is_glob(name) {
+                let object = find_object_by_name(client, class.id, name)?;
+                return show_object(client, &class, object, &query);
+            }
+        }
 
-        let mut nsmap = HashMap::new();
-        nsmap.insert(namespace.id, namespace.clone());
+        let objects = find_objects_by_names(client, class.id, &names)?;
+        if let [object] = objects.as_slice() {
+            return show_object(client, &class, object.clone(), &query);
+        }
+        let nsmap = find_entities_by_ids(&client.namespaces(), &objects, |o| o.namespace_id)?;
+        let classmap = HashMap::from([(class.id, class.clone())]);
+        let objects = objects
+            .iter()
+            .map(|object| FormattedObject::new(object, &classmap, &nsmap))
+            .collect::<Vec<_>>();
 
-        let mut classmap = HashMap::new();
-        classmap.insert(class.id, class.clone());
+        if get_format()? != OutputFormat::Text {
+            return objects.format();
+        }
+        if query.compare.is_some() {
+            return format_object_comparison(&objects);
+        }
+        for (i, object) in objects.iter().enumerate() {
+            if i > 0 {
+                append_line("")?;
+            }
+            object.format(15)?;
+            show_object_data(object, &query)?;
+        }
+        Ok(())
+    }
+}
 
-        let object = FormattedObject::new(&object, &classmap, &nsmap);
-        object.format(15)?;
+// The names given with -n/--name, which may be repeated, followed by the positionals.
+fn object_names(query: &ObjectInfo, tokens: &CommandTokenizer) -> Vec<String> {
+    let mut names = query
+        .options()
+        .iter()
+        .filter(|opt| opt.name == "name")
+        .flat_map(|opt| [opt.short_without_dash(), opt.long_without_dashes()])
+        .flatten()
+        .flat_map(|key| tokens.get_all_values(&key).to_vec())
+        .chain(tokens.get_positionals().iter().cloned())
+        .collect::<Vec<_>>();
+    // Names set by the wizard are not on the command line.
+    if names.is_empty() {
+        names.extend(query.name.clone());
+    }
+    let mut seen = HashSet::new();
+    names.retain(|name| seen.insert(name.clone()));
+    names
+}
 
-        if query.jsonpath.is_none() && query.data.is_none() {
-            return Ok(());
+// Look up the objects concurrently, warning about the names that are not found. Globs are
+// expanded to the objects they match, asking first if they match many. Fails if nothing
+// is found.
+fn find_objects_by_names(
+    client: &SyncClient<Authenticated>,
+    class_id: i32,
+    names: &[String],
+) -> Result<Vec<Object>, AppError> {
+    let (globs, names): (Vec<_>, Vec<_>) = names.iter().cloned().partition(|name| is_glob(name));
+
+    let mut objects = Vec::new();
+    let mut first_error: Option<AppError> = None;
+    for pattern in &globs {
+        let matches = find_objects_by_glob(client, class_id, pattern)?;
+        if matches.is_empty() {
+            add_warning(tr_args(
+                "{pattern} did not match any objects",
+                &[("pattern", pattern)],
+            ))?;
+            first_error.get_or_insert(AppError::EntityNotFound(pattern.clone()));
+        } else if !confirm_glob_matches(pattern, matches.len())? {
+            append_line(tr("Cancelled"))?;
+            return Err(AppError::Quiet);
         }
+        objects.extend(matches);
+    }
 
-        if object.data.is_none() {
-            add_warning("JSON data requested, but object has no data")?;
-            return Ok(());
+    for chunk in names.chunks(MAX_CONCURRENT_QUERIES) {
+        let results = std::thread::scope(|s| {
+            let handles = chunk
+                .iter()
+                .map(|name| s.spawn(move || find_object_by_name(client, class_id, name)))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(join_thread)
+                .collect::<Result<Vec<_>, AppError>>()
+        })?;
+
+        for (name, result) in chunk.iter().zip(results) {
+            match result {
+                Ok(object) => objects.push(object),
+                Err(err) => {
+                    add_warning(format!("{}: {}", name, err))?;
+                    first_error.get_or_insert(err.into());
+                }
+            }
         }
+    }
 
-        let json_data = object.data.unwrap().clone();
+    let mut seen = HashSet::new();
+    objects.retain(|object| seen.insert(object.id));
+    match first_error {
+        Some(err) if objects.is_empty() => Err(err),
+        _ => Ok(objects),
+    }
+}
 
-        if query.jsonpath.is_some() {
-            let jsonpath = query.jsonpath.clone().unwrap();
-            let path = jsonpath
-                .parse::<JsonPath>()
-                .map_err(|e| AppError::JsonPathError(e.to_string()))?;
+fn show_object(
+    client: &SyncClient<Authenticated>,
+    class: &Class,
+    object: Object,
+    query: &ObjectInfo,
+) -> Result<(), AppError> {
+    let namespace = client
+        .namespaces()
+        .find()
+        .add_filter_id(object.namespace_id)
+        .execute_expecting_single_result()?;
+
+    let mut nsmap = HashMap::new();
+    nsmap.insert(namespace.id, namespace.clone());
+
+    let mut classmap = HashMap::new();
+    classmap.insert(class.id, class.clone());
+
+    let object = FormattedObject::new(&object, &classmap, &nsmap);
+    object.format(15)?;
+
+    // Structured output formats already include the full data of the object.
+    if get_format()? != OutputFormat::Text {
+        return Ok(());
+    }
+    show_object_data(&object, query)
+}
 
-            let slice_of_data = path.find_slice(&json_data);
-            if slice_of_data.is_empty() {
-                add_warning("JSONPath did not match any data")?;
-                return Ok(());
-            }
+// The data of the object, or the part of it at --path, after the object itself.
+fn show_object_data(object: &FormattedObject, query: &ObjectInfo) -> Result<(), AppError> {
+    if query.jsonpath.is_none() && query.data.is_none() {
+        return Ok(());
+    }
 
-            // Hashmap to store the key value pairs, allowing for sorting and padding lookups
-            let mut key_values = HashMap::new();
+    if object.data.is_none() {
+        add_warning(tr("JSON data requested, but object has no data"))?;
+        return Ok(());
+    }
 
-            // Iterate over the slices and handle each JsonPathValue
-            for slice in slice_of_data {
-                match slice {
-                    JsonPathValue::Slice(value, path) => {
-                        let pretty_path = prettify_slice_path(&path);
-                        key_values.insert(pretty_path, value.clone());
-                    }
-                    JsonPathValue::NewValue(value) => {
-                        key_values.insert("Generated".to_string(), value.clone());
-                    }
-                    JsonPathValue::NoValue => {
-                        add_warning(format!("{} produced no results", jsonpath))?;
-                    }
+    let json_data = object.data.clone().unwrap();
+
+    if query.jsonpath.is_some() {
+        let jsonpath = query.jsonpath.clone().unwrap();
+        let path = jsonpath
+            .parse::<JsonPath>()
+            .map_err(|e| AppError::JsonPathError(e.to_string()))?;
+
+        let slice_of_data = path.find_slice(&json_data);
+        if slice_of_data.is_empty() {
+            add_warning("JSONPath did not match any data")?;
+            return Ok(());
+        }
+
+        // Hashmap to store the key value pairs, allowing for sorting and padding lookups
+        let mut key_values = HashMap::new();
+
+        // Iterate over the slices and handle each JsonPathValue
+        for slice in slice_of_data {
+            match slice {
+                JsonPathValue::Slice(value, path) => {
+                    let pretty_path = prettify_slice_path(&path);
+                    key_values.insert(pretty_path, value.clone());
+                }
+                JsonPathValue::NewValue(value) => {
+                    key_values.insert("Generated".to_string(), value.clone());
+                }
+                JsonPathValue::NoValue => {
+                    add_warning(format!("{} produced no results", jsonpath))?;
                 }
             }
+        }
+
+        let padding = key_values
+            .keys()
+            .map(|k| k.len())
+            .max()
+            .map_or(15, |len| len.max(15));
+
+        for (key, value) in key_values {
+            append_key_value(key, value, padding)?;
+        }
+    } else {
+        let flattener = smooth_json::Flattener {
+            ..Default::default()
+        };
+
+        let v = flattener.flatten(&json_data);
 
-            let padding = key_values
+        if let serde_json::Value::Object(map) = v {
+            let sorted_map: std::collections::BTreeMap<_, _> = map.into_iter().collect();
+            let padding = sorted_map
                 .keys()
                 .map(|k| k.len())
                 .max()
                 .map_or(15, |len| len.max(15));
 
-            for (key, value) in key_values {
+            for (key, value) in sorted_map {
                 append_key_value(key, value, padding)?;
             }
         } else {
-            let flattener = smooth_json::Flattener {
-                ..Default::default()
-            };
-
-            let v = flattener.flatten(&json_data);
-
-            if let serde_json::Value::Object(map) = v {
-                let sorted_map: std::collections::BTreeMap<_, _> = map.into_iter().collect();
-                let padding = sorted_map
-                    .keys()
-                    .map(|k| k.len())
-                    .max()
-                    .map_or(15, |len| len.max(15));
-
-                for (key, value) in sorted_map {
-                    append_key_value(key, value, padding)?;
-                }
-            } else {
-                add_warning("JSON is not an object")?;
-            }
+            add_warning("JSON is not an object")?;
         }
-
-        Ok(())
     }
+
+    Ok(())
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(mutating = "true")]
 pub struct ObjectDelete {
+    #[positional(index = 0, help = "Name of the object")]
     #[option(
         short = "n",
         long = "name",
@@ -260,9 +480,10 @@ pub struct ObjectDelete {
         short = "c",
         long = "class",
         help = "Class of the object",
-        autocomplete = "classes"
+        autocomplete = "classes",
+        context = "true"
     )]
-    pub class: Option<String>,
+    pub class: String,
 }
 
 impl CliCommand for ObjectDelete {
@@ -271,55 +492,137 @@ impl CliCommand for ObjectDelete {
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut query = self.new_from_tokens(tokens)?;
-        query.name = objectname_or_pos(&query, tokens, 1)?;
-
-        let class = if query.class.is_some() {
-            find_class_by_name(client, &query.class.unwrap())?
-        } else {
-            return Err(AppError::MissingOptions(vec!["class".to_string()]));
-        };
+        let query = self.new_from_tokens(tokens)?;
 
-        let object = find_object_by_name(client, class.id, &query.name.unwrap())?;
+        let class = find_class_by_name(client, &query.class)?;
+        let object = find_object_by_name(client, class.id, &query.name.unwrap_or_default())?;
 
+        snapshot_before(client, &class.name, &object, "delete")?;
         client.objects(class.id).delete(object.id)?;
         Ok(())
     }
 }
 
-impl GetObjectname for &ObjectDelete {
-    fn objectname(&self) -> Option<String> {
-        self.name.clone()
-    }
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Delete the objects matching a glob",
+    long_about = "Delete every object in a class whose name matches a glob, where `*` matches any run of characters and `?` a single character. The matching objects are listed and you are asked before anything is deleted, unless --yes is given. A glob may match at most 1000 objects.",
+    examples = r#"-c Host 'tmp-*'
+-c Host 'web??' --yes"#,
+    mutating = "true"
+)]
+pub struct ObjectPurge {
+    #[positional(index = 0, help = "Glob matching the names of the objects")]
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Glob matching the names of the objects",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: String,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the objects",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "y",
+        long = "yes",
+        help = "Delete the objects without asking",
+        flag = "true"
+    )]
+    pub yes: Option<bool>,
 }
 
-fn objectname_or_pos<U>(
-    query: U,
-    tokens: &CommandTokenizer,
-    pos: usize,
-) -> Result<Option<String>, AppError>
-where
-    U: GetObjectname,
-{
-    let pos0 = tokens.get_positionals().get(pos);
-    if query.objectname().is_none() {
-        if pos0.is_none() {
-            return Err(AppError::MissingOptions(vec!["name".to_string()]));
+impl CliCommand for ObjectPurge {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let class = find_class_by_name(client, &query.class)?;
+
+        let objects = if is_glob(&query.name) {
+            find_objects_by_glob(client, class.id, &query.name)?
+        } else {
+            vec![find_object_by_name(client, class.id, &query.name)?]
+        };
+        if objects.is_empty() {
+            add_warning(tr_args(
+                "{pattern} did not match any objects",
+                &[("pattern", &query.name)],
+            ))?;
+            return Ok(());
         }
-        return Ok(pos0.cloned());
-    };
-    Ok(query.objectname().clone())
+
+        if query.yes.is_none() {
+            for object in &objects {
+                append_line(&object.name)?;
+            }
+            flush_output()?;
+            if !confirm(&tr_args(
+                "Delete {count} objects?",
+                &[("count", &objects.len())],
+            ))? {
+                append_line(tr("No objects were deleted"))?;
+                return Ok(());
+            }
+        }
+
+        let total = objects.len();
+        let mut deleted = 0;
+        let progress = progress_bar(total, "Deleting");
+        for object in objects {
+            if take_interrupt() {
+                break;
+            }
+            let result = snapshot_before(client, &class.name, &object, "delete").and_then(|_| {
+                client
+                    .objects(class.id)
+                    .delete(object.id)
+                    .map_err(AppError::from)
+            });
+            match result {
+                Ok(_) => deleted += 1,
+                Err(err) => add_error(format!("{}: {}", object.name, err))?,
+            }
+            progress.inc(1);
+        }
+        finish_progress(&progress);
+
+        append_line(tr_args(
+            "Deleted {deleted} of {total} objects",
+            &[("deleted", &deleted), ("total", &total)],
+        ))?;
+        Ok(())
+    }
 }
 
+// The number of objects asked for per request by `object list --stream`.
+const STREAM_PAGE_SIZE: usize = 100;
+
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct ObjectList {
     #[option(
         short = "c",
         long = "class",
         help = "Name of the class",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: Option<String>,
+    #[option(
+        short = "C",
+        long = "classes",
+        help = "Comma-separated names of classes to list objects from",
         autocomplete = "classes"
     )]
-    pub class: String,
+    pub classes: Option<String>,
     #[option(
         short = "n",
         long = "name",
@@ -329,26 +632,69 @@ pub struct ObjectList {
     pub name: Option<String>,
     #[option(short = "d", long = "description", help = "Description of the class")]
     pub description: Option<String>,
+    #[option(
+        long = "count",
+        help = "Only show the number of matches",
+        flag = "true"
+    )]
+    pub count: Option<bool>,
+    #[option(
+        short = "s",
+        long = "sort",
+        help = "Sort by comma-separated fields, e.g. name or data.ip"
+    )]
+    pub sort: Option<String>,
+    #[option(
+        short = "r",
+        long = "reverse",
+        help = "Reverse the sort order",
+        flag = "true"
+    )]
+    pub reverse: Option<bool>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression, e.g. 'name__startswith=web,data__env=prod'"
+    )]
+    pub filter: Option<String>,
+    #[option(
+        short = "w",
+        long = "where",
+        help = "Conditions on the object data, e.g. 'data.os.family=linux,data.env!=test'"
+    )]
+    pub conditions: Option<String>,
+    #[option(
+        long = "stream",
+        help = "Print objects as they arrive instead of sorting them, Ctrl-C stops the listing",
+        flag = "true",
+        conflicts_with = "sort,reverse"
+    )]
+    pub stream: Option<bool>,
+}
+
+// The filters shared by the object listing and counting commands.
+fn object_filters(name: Option<&str>, description: Option<&str>) -> Vec<QueryFilter> {
+    let mut filters = vec![];
+    if let Some(name) = name {
+        filters.push(QueryFilter {
+            key: "name".to_string(),
+            value: name.to_string(),
+            operator: FilterOperator::IContains { is_negated: false },
+        });
+    }
+    if let Some(description) = description {
+        filters.push(QueryFilter {
+            key: "description".to_string(),
+            value: description.to_string(),
+            operator: FilterOperator::IContains { is_negated: false },
+        });
+    }
+    filters
 }
 
 impl IntoResourceFilter<Object> for &ObjectList {
     fn into_resource_filter(self) -> Vec<QueryFilter> {
-        let mut filters = vec![];
-        if let Some(name) = &self.name {
-            filters.push(QueryFilter {
-                key: "name".to_string(),
-                value: name.clone(),
-                operator: FilterOperator::IContains { is_negated: false },
-            });
-        }
-        if let Some(description) = &self.description {
-            filters.push(QueryFilter {
-                key: "description".to_string(),
-                value: description.clone(),
-                operator: FilterOperator::IContains { is_negated: false },
-            });
-        }
-        filters
+        object_filters(self.name.as_deref(), self.description.as_deref())
     }
 }
 
@@ -359,37 +705,380 @@ impl CliCommand for ObjectList {
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
         let new: ObjectList = self.new_from_tokens(tokens)?;
+        let filter = ListFilter::new(&new, new.filter.as_deref())?;
 
-        let class = find_class_by_name(client, &new.class)?;
+        let conditions = match &new.conditions {
+            Some(conditions) => parse_where_expression(conditions)?,
+            None => vec![],
+        };
+
+        let class_names = new
+            .class
+            .iter()
+            .chain(new.classes.iter())
+            .flat_map(|names| names.split(','))
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .collect::<Vec<_>>();
+        if class_names.is_empty() {
+            return Err(AppError::MissingOptions(vec!["class".to_string()]));
+        }
+
+        let class_ids = class_names
+            .iter()
+            .map(|name| find_class_by_name(client, name).map(|class| class.id))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Unfiltered counts come from the server, without listing the objects.
+        if new.count.is_some()
+            && conditions.is_empty()
+            && IntoResourceFilter::<Object>::into_resource_filter(&filter).is_empty()
+        {
+            let counts = count_objects_in_classes(client, &class_ids)?;
+            return format_count(counts.values().sum());
+        }
+
+        if new.stream.is_some() && new.count.is_none() {
+            return stream_objects(client, &class_ids, &filter, &conditions);
+        }
+
+        let objects = if let [class_id] = class_ids[..] {
+            find_objects(client, class_id, &filter, &conditions)?
+        } else {
+            // Query the classes concurrently, the class column tells the objects apart.
+            std::thread::scope(|s| {
+                let filter = &filter;
+                let conditions = &conditions;
+                let handles = class_ids
+                    .iter()
+                    .map(|&class_id| {
+                        s.spawn(move || find_objects(client, class_id, filter, conditions))
+                    })
+                    .collect::<Vec<_>>();
+
+                handles
+                    .into_iter()
+                    .map(|handle| join_thread(handle)?)
+                    .collect::<Result<Vec<_>, AppError>>()
+            })?
+            .into_iter()
+            .flatten()
+            .collect()
+        };
 
-        let objects = client.objects(class.id).filter(&new)?;
+        if new.count.is_some() {
+            return format_count(objects.len());
+        }
 
         if objects.is_empty() {
-            append_line("No objects found")?;
+            append_line(tr("No objects found"))?;
             return Ok(());
         }
 
-        let classmap = find_entities_by_ids(&client.classes(), &objects, |o| o.hubuum_class_id)?;
-        let nsmap = find_entities_by_ids(&client.namespaces(), &objects, |o| o.namespace_id)?;
+        // The classes and namespaces are independent, so look them up at the same time.
+        let (classmap, nsmap) = std::thread::scope(|s| {
+            let classes = s
+                .spawn(|| find_entities_by_ids(&client.classes(), &objects, |o| o.hubuum_class_id));
+            let namespaces = s
+                .spawn(|| find_entities_by_ids(&client.namespaces(), &objects, |o| o.namespace_id));
+            (join_thread(classes), join_thread(namespaces))
+        });
+        let (classmap, nsmap) = (classmap??, nsmap??);
 
         let objects = objects
             .iter()
             .map(|o| FormattedObject::new(o, &classmap, &nsmap))
             .collect::<Vec<_>>();
+        let objects = sort_entities(objects, new.sort.as_deref(), new.reverse.is_some())?;
 
         objects.format()?;
         Ok(())
     }
 }
 
+// Print the objects a page at a time as the server returns them, so large listings show
+// up at once and Ctrl-C can stop them between pages. Up to `MAX_CONCURRENT_QUERIES`
+// classes are paged through at a time, and a class only asks for its next page once the
+// previous one has been printed, so memory stays bounded by the pages in flight.
+fn stream_objects(
+    client: &SyncClient<Authenticated>,
+    class_ids: &[i32],
+    list: &ListFilter<ObjectList>,
+    conditions: &[DataCondition],
+) -> Result<(), AppError> {
+    if !matches!(get_format()?, OutputFormat::Text | OutputFormat::Jsonl) {
+        return Err(AppError::InvalidOption(
+            "--stream needs text or jsonl output".to_string(),
+        ));
+    }
+
+    let filters = IntoResourceFilter::<Object>::into_resource_filter(list);
+    let next_class = AtomicUsize::new(0);
+    let stop = AtomicBool::new(false);
+    let mut shown = 0;
+
+    let interrupted = std::thread::scope(|s| -> Result<bool, AppError> {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(0);
+        for _ in 0..class_ids.len().min(MAX_CONCURRENT_QUERIES) {
+            let sender = sender.clone();
+            let (filters, next_class, stop) = (&filters, &next_class, &stop);
+            s.spawn(move || {
+                while let Some(&class_id) =
+                    class_ids.get(next_class.fetch_add(1, Ordering::Relaxed))
+                {
+                    let send = |page| !stop.load(Ordering::Relaxed) && sender.send(page).is_ok();
+                    if !stream_class(client, class_id, filters, conditions, send) {
+                        return;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let result = (|| -> Result<bool, AppError> {
+            for page in &receiver {
+                if take_interrupt() {
+                    return Ok(true);
+                }
+                let page: Vec<Object> = page?;
+                if page.is_empty() {
+                    continue;
+                }
+                let classmap =
+                    find_entities_by_ids(&client.classes(), &page, |o| o.hubuum_class_id)?;
+                let nsmap = find_entities_by_ids(&client.namespaces(), &page, |o| o.namespace_id)?;
+                let rows = page
+                    .iter()
+                    .map(|o| FormattedObject::new(o, &classmap, &nsmap))
+                    .collect::<Vec<_>>();
+                rows.format()?;
+                flush_output()?;
+                shown += rows.len();
+            }
+            Ok(false)
+        })();
+        // Let the workers see that nobody is listening any more, and stop asking for pages.
+        stop.store(true, Ordering::Relaxed);
+        drop(receiver);
+        result
+    })?;
+
+    if interrupted {
+        add_warning(tr_args(
+            "Interrupted after {count} objects",
+            &[("count", &shown)],
+        ))?;
+    } else if shown == 0 {
+        append_line(tr("No objects found"))?;
+    }
+    Ok(())
+}
+
+// Page through the objects of a class, handing each page (or the error that ended the
+// listing) to `send`. The `--where` conditions are sent to the server, unless it rejects
+// them, in which case they are applied to each page here. Returns false once `send` does,
+// i.e. when the listing was stopped.
+fn stream_class(
+    client: &SyncClient<Authenticated>,
+    class_id: i32,
+    filters: &[QueryFilter],
+    conditions: &[DataCondition],
+    send: impl Fn(Result<Vec<Object>, AppError>) -> bool,
+) -> bool {
+    let path = format!("/api/v1/classes/{}/", class_id);
+    let mut server_filters = filters
+        .iter()
+        .cloned()
+        .chain(conditions.iter().map(DataCondition::query_filter))
+        .collect::<Vec<_>>();
+    let mut filter_locally = false;
+    let mut cursor = None;
+
+    loop {
+        let page = match get_page::<Object>(
+            client,
+            &path,
+            &server_filters,
+            STREAM_PAGE_SIZE,
+            cursor.as_deref(),
+        ) {
+            Err(AppError::HttpError(message))
+                if !filter_locally
+                    && cursor.is_none()
+                    && !conditions.is_empty()
+                    && is_rejected_filter(http_status(&message), &message) =>
+            {
+                if let Err(err) = add_warning(tr_args(
+                    "The server does not support filtering on {conditions}, filtering locally",
+                    &[("conditions", &conditions.iter().commafy())],
+                )) {
+                    return send(Err(err));
+                }
+                server_filters = filters.to_vec();
+                filter_locally = true;
+                continue;
+            }
+            Err(err) => return send(Err(err)),
+            Ok(page) => page,
+        };
+
+        let mut objects = page.items;
+        if filter_locally {
+            objects.retain(|o| conditions.iter().all(|c| c.matches(o.data.as_ref())));
+        }
+        if !send(Ok(objects)) {
+            return false;
+        }
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => return true,
+        }
+    }
+}
+
+fn find_objects(
+    client: &SyncClient<Authenticated>,
+    class_id: i32,
+    list: &ListFilter<ObjectList>,
+    conditions: &[DataCondition],
+) -> Result<Vec<Object>, AppError> {
+    if conditions.is_empty() {
+        Ok(client.objects(class_id).filter(list)?)
+    } else {
+        find_objects_where(client, class_id, list, conditions)
+    }
+}
+
+// Try to filter on the object data server-side. If the server rejects the data filter,
+// fetch the objects matching the other options and apply the conditions locally.
+fn find_objects_where(
+    client: &SyncClient<Authenticated>,
+    class_id: i32,
+    list: &ListFilter<ObjectList>,
+    conditions: &[DataCondition],
+) -> Result<Vec<Object>, AppError> {
+    let filters = IntoResourceFilter::<Object>::into_resource_filter(list);
+
+    let mut query = client.objects(class_id).find();
+    for filter in filters
+        .into_iter()
+        .chain(conditions.iter().map(DataCondition::query_filter))
+    {
+        query = query.add_filter(&filter.key, filter.operator, &filter.value);
+    }
+
+    match query.execute() {
+        Ok(objects) => Ok(objects),
+        Err(err) if is_unsupported_filter(&err) => {
+            add_warning(tr_args(
+                "The server does not support filtering on {conditions}, filtering locally",
+                &[("conditions", &conditions.iter().commafy())],
+            ))?;
+            let objects = client.objects(class_id).filter(list)?;
+            Ok(objects
+                .into_iter()
+                .filter(|o| conditions.iter().all(|c| c.matches(o.data.as_ref())))
+                .collect())
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+// Whether the server rejected a query because it does not support filtering on the JSON
+// data, which it answers with a 400 or 422 naming the filter. Other errors, such as a
+// missing permission or a server failure, are not a reason to retry without the filter.
+fn is_unsupported_filter(err: &ApiError) -> bool {
+    let ApiError::HttpWithBody { status, message } = err else {
+        return false;
+    };
+    is_rejected_filter(http_status(&status.to_string()), message)
+}
+
+fn is_rejected_filter(status: Option<u16>, message: &str) -> bool {
+    let message = message.to_lowercase();
+    matches!(status, Some(400 | 422))
+        && (message.contains("json_data") || message.contains("filter"))
+}
+
+// The status code at the start of e.g. "400" or "400 Bad Request: <body>".
+fn http_status(message: &str) -> Option<u16> {
+    message
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .next()
+        .and_then(|s| s.parse::<u16>().ok())
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
 #[command_info(
-    about = "Modify an object",
-    long_about = "Modify an object in a specific class with the specified properties.",
-    examples = r#"-n MyObject -c MyClaass -N namespace_1 -d "My object description"
---name MyObject --class MyClass --namespace namespace_1 --description 'My object' --data foo.bar=4"#
+    about = "Count objects",
+    long_about = "Count the objects in a class, optionally matching a name or description.",
+    examples = r#"-c MyClass
+--class MyClass --name web"#,
+    cancellable = "true"
 )]
-pub struct ObjectModify {
+pub struct ObjectCount {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Name of the class",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: Option<String>,
+    #[option(short = "d", long = "description", help = "Description of the class")]
+    pub description: Option<String>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression, e.g. 'name__startswith=web,data__env=prod'"
+    )]
+    pub filter: Option<String>,
+}
+
+impl IntoResourceFilter<Object> for &ObjectCount {
+    fn into_resource_filter(self) -> Vec<QueryFilter> {
+        object_filters(self.name.as_deref(), self.description.as_deref())
+    }
+}
+
+impl CliCommand for ObjectCount {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let filter = ListFilter::new(&new, new.filter.as_deref())?;
+        let class = find_class_by_name(client, &new.class)?;
+
+        if IntoResourceFilter::<Object>::into_resource_filter(&filter).is_empty() {
+            return format_count(count_objects(client, class.id)?);
+        }
+
+        // Counts can't be filtered, so we have to fetch the matches to count them.
+        let objects = retry_api(|| client.objects(class.id).filter(&filter))?;
+        format_count(objects.len())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Modify an object",
+    long_about = "Modify an object in a specific class with the specified properties. --unset removes keys or array elements from the data, --append adds a value to a list, creating it if needed, and --remove-item removes every element equal to a value from a list. They are applied in that order after --data, and --unset and --remove-item fail if there is nothing to remove unless --ignore-missing is given. Without --data or --unset, if the object lacks properties the JSON schema of its class requires, you are offered to enter them one by one.",
+    examples = r#"-n MyObject -c MyClaass -N namespace_1 -d "My object description"
+--name MyObject --class MyClass --namespace namespace_1 --description 'My object' --data foo.bar=4
+-n MyObject -c MyClass --unset foo.bar --unset 'tags[0]'
+-n MyObject -c MyClass --append tags=web --remove-item ports=8080"#,
+    mutating = "true"
+)]
+pub struct ObjectModify {
     #[option(
         short = "n",
         long = "name",
@@ -401,7 +1090,8 @@ pub struct ObjectModify {
         short = "c",
         long = "class",
         help = "Name of the class the object belongs to",
-        autocomplete = "classes"
+        autocomplete = "classes",
+        context = "true"
     )]
     pub class: String,
     #[option(short = "r", long = "rename", help = "Rename object")]
@@ -424,6 +1114,30 @@ pub struct ObjectModify {
     pub description: Option<String>,
     #[option(short = "D", long = "data", help = "JSON data for the object")]
     pub data: Option<String>,
+    #[option(
+        long = "unset",
+        help = "Remove a key or array element from the data, may be repeated",
+        autocomplete = "data_paths"
+    )]
+    pub unset: Option<String>,
+    #[option(
+        long = "append",
+        help = "Append a value to a list in the data as path=value, may be repeated",
+        autocomplete = "data_paths"
+    )]
+    pub append: Option<String>,
+    #[option(
+        long = "remove-item",
+        help = "Remove a value from a list in the data as path=value, may be repeated",
+        autocomplete = "data_paths"
+    )]
+    pub remove_item: Option<String>,
+    #[option(
+        long = "ignore-missing",
+        help = "Do not fail if a key to unset or an item to remove does not exist",
+        flag = "true"
+    )]
+    pub ignore_missing: Option<bool>,
 }
 
 impl CliCommand for ObjectModify {
@@ -438,14 +1152,47 @@ impl CliCommand for ObjectModify {
 
         let mut patch = ObjectPatch::default();
 
-        if let Some(data) = &new.data.clone() {
-            let jqesque = data.parse::<Jqesque>()?;
-            let mut json_data = serde_json::Value::Null;
-            if object.data.is_some() {
-                json_data = object.data.unwrap().clone();
+        let unset = repeated_values(tokens, &["unset"], &new.unset)
+            .iter()
+            .map(|path| path.parse::<DataPath>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let append = repeated_values(tokens, &["append"], &new.append)
+            .iter()
+            .map(|item| item.parse::<DataItem>())
+            .collect::<Result<Vec<_>, _>>()?;
+        let remove_items = repeated_values(tokens, &["remove-item"], &new.remove_item)
+            .iter()
+            .map(|item| item.parse::<DataItem>())
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if new.data.is_some() || !unset.is_empty() || !append.is_empty() || !remove_items.is_empty()
+        {
+            let mut json_data = object.data.clone().unwrap_or(serde_json::Value::Null);
+            if let Some(data) = &new.data {
+                data.parse::<Jqesque>()?.apply_to(&mut json_data)?;
+            }
+            for path in &unset {
+                if !path.remove(&mut json_data) && new.ignore_missing.is_none() {
+                    return Err(AppError::EntityNotFound(format!(
+                        "{} in the data of {}",
+                        path, object.name
+                    )));
+                }
+            }
+            for item in &append {
+                item.append(&mut json_data)?;
+            }
+            for item in &remove_items {
+                if item.remove_from(&mut json_data)? == 0 && new.ignore_missing.is_none() {
+                    return Err(AppError::EntityNotFound(format!(
+                        "{} in {} in the data of {}",
+                        item.value, item.path, object.name
+                    )));
+                }
             }
-            jqesque.apply_to(&mut json_data)?;
             patch.data = Some(json_data);
+        } else if let Some(schema) = &class.json_schema {
+            patch.data = prompt_required_data(&class.name, schema, object.data.as_ref())?;
         }
 
         if let Some(namespace) = &new.namespace {
@@ -461,6 +1208,7 @@ impl CliCommand for ObjectModify {
             patch.description = Some(description.clone());
         }
 
+        snapshot_before(client, &class.name, &object, "modify")?;
         let result = client.objects(class.id).update(object.id, patch)?;
 
         let mut classmap = HashMap::new();
@@ -481,3 +1229,764 @@ impl CliCommand for ObjectModify {
         Ok(())
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Snapshot an object",
+    long_about = "Save a timestamped copy of an object, including its full JSON data, to the local data directory.",
+    examples = r#"-c MyClass -n MyObject
+--class MyClass --name MyObject"#
+)]
+pub struct ObjectTakeSnapshot {
+    #[positional(index = 0, help = "Name of the object")]
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: Option<String>,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+}
+
+impl CliCommand for ObjectTakeSnapshot {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+
+        let class = find_class_by_name(client, &query.class)?;
+        let object = find_object_by_name(client, class.id, &query.name.unwrap())?;
+
+        let namespace = client
+            .namespaces()
+            .find()
+            .add_filter_id(object.namespace_id)
+            .execute_expecting_single_result()?;
+
+        let snapshot = ObjectSnapshot::new(&class.name, &namespace.name, &object);
+        save_object_snapshot(&snapshot)?;
+
+        append_line(format!(
+            "Snapshot {} of object '{}' saved",
+            snapshot.id(),
+            object.name
+        ))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Restore an object from a snapshot",
+    long_about = "Restore an object from a local snapshot. Besides the snapshots taken with object snapshot, one is taken automatically before every change or deletion of an object the CLI makes, keeping the last 10 of those. The latest snapshot is used unless a version from --list or a specific snapshot is given. If the object no longer exists it is recreated, otherwise it is reverted, after a snapshot of its current state is taken.",
+    examples = r#"-c MyClass -n MyObject --list
+-c MyClass -n MyObject
+-c MyClass -n MyObject --version 3
+--class MyClass --name MyObject --snapshot 20241206T101500.000"#,
+    mutating = "true"
+)]
+pub struct ObjectRestoreSnapshot {
+    #[positional(index = 0, help = "Name of the object")]
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: Option<String>,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "s",
+        long = "snapshot",
+        help = "Snapshot to restore, defaults to the latest"
+    )]
+    pub snapshot: Option<String>,
+    #[option(
+        short = "V",
+        long = "version",
+        help = "Version to restore, as numbered by --list",
+        conflicts_with = "snapshot"
+    )]
+    pub version: Option<usize>,
+    #[option(
+        short = "l",
+        long = "list",
+        help = "List available snapshots",
+        flag = "true"
+    )]
+    pub list: Option<bool>,
+}
+
+impl CliCommand for ObjectRestoreSnapshot {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let name = query.name.clone().unwrap();
+
+        let class = find_class_by_name(client, &query.class)?;
+        let snapshots = object_snapshots(&class.name, &name)?;
+
+        if snapshots.is_empty() {
+            return Err(AppError::EntityNotFound(format!(
+                "No snapshots of object '{}' in class '{}'",
+                name, class.name
+            )));
+        }
+
+        if query.list.is_some() {
+            let snapshots = snapshots
+                .iter()
+                .enumerate()
+                .map(|(i, s)| FormattedObjectSnapshot::new(s, i))
+                .collect::<Vec<_>>();
+            snapshots.format()?;
+            return Ok(());
+        }
+
+        let snapshot = match (&query.snapshot, query.version) {
+            (_, Some(version)) => {
+                let count = snapshots.len();
+                snapshots
+                    .into_iter()
+                    .nth(version.wrapping_sub(1))
+                    .ok_or_else(|| {
+                        AppError::EntityNotFound(format!(
+                            "Version {} of object '{}', there are {} versions",
+                            version, name, count
+                        ))
+                    })?
+            }
+            (Some(id), None) => snapshots
+                .into_iter()
+                .find(|s| &s.id() == id)
+                .ok_or_else(|| {
+                    AppError::EntityNotFound(format!("Snapshot {} of object '{}'", id, name))
+                })?,
+            (None, None) => snapshots.into_iter().last().unwrap(),
+        };
+
+        let saved = snapshot.object.clone();
+        let existing = client
+            .objects(class.id)
+            .find()
+            .add_filter_name_exact(&saved.name)
+            .execute()?;
+
+        let result = match existing.first() {
+            Some(object) => {
+                snapshot_before(client, &class.name, object, "restore")?;
+                let mut patch = ObjectPatch::default();
+                patch.name = Some(saved.name.clone());
+                patch.namespace_id = Some(saved.namespace_id);
+                patch.description = Some(saved.description.clone());
+                patch.data = saved.data.clone();
+                client.objects(class.id).update(object.id, patch)?
+            }
+            None => client.objects(class.id).create(ObjectPost {
+                name: saved.name.clone(),
+                hubuum_class_id: class.id,
+                namespace_id: saved.namespace_id,
+                description: saved.description.clone(),
+                data: saved.data.clone(),
+            })?,
+        };
+
+        append_line(format!(
+            "Restored object '{}' from snapshot {}",
+            result.name,
+            snapshot.id()
+        ))?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show the change history of an object",
+    long_about = "Show how an object changed, oldest first. The server keeps no history of objects, so the history is made from the snapshots stored on this machine: those taken with object snapshot, and the ones the CLI takes before it changes or deletes an object (only the newest 10 of those are kept). Each change is shown with the command from the local audit log that made it. Changes made with other clients or from other machines are not seen, only that the object differs from the snapshot before them. With --diff, each change is expanded to the fields it changed.",
+    examples = r#"-c Host web01
+-c Host -n web01 --diff
+-c Host web01 --last 5"#
+)]
+pub struct ObjectHistory {
+    #[positional(index = 0, help = "Name of the object")]
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: String,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "d",
+        long = "diff",
+        help = "Show the fields each change changed",
+        flag = "true"
+    )]
+    pub diff: Option<bool>,
+    #[option(short = "l", long = "last", help = "Only show the last N changes")]
+    pub last: Option<usize>,
+}
+
+/// One change to an object.
+#[derive(Debug, Serialize, Clone)]
+struct ObjectChange {
+    at: String,
+    user: String,
+    action: String,
+    changes: Vec<DataChange>,
+}
+
+impl CliCommand for ObjectHistory {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let class = find_class_by_name(client, &query.class)?;
+        let object = find_object_by_name(client, class.id, &query.name)?;
+
+        let mut history = local_history(&class.name, &object)?;
+        if let Some(last) = query.last {
+            history.drain(..history.len().saturating_sub(last));
+        }
+
+        if get_format()? != OutputFormat::Text {
+            return append_json(&history);
+        }
+        if history.is_empty() {
+            append_line(tr("No changes found"))?;
+        }
+        for change in history {
+            append_line(format!("{}  {}  {}", change.at, change.user, change.action))?;
+            if query.diff.is_none() {
+                continue;
+            }
+            for field in change.changes {
+                let show = |value: &Option<serde_json::Value>| {
+                    value.as_ref().map_or("-".to_string(), |v| v.to_string())
+                };
+                append_line(format!(
+                    "    {}: {} → {}",
+                    field.path,
+                    show(&field.before),
+                    show(&field.after)
+                ))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The history of an object from its local snapshots, oldest first. Each snapshot is
+// compared to the state after it, the next snapshot or the object as it is now, and
+// snapshots the object has not changed since are left out. The change is credited to the
+// first command in the audit log that targeted the object after the snapshot was taken,
+// which is the one the CLI took the snapshot for.
+fn local_history(class: &str, object: &Object) -> Result<Vec<ObjectChange>, AppError> {
+    let target = format!("{}/{}", class, object.name);
+    let commands = audit_log()?
+        .into_iter()
+        .filter(|entry| entry.success && entry.target.as_deref() == Some(target.as_str()))
+        .collect::<Vec<_>>();
+    let snapshots = object_snapshots(class, &object.name)?;
+
+    let mut history = Vec::new();
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        let after = snapshots.get(i + 1).map_or(object, |next| &next.object);
+        let changes = diff_data(&object_state(&snapshot.object)?, &object_state(after)?);
+        if changes.is_empty() {
+            continue;
+        }
+
+        // Snapshots are taken in UTC, the audit log is kept in local time.
+        let taken_at = Utc
+            .from_utc_datetime(&snapshot.taken_at)
+            .with_timezone(&Local)
+            .naive_local();
+        let command = commands.iter().find(|entry| entry.at >= taken_at);
+        history.push(ObjectChange {
+            at: taken_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            user: command.map_or("-".to_string(), |entry| entry.user.clone()),
+            action: match (command, &snapshot.reason) {
+                (Some(entry), _) => entry.line.clone(),
+                (None, Some(reason)) => reason.clone(),
+                (None, None) => tr("changed after snapshot"),
+            },
+            changes,
+        });
+    }
+    Ok(history)
+}
+
+// The fields of an object that a change can touch, leaving out the timestamps the server
+// keeps, which would show up in every change.
+fn object_state(object: &Object) -> Result<serde_json::Value, AppError> {
+    let mut state = serde_json::to_value(object)?;
+    if let Some(fields) = state.as_object_mut() {
+        fields.remove("created_at");
+        fields.remove("updated_at");
+    }
+    Ok(state)
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Import objects from a CSV file",
+    long_about = "Create or update the objects of a class from the rows of a CSV file with a header. Without a mapping, the columns name, description and namespace go into those fields and every other column into the data under its own name. With --map or --map-file, each field=column pair names the column to read a field from, where data fields are written as data.<key> and may be nested, e.g. data.net.ip. Numbers and true/false are imported as such. Existing objects are updated, with the imported data merged into their data, and only move to another namespace if their row names one. Nothing is written before you confirm the import, unless --yes is given.",
+    examples = r#"-c Host -N infra -f hosts.csv
+--class Host --namespace infra --file hosts.csv --map 'name=hostname,data.ip=ip_address,data.os=os'
+--class Host --file hosts.csv --map-file hosts.map --plan
+-c Host -f hosts.csv --yes"#,
+    mutating = "true"
+)]
+pub struct ObjectImport {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the objects",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "f",
+        long = "file",
+        help = "CSV file with a header",
+        path = "true"
+    )]
+    pub file: String,
+    #[option(
+        short = "N",
+        long = "namespace",
+        help = "Namespace of new objects whose row has none",
+        autocomplete = "namespaces"
+    )]
+    pub namespace: Option<String>,
+    #[option(
+        short = "m",
+        long = "map",
+        help = "Columns to import, e.g. 'name=hostname,data.ip=ip_address'",
+        conflicts_with = "map_file"
+    )]
+    pub map: Option<String>,
+    #[option(
+        long = "map-file",
+        help = "File with one field=column pair per line",
+        path = "true"
+    )]
+    pub map_file: Option<String>,
+    #[option(
+        long = "plan",
+        help = "Show the changes that will be made field by field",
+        flag = "true"
+    )]
+    pub plan: Option<bool>,
+    #[option(
+        short = "y",
+        long = "yes",
+        help = "Import without asking",
+        flag = "true"
+    )]
+    pub yes: Option<bool>,
+}
+
+// A row of the file, ready to be created as a new object or to update an existing one.
+enum ImportRow {
+    Create(ObjectPost),
+    Update(Object, ObjectPatch),
+    Unchanged,
+}
+
+impl CliCommand for ObjectImport {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = &self.new_from_tokens(tokens)?;
+        let class = find_class_by_name(client, &new.class)?;
+
+        let mut reader = csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_path(&new.file)?;
+        let headers = reader
+            .headers()?
+            .iter()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+
+        let mapping = match (&new.map, &new.map_file) {
+            (Some(map), _) => ColumnMapping::parse(map)?,
+            (None, Some(file)) => ColumnMapping::parse(&std::fs::read_to_string(file)?)?,
+            (None, None) => ColumnMapping::from_headers(&headers)?,
+        };
+        mapping.check_headers(&headers)?;
+
+        let mut rows = Vec::new();
+        for (i, record) in reader.records().enumerate() {
+            let record = record?.iter().map(str::to_string).collect::<Vec<_>>();
+            // The header is line 1.
+            rows.push((i + 2, mapping.map_row(&headers, &record)));
+        }
+
+        if rows.is_empty() {
+            append_line("No objects found in file")?;
+            return Ok(());
+        }
+
+        let existing = client
+            .objects(class.id)
+            .find()
+            .execute()?
+            .into_iter()
+            .map(|object| (object.name.clone(), object))
+            .collect::<HashMap<_, _>>();
+
+        // Each namespace is looked up once, however many rows name it.
+        let mut namespaces: HashMap<String, i32> = HashMap::new();
+        let mut namespace_id = |name: &str| -> Result<i32, AppError> {
+            if let Some(id) = namespaces.get(name) {
+                return Ok(*id);
+            }
+            let id = find_namespace_by_name(client, name)?.id;
+            namespaces.insert(name.to_string(), id);
+            Ok(id)
+        };
+        let mut imports = Vec::new();
+        let mut errors = Vec::new();
+        let mut unchanged = 0;
+
+        let progress = progress_bar(rows.len(), "Validating");
+        for (line, row) in rows {
+            let imported = import_row(
+                class.id,
+                new.namespace.as_deref(),
+                &existing,
+                row,
+                &mut namespace_id,
+            );
+            match imported {
+                Ok(ImportRow::Unchanged) => unchanged += 1,
+                Ok(import) => imports.push((line, import)),
+                Err(err) => errors.push(format!("Line {}: {}", line, err)),
+            }
+            progress.inc(1);
+        }
+        finish_progress(&progress);
+
+        if !errors.is_empty() {
+            for error in errors {
+                add_error(error)?;
+            }
+            add_warning("No objects were imported")?;
+            return Err(AppError::Quiet);
+        }
+
+        if imports.is_empty() {
+            append_line(tr_args(
+                "{count} objects were already up to date",
+                &[("count", &unchanged)],
+            ))?;
+            return Ok(());
+        }
+
+        if new.plan.is_some() {
+            let mut plan = Plan::new();
+            for (_, import) in &imports {
+                match import {
+                    ImportRow::Create(post) => plan.create(
+                        "object",
+                        &post.name,
+                        &object_fields(post.namespace_id, &post.description, post.data.as_ref()),
+                    )?,
+                    ImportRow::Update(object, patch) => plan.update(
+                        "object",
+                        &object.name,
+                        &object_fields(
+                            object.namespace_id,
+                            &object.description,
+                            object.data.as_ref(),
+                        ),
+                        &object_fields(
+                            patch.namespace_id.unwrap_or(object.namespace_id),
+                            patch.description.as_ref().unwrap_or(&object.description),
+                            patch.data.as_ref().or(object.data.as_ref()),
+                        ),
+                    )?,
+                    ImportRow::Unchanged => {}
+                }
+            }
+            plan.format(0)?;
+        } else {
+            let created = imports
+                .iter()
+                .filter(|(_, import)| matches!(import, ImportRow::Create(_)))
+                .count();
+            append_line(tr_args(
+                "{created} objects will be created and {updated} updated",
+                &[
+                    ("created", &created),
+                    ("updated", &(imports.len() - created)),
+                ],
+            ))?;
+        }
+        flush_output()?;
+
+        if new.yes.is_none() && !confirm(&tr("Apply this plan?"))? {
+            append_line(tr("Plan not applied"))?;
+            return Ok(());
+        }
+
+        let total = imports.len();
+        let mut imported = 0;
+        let progress = progress_bar(total, "Importing");
+        for (line, import) in imports {
+            if take_interrupt() {
+                break;
+            }
+            let result = match import {
+                ImportRow::Create(post) => client
+                    .objects(class.id)
+                    .create(post)
+                    .map_err(AppError::from),
+                ImportRow::Update(object, patch) => {
+                    snapshot_before(client, &class.name, &object, "import").and_then(|_| {
+                        client
+                            .objects(class.id)
+                            .update(object.id, patch)
+                            .map_err(AppError::from)
+                    })
+                }
+                ImportRow::Unchanged => continue,
+            };
+            match result {
+                Ok(_) => imported += 1,
+                Err(err) => add_error(format!("Line {}: {}", line, err))?,
+            }
+            progress.inc(1);
+        }
+        finish_progress(&progress);
+
+        append_line(tr_args(
+            "Imported {imported} of {total} objects",
+            &[("imported", &imported), ("total", &total)],
+        ))?;
+        if unchanged > 0 {
+            append_line(tr_args(
+                "{count} objects were already up to date",
+                &[("count", &unchanged)],
+            ))?;
+        }
+
+        Ok(())
+    }
+}
+
+// What to do with a row of an import. The default namespace is only for new objects,
+// existing objects only move if the row itself names a namespace.
+fn import_row(
+    class_id: i32,
+    default_namespace: Option<&str>,
+    existing: &HashMap<String, Object>,
+    row: MappedRow,
+    namespace_id: &mut impl FnMut(&str) -> Result<i32, AppError>,
+) -> Result<ImportRow, AppError> {
+    let name = row
+        .name
+        .ok_or_else(|| AppError::MissingOptions(vec!["name".to_string()]))?;
+
+    if let Some(object) = existing.get(&name) {
+        let namespace_id = match &row.namespace {
+            Some(namespace) => Some(namespace_id(namespace)?),
+            None => None,
+        };
+        // Only what the row changes is sent, and rows that change nothing are skipped.
+        let data = (!row.data.is_empty()).then(|| merge_data(object.data.as_ref(), &row.data));
+        let patch = ObjectPatch {
+            namespace_id: namespace_id.filter(|id| *id != object.namespace_id),
+            description: row.description.filter(|d| *d != object.description),
+            data: data.filter(|d| Some(d) != object.data.as_ref()),
+            ..Default::default()
+        };
+        if patch.namespace_id.is_none() && patch.description.is_none() && patch.data.is_none() {
+            return Ok(ImportRow::Unchanged);
+        }
+        return Ok(ImportRow::Update(object.clone(), patch));
+    }
+
+    let namespace = row
+        .namespace
+        .as_deref()
+        .or(default_namespace)
+        .ok_or_else(|| AppError::MissingOptions(vec!["namespace".to_string()]))?;
+    Ok(ImportRow::Create(ObjectPost {
+        name: name.clone(),
+        hubuum_class_id: class_id,
+        namespace_id: namespace_id(namespace)?,
+        description: row.description.unwrap_or(name),
+        data: (!row.data.is_empty()).then_some(serde_json::Value::Object(row.data)),
+    }))
+}
+
+// The fields an import can change, for showing updates in a plan.
+fn object_fields(
+    namespace_id: i32,
+    description: &str,
+    data: Option<&serde_json::Value>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "namespace_id": namespace_id,
+        "description": description,
+        "data": data,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn existing() -> HashMap<String, Object> {
+        let object: Object = serde_json::from_value(json!({
+            "id": 7,
+            "name": "web01",
+            "description": "Web server",
+            "namespace_id": 1,
+            "hubuum_class_id": 3,
+            "data": {"os": "linux"},
+            "created_at": "2024-01-01T00:00:00",
+            "updated_at": "2024-01-01T00:00:00",
+        }))
+        .unwrap();
+        HashMap::from([(object.name.clone(), object)])
+    }
+
+    fn row(name: &str, namespace: Option<&str>, data: serde_json::Value) -> MappedRow {
+        MappedRow {
+            name: Some(name.to_string()),
+            description: None,
+            namespace: namespace.map(str::to_string),
+            data: data.as_object().cloned().unwrap_or_default(),
+        }
+    }
+
+    fn namespace_id(name: &str) -> Result<i32, AppError> {
+        match name {
+            "infra" => Ok(1),
+            "lab" => Ok(2),
+            _ => Err(AppError::EntityNotFound(name.to_string())),
+        }
+    }
+
+    #[test]
+    fn test_default_namespace_is_used_for_new_objects() {
+        let import = import_row(
+            3,
+            Some("lab"),
+            &existing(),
+            row("web02", None, json!({})),
+            &mut namespace_id,
+        )
+        .unwrap();
+        match import {
+            ImportRow::Create(post) => assert_eq!(post.namespace_id, 2),
+            _ => panic!("expected a create"),
+        }
+    }
+
+    #[test]
+    fn test_default_namespace_does_not_move_existing_objects() {
+        let import = import_row(
+            3,
+            Some("lab"),
+            &existing(),
+            row("web01", None, json!({"os": "linux"})),
+            &mut namespace_id,
+        )
+        .unwrap();
+        assert!(matches!(import, ImportRow::Unchanged));
+    }
+
+    #[test]
+    fn test_row_namespace_moves_existing_objects() {
+        let import = import_row(
+            3,
+            None,
+            &existing(),
+            row("web01", Some("lab"), json!({})),
+            &mut namespace_id,
+        )
+        .unwrap();
+        match import {
+            ImportRow::Update(_, patch) => {
+                assert_eq!(patch.namespace_id, Some(2));
+                assert!(patch.data.is_none());
+                assert!(patch.description.is_none());
+            }
+            _ => panic!("expected an update"),
+        }
+    }
+
+    #[test]
+    fn test_only_changed_data_is_sent() {
+        let import = import_row(
+            3,
+            None,
+            &existing(),
+            row("web01", Some("infra"), json!({"env": "prod"})),
+            &mut namespace_id,
+        )
+        .unwrap();
+        match import {
+            ImportRow::Update(_, patch) => {
+                assert_eq!(patch.namespace_id, None);
+                assert_eq!(patch.data, Some(json!({"os": "linux", "env": "prod"})));
+            }
+            _ => panic!("expected an update"),
+        }
+    }
+
+    #[test]
+    fn test_new_objects_need_a_namespace() {
+        let import = import_row(
+            3,
+            None,
+            &existing(),
+            row("web02", None, json!({})),
+            &mut namespace_id,
+        );
+        assert!(matches!(import, Err(AppError::MissingOptions(_))));
+    }
+}
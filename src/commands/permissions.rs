@@ -0,0 +1,169 @@
+use std::collections::{BTreeMap, HashSet};
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, FilterOperator, SyncClient};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use super::shared::find_namespace_by_name;
+use super::CliCommand;
+use super::{CliCommandInfo, CliOption};
+
+use crate::api::{get_json, retry_api};
+use crate::autocomplete::{groups, namespaces, users};
+use crate::errors::AppError;
+use crate::formatting::{FormattedPermissions, OutputFormatter};
+use crate::i18n::tr;
+use crate::models::OutputFormat;
+use crate::output::{append_line, get_format};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show who may do what in namespaces",
+    long_about = "Show the permissions groups have in namespaces as a matrix, with a column per kind of entity and a letter per action granted: C for create, R for read, U for update, D for delete and G for delegate. Limit it to a namespace, a group, or a user, for whom the permissions of all their groups are combined.",
+    examples = r#"--namespace production
+--group operators
+--user alice --namespace production"#,
+    cancellable = "true"
+)]
+pub struct PermissionsShow {
+    #[option(
+        short = "N",
+        long = "namespace",
+        help = "Only show this namespace",
+        autocomplete = "namespaces",
+        context = "true"
+    )]
+    pub namespace: Option<String>,
+    #[option(
+        short = "g",
+        long = "group",
+        help = "Only show this group",
+        autocomplete = "groups"
+    )]
+    pub group: Option<String>,
+    #[option(
+        short = "u",
+        long = "user",
+        help = "Show the combined permissions of the groups of this user",
+        autocomplete = "users",
+        conflicts_with = "group"
+    )]
+    pub user: Option<String>,
+}
+
+impl CliCommand for PermissionsShow {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+        let namespaces = match &query.namespace {
+            Some(name) => vec![find_namespace_by_name(client, name)?],
+            None => retry_api(|| client.namespaces().find().execute())?,
+        };
+        let mut groups = retry_api(|| client.groups().find().execute())?;
+        if let Some(name) = &query.group {
+            groups.retain(|group| &group.groupname == name);
+            if groups.is_empty() {
+                return Err(AppError::EntityNotFound(format!("group {}", name)));
+            }
+        }
+        if let Some(username) = &query.user {
+            let user = client
+                .users()
+                .find()
+                .add_filter(
+                    "username",
+                    FilterOperator::Equals { is_negated: false },
+                    username,
+                )
+                .execute_expecting_single_result()?;
+            let mut member_of = HashSet::new();
+            for group in &groups {
+                let members =
+                    get_json(client, &format!("/api/v1/iam/groups/{}/members", group.id))?;
+                if members
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|m| m.get("id").and_then(Value::as_i64) == Some(user.id as i64))
+                {
+                    member_of.insert(group.id);
+                }
+            }
+            groups.retain(|group| member_of.contains(&group.id));
+        }
+        let group_names = groups
+            .iter()
+            .map(|g| (g.id, g.groupname.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        let mut rows = Vec::new();
+        for namespace in &namespaces {
+            let entries = get_json(
+                client,
+                &format!("/api/v1/namespaces/{}/permissions", namespace.id),
+            )?;
+            let entries = entries
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| {
+                    let id = entry.get("group_id").and_then(Value::as_i64)? as i32;
+                    group_names.get(&id).map(|name| (name.clone(), entry))
+                })
+                .collect::<Vec<_>>();
+            if entries.is_empty() {
+                continue;
+            }
+
+            match &query.user {
+                Some(username) => {
+                    let names = entries
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>();
+                    let combined = combine(entries.iter().map(|(_, entry)| *entry));
+                    rows.push(FormattedPermissions::new(
+                        namespace.id,
+                        &namespace.name,
+                        &format!("{} ({})", username, names.join(", ")),
+                        &combined,
+                    ));
+                }
+                None => rows.extend(entries.iter().map(|(group, entry)| {
+                    FormattedPermissions::new(namespace.id, &namespace.name, group, entry)
+                })),
+            }
+        }
+
+        if rows.is_empty() {
+            append_line(tr("No permissions found"))?;
+            return Ok(());
+        }
+        rows.format()?;
+        if get_format()? == OutputFormat::Text {
+            append_line(tr(
+                "C create, R read, U update, D delete, G delegate, - not granted",
+            ))?;
+        }
+        Ok(())
+    }
+}
+
+// The permissions granted by any of the entries.
+fn combine<'a>(entries: impl Iterator<Item = &'a Value>) -> Value {
+    let mut combined = Map::new();
+    for (key, value) in entries.filter_map(Value::as_object).flatten() {
+        if !key.starts_with("has_") {
+            continue;
+        }
+        let granted = value.as_bool() == Some(true)
+            || combined.get(key).and_then(Value::as_bool) == Some(true);
+        combined.insert(key.clone(), Value::Bool(granted));
+    }
+    Value::Object(combined)
+}
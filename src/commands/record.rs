@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::errors::AppError;
+use crate::files::get_transcript_dir;
+use crate::i18n::{tr, tr_args};
+use crate::output::{add_warning, append_line};
+use crate::recording::{recording_path, start_recording, stop_recording};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Record the session to a file",
+    long_about = "Write the commands run from now on, with timestamps and the output they give, to a file. Without --file the transcript goes into the transcripts directory under the data directory. Stop the recording with 'record stop'.",
+    examples = r#"--file change-1234.log"#
+)]
+pub struct RecordStart {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "File to record to, appended to if it exists",
+        path = "true"
+    )]
+    pub file: Option<String>,
+}
+
+impl CliCommand for RecordStart {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let path = match new.file {
+            Some(file) => PathBuf::from(file),
+            None => get_transcript_dir()?.join(format!(
+                "session-{}.log",
+                chrono::Local::now().format("%Y%m%d-%H%M%S")
+            )),
+        };
+
+        if let Some(previous) = stop_recording()? {
+            add_warning(tr_args(
+                "Stopped recording to {file}",
+                &[("file", &previous.display())],
+            ))?;
+        }
+        start_recording(&path)?;
+        append_line(tr_args("Recording to {file}", &[("file", &path.display())]))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Stop recording the session",
+    long_about = "Stop the recording started with 'record start'."
+)]
+pub struct RecordStop {}
+
+impl CliCommand for RecordStop {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        match stop_recording()? {
+            Some(path) => append_line(tr_args(
+                "Stopped recording to {file}",
+                &[("file", &path.display())],
+            )),
+            None => add_warning(tr("Not recording")),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show whether the session is recorded",
+    long_about = "Show the file the session is being recorded to, if any."
+)]
+pub struct RecordStatus {}
+
+impl CliCommand for RecordStatus {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        match recording_path()? {
+            Some(path) => append_line(tr_args("Recording to {file}", &[("file", &path.display())])),
+            None => append_line(tr("Not recording")),
+        }
+    }
+}
@@ -1,7 +1,10 @@
 use cli_command_derive::CliCommand;
 
-use hubuum_client::{Authenticated, Class, ClassRelationPost, ObjectRelationPost, SyncClient};
+use hubuum_client::{
+    Authenticated, Class, ClassRelation, ClassRelationPost, Object, ObjectRelationPost, SyncClient,
+};
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::collections::HashMap;
 use std::vec;
 
@@ -15,7 +18,11 @@ use crate::errors::AppError;
 use crate::formatting::{
     FormattedClassRelation, FormattedObjectRelation, OutputFormatter, OutputFormatterWithPadding,
 };
-use crate::output::append_line;
+use crate::i18n::tr;
+use crate::interrupt::take_interrupt;
+use crate::output::{add_error, add_warning, append_line, flush_output};
+use crate::plan::{confirm, Plan};
+use crate::progress::{finish_progress, progress_bar};
 use crate::tokenizer::CommandTokenizer;
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
@@ -24,7 +31,8 @@ use crate::tokenizer::CommandTokenizer;
     long_about = "Create a new relationship between classes or objects.",
     examples = r#"--class_from FromClass --class_to ToClass
     --class_from FromClass --class_to ToClass --object_from FromObject --object_to ToObject
-    "#
+    "#,
+    mutating = "true"
 )]
 pub struct RelationNew {
     #[option(
@@ -63,7 +71,8 @@ pub struct RelationNew {
     long_about = "Delete a new relationship between classes or objects.",
     examples = r#"--class_from FromClass --class_to ToClass
     --class_from FromClass --class_to ToClass --object_from FromObject --object_to ToObject
-    "#
+    "#,
+    mutating = "true"
 )]
 pub struct RelationDelete {
     #[option(
@@ -102,7 +111,8 @@ pub struct RelationDelete {
     long_about = "List relationships between classes or objects.",
     examples = r#"--class_from FromClass --class_to ToClass
     --class_from FromClass --class_to ToClass --object_from FromObject --object_to ToObject
-    "#
+    "#,
+    cancellable = "true"
 )]
 pub struct RelationList {
     #[option(
@@ -262,7 +272,7 @@ impl CliCommand for RelationList {
         let class_relations = query.execute()?;
 
         if class_relations.is_empty() {
-            println!("No relations found");
+            append_line(tr("No relations found"))?;
             return Ok(());
         }
 
@@ -375,7 +385,7 @@ impl CliCommand for RelationList {
         let object_relations = query.execute()?;
 
         if object_relations.is_empty() {
-            println!("No relations found");
+            append_line(tr("No relations found"))?;
             return Ok(());
         }
 
@@ -584,8 +594,14 @@ fn delete_object_relation(
 fn validate_object_names<T: HasObjectNames>(new: &T) -> Result<(String, String), AppError> {
     match (new.object_from(), new.object_to()) {
         (Some(from), Some(to)) => Ok((from.to_string(), to.to_string())),
-        (None, _) => Err(AppError::MissingOptions(vec!["object_from".to_string()])),
-        (_, None) => Err(AppError::MissingOptions(vec!["object_to".to_string()])),
+        (from, to) => {
+            let missing = [("object_from", from), ("object_to", to)]
+                .into_iter()
+                .filter(|(_, name)| name.is_none())
+                .map(|(option, _)| option.to_string())
+                .collect();
+            Err(AppError::MissingOptions(missing))
+        }
     }
 }
 
@@ -611,3 +627,226 @@ impl HasObjectNames for RelationDelete {
         &self.object_to
     }
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Import object relations",
+    long_about = "Create object relations in bulk from a CSV file. Each row names the class and object the relationship starts from, and the class and object it goes to (class_from,object_from,class_to,object_to). A header row is optional. Every row is validated against the server before any relation is created.",
+    examples = r#"--file edges.csv"#,
+    mutating = "true"
+)]
+pub struct RelationImport {
+    #[option(
+        short = "f",
+        long = "file",
+        help = "CSV file with class_from,object_from,class_to,object_to rows",
+        path = "true"
+    )]
+    pub file: String,
+    #[option(
+        long = "plan",
+        help = "Show the relations that will be created and ask before creating them",
+        flag = "true"
+    )]
+    pub plan: Option<bool>,
+    #[option(
+        short = "y",
+        long = "yes",
+        help = "Apply the plan without asking",
+        flag = "true",
+        requires = "plan"
+    )]
+    pub yes: Option<bool>,
+}
+
+impl CliCommand for RelationImport {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = &self.new_from_tokens(tokens)?;
+        let edges = read_edges(&new.file)?;
+
+        if edges.is_empty() {
+            append_line("No relations found in file")?;
+            return Ok(());
+        }
+
+        let mut resolver = EdgeResolver::new(client);
+        let mut posts = Vec::new();
+        let mut errors = Vec::new();
+
+        let progress = progress_bar(edges.len(), "Validating");
+        for edge in &edges {
+            match resolver.resolve(edge) {
+                Ok(post) => posts.push((edge, post)),
+                Err(err) => errors.push(format!("Line {}: {}", edge.line, err)),
+            }
+            progress.inc(1);
+        }
+        finish_progress(&progress);
+
+        if !errors.is_empty() {
+            for error in errors {
+                add_error(error)?;
+            }
+            add_warning("No relations were created")?;
+            return Err(AppError::Quiet);
+        }
+
+        if new.plan.is_some() {
+            let mut plan = Plan::new();
+            for (edge, _) in &posts {
+                plan.create(
+                    "object relation",
+                    &format!(
+                        "{}/{} -> {}/{}",
+                        edge.class_from, edge.object_from, edge.class_to, edge.object_to
+                    ),
+                    &json!({ "line": edge.line }),
+                )?;
+            }
+            plan.format(0)?;
+            flush_output()?;
+
+            if new.yes.is_none() && !confirm(&tr("Apply this plan?"))? {
+                append_line(tr("Plan not applied"))?;
+                return Ok(());
+            }
+        }
+
+        let mut created = 0;
+        let progress = progress_bar(posts.len(), "Creating");
+        for (edge, post) in posts {
+            if take_interrupt() {
+                break;
+            }
+            match client.object_relation().create(post) {
+                Ok(_) => created += 1,
+                Err(err) => add_error(format!("Line {}: {}", edge.line, err))?,
+            }
+            progress.inc(1);
+        }
+        finish_progress(&progress);
+
+        append_line(format!(
+            "Created {} of {} object relations",
+            created,
+            edges.len()
+        ))?;
+
+        Ok(())
+    }
+}
+
+/// A single row of a relation import file.
+struct Edge {
+    line: usize,
+    class_from: String,
+    object_from: String,
+    class_to: String,
+    object_to: String,
+}
+
+const EDGE_HEADER: [&str; 4] = ["class_from", "object_from", "class_to", "object_to"];
+
+fn read_edges(file: &str) -> Result<Vec<Edge>, AppError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_path(file)?;
+
+    let mut edges = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record?;
+        let line = i + 1;
+
+        if line == 1 && record.iter().eq(EDGE_HEADER.iter().copied()) {
+            continue;
+        }
+
+        if record.len() != EDGE_HEADER.len() || record.iter().any(|field| field.is_empty()) {
+            return Err(AppError::ParseError(format!(
+                "Line {}: expected {} non-empty columns ({})",
+                line,
+                EDGE_HEADER.len(),
+                EDGE_HEADER.join(",")
+            )));
+        }
+
+        edges.push(Edge {
+            line,
+            class_from: record[0].to_string(),
+            object_from: record[1].to_string(),
+            class_to: record[2].to_string(),
+            object_to: record[3].to_string(),
+        });
+    }
+
+    Ok(edges)
+}
+
+/// Resolves the names in an edge list to IDs, caching lookups as many rows share classes
+/// and objects.
+struct EdgeResolver<'a> {
+    client: &'a SyncClient<Authenticated>,
+    classes: HashMap<String, Class>,
+    class_relations: HashMap<(i32, i32), ClassRelation>,
+    objects: HashMap<(i32, String), Object>,
+}
+
+impl<'a> EdgeResolver<'a> {
+    fn new(client: &'a SyncClient<Authenticated>) -> Self {
+        EdgeResolver {
+            client,
+            classes: HashMap::new(),
+            class_relations: HashMap::new(),
+            objects: HashMap::new(),
+        }
+    }
+
+    fn class(&mut self, name: &str) -> Result<Class, AppError> {
+        if let Some(class) = self.classes.get(name) {
+            return Ok(class.clone());
+        }
+        let class = find_class_by_name(self.client, name)?;
+        self.classes.insert(name.to_string(), class.clone());
+        Ok(class)
+    }
+
+    fn class_relation(&mut self, from: &Class, to: &Class) -> Result<ClassRelation, AppError> {
+        if let Some(relation) = self.class_relations.get(&(from.id, to.id)) {
+            return Ok(relation.clone());
+        }
+        let relation = find_class_relation(self.client, from.id, to.id)?;
+        self.class_relations
+            .insert((from.id, to.id), relation.clone());
+        Ok(relation)
+    }
+
+    fn object(&mut self, class: &Class, name: &str) -> Result<Object, AppError> {
+        let key = (class.id, name.to_string());
+        if let Some(object) = self.objects.get(&key) {
+            return Ok(object.clone());
+        }
+        let object = find_object_by_name(self.client, class.id, name)?;
+        self.objects.insert(key, object.clone());
+        Ok(object)
+    }
+
+    fn resolve(&mut self, edge: &Edge) -> Result<ObjectRelationPost, AppError> {
+        let class_from = self.class(&edge.class_from)?;
+        let class_to = self.class(&edge.class_to)?;
+        let class_relation = self.class_relation(&class_from, &class_to)?;
+        let object_from = self.object(&class_from, &edge.object_from)?;
+        let object_to = self.object(&class_to, &edge.object_to)?;
+
+        Ok(ObjectRelationPost {
+            class_relation_id: class_relation.id,
+            from_hubuum_object_id: object_from.id,
+            to_hubuum_object_id: object_to.id,
+        })
+    }
+}
@@ -0,0 +1,193 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, FilterOperator, NamespacePost, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::api::{get_json, retry_api};
+use crate::autocomplete::groups;
+use crate::config::get_config;
+use crate::errors::AppError;
+use crate::i18n::{tr, tr_args};
+use crate::output::{add_error, append_line};
+use crate::progress::{finish_progress, progress_bar};
+use crate::sandbox::{current_sandbox, set_sandbox, Sandbox};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Start a sandbox namespace",
+    long_about = "Create a temporary namespace with a unique name. Until the sandbox is destroyed, create commands use it when no namespace is given.",
+    examples = r#"--group admins"#,
+    mutating = "true"
+)]
+pub struct SandboxStart {
+    #[option(
+        short = "g",
+        long = "group",
+        help = "Group owning the sandbox, defaults to your first group",
+        autocomplete = "groups"
+    )]
+    pub group: Option<String>,
+}
+
+impl CliCommand for SandboxStart {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+
+        if let Some(sandbox) = current_sandbox()? {
+            return Err(AppError::CommandExecutionError(tr_args(
+                "The sandbox {namespace} is already active",
+                &[("namespace", &sandbox.namespace)],
+            )));
+        }
+
+        let config = get_config()?;
+        let group_id = match &new.group {
+            Some(group) => {
+                client
+                    .groups()
+                    .find()
+                    .add_filter_name_exact(group)
+                    .execute_expecting_single_result()?
+                    .id
+            }
+            None => {
+                let user = client
+                    .users()
+                    .find()
+                    .add_filter(
+                        "username",
+                        FilterOperator::Equals { is_negated: false },
+                        &config.server.username,
+                    )
+                    .execute_expecting_single_result()?;
+                get_json(client, &format!("/api/v1/iam/users/{}/groups", user.id))?
+                    .as_array()
+                    .and_then(|groups| groups.first())
+                    .and_then(|group| group.get("id"))
+                    .and_then(|id| id.as_i64())
+                    .ok_or_else(|| AppError::MissingOptions(vec!["group".to_string()]))?
+                    as i32
+            }
+        };
+
+        let name = format!(
+            "sandbox-{}-{}",
+            config.server.username,
+            chrono::Utc::now().format("%Y%m%d%H%M%S")
+        );
+        let namespace = client.namespaces().create(NamespacePost {
+            name: name.clone(),
+            description: tr("Temporary sandbox namespace"),
+            group_id,
+        })?;
+
+        set_sandbox(Some(Sandbox {
+            namespace: namespace.name.clone(),
+            namespace_id: namespace.id,
+        }))?;
+
+        append_line(tr_args(
+            "Started sandbox {namespace}, remove it with 'sandbox destroy'",
+            &[("namespace", &namespace.name)],
+        ))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Destroy the sandbox namespace",
+    long_about = "Delete the sandbox namespace and every object and class in it.",
+    mutating = "true"
+)]
+pub struct SandboxDestroy {}
+
+impl CliCommand for SandboxDestroy {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+
+        let Some(sandbox) = current_sandbox()? else {
+            return Err(AppError::CommandExecutionError(tr("No sandbox is active")));
+        };
+
+        let classes = retry_api(|| client.classes().find().execute())?;
+        let mut objects = Vec::new();
+        for class in &classes {
+            let found = client
+                .objects(class.id)
+                .find()
+                .add_filter(
+                    "namespace_id",
+                    FilterOperator::Equals { is_negated: false },
+                    sandbox.namespace_id.to_string(),
+                )
+                .execute()?;
+            objects.extend(found.into_iter().map(|o| (class.id, o)));
+        }
+        let classes = classes
+            .into_iter()
+            .filter(|c| c.namespace.id == sandbox.namespace_id)
+            .collect::<Vec<_>>();
+
+        let mut failed = false;
+        let progress = progress_bar(objects.len() + classes.len(), "Destroying");
+        for (class_id, object) in &objects {
+            if let Err(err) = client.objects(*class_id).delete(object.id) {
+                add_error(format!("object {}: {}", object.name, err))?;
+                failed = true;
+            }
+            progress.inc(1);
+        }
+        for class in &classes {
+            if let Err(err) = client.classes().delete(class.id) {
+                add_error(format!("class {}: {}", class.name, err))?;
+                failed = true;
+            }
+            progress.inc(1);
+        }
+        finish_progress(&progress);
+
+        // Keep the sandbox active if we failed, so destroy can be retried.
+        if failed {
+            return Err(AppError::Quiet);
+        }
+
+        client.namespaces().delete(sandbox.namespace_id)?;
+        set_sandbox(None)?;
+
+        append_line(tr_args(
+            "Destroyed sandbox {namespace}",
+            &[("namespace", &sandbox.namespace)],
+        ))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(about = "Show the active sandbox")]
+pub struct SandboxStatus {}
+
+impl CliCommand for SandboxStatus {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+
+        match current_sandbox()? {
+            Some(sandbox) => append_line(sandbox.namespace),
+            None => append_line(tr("No sandbox is active")),
+        }
+    }
+}
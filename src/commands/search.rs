@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, Class, FilterOperator, Object, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::shared::{find_entities_by_ids, glob_matches, join_thread, MAX_CONCURRENT_QUERIES};
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::api::retry_api;
+use crate::autocomplete::classes;
+use crate::errors::AppError;
+use crate::formatting::{FormattedObject, OutputFormatter};
+use crate::i18n::{tr, tr_args};
+use crate::models::OutputFormat;
+use crate::output::{append_line, get_format};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Search for objects in all classes",
+    long_about = "Search the name and description of objects in all classes, optionally including their data. Results are grouped per class, with the best matches first.",
+    examples = r#"web
+--term web --class-glob 'net*'
+linux --data"#,
+    cancellable = "true"
+)]
+pub struct Search {
+    #[positional(index = 0, help = "Term to search for")]
+    #[option(short = "t", long = "term", help = "Term to search for")]
+    pub term: Option<String>,
+    #[option(
+        short = "c",
+        long = "class-glob",
+        help = "Only search classes matching this glob, e.g. 'net*'",
+        autocomplete = "classes"
+    )]
+    pub class_glob: Option<String>,
+    #[option(
+        long = "data",
+        help = "Also search the data of the objects",
+        flag = "true"
+    )]
+    pub data: Option<bool>,
+}
+
+// Lower is better.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchRank {
+    ExactName,
+    NamePrefix,
+    Name,
+    Description,
+    Data,
+}
+
+fn rank_object(object: &Object, term: &str) -> Option<MatchRank> {
+    let term = term.to_lowercase();
+    let name = object.name.to_lowercase();
+
+    if name == term {
+        Some(MatchRank::ExactName)
+    } else if name.starts_with(&term) {
+        Some(MatchRank::NamePrefix)
+    } else if name.contains(&term) {
+        Some(MatchRank::Name)
+    } else if object.description.to_lowercase().contains(&term) {
+        Some(MatchRank::Description)
+    } else if object
+        .data
+        .as_ref()
+        .is_some_and(|data| data.to_string().to_lowercase().contains(&term))
+    {
+        Some(MatchRank::Data)
+    } else {
+        None
+    }
+}
+
+fn search_class(
+    client: &SyncClient<Authenticated>,
+    class: &Class,
+    term: &str,
+    include_data: bool,
+) -> Result<Vec<(MatchRank, Object)>, AppError> {
+    // The API combines filters with AND, so name and description are separate queries.
+    // Data can not be searched server-side, so then we have to fetch every object.
+    let objects = if include_data {
+        retry_api(|| client.objects(class.id).find().execute())?
+    } else {
+        let mut objects = HashMap::new();
+        for field in ["name", "description"] {
+            let found = client
+                .objects(class.id)
+                .find()
+                .add_filter(field, FilterOperator::IContains { is_negated: false }, term)
+                .execute()?;
+            objects.extend(found.into_iter().map(|o| (o.id, o)));
+        }
+        objects.into_values().collect()
+    };
+
+    let mut ranked = objects
+        .into_iter()
+        .filter_map(|o| rank_object(&o, term).map(|rank| (rank, o)))
+        .collect::<Vec<_>>();
+    ranked.sort_by(|(a, ao), (b, bo)| a.cmp(b).then_with(|| ao.name.cmp(&bo.name)));
+
+    Ok(ranked)
+}
+
+impl CliCommand for Search {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let term = new.term.clone().unwrap_or_default();
+        let include_data = new.data.is_some();
+        let term = &term;
+
+        let classes = client
+            .classes()
+            .find()
+            .execute()?
+            .into_iter()
+            .filter(|c| match &new.class_glob {
+                Some(glob) => glob_matches(glob, &c.name),
+                None => true,
+            })
+            .collect::<Vec<_>>();
+
+        let mut groups = Vec::new();
+        for chunk in classes.chunks(MAX_CONCURRENT_QUERIES) {
+            let results = std::thread::scope(|s| {
+                let handles = chunk
+                    .iter()
+                    .map(|class| s.spawn(move || search_class(client, class, term, include_data)))
+                    .collect::<Vec<_>>();
+
+                handles
+                    .into_iter()
+                    .map(|handle| join_thread(handle)?)
+                    .collect::<Result<Vec<_>, AppError>>()
+            })?;
+
+            groups.extend(
+                chunk
+                    .iter()
+                    .zip(results)
+                    .filter(|(_, matches)| !matches.is_empty()),
+            );
+        }
+
+        if groups.is_empty() {
+            append_line(tr("No objects found"))?;
+            return Ok(());
+        }
+
+        // Classes with the best matches first, then by the number of matches.
+        groups.sort_by(|(ac, am), (bc, bm)| {
+            am[0]
+                .0
+                .cmp(&bm[0].0)
+                .then_with(|| bm.len().cmp(&am.len()))
+                .then_with(|| ac.name.cmp(&bc.name))
+        });
+
+        let classmap = classes
+            .iter()
+            .map(|c| (c.id, c.clone()))
+            .collect::<HashMap<_, _>>();
+        let objects = groups
+            .iter()
+            .flat_map(|(_, matches)| matches.iter().map(|(_, o)| o))
+            .collect::<Vec<_>>();
+        let nsmap = find_entities_by_ids(&client.namespaces(), objects, |o| o.namespace_id)?;
+
+        let formatted = |matches: &[(MatchRank, Object)]| {
+            matches
+                .iter()
+                .map(|(_, o)| FormattedObject::new(o, &classmap, &nsmap))
+                .collect::<Vec<_>>()
+        };
+
+        if get_format()? != OutputFormat::Text {
+            let objects = groups
+                .iter()
+                .flat_map(|(_, matches)| formatted(matches))
+                .collect::<Vec<_>>();
+            objects.format()?;
+            return Ok(());
+        }
+
+        for (class, matches) in &groups {
+            append_line(tr_args(
+                "{class} ({count} matches)",
+                &[("class", &class.name), ("count", &matches.len())],
+            ))?;
+            formatted(matches).format()?;
+        }
+
+        Ok(())
+    }
+}
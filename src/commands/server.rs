@@ -0,0 +1,122 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::api::{base_url, ping, server_version};
+use crate::config::get_config;
+use crate::errors::AppError;
+use crate::formatting::{FormattedServerInfo, FormattedVersion, OutputFormatterWithPadding};
+use crate::i18n::{tr, tr_args};
+use crate::output::{add_warning, append_line};
+use crate::tokenizer::CommandTokenizer;
+use crate::version::{incompatibility, CLIENT_VERSION, CLI_VERSION};
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Check that the server is up",
+    long_about = "Ask the server for its health and report the round-trip time."
+)]
+pub struct Ping {}
+
+impl CliCommand for Ping {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let config = get_config()?;
+        let latency = ping(client)?;
+
+        append_line(tr_args(
+            "{server} is up ({latency} ms)",
+            &[
+                ("server", &base_url(&config)),
+                ("latency", &latency.as_millis()),
+            ],
+        ))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show information about the server",
+    long_about = "Show the version of the server, the API versions it serves and the round-trip time, and warn if the API version used by the client is not served."
+)]
+pub struct ServerInfo {}
+
+impl CliCommand for ServerInfo {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let config = get_config()?;
+        let latency = ping(client)?;
+        let version = server_version(client)?;
+
+        if let Some(warning) = version.compatibility_warning(&config.server.api_version) {
+            add_warning(warning)?;
+        }
+
+        FormattedServerInfo {
+            server: base_url(&config),
+            version: version.version,
+            api_versions: version.api_versions,
+            client_api_version: config.server.api_version.clone(),
+            latency_ms: latency.as_millis(),
+        }
+        .format(20)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show version information",
+    long_about = "Show the version of the CLI, the hubuum client library and the server, and warn if the server is known not to work with this CLI."
+)]
+pub struct Version {}
+
+impl CliCommand for Version {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        // Older servers have no version endpoint, which is no reason to fail.
+        let server = match server_version(client) {
+            Ok(version) => version.version,
+            Err(err) => {
+                debug!("Could not get the server version: {}", err);
+                None
+            }
+        };
+        if let Some(problem) = server.as_deref().and_then(incompatibility) {
+            add_warning(problem)?;
+        }
+        show_version(Some(server.unwrap_or_else(|| tr("unknown"))))
+    }
+
+    // The local versions can be shown without asking the server.
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        show_version(None)
+    }
+}
+
+fn show_version(server: Option<String>) -> Result<(), AppError> {
+    FormattedVersion {
+        cli: CLI_VERSION.to_string(),
+        client: CLIENT_VERSION.to_string(),
+        server,
+    }
+    .format(15)
+}
@@ -0,0 +1,341 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use jqesque::Jqesque;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::errors::AppError;
+
+/// One step into a JSON document.
+#[derive(Debug, Clone, PartialEq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// A path to a single value in the data of an object, e.g. `network.interfaces[0].ip`.
+/// A leading `$.` is accepted so the paths offered by completion and used by
+/// `object info --path` work as well, as are keys quoted like `['a key']`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataPath {
+    path: String,
+    segments: Vec<PathSegment>,
+}
+
+impl FromStr for DataPath {
+    type Err = AppError;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let invalid = || AppError::ParseError(format!("Invalid data path '{}'", path));
+        let rest = path.strip_prefix('$').unwrap_or(path);
+        let mut chars = rest.chars();
+        let mut segments = Vec::new();
+        let mut key = String::new();
+        // Whether a dot may come next without a key before it: at the start, as in `$.key`,
+        // and after an index, as in `items[0].key`.
+        let mut bare_dot = true;
+
+        while let Some(c) = chars.next() {
+            match c {
+                '.' => {
+                    if !key.is_empty() {
+                        segments.push(PathSegment::Key(std::mem::take(&mut key)));
+                    } else if !bare_dot {
+                        return Err(invalid());
+                    }
+                    bare_dot = false;
+                }
+                '[' => {
+                    if !key.is_empty() {
+                        segments.push(PathSegment::Key(std::mem::take(&mut key)));
+                    }
+                    let mut inner = String::new();
+                    loop {
+                        match chars.next() {
+                            Some(']') => break,
+                            Some(c) => inner.push(c),
+                            None => return Err(invalid()),
+                        }
+                    }
+                    let quoted = inner
+                        .strip_prefix('\'')
+                        .and_then(|inner| inner.strip_suffix('\''))
+                        .or_else(|| {
+                            inner
+                                .strip_prefix('"')
+                                .and_then(|inner| inner.strip_suffix('"'))
+                        });
+                    match quoted {
+                        Some(quoted) => segments.push(PathSegment::Key(quoted.replace("\\'", "'"))),
+                        None => segments.push(PathSegment::Index(
+                            inner.trim().parse().map_err(|_| invalid())?,
+                        )),
+                    }
+                    bare_dot = true;
+                }
+                c => {
+                    key.push(c);
+                    bare_dot = false;
+                }
+            }
+        }
+        if !key.is_empty() {
+            segments.push(PathSegment::Key(key));
+        }
+
+        if segments.is_empty() || rest.ends_with('.') {
+            return Err(invalid());
+        }
+        Ok(DataPath {
+            path: path.to_string(),
+            segments,
+        })
+    }
+}
+
+impl Display for DataPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.path)
+    }
+}
+
+impl DataPath {
+    /// The value at the path, if there is one.
+    pub fn get_mut<'a>(&self, data: &'a mut Value) -> Option<&'a mut Value> {
+        self.segments
+            .iter()
+            .try_fold(data, |value, segment| step(value, segment))
+    }
+
+    /// Remove the key or array element at the path. Returns whether it was there.
+    pub fn remove(&self, data: &mut Value) -> bool {
+        let Some((parent, last)) = self.parent_mut(data) else {
+            return false;
+        };
+
+        match (parent, last) {
+            (Value::Object(map), PathSegment::Key(key)) => map.remove(key).is_some(),
+            (Value::Array(items), PathSegment::Index(i)) if *i < items.len() => {
+                items.remove(*i);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Set a key that does not exist yet. Missing objects on the way are not created, but
+    /// empty data becomes an object. Returns whether the key could be set.
+    fn insert(&self, data: &mut Value, value: Value) -> bool {
+        let Some((parent, PathSegment::Key(key))) = self.parent_mut(data) else {
+            return false;
+        };
+        if parent.is_null() {
+            *parent = Value::Object(Map::new());
+        }
+        match parent {
+            Value::Object(map) => {
+                map.insert(key.clone(), value);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // The value holding the last segment of the path, and that segment.
+    fn parent_mut<'a>(&self, data: &'a mut Value) -> Option<(&'a mut Value, &PathSegment)> {
+        let (last, parents) = self
+            .segments
+            .split_last()
+            .expect("A data path has at least one segment");
+        let parent = parents
+            .iter()
+            .try_fold(data, |value, segment| step(value, segment))?;
+        Some((parent, last))
+    }
+}
+
+/// An item of an array in the data of an object, from `path=value`. The value is read as
+/// JSON if it can be, and as a string otherwise, so `ports=443` is a number and
+/// `tags=web` a string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DataItem {
+    pub path: DataPath,
+    pub value: Value,
+}
+
+impl FromStr for DataItem {
+    type Err = AppError;
+
+    fn from_str(item: &str) -> Result<Self, Self::Err> {
+        let (path, value) = item
+            .split_once('=')
+            .ok_or_else(|| AppError::ParseError(format!("Expected path=value, got '{}'", item)))?;
+        Ok(DataItem {
+            path: path.trim().parse()?,
+            value: serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string())),
+        })
+    }
+}
+
+impl DataItem {
+    /// Append the value to the array at the path, creating the array if the key does not
+    /// exist.
+    pub fn append(&self, data: &mut Value) -> Result<(), AppError> {
+        match self.path.get_mut(data) {
+            Some(Value::Array(items)) => {
+                items.push(self.value.clone());
+                Ok(())
+            }
+            Some(_) => Err(self.not_an_array()),
+            None if self
+                .path
+                .insert(data, Value::Array(vec![self.value.clone()])) =>
+            {
+                Ok(())
+            }
+            None => Err(AppError::EntityNotFound(self.path.to_string())),
+        }
+    }
+
+    /// Remove every element equal to the value from the array at the path. Returns how
+    /// many were removed.
+    pub fn remove_from(&self, data: &mut Value) -> Result<usize, AppError> {
+        match self.path.get_mut(data) {
+            Some(Value::Array(items)) => {
+                let before = items.len();
+                items.retain(|item| item != &self.value);
+                Ok(before - items.len())
+            }
+            Some(_) => Err(self.not_an_array()),
+            None => Ok(0),
+        }
+    }
+
+    fn not_an_array(&self) -> AppError {
+        AppError::InvalidOption(format!("{} is not an array", self.path))
+    }
+}
+
+/// A step in a migration of the data of objects, from one of:
+///
+/// - `remove(path)`, which removes the key at the path if it is there,
+/// - `target=value`, which sets the target to a JSON value, like `status="active"` or `count=0`,
+/// - `target=source`, which copies the value at the source path to the target. Objects
+///   without the source are left as they are.
+///
+/// A value that is not valid JSON is taken to be a source path, so strings must be quoted.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DataTransform {
+    Remove(DataPath),
+    Set { target: String, value: Value },
+    Copy { target: String, source: DataPath },
+}
+
+impl FromStr for DataTransform {
+    type Err = AppError;
+
+    fn from_str(transform: &str) -> Result<Self, Self::Err> {
+        let transform = transform.trim();
+        if let Some(path) = transform
+            .strip_prefix("remove(")
+            .and_then(|rest| rest.strip_suffix(')'))
+        {
+            return Ok(DataTransform::Remove(path.trim().parse()?));
+        }
+
+        let (target, source) = transform.split_once('=').ok_or_else(|| {
+            AppError::ParseError(format!(
+                "Expected target=source, target=value or remove(path), got '{}'",
+                transform
+            ))
+        })?;
+        // Checked as a data path, but applied with jqesque which creates what is missing.
+        let target = target.trim();
+        target.parse::<DataPath>()?;
+        let target = target.trim_start_matches('$').trim_start_matches('.');
+
+        let source = source.trim();
+        Ok(match serde_json::from_str(source) {
+            Ok(value) => DataTransform::Set {
+                target: target.to_string(),
+                value,
+            },
+            Err(_) => DataTransform::Copy {
+                target: target.to_string(),
+                source: source.parse()?,
+            },
+        })
+    }
+}
+
+impl DataTransform {
+    pub fn apply(&self, data: &mut Value) -> Result<(), AppError> {
+        match self {
+            DataTransform::Remove(path) => {
+                path.remove(data);
+                Ok(())
+            }
+            DataTransform::Set { target, value } => assign(data, target, value),
+            DataTransform::Copy { target, source } => {
+                let value = source.get_mut(data).cloned();
+                match value {
+                    Some(value) => assign(data, target, &value),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+/// A value that differs between two JSON documents.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DataChange {
+    /// The flattened path of the value, e.g. `data.location.rack`.
+    pub path: String,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+/// The values that differ between two documents, with nested keys flattened to dotted
+/// paths, sorted by path.
+pub fn diff_data(before: &Value, after: &Value) -> Vec<DataChange> {
+    let flatten = |value: &Value| match (smooth_json::Flattener {
+        ..Default::default()
+    })
+    .flatten(value)
+    {
+        Value::Object(map) => map.into_iter().collect::<BTreeMap<_, _>>(),
+        Value::Null => BTreeMap::new(),
+        value => BTreeMap::from([(String::new(), value)]),
+    };
+    let before = flatten(before);
+    let after = flatten(after);
+
+    let paths = before.keys().chain(after.keys()).collect::<BTreeSet<_>>();
+    paths
+        .into_iter()
+        .filter(|path| before.get(*path) != after.get(*path))
+        .map(|path| DataChange {
+            path: path.clone(),
+            before: before.get(path).cloned(),
+            after: after.get(path).cloned(),
+        })
+        .collect()
+}
+
+fn assign(data: &mut Value, target: &str, value: &Value) -> Result<(), AppError> {
+    format!("{}={}", target, value)
+        .parse::<Jqesque>()?
+        .apply_to(data)?;
+    Ok(())
+}
+
+fn step<'a>(value: &'a mut Value, segment: &PathSegment) -> Option<&'a mut Value> {
+    match (value, segment) {
+        (Value::Object(map), PathSegment::Key(key)) => map.get_mut(key),
+        (Value::Array(items), PathSegment::Index(i)) => items.get_mut(*i),
+        _ => None,
+    }
+}
@@ -0,0 +1,384 @@
+use hubuum_client::{FilterOperator, IntoResourceFilter, QueryFilter};
+use serde_json::Value;
+
+use crate::errors::AppError;
+
+const OPERATORS: &[&str] = &[
+    "equals",
+    "iequals",
+    "contains",
+    "icontains",
+    "startswith",
+    "istartswith",
+    "endswith",
+    "iendswith",
+    "like",
+    "regex",
+    "gt",
+    "gte",
+    "lt",
+    "lte",
+    "between",
+];
+
+fn filter_operator(name: &str, is_negated: bool) -> Option<FilterOperator> {
+    let operator = match name {
+        "equals" => FilterOperator::Equals { is_negated },
+        "iequals" => FilterOperator::IEquals { is_negated },
+        "contains" => FilterOperator::Contains { is_negated },
+        "icontains" => FilterOperator::IContains { is_negated },
+        "startswith" => FilterOperator::StartsWith { is_negated },
+        "istartswith" => FilterOperator::IStartsWith { is_negated },
+        "endswith" => FilterOperator::EndsWith { is_negated },
+        "iendswith" => FilterOperator::IEndsWith { is_negated },
+        "like" => FilterOperator::Like { is_negated },
+        "regex" => FilterOperator::Regex { is_negated },
+        "gt" => FilterOperator::Gt { is_negated },
+        "gte" => FilterOperator::Gte { is_negated },
+        "lt" => FilterOperator::Lt { is_negated },
+        "lte" => FilterOperator::Lte { is_negated },
+        "between" => FilterOperator::Between { is_negated },
+        _ => return None,
+    };
+    Some(operator)
+}
+
+fn is_filter_key(key: &str) -> bool {
+    !key.is_empty()
+        && key
+            .trim_end_matches('!')
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.')
+}
+
+// Split on commas that start a new `key=value` clause, so values may contain commas
+// (e.g. `id__between=1,10`).
+fn split_clauses(expression: &str) -> Vec<String> {
+    let mut clauses: Vec<String> = Vec::new();
+
+    for piece in expression.split(',') {
+        let starts_clause = piece
+            .split_once('=')
+            .is_some_and(|(key, _)| is_filter_key(key.trim()));
+
+        match clauses.last_mut() {
+            Some(last) if !starts_clause => {
+                last.push(',');
+                last.push_str(piece);
+            }
+            _ => clauses.push(piece.to_string()),
+        }
+    }
+
+    clauses
+        .into_iter()
+        .map(|c| c.trim().to_string())
+        .filter(|c| !c.is_empty())
+        .collect()
+}
+
+/// Parse an operator name such as `icontains` or `not_startswith`.
+pub fn parse_filter_operator(name: &str) -> Result<FilterOperator, AppError> {
+    let (name, is_negated) = match name.strip_prefix("not_") {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+
+    filter_operator(name, is_negated).ok_or_else(|| {
+        AppError::ParseError(format!(
+            "Unknown operator '{}', expected one of {}",
+            name,
+            OPERATORS.join(", ")
+        ))
+    })
+}
+
+/// A clause of a filter expression. It is kept apart from the `QueryFilter` it becomes, so a
+/// parsed expression can be reused for every attempt of a request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterClause {
+    key: String,
+    value: String,
+    operator: &'static str,
+    is_negated: bool,
+}
+
+impl FilterClause {
+    /// The API filter for this clause.
+    pub fn query_filter(&self) -> QueryFilter {
+        QueryFilter {
+            key: self.key.clone(),
+            value: self.value.clone(),
+            operator: filter_operator(self.operator, self.is_negated)
+                .expect("clauses are only built with operators from OPERATORS"),
+        }
+    }
+}
+
+fn known_operator(name: &str) -> Option<&'static str> {
+    OPERATORS.iter().copied().find(|operator| *operator == name)
+}
+
+fn parse_clause(clause: &str) -> Result<FilterClause, AppError> {
+    let invalid = || {
+        AppError::ParseError(format!(
+            "Invalid filter '{}', expected field=value or field__operator=value",
+            clause
+        ))
+    };
+
+    let (key, value) = clause.split_once('=').ok_or_else(invalid)?;
+    let key = key.trim();
+    if !is_filter_key(key) {
+        return Err(invalid());
+    }
+
+    // `field!=value` is shorthand for `field__not_equals=value`.
+    let (key, shorthand_negated) = match key.strip_suffix('!') {
+        Some(key) => (key, true),
+        None => (key, false),
+    };
+
+    let mut segments = key.split("__").collect::<Vec<_>>();
+    let last = segments.last().copied().unwrap_or_default();
+    let explicit = match last.strip_prefix("not_").and_then(known_operator) {
+        Some(name) => Some((name, true)),
+        None => known_operator(last).map(|name| (name, false)),
+    };
+
+    let (operator, is_negated) = match explicit {
+        Some(operator) if segments.len() > 1 => {
+            segments.pop();
+            operator
+        }
+        _ => ("equals", false),
+    };
+
+    if shorthand_negated && (operator != "equals" || is_negated) {
+        return Err(invalid());
+    }
+
+    // Keys inside the JSON data of an entity are queried through `json_data`, with the
+    // path to the key as part of the value.
+    let (key, value) = match segments.split_first() {
+        Some((&"data", path)) if !path.is_empty() => (
+            "json_data".to_string(),
+            format!("{}={}", path.join(","), value.trim()),
+        ),
+        Some((&"data", _)) => ("json_data".to_string(), value.trim().to_string()),
+        _ => (segments.join("__"), value.trim().to_string()),
+    };
+
+    Ok(FilterClause {
+        key,
+        value,
+        operator,
+        is_negated: is_negated || shorthand_negated,
+    })
+}
+
+/// Parse a filter expression such as `name__startswith=web,description__not_icontains=old`.
+///
+/// Each clause is `field=value` (equals) or `field__operator=value`, where the operator is
+/// any of the operators supported by the API, optionally prefixed with `not_` to negate it.
+/// `field!=value` is accepted as a shorthand for `field__not_equals=value`, and fields below
+/// `data` (e.g. `data__env=prod`) filter on the JSON data of the entity.
+pub fn parse_filter_expression(expression: &str) -> Result<Vec<FilterClause>, AppError> {
+    split_clauses(expression)
+        .iter()
+        .map(|clause| parse_clause(clause))
+        .collect()
+}
+
+/// The filters of a list command: the ones built from its options, followed by the clauses
+/// of its `--filter` expression.
+pub struct ListFilter<'a, C> {
+    command: &'a C,
+    clauses: Vec<FilterClause>,
+}
+
+impl<'a, C> ListFilter<'a, C> {
+    /// Parse the filter expression of a command, if it has one.
+    pub fn new(command: &'a C, expression: Option<&str>) -> Result<Self, AppError> {
+        let clauses = match expression {
+            Some(expression) => parse_filter_expression(expression)?,
+            None => Vec::new(),
+        };
+        Ok(Self { command, clauses })
+    }
+}
+
+impl<'a, T, C> IntoResourceFilter<T> for &ListFilter<'a, C>
+where
+    &'a C: IntoResourceFilter<T>,
+{
+    fn into_resource_filter(self) -> Vec<QueryFilter> {
+        let mut filters = IntoResourceFilter::<T>::into_resource_filter(self.command);
+        filters.extend(self.clauses.iter().map(FilterClause::query_filter));
+        filters
+    }
+}
+
+/// A condition on the JSON data of an object, e.g. `data.os.family=linux`.
+#[derive(Debug, Clone)]
+pub struct DataCondition {
+    path: Vec<String>,
+    value: String,
+    is_negated: bool,
+}
+
+impl DataCondition {
+    /// The API filter matching this condition server-side.
+    pub fn query_filter(&self) -> QueryFilter {
+        QueryFilter {
+            key: "json_data".to_string(),
+            value: format!("{}={}", self.path.join(","), self.value),
+            operator: FilterOperator::Equals {
+                is_negated: self.is_negated,
+            },
+        }
+    }
+
+    /// Evaluate the condition against the data of an object. Strings are compared as-is,
+    /// other values by their JSON representation (e.g. `5` or `true`).
+    pub fn matches(&self, data: Option<&Value>) -> bool {
+        let pointer = format!("/{}", self.path.join("/"));
+        let found = match data.and_then(|d| d.pointer(&pointer)) {
+            Some(Value::String(s)) => s == &self.value,
+            Some(value) => value.to_string() == self.value,
+            None => false,
+        };
+        found != self.is_negated
+    }
+}
+
+impl std::fmt::Display for DataCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let operator = if self.is_negated { "!=" } else { "=" };
+        write!(f, "data.{}{}{}", self.path.join("."), operator, self.value)
+    }
+}
+
+/// Parse a comma-separated list of conditions on JSON data, such as
+/// `data.os.family=linux,data.env!=test`. The leading `data.` is optional.
+pub fn parse_where_expression(expression: &str) -> Result<Vec<DataCondition>, AppError> {
+    split_clauses(expression)
+        .iter()
+        .map(|clause| {
+            let invalid = || {
+                AppError::ParseError(format!(
+                    "Invalid condition '{}', expected data.key=value or data.key!=value",
+                    clause
+                ))
+            };
+
+            let (key, value) = clause.split_once('=').ok_or_else(invalid)?;
+            let (key, is_negated) = match key.trim().strip_suffix('!') {
+                Some(key) => (key, true),
+                None => (key.trim(), false),
+            };
+
+            let key = key.strip_prefix("data.").unwrap_or(key);
+            let path = key.split('.').map(str::to_string).collect::<Vec<_>>();
+            if path.iter().any(|segment| segment.is_empty()) {
+                return Err(invalid());
+            }
+
+            Ok(DataCondition {
+                path,
+                value: value.trim().to_string(),
+                is_negated,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn clause(key: &str, value: &str, operator: &'static str, is_negated: bool) -> FilterClause {
+        FilterClause {
+            key: key.to_string(),
+            value: value.to_string(),
+            operator,
+            is_negated,
+        }
+    }
+
+    #[test]
+    fn test_plain_clause_is_equals() {
+        assert_eq!(
+            parse_filter_expression("name=web01").unwrap(),
+            vec![clause("name", "web01", "equals", false)]
+        );
+    }
+
+    #[test]
+    fn test_explicit_operators() {
+        assert_eq!(
+            parse_filter_expression("name__startswith=web, description__not_icontains=old")
+                .unwrap(),
+            vec![
+                clause("name", "web", "startswith", false),
+                clause("description", "old", "icontains", true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_not_equals_shorthand() {
+        assert_eq!(
+            parse_filter_expression("name!=web01").unwrap(),
+            vec![clause("name", "web01", "equals", true)]
+        );
+        assert!(parse_filter_expression("name__contains!=web").is_err());
+    }
+
+    #[test]
+    fn test_data_fields_use_json_data() {
+        assert_eq!(
+            parse_filter_expression("data__os__family=linux,data__port__gt=1024").unwrap(),
+            vec![
+                clause("json_data", "os,family=linux", "equals", false),
+                clause("json_data", "port=1024", "gt", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_commas_in_values() {
+        assert_eq!(
+            parse_filter_expression("id__between=1,10,name=web").unwrap(),
+            vec![
+                clause("id", "1,10", "between", false),
+                clause("name", "web", "equals", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unknown_suffix_is_part_of_the_key() {
+        assert_eq!(
+            parse_filter_expression("created__at=2024").unwrap(),
+            vec![clause("created__at", "2024", "equals", false)]
+        );
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expression in ["name", "=web", "na me=web", "name__regex!=x"] {
+            assert!(
+                parse_filter_expression(expression).is_err(),
+                "{} should not parse",
+                expression
+            );
+        }
+    }
+
+    #[test]
+    fn test_filter_operator_names() {
+        assert!(parse_filter_operator("not_regex").is_ok());
+        assert!(parse_filter_operator("nope").is_err());
+    }
+}
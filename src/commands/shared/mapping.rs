@@ -0,0 +1,192 @@
+use serde_json::{Map, Value};
+
+use crate::errors::AppError;
+
+/// A field of an object that a CSV column can be imported into.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ObjectField {
+    Name,
+    Description,
+    Namespace,
+    /// A key in the data, nested keys separated by dots.
+    Data(Vec<String>),
+}
+
+impl ObjectField {
+    fn parse(field: &str) -> Result<Self, AppError> {
+        match field {
+            "name" => Ok(ObjectField::Name),
+            "description" => Ok(ObjectField::Description),
+            "namespace" => Ok(ObjectField::Namespace),
+            _ => {
+                let path = field
+                    .strip_prefix("data.")
+                    .ok_or_else(|| {
+                        AppError::ParseError(format!(
+                            "Can not import into '{}', expected name, description, namespace or data.<key>",
+                            field
+                        ))
+                    })?
+                    .split('.')
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                if path.iter().any(String::is_empty) {
+                    return Err(AppError::ParseError(format!(
+                        "Invalid data path '{}'",
+                        field
+                    )));
+                }
+                Ok(ObjectField::Data(path))
+            }
+        }
+    }
+}
+
+/// The fields of one CSV row after mapping.
+#[derive(Debug, Default)]
+pub struct MappedRow {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub namespace: Option<String>,
+    pub data: Map<String, Value>,
+}
+
+/// Which CSV column goes into which object field, e.g. from
+/// `name=hostname,data.ip=ip_address`.
+#[derive(Debug, Clone)]
+pub struct ColumnMapping {
+    columns: Vec<(ObjectField, String)>,
+}
+
+impl ColumnMapping {
+    /// Parse `field=column` pairs, separated by commas or newlines. In a mapping file, lines
+    /// starting with `#` are comments.
+    pub fn parse(expression: &str) -> Result<Self, AppError> {
+        let mut columns = Vec::new();
+        for pair in expression
+            .split([',', '\n'])
+            .map(str::trim)
+            .filter(|p| !p.is_empty() && !p.starts_with('#'))
+        {
+            let (field, column) = pair.split_once('=').ok_or_else(|| {
+                AppError::ParseError(format!("Invalid mapping '{}', expected field=column", pair))
+            })?;
+            columns.push((ObjectField::parse(field.trim())?, column.trim().to_string()));
+        }
+        Ok(ColumnMapping { columns })
+    }
+
+    /// Without a mapping, columns named like a field go into it, and the other columns go
+    /// into the data under their own name.
+    pub fn from_headers(headers: &[String]) -> Result<Self, AppError> {
+        let columns = headers
+            .iter()
+            .map(|header| {
+                let field = match header.as_str() {
+                    "name" | "description" | "namespace" => header.clone(),
+                    header if header.starts_with("data.") => header.to_string(),
+                    header => format!("data.{}", header),
+                };
+                Ok((ObjectField::parse(&field)?, header.clone()))
+            })
+            .collect::<Result<Vec<_>, AppError>>()?;
+        Ok(ColumnMapping { columns })
+    }
+
+    /// Check that every mapped column is in the file.
+    pub fn check_headers(&self, headers: &[String]) -> Result<(), AppError> {
+        let missing = self
+            .columns
+            .iter()
+            .map(|(_, column)| column)
+            .filter(|column| !headers.contains(column))
+            .cloned()
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(AppError::ParseError(format!(
+            "Columns not found in the file: {}",
+            missing.join(", ")
+        )))
+    }
+
+    /// Map a row, with `headers` naming its columns. Empty cells are left out.
+    pub fn map_row(&self, headers: &[String], row: &[String]) -> MappedRow {
+        let mut mapped = MappedRow::default();
+        for (field, column) in &self.columns {
+            let Some(value) = headers
+                .iter()
+                .position(|h| h == column)
+                .and_then(|i| row.get(i))
+                .filter(|v| !v.is_empty())
+            else {
+                continue;
+            };
+            match field {
+                ObjectField::Name => mapped.name = Some(value.clone()),
+                ObjectField::Description => mapped.description = Some(value.clone()),
+                ObjectField::Namespace => mapped.namespace = Some(value.clone()),
+                ObjectField::Data(path) => set_path(&mut mapped.data, path, coerce(value)),
+            }
+        }
+        mapped
+    }
+}
+
+// Set a nested key, creating the objects on the way.
+fn set_path(data: &mut Map<String, Value>, path: &[String], value: Value) {
+    let (last, parents) = path.split_last().expect("Data paths are never empty");
+    let mut current = data;
+    for key in parents {
+        let entry = current
+            .entry(key.clone())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if !entry.is_object() {
+            *entry = Value::Object(Map::new());
+        }
+        current = entry.as_object_mut().expect("Just made an object");
+    }
+    current.insert(last.clone(), value);
+}
+
+/// CSV values are text, so numbers and booleans are recognised. Numbers with leading zeros,
+/// like `007`, stay text as the zeros would be lost.
+pub fn coerce(value: &str) -> Value {
+    match value {
+        "true" | "True" | "TRUE" => return Value::Bool(true),
+        "false" | "False" | "FALSE" => return Value::Bool(false),
+        _ => {}
+    }
+
+    let digits = value.trim_start_matches('-');
+    if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+        return Value::String(value.to_string());
+    }
+    if let Ok(number) = value.parse::<i64>() {
+        return Value::from(number);
+    }
+    if let Ok(number) = value.parse::<f64>() {
+        if number.is_finite() {
+            return Value::from(number);
+        }
+    }
+    Value::String(value.to_string())
+}
+
+/// Merge imported data into the data of an existing object, keeping keys the import does
+/// not set.
+pub fn merge_data(existing: Option<&Value>, imported: &Map<String, Value>) -> Value {
+    let mut merged = match existing {
+        Some(Value::Object(map)) => map.clone(),
+        _ => Map::new(),
+    };
+    for (key, value) in imported {
+        let value = match (merged.get(key), value) {
+            (Some(old @ Value::Object(_)), Value::Object(new)) => merge_data(Some(old), new),
+            _ => value.clone(),
+        };
+        merged.insert(key.clone(), value);
+    }
+    Value::Object(merged)
+}
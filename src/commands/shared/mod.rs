@@ -1,3 +1,15 @@
+mod data;
+mod filter;
+mod mapping;
+
+pub use data::{diff_data, DataChange, DataItem, DataPath, DataTransform};
+pub use filter::{
+    parse_filter_expression, parse_filter_operator, parse_where_expression, DataCondition,
+    FilterClause, ListFilter,
+};
+pub use mapping::{merge_data, ColumnMapping, MappedRow};
+
+use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
 use std::fmt::Display;
 use std::hash::Hash;
@@ -6,8 +18,28 @@ use hubuum_client::{
     client::sync::Resource, client::GetID, ApiError, ApiResource, Authenticated, Class,
     ClassRelation, FilterOperator, Namespace, Object, ObjectRelation, SyncClient,
 };
+use serde::Serialize;
+use serde_json::Value;
 
+use crate::api::{head_count, retry_api};
 use crate::errors::AppError;
+use crate::i18n::tr_args;
+use crate::lookup_cache::{cached_class, cached_namespace};
+use crate::output::add_warning;
+use crate::plan::confirm;
+use crate::tokenizer::CommandTokenizer;
+
+/// The number of requests we send to the server at the same time.
+pub const MAX_CONCURRENT_QUERIES: usize = 8;
+
+/// The number of ids we look up in one request.
+const IDS_PER_QUERY: usize = 100;
+
+/// The number of objects a glob may match before we ask whether to go on.
+pub const GLOB_CONFIRM_THRESHOLD: usize = 20;
+
+/// The most objects a glob may match, so a stray `*` does not reach a whole large class.
+pub const GLOB_MAX_MATCHES: usize = 1000;
 
 /// Extension trait for iterators to remove duplicates.
 pub trait Uniqify: Iterator + Sized {
@@ -63,6 +95,10 @@ pub trait CommafyVia: IntoIterator + Sized {
 
 impl<T: IntoIterator> CommafyVia for T {}
 
+/// Look up the entities with the ids of `objects`, e.g. the classes of a list of objects.
+///
+/// The ids are sent as comma-separated lists, at most `IDS_PER_QUERY` per request so the
+/// query string stays short, and up to `MAX_CONCURRENT_QUERIES` requests run at a time.
 pub fn find_entities_by_ids<T, I, F>(
     resource: &Resource<T>,
     objects: I,
@@ -73,24 +109,234 @@ where
     I: IntoIterator,
     I::Item: Copy,
     F: Fn(I::Item) -> i32,
-    T::GetOutput: GetID,
+    T::GetOutput: GetID + Send,
+    Resource<T>: Sync,
 {
-    // Extract the comma-separated string of unique IDs
-    let ids = objects.commafy_via(extract_id);
+    let mut ids = objects
+        .into_iter()
+        .map(extract_id)
+        .uniqify()
+        .collect::<Vec<_>>();
+    ids.sort();
+    let queries = ids
+        .chunks(IDS_PER_QUERY)
+        .map(|chunk| chunk.iter().commafy())
+        .collect::<Vec<_>>();
 
-    // Use the Resource<T> to add filter and execute the find operation
+    let mut map = HashMap::new();
+    for batch in queries.chunks(MAX_CONCURRENT_QUERIES) {
+        let results = std::thread::scope(|s| {
+            let handles = batch
+                .iter()
+                .map(|ids| {
+                    s.spawn(move || {
+                        retry_api(|| {
+                            resource
+                                .find()
+                                .add_filter(
+                                    "id",
+                                    FilterOperator::Equals { is_negated: false },
+                                    ids.clone(),
+                                )
+                                .execute()
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(join_thread)
+                .collect::<Result<Vec<_>, AppError>>()
+        })?;
 
-    let results = resource
-        .find()
-        .add_filter("id", FilterOperator::Equals { is_negated: false }, ids)
-        .execute()?;
+        for result in results {
+            map.extend(result?.into_iter().map(|entity| (entity.id(), entity)));
+        }
+    }
+
+    Ok(map)
+}
+
+/// The result of a scoped thread, or an error rather than a panic if the thread panicked.
+pub fn join_thread<T>(handle: std::thread::ScopedJoinHandle<'_, T>) -> Result<T, AppError> {
+    handle
+        .join()
+        .map_err(|_| AppError::CommandExecutionError("A worker thread panicked".to_string()))
+}
+
+/// The number of objects in a class. Servers that send a total count answer a HEAD request,
+/// for the others the objects are listed and counted.
+pub fn count_objects(client: &SyncClient<Authenticated>, class_id: i32) -> Result<usize, AppError> {
+    if let Some(count) = head_count(client, &format!("/api/v1/classes/{}/", class_id))? {
+        return Ok(count);
+    }
+    Ok(retry_api(|| client.objects(class_id).find().execute())?.len())
+}
 
-    let map = results
+/// The number of objects in each of `class_ids`, counting up to `MAX_CONCURRENT_QUERIES`
+/// classes at a time.
+pub fn count_objects_in_classes(
+    client: &SyncClient<Authenticated>,
+    class_ids: &[i32],
+) -> Result<HashMap<i32, usize>, AppError> {
+    let mut counts = HashMap::new();
+    for chunk in class_ids.chunks(MAX_CONCURRENT_QUERIES) {
+        let results = std::thread::scope(|s| {
+            let handles = chunk
+                .iter()
+                .map(|&class_id| s.spawn(move || (class_id, count_objects(client, class_id))))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(join_thread)
+                .collect::<Result<Vec<_>, AppError>>()
+        })?;
+
+        for (class_id, count) in results {
+            counts.insert(class_id, count?);
+        }
+    }
+    Ok(counts)
+}
+
+/// The number of objects in each namespace among the objects of `class_ids`, looking at up
+/// to `MAX_CONCURRENT_QUERIES` classes at a time. Objects need not share the namespace of
+/// their class, so only the classes with objects are listed.
+pub fn count_objects_per_namespace(
+    client: &SyncClient<Authenticated>,
+    class_ids: &[i32],
+) -> Result<HashMap<i32, usize>, AppError> {
+    let populated = count_objects_in_classes(client, class_ids)?
         .into_iter()
-        .map(|entity| (entity.id(), entity))
-        .collect::<HashMap<i32, T::GetOutput>>();
+        .filter(|(_, count)| *count > 0)
+        .map(|(class_id, _)| class_id)
+        .collect::<Vec<_>>();
 
-    Ok(map)
+    let mut counts = HashMap::new();
+    for chunk in populated.chunks(MAX_CONCURRENT_QUERIES) {
+        let results = std::thread::scope(|s| {
+            let handles = chunk
+                .iter()
+                .map(|&class_id| {
+                    s.spawn(move || retry_api(|| client.objects(class_id).find().execute()))
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(join_thread)
+                .collect::<Result<Vec<_>, AppError>>()
+        })?;
+
+        for objects in results {
+            for object in objects? {
+                *counts.entry(object.namespace_id).or_default() += 1;
+            }
+        }
+    }
+    Ok(counts)
+}
+
+/// The values of an option that may be repeated, given with any of `keys` (the short and
+/// long option without dashes), or the value of the field if it was set some other way,
+/// e.g. by the wizard.
+pub fn repeated_values(
+    tokens: &CommandTokenizer,
+    keys: &[&str],
+    value: &Option<String>,
+) -> Vec<String> {
+    let values = keys
+        .iter()
+        .flat_map(|key| tokens.get_all_values(key).to_vec())
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        return value.iter().cloned().collect();
+    }
+    values
+}
+
+/// Match a name against a shell-style glob, where `*` matches any run of characters
+/// and `?` matches a single character.
+pub fn glob_matches(pattern: &str, name: &str) -> bool {
+    regex::Regex::new(&glob_regex(pattern)).is_ok_and(|re| re.is_match(name))
+}
+
+/// Whether a name is a glob pattern rather than a literal name.
+pub fn is_glob(name: &str) -> bool {
+    name.contains(['*', '?'])
+}
+
+// An anchored regular expression matching the same names as the glob.
+fn glob_regex(pattern: &str) -> String {
+    let regex = pattern
+        .chars()
+        .map(|c| match c {
+            '*' => ".*".to_string(),
+            '?' => ".".to_string(),
+            c => regex::escape(&c.to_string()),
+        })
+        .collect::<String>();
+    format!("^{}$", regex)
+}
+
+/// The objects in a class whose names match a glob, sorted by name. The server narrows
+/// the candidates with `startswith` on the literal prefix of the pattern, or with a
+/// regex if it starts with a wildcard, and the names are then matched against the glob.
+/// Fails if more than `GLOB_MAX_MATCHES` objects match.
+pub fn find_objects_by_glob(
+    client: &SyncClient<Authenticated>,
+    class_id: i32,
+    pattern: &str,
+) -> Result<Vec<Object>, AppError> {
+    let prefix = pattern
+        .chars()
+        .take_while(|c| !matches!(c, '*' | '?'))
+        .collect::<String>();
+    let candidates = retry_api(|| {
+        let query = client.objects(class_id).find();
+        if prefix.is_empty() {
+            query.add_filter(
+                "name",
+                FilterOperator::Regex { is_negated: false },
+                glob_regex(pattern),
+            )
+        } else {
+            query.add_filter(
+                "name",
+                FilterOperator::StartsWith { is_negated: false },
+                &prefix,
+            )
+        }
+        .execute()
+    })?;
+
+    let mut objects = candidates
+        .into_iter()
+        .filter(|object| glob_matches(pattern, &object.name))
+        .collect::<Vec<_>>();
+
+    if objects.len() > GLOB_MAX_MATCHES {
+        return Err(AppError::InvalidOption(tr_args(
+            "{pattern} matches {count} objects, more than the limit of {max}",
+            &[
+                ("pattern", &pattern),
+                ("count", &objects.len()),
+                ("max", &GLOB_MAX_MATCHES),
+            ],
+        )));
+    }
+    objects.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(objects)
+}
+
+/// Ask before acting on more than `GLOB_CONFIRM_THRESHOLD` objects matched by a glob.
+pub fn confirm_glob_matches(pattern: &str, count: usize) -> Result<bool, AppError> {
+    if count <= GLOB_CONFIRM_THRESHOLD {
+        return Ok(true);
+    }
+    confirm(&tr_args(
+        "{pattern} matches {count} objects, continue?",
+        &[("pattern", &pattern), ("count", &count)],
+    ))
 }
 
 pub fn find_classes(
@@ -107,22 +353,30 @@ pub fn find_class_by_name(
     client: &SyncClient<Authenticated>,
     name: &str,
 ) -> Result<Class, ApiError> {
-    client
-        .classes()
-        .find()
-        .add_filter_name_exact(name)
-        .execute_expecting_single_result()
+    cached_class(name, || {
+        retry_api(|| {
+            client
+                .classes()
+                .find()
+                .add_filter_name_exact(name)
+                .execute_expecting_single_result()
+        })
+    })
 }
 
 pub fn find_namespace_by_name(
     client: &SyncClient<Authenticated>,
     name: &str,
 ) -> Result<Namespace, ApiError> {
-    client
-        .namespaces()
-        .find()
-        .add_filter_name_exact(name)
-        .execute_expecting_single_result()
+    cached_namespace(name, || {
+        retry_api(|| {
+            client
+                .namespaces()
+                .find()
+                .add_filter_name_exact(name)
+                .execute_expecting_single_result()
+        })
+    })
 }
 
 pub fn find_class_relation(
@@ -143,11 +397,13 @@ pub fn find_object_by_name(
     class_id: i32,
     name: &str,
 ) -> Result<Object, ApiError> {
-    client
-        .objects(class_id)
-        .find()
-        .add_filter_name_exact(name)
-        .execute_expecting_single_result()
+    retry_api(|| {
+        client
+            .objects(class_id)
+            .find()
+            .add_filter_name_exact(name)
+            .execute_expecting_single_result()
+    })
 }
 
 pub fn find_object_relation(
@@ -171,3 +427,96 @@ pub fn prettify_slice_path(path: &str) -> String {
         .replace("['", "")
         .replace("']", "")
 }
+
+/// Sort entities by a comma-separated list of fields, e.g. `namespace,name` or `data.ip`.
+///
+/// The API does not support sorting, so this is done client side on the serialized form of
+/// the entities, which means nested fields can be reached with dots. The sort is stable, and
+/// `reverse` flips the order of the fields without reordering entities that compare equal.
+pub fn sort_entities<T: Serialize>(
+    entities: Vec<T>,
+    sort: Option<&str>,
+    reverse: bool,
+) -> Result<Vec<T>, AppError> {
+    let fields = match sort {
+        Some(sort) => sort
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .collect::<Vec<_>>(),
+        None => Vec::new(),
+    };
+
+    if fields.is_empty() {
+        let mut entities = entities;
+        if reverse {
+            entities.reverse();
+        }
+        return Ok(entities);
+    }
+
+    let mut keyed = entities
+        .into_iter()
+        .map(|entity| {
+            let value = serde_json::to_value(&entity)?;
+            let keys = fields
+                .iter()
+                .map(|field| {
+                    value
+                        .pointer(&format!("/{}", field.replace('.', "/")))
+                        .cloned()
+                        .unwrap_or(Value::Null)
+                })
+                .collect::<Vec<_>>();
+            Ok((keys, entity))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+
+    for (i, field) in fields.iter().enumerate() {
+        if !keyed.is_empty() && keyed.iter().all(|(keys, _)| keys[i].is_null()) {
+            add_warning(format!("Sort field '{}' not found", field))?;
+        }
+    }
+
+    keyed.sort_by(|(a, _), (b, _)| {
+        let ordering = a
+            .iter()
+            .zip(b.iter())
+            .map(|(a, b)| compare_json(a, b))
+            .find(|o| *o != Ordering::Equal)
+            .unwrap_or(Ordering::Equal);
+
+        if reverse {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+
+    Ok(keyed.into_iter().map(|(_, entity)| entity).collect())
+}
+
+// Values of different types are ordered null < bool < number < string < array < object.
+fn compare_json(a: &Value, b: &Value) -> Ordering {
+    fn rank(value: &Value) -> u8 {
+        match value {
+            Value::Null => 0,
+            Value::Bool(_) => 1,
+            Value::Number(_) => 2,
+            Value::String(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        }
+    }
+
+    match (a, b) {
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Number(a), Value::Number(b)) => a
+            .as_f64()
+            .partial_cmp(&b.as_f64())
+            .unwrap_or(Ordering::Equal),
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        _ if rank(a) == rank(b) => a.to_string().cmp(&b.to_string()),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
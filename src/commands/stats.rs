@@ -0,0 +1,209 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, Class, SyncClient};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use super::shared::{count_objects_in_classes, join_thread};
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::api::retry_api;
+use crate::config::get_config;
+use crate::errors::AppError;
+use crate::formatting::{ClassObjectCount, FormattedServerStats, OutputFormatterWithPadding};
+use crate::i18n::tr;
+use crate::models::OutputFormat;
+use crate::output::{append_json, append_line, get_format};
+use crate::timing::{clear_timings, timing_stats, TimingKind};
+use crate::tokenizer::CommandTokenizer;
+
+// How long the counts are reused before asking the server again.
+const STATS_CACHE_TIME: Duration = Duration::from_secs(60);
+
+// The counts from the last run, with the object counts of every class sorted by size, and
+// the user@host:port they were counted as, as `login` may switch to another server or user.
+static STATS_CACHE: Lazy<Mutex<Option<(String, Instant, FormattedServerStats)>>> =
+    Lazy::new(|| Mutex::new(None));
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show an overview of the server",
+    long_about = "Show the number of classes, objects, namespaces, users and groups on the server, and the classes with the most objects. The counts are cached for a minute.",
+    examples = r#"--top 5
+--refresh"#,
+    cancellable = "true"
+)]
+pub struct Stats {
+    #[option(
+        short = "t",
+        long = "top",
+        help = "Number of classes to show object counts for",
+        default = "10"
+    )]
+    pub top: usize,
+    #[option(
+        short = "r",
+        long = "refresh",
+        help = "Ignore cached counts",
+        flag = "true"
+    )]
+    pub refresh: Option<bool>,
+}
+
+// Only objects can be counted without listing them, and only by some servers.
+fn gather_stats(client: &SyncClient<Authenticated>) -> Result<FormattedServerStats, AppError> {
+    let (classes, namespaces, users, groups) = std::thread::scope(|s| {
+        let classes = s.spawn(|| -> Result<Vec<Class>, AppError> {
+            Ok(retry_api(|| client.classes().find().execute())?)
+        });
+        let namespaces = s.spawn(|| -> Result<usize, AppError> {
+            Ok(retry_api(|| client.namespaces().find().execute())?.len())
+        });
+        let users = s.spawn(|| -> Result<usize, AppError> {
+            Ok(retry_api(|| client.users().find().execute())?.len())
+        });
+        let groups = s.spawn(|| -> Result<usize, AppError> {
+            Ok(retry_api(|| client.groups().find().execute())?.len())
+        });
+        Ok::<_, AppError>((
+            join_thread(classes)??,
+            join_thread(namespaces)??,
+            join_thread(users)??,
+            join_thread(groups)??,
+        ))
+    })?;
+
+    let counts = count_objects_in_classes(
+        client,
+        &classes.iter().map(|class| class.id).collect::<Vec<_>>(),
+    )?;
+    let mut class_counts = classes
+        .iter()
+        .map(|class| ClassObjectCount {
+            class: class.name.clone(),
+            objects: counts.get(&class.id).copied().unwrap_or(0),
+        })
+        .collect::<Vec<_>>();
+    class_counts.sort_by(|a, b| b.objects.cmp(&a.objects).then(a.class.cmp(&b.class)));
+
+    Ok(FormattedServerStats {
+        classes: classes.len(),
+        objects: class_counts.iter().map(|c| c.objects).sum(),
+        namespaces,
+        users,
+        groups,
+        top_classes: class_counts,
+    })
+}
+
+impl CliCommand for Stats {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let config = get_config()?;
+        let key = format!(
+            "{}@{}:{}",
+            config.server.username, config.server.hostname, config.server.port
+        );
+        let mut cache = STATS_CACHE.lock().map_err(|_| AppError::LockError)?;
+
+        let cached = cache
+            .as_ref()
+            .filter(|(cached_key, at, _)| {
+                new.refresh.is_none() && *cached_key == key && at.elapsed() < STATS_CACHE_TIME
+            })
+            .map(|(_, _, stats)| stats.clone());
+        let mut stats = match cached {
+            Some(stats) => stats,
+            None => {
+                let stats = gather_stats(client)?;
+                *cache = Some((key, Instant::now(), stats.clone()));
+                stats
+            }
+        };
+
+        stats.top_classes.truncate(new.top);
+        stats.format(15)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show how long commands and requests took",
+    long_about = "Show the number of runs and the mean, 95th percentile and slowest latency of every command run in this session, and of the requests the CLI sent to each endpoint, slowest first. Requests made by the client library are part of the command timings only.",
+    examples = r#"--reset"#
+)]
+pub struct StatsTiming {
+    #[option(
+        long = "reset",
+        help = "Forget the timings after showing them",
+        flag = "true"
+    )]
+    pub reset: Option<bool>,
+}
+
+impl CliCommand for StatsTiming {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let stats = timing_stats();
+        if new.reset.is_some() {
+            clear_timings();
+        }
+
+        if get_format()? != OutputFormat::Text {
+            return append_json(&stats);
+        }
+        if stats.is_empty() {
+            return append_line(tr("Nothing has been timed yet"));
+        }
+
+        let width = stats.iter().map(|s| s.name.len()).max().unwrap_or(0);
+        let mut kind = None;
+        for stat in stats {
+            if kind != Some(stat.kind) {
+                if kind.is_some() {
+                    append_line("")?;
+                }
+                let title = match stat.kind {
+                    TimingKind::Command => tr("Commands"),
+                    TimingKind::Endpoint => tr("Endpoints"),
+                };
+                append_line(format!(
+                    "{:<width$} {:>6} {:>9} {:>9} {:>9}",
+                    title,
+                    tr("Count"),
+                    tr("Mean"),
+                    "p95",
+                    tr("Slowest"),
+                    width = width
+                ))?;
+                kind = Some(stat.kind);
+            }
+            append_line(format!(
+                "{:<width$} {:>6} {:>6.0} ms {:>6} ms {:>6} ms",
+                stat.name,
+                stat.count,
+                stat.mean_ms,
+                stat.p95_ms,
+                stat.max_ms,
+                width = width
+            ))?;
+        }
+        Ok(())
+    }
+}
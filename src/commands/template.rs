@@ -0,0 +1,337 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, ClassPost, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::shared::{
+    find_class_by_name, find_namespace_by_name, find_object_by_name, repeated_values,
+};
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::autocomplete::{
+    class_templates, classes, namespaces, object_templates, objects_from_class,
+};
+use crate::errors::AppError;
+use crate::formatting::{
+    FormattedClassTemplate, FormattedObjectTemplate, OutputFormatter, OutputFormatterWithPadding,
+};
+use crate::output::{append_json, append_line};
+use crate::templates::{
+    self, find_class_template, save_class_template, save_object_template, ObjectTemplate,
+};
+use crate::tokenizer::CommandTokenizer;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "List class templates",
+    long_about = "List the available class templates. User-defined templates are read from JSON files in the templates directory under the data directory.",
+    examples = r#"--name host"#
+)]
+pub struct ClassTemplateList {
+    #[option(short = "n", long = "name", help = "Name of the template")]
+    pub name: Option<String>,
+}
+
+impl CliCommand for ClassTemplateList {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+
+        let templates = templates::class_templates()?
+            .iter()
+            .filter(|t| match &query.name {
+                Some(name) => t.name.to_lowercase().contains(&name.to_lowercase()),
+                None => true,
+            })
+            .enumerate()
+            .map(|(i, t)| FormattedClassTemplate::new(t, i))
+            .collect::<Vec<_>>();
+
+        if templates.is_empty() {
+            append_line("No class templates found")?;
+            return Ok(());
+        }
+
+        templates.format()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Create a class from a template",
+    long_about = "Create a new class in a namespace from a class template, including its JSON schema.",
+    examples = r#"-t Host -N namespace_1
+--template Service --namespace namespace_1 --name WebService --description 'Web services'"#,
+    mutating = "true"
+)]
+pub struct ClassTemplateApply {
+    #[option(
+        short = "t",
+        long = "template",
+        help = "Name of the template",
+        autocomplete = "class_templates"
+    )]
+    pub template: String,
+    #[option(
+        short = "N",
+        long = "namespace",
+        help = "Namespace name",
+        autocomplete = "namespaces",
+        context = "true"
+    )]
+    pub namespace: String,
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the class, defaults to the template name"
+    )]
+    pub name: Option<String>,
+    #[option(
+        short = "d",
+        long = "description",
+        help = "Description of the class, defaults to the template description"
+    )]
+    pub description: Option<String>,
+}
+
+impl CliCommand for ClassTemplateApply {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let template = find_class_template(&new.template)?;
+        let namespace = find_namespace_by_name(client, &new.namespace)?;
+
+        let result = client.classes().create(ClassPost {
+            name: new.name.unwrap_or(template.name),
+            namespace_id: namespace.id,
+            description: new.description.unwrap_or(template.description),
+            json_schema: Some(template.json_schema),
+            validate_schema: Some(template.validate_schema),
+        })?;
+
+        result.format(15)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Scaffold a class from a template",
+    long_about = "Start a class from one of the predefined class definitions, such as host, network or service, and customize it: rename it, change its description, and add properties with --property name=type, where type is a JSON schema type, optionally making them required with --require. With a namespace the class is created, with --save the customized definition is stored as a user template for later use or editing, and without either it is shown.",
+    examples = r#"--template host
+--template network --namespace infra
+-t host -N infra -n Server --property rack=string --property serial=string --require serial
+-t service -n WebService --property tls=boolean --save"#,
+    mutating = "true"
+)]
+pub struct ClassScaffold {
+    #[positional(index = 0, help = "Name of the template")]
+    #[option(
+        short = "t",
+        long = "template",
+        help = "Name of the template",
+        autocomplete = "class_templates"
+    )]
+    pub template: String,
+    #[option(
+        short = "N",
+        long = "namespace",
+        help = "Namespace to create the class in",
+        autocomplete = "namespaces",
+        context = "true"
+    )]
+    pub namespace: Option<String>,
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the class, defaults to the template name"
+    )]
+    pub name: Option<String>,
+    #[option(
+        short = "d",
+        long = "description",
+        help = "Description of the class, defaults to the template description"
+    )]
+    pub description: Option<String>,
+    #[option(
+        short = "p",
+        long = "property",
+        help = "Property to add as name=type, may be repeated"
+    )]
+    pub property: Option<String>,
+    #[option(
+        short = "r",
+        long = "require",
+        help = "Property to make required, may be repeated"
+    )]
+    pub require: Option<String>,
+    #[option(
+        short = "s",
+        long = "save",
+        help = "Save the class definition as a user template",
+        flag = "true"
+    )]
+    pub save: Option<bool>,
+}
+
+impl CliCommand for ClassScaffold {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let mut template = find_class_template(&new.template)?;
+
+        if let Some(name) = &new.name {
+            template.name = name.clone();
+        }
+        if let Some(description) = &new.description {
+            template.description = description.clone();
+        }
+        for property in repeated_values(tokens, &["p", "property"], &new.property) {
+            let (name, kind) = property.split_once('=').ok_or_else(|| {
+                AppError::ParseError(format!("Expected name=type, got '{}'", property))
+            })?;
+            template.add_property(name.trim(), kind.trim())?;
+        }
+        for name in repeated_values(tokens, &["r", "require"], &new.require) {
+            template.require_property(&name)?;
+        }
+
+        if new.save.is_some() {
+            let path = save_class_template(&template)?;
+            append_line(format!(
+                "Saved template '{}' to {}",
+                template.name,
+                path.display()
+            ))?;
+        }
+
+        let Some(namespace) = &new.namespace else {
+            if new.save.is_none() {
+                append_json(&template)?;
+            }
+            return Ok(());
+        };
+        let namespace = find_namespace_by_name(client, namespace)?;
+        let result = client.classes().create(ClassPost {
+            name: template.name,
+            namespace_id: namespace.id,
+            description: template.description,
+            json_schema: Some(template.json_schema),
+            validate_schema: Some(template.validate_schema),
+        })?;
+
+        result.format(15)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Save an object as a template",
+    long_about = "Save the description and data of an object as a local template, to create similar objects from with object create --from-template. Where the name of the object appears as a word in them, e.g. in web01.example.com, it is replaced by the {{name}} placeholder, which is filled in with the name of each new object, as are {{class}} and {{namespace}}.",
+    examples = r#"-c Host -n web01 --as web-server
+--class Host --name db01 --as database"#
+)]
+pub struct ObjectTemplateSave {
+    #[option(
+        short = "n",
+        long = "name",
+        help = "Name of the object",
+        autocomplete = "objects_from_class"
+    )]
+    pub name: String,
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Class of the object",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: String,
+    #[option(
+        short = "a",
+        long = "as",
+        help = "Name of the template",
+        autocomplete = "object_templates"
+    )]
+    pub template: String,
+}
+
+impl CliCommand for ObjectTemplateSave {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let class = find_class_by_name(client, &new.class)?;
+        let object = find_object_by_name(client, class.id, &new.name)?;
+
+        let template = ObjectTemplate::from_object(&new.template, &class.name, &object)?;
+        let path = save_object_template(&template)?;
+        append_line(format!(
+            "Saved template '{}' to {}",
+            template.name,
+            path.display()
+        ))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "List object templates",
+    long_about = "List the object templates saved with object template save.",
+    examples = r#"--class Host"#
+)]
+pub struct ObjectTemplateList {
+    #[option(
+        short = "c",
+        long = "class",
+        help = "Only show templates saved from objects of this class",
+        autocomplete = "classes",
+        context = "true"
+    )]
+    pub class: Option<String>,
+}
+
+impl CliCommand for ObjectTemplateList {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+
+        let templates = templates::object_templates()?
+            .iter()
+            .filter(|t| {
+                query
+                    .class
+                    .as_ref()
+                    .is_none_or(|class| t.class.eq_ignore_ascii_case(class))
+            })
+            .enumerate()
+            .map(|(i, t)| FormattedObjectTemplate::new(t, i))
+            .collect::<Vec<_>>();
+
+        if templates.is_empty() {
+            append_line("No object templates found")?;
+            return Ok(());
+        }
+
+        templates.format()?;
+        Ok(())
+    }
+}
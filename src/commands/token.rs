@@ -0,0 +1,149 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, FilterOperator, SyncClient};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::api::{get_json, post_json};
+use crate::config::get_config;
+use crate::errors::AppError;
+use crate::files::remove_tokens_from_tokenfile;
+use crate::formatting::{mask_token, FormattedToken, OutputFormatter};
+use crate::i18n::{tr, tr_args};
+use crate::output::append_line;
+use crate::session;
+use crate::tokenizer::CommandTokenizer;
+
+// The tokens of the logged in user, as returned by the API.
+fn user_tokens(client: &SyncClient<Authenticated>) -> Result<Vec<Value>, AppError> {
+    let config = get_config()?;
+    let user = client
+        .users()
+        .find()
+        .add_filter(
+            "username",
+            FilterOperator::Equals { is_negated: false },
+            &config.server.username,
+        )
+        .execute_expecting_single_result()?;
+
+    let tokens = get_json(client, &format!("/api/v1/iam/users/{}/tokens", user.id))?;
+    Ok(tokens.as_array().cloned().unwrap_or_default())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "List your API tokens",
+    long_about = "List the active API tokens of the logged in user. Tokens are shortened, the start of a token is enough to revoke it."
+)]
+pub struct TokenList {}
+
+impl CliCommand for TokenList {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+
+        let current = client.get_token().to_string();
+        let tokens = user_tokens(client)?
+            .iter()
+            .enumerate()
+            .map(|(i, token)| FormattedToken::new(token, &current, i))
+            .collect::<Vec<_>>();
+
+        tokens.format()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Revoke API tokens",
+    long_about = "Revoke one of your API tokens, given by its start as shown by 'token list', or all of them. Revoked tokens are also removed from the local token file. Revoking the token in use logs you out.",
+    examples = r#"--token 3f9a1c2b
+--all"#,
+    mutating = "true"
+)]
+pub struct TokenRevoke {
+    #[option(short = "t", long = "token", help = "The start of the token to revoke")]
+    pub token: Option<String>,
+    #[option(
+        short = "a",
+        long = "all",
+        help = "Revoke all your tokens, including the one in use",
+        flag = "true"
+    )]
+    pub all: Option<bool>,
+}
+
+impl CliCommand for TokenRevoke {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let mut new = self.new_from_tokens(tokens)?;
+        if new.token.is_none() {
+            new.token = tokens.get_positionals().first().cloned();
+        }
+        let config = get_config()?;
+
+        if new.all.is_some() {
+            post_json(client, "/api/v0/auth/logout_all", None)?;
+            remove_tokens_from_tokenfile(|entry| {
+                entry.hostname == config.server.hostname && entry.username == config.server.username
+            })?;
+            // The token in use is revoked as well, so the session can't go on with it.
+            session::clear_client()?;
+            append_line(tr(
+                "Revoked all tokens and logged out, use 'login' to log in again",
+            ))?;
+            return Ok(());
+        }
+
+        let prefix = new
+            .token
+            .ok_or_else(|| AppError::MissingOptions(vec!["token".to_string()]))?;
+        let matches = user_tokens(client)?
+            .iter()
+            .filter_map(|t| t.get("token").and_then(|t| t.as_str()).map(str::to_string))
+            .filter(|t| t.starts_with(&prefix))
+            .collect::<Vec<_>>();
+
+        let token = match matches.as_slice() {
+            [token] => token.clone(),
+            [] => return Err(AppError::EntityNotFound(format!("Token {}", prefix))),
+            _ => {
+                return Err(AppError::MultipleEntitiesFound(format!(
+                    "{} tokens start with {}",
+                    matches.len(),
+                    prefix
+                )))
+            }
+        };
+
+        let current = token == client.get_token();
+        if current {
+            post_json(client, "/api/v0/auth/logout", None)?;
+        } else {
+            post_json(
+                client,
+                "/api/v0/auth/logout_token",
+                Some(&json!({ "token": token })),
+            )?;
+        }
+        remove_tokens_from_tokenfile(|entry| entry.token == token)?;
+        if current {
+            session::clear_client()?;
+        }
+
+        append_line(tr_args(
+            "Revoked token {token}",
+            &[("token", &mask_token(&token))],
+        ))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,77 @@
+use cli_command_derive::CliCommand;
+use hubuum_client::{Authenticated, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use super::{CliCommand, CliCommandInfo, CliOption};
+
+use crate::errors::AppError;
+use crate::http_trace::last_exchanges;
+use crate::models::OutputFormat;
+use crate::output::{append_json, append_line, get_format};
+use crate::tokenizer::CommandTokenizer;
+
+const DEFAULT_COUNT: usize = 10;
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show the last requests to the server",
+    long_about = "Show the last requests sent to the server and the responses, with the status, how long they took and the start of the bodies. Passwords, tokens and other secrets are replaced by ***. Requests made by the client library itself are not traced.",
+    examples = r#"5
+--count 20"#
+)]
+pub struct TraceLast {
+    #[option(
+        short = "n",
+        long = "count",
+        help = "Number of requests to show, defaults to 10"
+    )]
+    pub count: Option<usize>,
+}
+
+impl CliCommand for TraceLast {
+    fn execute(
+        &self,
+        _client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.execute_unauthenticated(tokens)
+    }
+
+    fn execute_unauthenticated(&self, tokens: &CommandTokenizer) -> Result<(), AppError> {
+        let new = self.new_from_tokens(tokens)?;
+        let count = match (new.count, tokens.get_positionals().first()) {
+            (Some(count), _) => count,
+            (None, Some(count)) => count.parse()?,
+            (None, None) => DEFAULT_COUNT,
+        };
+        let exchanges = last_exchanges(count);
+
+        match get_format()? {
+            OutputFormat::Text => {
+                for exchange in exchanges {
+                    let outcome = match (exchange.status, &exchange.error) {
+                        (Some(status), _) => status.to_string(),
+                        (None, Some(error)) => error.clone(),
+                        (None, None) => "-".to_string(),
+                    };
+                    append_line(format!(
+                        "{}  {} {}  {}  {} ms",
+                        exchange.at.format("%H:%M:%S"),
+                        exchange.method,
+                        exchange.url,
+                        outcome,
+                        exchange.elapsed_ms
+                    ))?;
+                    if let Some(body) = exchange.request_body {
+                        append_line(format!("    > {}", body))?;
+                    }
+                    if let Some(body) = exchange.response_body {
+                        append_line(format!("    < {}", body))?;
+                    }
+                }
+                Ok(())
+            }
+            _ => append_json(&exchanges),
+        }
+    }
+}
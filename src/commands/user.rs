@@ -1,31 +1,49 @@
+use std::collections::HashSet;
+
 use cli_command_derive::CliCommand;
 use hubuum_client::{
-    Authenticated, FilterOperator, IntoResourceFilter, QueryFilter, SyncClient, User, UserPost,
+    Authenticated, Credentials, FilterOperator, IntoResourceFilter, QueryFilter, SyncClient, User,
+    UserPatch, UserPost,
 };
 use serde::{Deserialize, Serialize};
 
 use rand::distributions::Alphanumeric;
 use rand::{thread_rng, Rng};
 
+use super::shared::{parse_filter_operator, sort_entities, ListFilter};
+
+use crate::api::{base_url, get_json, new_client, post_json, retry_api};
+use crate::autocomplete::{groups, users};
+use crate::config::get_config;
 use crate::errors::AppError;
-use crate::formatting::{OutputFormatter, OutputFormatterWithPadding};
-use crate::output::{append_key_value, append_line};
+use crate::files::{get_token_entry_from_tokenfile, write_token_to_tokenfile};
+use crate::formatting::{
+    format_count, FormattedWhoami, OutputFormatter, OutputFormatterWithPadding,
+};
+use crate::i18n::{tr, tr_args};
+use crate::models::TokenEntry;
+use crate::output::{add_warning, append_key_value, append_line, mark_secret, set_primary_output};
+use crate::progress::stop_spinner;
 
 use crate::tokenizer::CommandTokenizer;
 
 use super::CliCommand;
 use super::{CliCommandInfo, CliOption};
 
-trait GetUsername {
-    fn username(&self) -> Option<String>;
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(mutating = "true")]
 pub struct UserNew {
     #[option(short = "u", long = "username", help = "Username of the user")]
     pub username: String,
     #[option(short = "e", long = "email", help = "Email address for the user")]
     pub email: Option<String>,
+    #[option(
+        short = "i",
+        long = "interactive",
+        help = "Ask for the options that were not given",
+        flag = "true"
+    )]
+    pub interactive: Option<bool>,
 }
 
 impl UserNew {
@@ -50,15 +68,24 @@ impl CliCommand for UserNew {
         let user = client.users().create(new)?;
 
         user.format(15)?;
-        append_key_value("Password", password, 15)?;
+        append_key_value(tr("Password"), &password, 15)?;
+        mark_secret(&password)?;
+        set_primary_output(password)?;
 
         Ok(())
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(mutating = "true")]
 pub struct UserDelete {
-    #[option(short = "u", long = "username", help = "Username of the user")]
+    #[positional(index = 0, help = "Username of the user")]
+    #[option(
+        short = "u",
+        long = "username",
+        help = "Username of the user",
+        autocomplete = "users"
+    )]
     pub username: Option<String>,
 }
 
@@ -78,34 +105,128 @@ impl IntoResourceFilter<User> for &UserDelete {
     }
 }
 
-impl GetUsername for &UserDelete {
-    fn username(&self) -> Option<String> {
-        self.username.clone()
+impl CliCommand for UserDelete {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        let query = self.new_from_tokens(tokens)?;
+
+        let user = client.users().filter_expecting_single_result(&query)?;
+
+        client.users().delete(user.id)?;
+        append_line(tr_args(
+            "User '{username}' deleted",
+            &[("username", &user.username)],
+        ))?;
+
+        Ok(())
     }
 }
 
-impl CliCommand for UserDelete {
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Modify a user",
+    long_about = "Change the email address of a user, or reset their password to a given value or a new random password that is shown once.",
+    examples = r#"alice --email alice@example.com
+--username alice --reset-password
+--username alice --password 'correct horse battery staple'"#,
+    mutating = "true"
+)]
+pub struct UserModify {
+    #[positional(index = 0, help = "Username of the user")]
+    #[option(
+        short = "u",
+        long = "username",
+        help = "Username of the user",
+        autocomplete = "users"
+    )]
+    pub username: Option<String>,
+    #[option(short = "e", long = "email", help = "Email address for the user")]
+    pub email: Option<String>,
+    #[option(
+        short = "p",
+        long = "password",
+        help = "New password for the user",
+        conflicts_with = "reset_password",
+        secret = "true"
+    )]
+    pub password: Option<String>,
+    #[option(
+        short = "R",
+        long = "reset-password",
+        help = "Reset the password to a new random password",
+        flag = "true"
+    )]
+    pub reset_password: Option<bool>,
+}
+
+impl IntoResourceFilter<User> for &UserModify {
+    fn into_resource_filter(self) -> Vec<QueryFilter> {
+        let mut filters = vec![];
+
+        if let Some(username) = &self.username {
+            filters.push(QueryFilter {
+                key: "username".to_string(),
+                value: username.clone(),
+                operator: FilterOperator::Equals { is_negated: false },
+            });
+        }
+
+        filters
+    }
+}
+
+impl CliCommand for UserModify {
     fn execute(
         &self,
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut query = self.new_from_tokens(tokens)?;
-
-        query.username = username_or_pos(&query, tokens, 0)?;
+        let query = self.new_from_tokens(tokens)?;
 
         let user = client.users().filter_expecting_single_result(&query)?;
 
-        client.users().delete(user.id)?;
-        append_line(format!("User '{}' deleted", user.username.clone()))?;
+        let mut patch = UserPatch::default();
+
+        if let Some(email) = &query.email {
+            patch.email = Some(email.clone());
+        }
+
+        let password = match (&query.password, query.reset_password) {
+            (Some(password), _) => Some(password.clone()),
+            (None, Some(_)) => Some(generate_random_password(20)),
+            (None, None) => None,
+        };
+        patch.password.clone_from(&password);
+
+        let user = client.users().update(user.id, patch)?;
+
+        user.format(15)?;
+        // Only show generated passwords, the user already knows the one they gave us.
+        if query.reset_password.is_some() {
+            if let Some(password) = password {
+                append_key_value(tr("Password"), &password, 15)?;
+                mark_secret(&password)?;
+                set_primary_output(password)?;
+            }
+        }
 
         Ok(())
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct UserInfo {
-    #[option(short = "u", long = "username", help = "Username of the user")]
+    #[positional(index = 0, help = "Username of the user")]
+    #[option(
+        short = "u",
+        long = "username",
+        help = "Username of the user",
+        autocomplete = "users"
+    )]
     pub username: Option<String>,
     #[option(short = "e", long = "email", help = "Email address for the user")]
     pub email: Option<String>,
@@ -155,21 +276,13 @@ impl IntoResourceFilter<User> for &UserInfo {
     }
 }
 
-impl GetUsername for &UserInfo {
-    fn username(&self) -> Option<String> {
-        self.username.clone()
-    }
-}
-
 impl CliCommand for UserInfo {
     fn execute(
         &self,
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let mut query = self.new_from_tokens(tokens)?;
-
-        query.username = username_or_pos(&query, tokens, 0)?;
+        let query = self.new_from_tokens(tokens)?;
 
         client
             .users()
@@ -181,8 +294,14 @@ impl CliCommand for UserInfo {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(cancellable = "true")]
 pub struct UserList {
-    #[option(short = "u", long = "username", help = "Username of the user")]
+    #[option(
+        short = "u",
+        long = "username",
+        help = "Username of the user",
+        autocomplete = "users"
+    )]
     pub username: Option<String>,
     #[option(short = "e", long = "email", help = "Email address for the user")]
     pub email: Option<String>,
@@ -190,6 +309,118 @@ pub struct UserList {
     pub created_at: Option<chrono::NaiveDateTime>,
     #[option(short = "U", long = "updated-at", help = "Updated at timestamp")]
     pub updated_at: Option<chrono::NaiveDateTime>,
+    #[option(
+        short = "m",
+        long = "match",
+        help = "Operator for username and email, e.g. equals or startswith (default icontains)",
+        values = "equals,iequals,contains,icontains,startswith,istartswith,endswith,iendswith,like,regex"
+    )]
+    pub match_operator: Option<String>,
+    #[option(
+        short = "g",
+        long = "group",
+        help = "Only list members of this group",
+        autocomplete = "groups"
+    )]
+    pub group: Option<String>,
+    #[option(
+        short = "f",
+        long = "filter",
+        help = "Filter expression, e.g. 'username__startswith=adm,email__endswith=example.com'"
+    )]
+    pub filter: Option<String>,
+    #[option(
+        long = "count",
+        help = "Only show the number of matches",
+        flag = "true"
+    )]
+    pub count: Option<bool>,
+    #[option(
+        short = "s",
+        long = "sort",
+        help = "Sort by comma-separated fields, e.g. username or created_at"
+    )]
+    pub sort: Option<String>,
+    #[option(
+        short = "r",
+        long = "reverse",
+        help = "Reverse the sort order",
+        flag = "true"
+    )]
+    pub reverse: Option<bool>,
+}
+
+impl IntoResourceFilter<User> for &UserList {
+    fn into_resource_filter(self) -> Vec<QueryFilter> {
+        let mut filters = vec![];
+        // Validated with parse_filter_operator in execute before the query is built.
+        let operator = || match &self.match_operator {
+            Some(operator) => parse_filter_operator(operator)
+                .unwrap_or(FilterOperator::IContains { is_negated: false }),
+            None => FilterOperator::IContains { is_negated: false },
+        };
+
+        if let Some(username) = &self.username {
+            filters.push(QueryFilter {
+                key: "username".to_string(),
+                value: username.clone(),
+                operator: operator(),
+            });
+        }
+
+        if let Some(email) = &self.email {
+            filters.push(QueryFilter {
+                key: "email".to_string(),
+                value: email.clone(),
+                operator: operator(),
+            });
+        }
+
+        if let Some(created_at) = &self.created_at {
+            filters.push(QueryFilter {
+                key: "created_at".to_string(),
+                value: created_at.to_string(),
+                operator: FilterOperator::Equals { is_negated: false },
+            });
+        }
+
+        if let Some(updated_at) = &self.updated_at {
+            filters.push(QueryFilter {
+                key: "updated_at".to_string(),
+                value: updated_at.to_string(),
+                operator: FilterOperator::Equals { is_negated: false },
+            });
+        }
+        filters
+    }
+}
+
+// The IDs of the members of a group, looked up by its name.
+fn group_member_ids(
+    client: &SyncClient<Authenticated>,
+    groupname: &str,
+) -> Result<HashSet<i32>, AppError> {
+    let group = client
+        .groups()
+        .find()
+        .add_filter(
+            "groupname",
+            FilterOperator::Equals { is_negated: false },
+            groupname,
+        )
+        .execute_expecting_single_result()?;
+
+    let members = get_json(client, &format!("/api/v1/iam/groups/{}/members", group.id))?;
+    Ok(members
+        .as_array()
+        .map(|members| {
+            members
+                .iter()
+                .filter_map(|m| m.get("id").and_then(|id| id.as_i64()))
+                .map(|id| id as i32)
+                .collect()
+        })
+        .unwrap_or_default())
 }
 
 impl CliCommand for UserList {
@@ -198,14 +429,155 @@ impl CliCommand for UserList {
         client: &SyncClient<Authenticated>,
         tokens: &CommandTokenizer,
     ) -> Result<(), AppError> {
-        let _ = self.new_from_tokens(tokens)?;
-        let users = client.users().find().execute()?;
+        let new = self.new_from_tokens(tokens)?;
+        if let Some(operator) = &new.match_operator {
+            parse_filter_operator(operator)?;
+        }
+        let filter = ListFilter::new(&new, new.filter.as_deref())?;
+
+        let mut users = retry_api(|| client.users().filter(&filter))?;
+        if let Some(group) = &new.group {
+            let members = group_member_ids(client, group)?;
+            users.retain(|user| members.contains(&user.id));
+        }
+
+        if new.count.is_some() {
+            return format_count(users.len());
+        }
+
+        let users = sort_entities(users, new.sort.as_deref(), new.reverse.is_some())?;
+
         users.format()?;
 
         Ok(())
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Show who you are logged in as",
+    long_about = "Show the logged in user, their groups, the server in use and when the token was issued."
+)]
+pub struct Whoami {}
+
+impl CliCommand for Whoami {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let config = get_config()?;
+
+        let user = client
+            .users()
+            .find()
+            .add_filter(
+                "username",
+                FilterOperator::Equals { is_negated: false },
+                &config.server.username,
+            )
+            .execute_expecting_single_result()?;
+
+        let groups = get_json(client, &format!("/api/v1/iam/users/{}/groups", user.id))?;
+        let groups = groups
+            .as_array()
+            .map(|groups| {
+                groups
+                    .iter()
+                    .filter_map(|g| g.get("groupname").and_then(|n| n.as_str()))
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let token_entry =
+            get_token_entry_from_tokenfile(&config.server.hostname, &config.server.username)?;
+
+        FormattedWhoami {
+            username: user.username.clone(),
+            id: user.id,
+            email: user.email.clone(),
+            groups,
+            server: base_url(&config),
+            token_issued_at: token_entry.and_then(|entry| entry.issued_at),
+        }
+        .format(15)?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, CliCommand, Default)]
+#[command_info(
+    about = "Change your password",
+    long_about = "Change the password of the logged in user. The current password is checked first, and the stored token is replaced with one for the new password.",
+    mutating = "true"
+)]
+pub struct Passwd {}
+
+impl CliCommand for Passwd {
+    fn execute(
+        &self,
+        client: &SyncClient<Authenticated>,
+        tokens: &CommandTokenizer,
+    ) -> Result<(), AppError> {
+        self.new_from_tokens(tokens)?;
+        let config = get_config()?;
+        let username = config.server.username.clone();
+        let unauthenticated = new_client(&config)?;
+
+        stop_spinner();
+        let current = rpassword::prompt_password(tr("Current password: "))?;
+        let verified = unauthenticated
+            .clone()
+            .login(Credentials::new(username.clone(), current))
+            .map_err(|_| AppError::AuthError(tr("The current password is not correct")))?;
+        // The login was only to check the password, so don't leave its token behind.
+        if let Err(err) = post_json(&verified, "/api/v0/auth/logout", None) {
+            add_warning(format!("Failed to revoke the token on the server: {}", err))?;
+        }
+
+        let password = rpassword::prompt_password(tr("New password: "))?;
+        if password.is_empty() {
+            return Err(AppError::InvalidOption(tr(
+                "The new password can not be empty",
+            )));
+        }
+        if password != rpassword::prompt_password(tr("Repeat new password: "))? {
+            return Err(AppError::InvalidOption(tr("The passwords do not match")));
+        }
+
+        let user = client
+            .users()
+            .find()
+            .add_filter(
+                "username",
+                FilterOperator::Equals { is_negated: false },
+                &username,
+            )
+            .execute_expecting_single_result()?;
+
+        let patch = UserPatch {
+            password: Some(password.clone()),
+            ..Default::default()
+        };
+        client.users().update(user.id, patch)?;
+
+        // Replace the stored token with one issued for the new password.
+        let refreshed = unauthenticated.login(Credentials::new(username.clone(), password))?;
+        write_token_to_tokenfile(TokenEntry {
+            hostname: config.server.hostname.clone(),
+            username,
+            token: refreshed.get_token().to_string(),
+            issued_at: Some(chrono::Utc::now().naive_utc()),
+        })?;
+
+        append_line(tr("Password changed"))?;
+        Ok(())
+    }
+}
+
 pub fn generate_random_password(length: usize) -> String {
     let mut rng = thread_rng();
     std::iter::repeat(())
@@ -214,21 +586,3 @@ pub fn generate_random_password(length: usize) -> String {
         .take(length)
         .collect()
 }
-
-fn username_or_pos<U>(
-    query: U,
-    tokens: &CommandTokenizer,
-    pos: usize,
-) -> Result<Option<String>, AppError>
-where
-    U: GetUsername,
-{
-    let pos0 = tokens.get_positionals().get(pos);
-    if query.username().is_none() {
-        if pos0.is_none() {
-            return Err(AppError::MissingOptions(vec!["username".to_string()]));
-        }
-        return Ok(pos0.cloned());
-    };
-    Ok(query.username().clone())
-}
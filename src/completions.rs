@@ -0,0 +1,190 @@
+// Shell completion scripts for the binary itself, so `hubuum-cli --command "obj<TAB>` works
+// outside the REPL. The scripts are generated from the clap arguments and the REPL command
+// tree, and are static: regenerate them after upgrading.
+use clap::{Arg, Command};
+
+use crate::commandlist::CommandList;
+use crate::errors::AppError;
+
+pub const SHELLS: [&str; 3] = ["bash", "zsh", "fish"];
+
+const BIN_NAME: &str = env!("CARGO_BIN_NAME");
+
+struct TopLevelArg {
+    long: String,
+    help: String,
+    takes_value: bool,
+    is_file: bool,
+    values: Vec<String>,
+}
+
+impl TopLevelArg {
+    fn from_arg(arg: &Arg) -> Option<Self> {
+        let long = arg.get_long()?.to_string();
+        let value_names = arg.get_value_names().unwrap_or_default();
+        Some(TopLevelArg {
+            long,
+            help: arg.get_help().map(|h| h.to_string()).unwrap_or_default(),
+            takes_value: arg.get_action().takes_values(),
+            is_file: value_names.iter().any(|name| name.as_str() == "FILE"),
+            values: arg
+                .get_possible_values()
+                .iter()
+                .map(|v| v.get_name().to_string())
+                .collect(),
+        })
+    }
+}
+
+/// The completion script for `shell`, one of [`SHELLS`].
+pub fn completion_script(
+    shell: &str,
+    cli: &Command,
+    repl: &CommandList,
+) -> Result<String, AppError> {
+    let args = cli
+        .get_arguments()
+        .filter_map(TopLevelArg::from_arg)
+        .collect::<Vec<_>>();
+    let commands = repl
+        .all_commands()
+        .into_iter()
+        .map(|(path, command)| (path, command.about().unwrap_or_default()))
+        .collect::<Vec<_>>();
+
+    match shell {
+        "bash" => Ok(bash(&args, &commands)),
+        "zsh" => Ok(zsh(&args, &commands)),
+        "fish" => Ok(fish(&args, &commands)),
+        _ => Err(AppError::ParseError(format!(
+            "Unsupported shell '{}', expected one of {}",
+            shell,
+            SHELLS.join(", ")
+        ))),
+    }
+}
+
+fn function_name() -> String {
+    format!("_{}", BIN_NAME.replace('-', "_"))
+}
+
+fn bash(args: &[TopLevelArg], commands: &[(String, String)]) -> String {
+    let mut cases = String::new();
+    let command_words = commands
+        .iter()
+        .map(|(path, _)| path.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+    cases.push_str(&format!(
+        "        --command)\n            local IFS=$'\\n'\n            COMPREPLY=($(compgen -W '{}' -- \"${{cur#[\\\"\\']}}\"))\n            return ;;\n",
+        command_words
+    ));
+    for arg in args.iter().filter(|a| a.takes_value && a.long != "command") {
+        let reply = if arg.is_file {
+            "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string()
+        } else if !arg.values.is_empty() {
+            format!(
+                "COMPREPLY=($(compgen -W '{}' -- \"$cur\"))",
+                arg.values.join(" ")
+            )
+        } else {
+            "COMPREPLY=()".to_string()
+        };
+        cases.push_str(&format!(
+            "        --{})\n            {}\n            return ;;\n",
+            arg.long, reply
+        ));
+    }
+
+    let flags = args
+        .iter()
+        .map(|a| format!("--{}", a.long))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    format!(
+        "{name}() {{\n    local cur prev\n    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"\n    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"\n\n    case \"$prev\" in\n{cases}    esac\n\n    COMPREPLY=($(compgen -W '{flags}' -- \"$cur\"))\n}}\n\ncomplete -F {name} {bin}\n",
+        name = function_name(),
+        cases = cases,
+        flags = flags,
+        bin = BIN_NAME,
+    )
+}
+
+// Escape text for use inside the brackets of a zsh `_arguments` spec.
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh(args: &[TopLevelArg], commands: &[(String, String)]) -> String {
+    let specs = args
+        .iter()
+        .map(|arg| {
+            let action = if arg.long == "command" {
+                ":command:->command".to_string()
+            } else if arg.is_file {
+                format!(":{}:_files", arg.long)
+            } else if !arg.values.is_empty() {
+                format!(":{}:({})", arg.long, arg.values.join(" "))
+            } else if arg.takes_value {
+                format!(":{}: ", arg.long)
+            } else {
+                String::new()
+            };
+            format!("    '--{}[{}]{}'", arg.long, zsh_escape(&arg.help), action)
+        })
+        .collect::<Vec<_>>()
+        .join(" \\\n");
+
+    let described = commands
+        .iter()
+        .map(|(path, about)| format!("        '{}:{}'", zsh_escape(path), zsh_escape(about)))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    format!(
+        "#compdef {bin}\n\n{name}() {{\n    local state\n    local -a repl_commands\n    repl_commands=(\n{described}\n    )\n\n    _arguments \\\n{specs}\n\n    case $state in\n        command)\n            _describe 'command' repl_commands\n            ;;\n    esac\n}}\n\n{name} \"$@\"\n",
+        bin = BIN_NAME,
+        name = function_name(),
+        described = described,
+        specs = specs,
+    )
+}
+
+fn fish_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\'', "\\'")
+}
+
+fn fish(args: &[TopLevelArg], commands: &[(String, String)]) -> String {
+    let mut script = String::new();
+    for arg in args {
+        let mut line = format!(
+            "complete -c {} -l {} -d '{}'",
+            BIN_NAME,
+            arg.long,
+            fish_escape(&arg.help)
+        );
+        if arg.is_file {
+            line.push_str(" -r -F");
+        } else if !arg.values.is_empty() {
+            line.push_str(&format!(" -x -a '{}'", arg.values.join(" ")));
+        } else if arg.takes_value {
+            line.push_str(" -x");
+        }
+        script.push_str(&line);
+        script.push('\n');
+    }
+
+    for (path, about) in commands {
+        script.push_str(&format!(
+            "complete -c {} -l command -x -a \"'{}'\" -d '{}'\n",
+            BIN_NAME,
+            path,
+            fish_escape(about)
+        ));
+    }
+    script
+}
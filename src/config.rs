@@ -1,16 +1,30 @@
 use config::{Config, ConfigError, Environment, File};
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::sync::RwLock;
 
 use crate::defaults::Defaults;
+use crate::errors::AppError;
 use crate::files::get_system_config_path;
-use crate::models::Protocol;
+use crate::models::{ByteSize, HumanDuration, Protocol};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub cache: CacheConfig,
     pub completion: CompletionConfig,
+    pub display: DisplayConfig,
+    pub auth: AuthConfig,
+    pub repl: ReplConfig,
+    pub http: HttpConfig,
+    pub log: LogConfig,
+    pub fetch: FetchConfig,
+    pub offline: OfflineConfig,
+    // The name of the configuration file given on the command line, for the prompt.
+    #[serde(skip)]
+    pub profile: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -33,6 +47,90 @@ pub struct CacheConfig {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct CompletionConfig {
     pub disable_api_related: bool,
+    pub fuzzy: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DisplayConfig {
+    pub language: String,
+    pub prompt: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AuthConfig {
+    pub providers: Vec<String>,
+    pub command: Option<String>,
+    pub keyring_service: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ReplConfig {
+    pub edit_mode: String,
+    // One history for all servers, instead of one per server.
+    pub shared_history: bool,
+    // Key (e.g. `ctrl-r`) to editing action (e.g. `history-search-backward`).
+    #[serde(default)]
+    pub bindings: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct HttpConfig {
+    // Timeouts in seconds.
+    pub connect_timeout: u64,
+    pub request_timeout: u64,
+    // Idempotent requests are retried this many times, waiting `retry_backoff` milliseconds
+    // before the first retry and twice as long before each following, up to
+    // `retry_backoff_max`.
+    pub retries: u32,
+    pub retry_backoff: u64,
+    pub retry_backoff_max: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LogConfig {
+    // Log here instead of log.txt in the data directory.
+    pub file: Option<String>,
+    // The log is rotated at startup and when it grows past `max_size` bytes, keeping `keep`
+    // old logs next to it as <file>.1 (the newest) to <file>.N.
+    pub max_size: u64,
+    pub keep: usize,
+    // `text`, or `json` for one JSON object per line, for log collectors.
+    pub format: String,
+}
+
+// Option values given as http:// or https:// URLs are fetched by the tokenizer.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FetchConfig {
+    // Set to false to use such values as they are.
+    pub enabled: bool,
+    // Timeout in seconds.
+    pub timeout: u64,
+    // Responses larger than this many bytes are refused.
+    pub max_size: u64,
+    // Responses must have one of these content types, any type if empty.
+    pub content_types: Vec<String>,
+    // Sent as `Authorization: Bearer <token>`.
+    pub bearer_token: Option<String>,
+    // Header name to value, sent with every fetch.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    // The hosts the bearer token and headers are sent to, and only over https.
+    #[serde(default)]
+    pub credential_hosts: Vec<String>,
+    // Replace `${NAME}` in option values with the environment variable NAME. Off by
+    // default, as values may contain `${` of their own; `env://NAME` always works.
+    #[serde(default)]
+    pub expand_env: bool,
+}
+
+// The output of read commands is saved for `--offline` use, and when the server can't be
+// reached.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OfflineConfig {
+    // Saved output older than this many seconds is not shown.
+    pub max_age: u64,
+    // The oldest saved output is removed when the total grows past this many bytes.
+    pub max_size: u64,
 }
 
 impl Default for AppConfig {
@@ -53,19 +151,92 @@ impl Default for AppConfig {
             },
             completion: CompletionConfig {
                 disable_api_related: Defaults::COMPLETION_DISABLE_API_RELATED,
+                fuzzy: Defaults::COMPLETION_FUZZY,
+            },
+            display: DisplayConfig {
+                language: Defaults::DISPLAY_LANGUAGE.to_string(),
+                prompt: Defaults::DISPLAY_PROMPT.to_string(),
+            },
+            auth: AuthConfig {
+                providers: Defaults::AUTH_PROVIDERS
+                    .iter()
+                    .map(|p| p.to_string())
+                    .collect(),
+                command: None,
+                keyring_service: Defaults::AUTH_KEYRING_SERVICE.to_string(),
+            },
+            repl: ReplConfig {
+                edit_mode: Defaults::REPL_EDIT_MODE.to_string(),
+                shared_history: Defaults::REPL_SHARED_HISTORY,
+                bindings: BTreeMap::new(),
+            },
+            http: HttpConfig {
+                connect_timeout: Defaults::HTTP_CONNECT_TIMEOUT,
+                request_timeout: Defaults::HTTP_REQUEST_TIMEOUT,
+                retries: Defaults::HTTP_RETRIES,
+                retry_backoff: Defaults::HTTP_RETRY_BACKOFF,
+                retry_backoff_max: Defaults::HTTP_RETRY_BACKOFF_MAX,
+            },
+            log: LogConfig {
+                file: None,
+                max_size: Defaults::LOG_MAX_SIZE,
+                keep: Defaults::LOG_KEEP,
+                format: Defaults::LOG_FORMAT.to_string(),
+            },
+            fetch: FetchConfig {
+                enabled: Defaults::FETCH_ENABLED,
+                timeout: Defaults::FETCH_TIMEOUT,
+                max_size: Defaults::FETCH_MAX_SIZE,
+                content_types: Defaults::FETCH_CONTENT_TYPES
+                    .iter()
+                    .map(|t| t.to_string())
+                    .collect(),
+                bearer_token: None,
+                headers: BTreeMap::new(),
+                credential_hosts: Vec::new(),
+                expand_env: Defaults::FETCH_EXPAND_ENV,
             },
+            offline: OfflineConfig {
+                max_age: Defaults::OFFLINE_MAX_AGE,
+                max_size: Defaults::OFFLINE_MAX_SIZE,
+            },
+            profile: Defaults::PROFILE.to_string(),
         }
     }
 }
 
-pub fn load_config(cli_config_path: Option<PathBuf>) -> Result<AppConfig, ConfigError> {
-    let system_config = get_system_config_path();
-    let user_config = dirs::config_dir()
+// Shown in place of secrets.
+const REDACTED: &str = "***";
+
+impl AppConfig {
+    /// A copy fit for showing, with the secrets masked: the bearer token and the values of
+    /// the headers sent when fetching option values, as headers such as `Authorization`
+    /// carry credentials.
+    pub fn redacted(&self) -> AppConfig {
+        let mut config = self.clone();
+        if let Some(token) = config.fetch.bearer_token.as_mut() {
+            *token = REDACTED.to_string();
+        }
+        for value in config.fetch.headers.values_mut() {
+            *value = REDACTED.to_string();
+        }
+        config
+    }
+}
+
+/// The configuration file of the user, which `config set --save` writes to.
+pub fn get_user_config_path() -> PathBuf {
+    dirs::config_dir()
         .map(|mut path| {
             path.push(".hubuum_cli/config.toml");
             path
         })
-        .unwrap_or_else(|| PathBuf::from("config.toml"));
+        .unwrap_or_else(|| PathBuf::from("config.toml"))
+}
+
+pub fn load_config(cli_config_path: Option<PathBuf>) -> Result<AppConfig, ConfigError> {
+    let system_config = get_system_config_path();
+    let user_config = get_user_config_path();
 
     let mut builder = Config::builder()
         // Start with default values
@@ -82,6 +253,31 @@ pub fn load_config(cli_config_path: Option<PathBuf>) -> Result<AppConfig, Config
             "completion.disable_api_related",
             Defaults::COMPLETION_DISABLE_API_RELATED,
         )?
+        .set_default("completion.fuzzy", Defaults::COMPLETION_FUZZY)?
+        .set_default("display.language", Defaults::DISPLAY_LANGUAGE)?
+        .set_default("display.prompt", Defaults::DISPLAY_PROMPT)?
+        .set_default("auth.providers", Defaults::AUTH_PROVIDERS.to_vec())?
+        .set_default("auth.keyring_service", Defaults::AUTH_KEYRING_SERVICE)?
+        .set_default("repl.edit_mode", Defaults::REPL_EDIT_MODE)?
+        .set_default("repl.shared_history", Defaults::REPL_SHARED_HISTORY)?
+        .set_default("http.connect_timeout", Defaults::HTTP_CONNECT_TIMEOUT)?
+        .set_default("http.request_timeout", Defaults::HTTP_REQUEST_TIMEOUT)?
+        .set_default("http.retries", Defaults::HTTP_RETRIES)?
+        .set_default("http.retry_backoff", Defaults::HTTP_RETRY_BACKOFF)?
+        .set_default("http.retry_backoff_max", Defaults::HTTP_RETRY_BACKOFF_MAX)?
+        .set_default("log.max_size", Defaults::LOG_MAX_SIZE)?
+        .set_default("log.keep", Defaults::LOG_KEEP as u64)?
+        .set_default("log.format", Defaults::LOG_FORMAT)?
+        .set_default("fetch.enabled", Defaults::FETCH_ENABLED)?
+        .set_default("fetch.expand_env", Defaults::FETCH_EXPAND_ENV)?
+        .set_default("fetch.timeout", Defaults::FETCH_TIMEOUT)?
+        .set_default("fetch.max_size", Defaults::FETCH_MAX_SIZE)?
+        .set_default(
+            "fetch.content_types",
+            Defaults::FETCH_CONTENT_TYPES.to_vec(),
+        )?
+        .set_default("offline.max_age", Defaults::OFFLINE_MAX_AGE)?
+        .set_default("offline.max_size", Defaults::OFFLINE_MAX_SIZE)?
         // 1. Load system-wide config
         .add_source(File::from(system_config).required(false))
         // 2. Load user-specific config
@@ -90,11 +286,172 @@ pub fn load_config(cli_config_path: Option<PathBuf>) -> Result<AppConfig, Config
         .add_source(Environment::with_prefix("HUBUUM_CLI").separator("__"));
 
     // 4. Load CLI-specified config file, if provided
-    if let Some(config_path) = cli_config_path {
-        builder = builder.add_source(File::from(config_path).required(true));
+    if let Some(config_path) = &cli_config_path {
+        builder = builder.add_source(File::from(config_path.clone()).required(true));
     }
 
     let config = builder.build()?;
 
-    config.try_deserialize()
+    let mut config: AppConfig = config.try_deserialize()?;
+    config.profile = cli_config_path
+        .as_deref()
+        .and_then(|path| path.file_stem())
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| Defaults::PROFILE.to_string());
+    Ok(config)
+}
+
+// The configuration in use, for commands that need to know about it.
+static CONFIG: Lazy<RwLock<AppConfig>> = Lazy::new(|| RwLock::new(AppConfig::default()));
+
+pub fn set_config(config: AppConfig) -> Result<(), AppError> {
+    *CONFIG.write().map_err(|_| AppError::LockError)? = config;
+    Ok(())
+}
+
+pub fn get_config() -> Result<AppConfig, AppError> {
+    Ok(CONFIG.read().map_err(|_| AppError::LockError)?.clone())
+}
+
+fn unknown_key(key: &str) -> AppError {
+    AppError::ConfigError(format!("Unknown configuration key '{}'", key))
+}
+
+/// Look up a dotted key such as `server.port` in the configuration.
+pub fn get_config_value(config: &AppConfig, key: &str) -> Result<serde_json::Value, AppError> {
+    let value = serde_json::to_value(config)?;
+    value
+        .pointer(&format!("/{}", key.replace('.', "/")))
+        .cloned()
+        .ok_or_else(|| unknown_key(key))
+}
+
+/// Set a dotted key to a value given as text, returning the updated configuration. The text
+/// is read as the type of the current value, and lists are comma-separated.
+pub fn set_config_value(
+    config: &AppConfig,
+    key: &str,
+    value: &str,
+) -> Result<(AppConfig, serde_json::Value), AppError> {
+    use serde_json::Value;
+
+    let mut root = serde_json::to_value(config)?;
+    let slot = root
+        .pointer_mut(&format!("/{}", key.replace('.', "/")))
+        .ok_or_else(|| unknown_key(key))?;
+
+    let invalid = |expected: &str| {
+        AppError::ConfigError(format!(
+            "Invalid value '{}' for {}, expected {}",
+            value, key, expected
+        ))
+    };
+    let new = match slot {
+        Value::Bool(_) => Value::Bool(value.parse().map_err(|_| invalid("true or false"))?),
+        // Sizes and timeouts may also be given with a unit, e.g. 10MB or 30s.
+        Value::Number(_) if key.ends_with("max_size") => value
+            .parse::<serde_json::Number>()
+            .map(Value::Number)
+            .or_else(|_| value.parse::<ByteSize>().map(|size| Value::from(size.0)))
+            .map_err(|_| invalid("a number of bytes or a size like 10MB"))?,
+        Value::Number(_) if key.ends_with("timeout") => value
+            .parse::<serde_json::Number>()
+            .map(Value::Number)
+            .or_else(|_| {
+                value
+                    .parse::<HumanDuration>()
+                    .map(|duration| Value::from(duration.as_secs()))
+            })
+            .map_err(|_| invalid("a number of seconds or a duration like 30s"))?,
+        Value::Number(_) => value
+            .parse::<serde_json::Number>()
+            .map(Value::Number)
+            .map_err(|_| invalid("a number"))?,
+        Value::Array(_) => Value::Array(
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| Value::String(v.to_string()))
+                .collect(),
+        ),
+        Value::Object(_) => return Err(invalid("a single value, not a section")),
+        Value::String(_) | Value::Null => Value::String(value.to_string()),
+    };
+    *slot = new.clone();
+
+    let config = serde_json::from_value(root)
+        .map_err(|e| AppError::ConfigError(format!("Invalid value for {}: {}", key, e)))?;
+    Ok((config, new))
+}
+
+/// Write a single key to the configuration file of the user, keeping the rest of the file.
+pub fn save_config_value(key: &str, value: &serde_json::Value) -> Result<PathBuf, AppError> {
+    let path = get_user_config_path();
+    let mut document: toml::Table = match std::fs::read_to_string(&path) {
+        Ok(content) => content
+            .parse()
+            .map_err(|e| AppError::ConfigError(format!("{}: {}", path.display(), e)))?,
+        Err(_) => toml::Table::new(),
+    };
+
+    let value: toml::Value = serde_json::from_value(value.clone())?;
+    let mut parts = key.split('.').collect::<Vec<_>>();
+    let last = parts.pop().ok_or_else(|| unknown_key(key))?;
+    let mut table = &mut document;
+    for part in parts {
+        table = table
+            .entry(part)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| unknown_key(key))?;
+    }
+    table.insert(last.to_string(), value);
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let content =
+        toml::to_string_pretty(&document).map_err(|e| AppError::ConfigError(e.to_string()))?;
+    std::fs::write(&path, content)?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacted_masks_fetch_credentials() {
+        let mut config = AppConfig::default();
+        config.fetch.bearer_token = Some("s3cret".to_string());
+        config.fetch.headers.insert(
+            "Authorization".to_string(),
+            "Basic dXNlcjpwdw==".to_string(),
+        );
+        config
+            .fetch
+            .credential_hosts
+            .push("files.example.com".to_string());
+
+        let redacted = config.redacted();
+        assert_eq!(redacted.fetch.bearer_token.as_deref(), Some(REDACTED));
+        assert_eq!(redacted.fetch.headers["Authorization"], REDACTED);
+        assert_eq!(
+            redacted.fetch.credential_hosts,
+            config.fetch.credential_hosts
+        );
+
+        // Nothing of the secrets survives in the structured output either.
+        let shown = serde_json::to_string(&redacted).unwrap();
+        assert!(!shown.contains("s3cret"));
+        assert!(!shown.contains("dXNlcjpwdw=="));
+    }
+
+    #[test]
+    fn redacted_leaves_unset_token_unset() {
+        let redacted = AppConfig::default().redacted();
+        assert_eq!(redacted.fetch.bearer_token, None);
+        assert!(redacted.fetch.headers.is_empty());
+    }
 }
@@ -0,0 +1,326 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+use crate::config::{get_user_config_path, AppConfig};
+use crate::errors::AppError;
+use crate::files::get_system_config_path;
+use crate::plan::flatten;
+
+// Settings that have been renamed, as (old key, current key). No setting has been renamed
+// yet; add the old name here when one is, so it is reported and migrated.
+const RENAMED: &[(&str, &str)] = &[];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// A problem found in a configuration file.
+#[derive(Debug, Clone)]
+pub struct ConfigProblem {
+    pub file: PathBuf,
+    pub line: Option<usize>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.line {
+            Some(line) => write!(
+                f,
+                "{}:{}: {}: {}",
+                self.file.display(),
+                line,
+                severity,
+                self.message
+            ),
+            None => write!(f, "{}: {}: {}", self.file.display(), severity, self.message),
+        }
+    }
+}
+
+/// The configuration files that are read, in the order they are applied.
+pub fn config_files(cli_config_path: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut files = vec![get_system_config_path(), get_user_config_path()];
+    files.extend(cli_config_path);
+    files.into_iter().filter(|file| file.exists()).collect()
+}
+
+// The line each dotted key is set on, found by following the `[section]` headers.
+fn key_lines(content: &str) -> BTreeMap<String, usize> {
+    let mut lines = BTreeMap::new();
+    let mut section = String::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            section = header.trim().to_string();
+        } else if let Some((key, _)) = line.split_once('=') {
+            let key = key.trim().trim_matches('"');
+            if key.is_empty() || key.starts_with('#') {
+                continue;
+            }
+            let key = if section.is_empty() {
+                key.to_string()
+            } else {
+                format!("{}.{}", section, key)
+            };
+            lines.insert(key, number + 1);
+        }
+    }
+    lines
+}
+
+fn leaves(prefix: &str, table: &toml::Table, out: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            toml::Value::Table(table) => leaves(&key, table, out),
+            value => out.push((key, value.clone())),
+        }
+    }
+}
+
+// Whether a value from the file can be used for a setting with the given default.
+fn type_matches(expected: &Value, value: &toml::Value) -> Option<&'static str> {
+    let ok = match expected {
+        Value::Bool(_) => value.is_bool(),
+        Value::Number(_) => value.is_integer() || value.is_float(),
+        Value::Array(_) => value
+            .as_array()
+            .is_some_and(|items| items.iter().all(|item| item.is_str())),
+        Value::String(_) | Value::Null => value.is_str(),
+        Value::Object(_) => value.is_table(),
+    };
+    if ok {
+        return None;
+    }
+
+    Some(match expected {
+        Value::Bool(_) => "true or false",
+        Value::Number(_) => "a number",
+        Value::Array(_) => "a list of strings",
+        Value::Object(_) => "a section",
+        Value::String(_) | Value::Null => "a string",
+    })
+}
+
+/// Check a configuration file for syntax errors, unknown keys, values of the wrong type
+/// and renamed settings.
+pub fn validate_file(file: &Path) -> Result<Vec<ConfigProblem>, AppError> {
+    let content = std::fs::read_to_string(file)?;
+    let problem = |line: Option<usize>, severity: Severity, message: String| ConfigProblem {
+        file: file.to_path_buf(),
+        line,
+        severity,
+        message,
+    };
+
+    let table: toml::Table = match content.parse() {
+        Ok(table) => table,
+        Err(err) => {
+            let line = err
+                .span()
+                .map(|span| content[..span.start].matches('\n').count() + 1);
+            return Ok(vec![problem(
+                line,
+                Severity::Error,
+                err.message().to_string(),
+            )]);
+        }
+    };
+
+    let mut known = BTreeMap::new();
+    flatten("", &serde_json::to_value(AppConfig::default())?, &mut known);
+    let lines = key_lines(&content);
+
+    let mut values = Vec::new();
+    leaves("", &table, &mut values);
+
+    let mut problems = Vec::new();
+    for (key, value) in values {
+        let line = lines.get(&key).copied();
+        if let Some((_, new)) = RENAMED.iter().find(|(old, _)| *old == key) {
+            problems.push(problem(
+                line,
+                Severity::Warning,
+                format!(
+                    "'{}' is deprecated, use '{}' (see 'config migrate')",
+                    key, new
+                ),
+            ));
+        } else if let Some(expected) = known.get(&key) {
+            if let Some(expected) = type_matches(expected, &value) {
+                problems.push(problem(
+                    line,
+                    Severity::Error,
+                    format!("'{}' is {}, expected {}", key, value.type_str(), expected),
+                ));
+            }
+        } else if known
+            .iter()
+            .any(|(name, value)| value.is_object() && key.starts_with(&format!("{}.", name)))
+        {
+            // Maps such as repl.bindings take any key, with string values.
+            if !value.is_str() {
+                problems.push(problem(
+                    line,
+                    Severity::Error,
+                    format!("'{}' is {}, expected a string", key, value.type_str()),
+                ));
+            }
+        } else {
+            problems.push(problem(
+                line,
+                Severity::Warning,
+                format!("Unknown key '{}'", key),
+            ));
+        }
+    }
+
+    Ok(problems)
+}
+
+// The file name with `.bak` appended, e.g. `config.toml.bak` or `hubuum.conf.bak`.
+fn backup_path(file: &Path) -> PathBuf {
+    let mut backup = file.as_os_str().to_owned();
+    backup.push(".bak");
+    PathBuf::from(backup)
+}
+
+/// Rewrite renamed settings in a configuration file to their current names, keeping a
+/// copy of the original file as `<file>.bak`. Returns the renamed keys.
+pub fn migrate_file(file: &Path) -> Result<Vec<(String, String)>, AppError> {
+    migrate_renamed(file, RENAMED)
+}
+
+fn migrate_renamed(
+    file: &Path,
+    renames: &[(&str, &str)],
+) -> Result<Vec<(String, String)>, AppError> {
+    let content = std::fs::read_to_string(file)?;
+    let mut table: toml::Table = content
+        .parse()
+        .map_err(|e| AppError::ConfigError(format!("{}: {}", file.display(), e)))?;
+
+    let mut renamed = Vec::new();
+    for (old, new) in renames {
+        let (old_section, old_key) = old.split_once('.').unwrap_or(("", old));
+        let Some(value) = table
+            .get_mut(old_section)
+            .and_then(|section| section.as_table_mut())
+            .and_then(|section| section.remove(old_key))
+        else {
+            continue;
+        };
+
+        let (new_section, new_key) = new.split_once('.').unwrap_or(("", new));
+        let section = table
+            .entry(new_section)
+            .or_insert_with(|| toml::Value::Table(toml::Table::new()))
+            .as_table_mut()
+            .ok_or_else(|| AppError::ConfigError(format!("'{}' is not a section", new_section)))?;
+        // A value already set under the current name wins over the old one.
+        section.entry(new_key).or_insert(value);
+        renamed.push((old.to_string(), new.to_string()));
+    }
+
+    if !renamed.is_empty() {
+        std::fs::copy(file, backup_path(file))?;
+        let content =
+            toml::to_string_pretty(&table).map_err(|e| AppError::ConfigError(e.to_string()))?;
+        std::fs::write(file, content)?;
+    }
+    Ok(renamed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RENAMES: &[(&str, &str)] = &[("server.host", "server.hostname")];
+
+    // A configuration file in a directory of its own, so the tests don't share backups.
+    fn config_file(test: &str, name: &str, content: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hubuum-cli-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(name);
+        std::fs::write(&file, content).unwrap();
+        file
+    }
+
+    #[test]
+    fn backup_path_appends_to_the_file_name() {
+        assert_eq!(
+            backup_path(Path::new("/etc/hubuum/config.toml")),
+            PathBuf::from("/etc/hubuum/config.toml.bak")
+        );
+        assert_eq!(
+            backup_path(Path::new("hubuum.conf")),
+            PathBuf::from("hubuum.conf.bak")
+        );
+    }
+
+    #[test]
+    fn migrate_renames_keys_and_keeps_a_backup() {
+        let original = "[server]\nhost = \"hubuum.example.com\"\nport = 443\n";
+        let file = config_file("migrate-renames", "hubuum.conf", original);
+
+        let renamed = migrate_renamed(&file, RENAMES).unwrap();
+        assert_eq!(
+            renamed,
+            vec![("server.host".to_string(), "server.hostname".to_string())]
+        );
+
+        let table: toml::Table = std::fs::read_to_string(&file).unwrap().parse().unwrap();
+        let server = table["server"].as_table().unwrap();
+        assert_eq!(server["hostname"].as_str(), Some("hubuum.example.com"));
+        assert_eq!(server["port"].as_integer(), Some(443));
+        assert!(!server.contains_key("host"));
+
+        let backup = std::fs::read_to_string(backup_path(&file)).unwrap();
+        assert_eq!(backup, original);
+    }
+
+    #[test]
+    fn migrate_keeps_the_current_name_over_the_old() {
+        let file = config_file(
+            "migrate-current-wins",
+            "config.toml",
+            "[server]\nhost = \"old.example.com\"\nhostname = \"new.example.com\"\n",
+        );
+
+        migrate_renamed(&file, RENAMES).unwrap();
+
+        let table: toml::Table = std::fs::read_to_string(&file).unwrap().parse().unwrap();
+        let server = table["server"].as_table().unwrap();
+        assert_eq!(server["hostname"].as_str(), Some("new.example.com"));
+        assert!(!server.contains_key("host"));
+    }
+
+    #[test]
+    fn migrate_leaves_current_files_alone() {
+        let file = config_file(
+            "migrate-nothing",
+            "config.toml",
+            "[server]\nhostname = \"localhost\"\n",
+        );
+
+        assert!(migrate_renamed(&file, RENAMES).unwrap().is_empty());
+        assert!(!backup_path(&file).exists());
+    }
+}
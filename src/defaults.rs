@@ -11,6 +11,36 @@ impl Defaults {
     pub const CACHE_SIZE: i32 = 104_857_600; // 100 MB
     pub const CACHE_DISABLE: bool = false;
     pub const COMPLETION_DISABLE_API_RELATED: bool = false;
+    pub const COMPLETION_FUZZY: bool = false;
+    pub const DISPLAY_LANGUAGE: &'static str = "en";
+    pub const DISPLAY_PROMPT: &'static str = "{username}@{host}:{port}{context} > ";
+    pub const PROFILE: &'static str = "default";
+    pub const REPL_EDIT_MODE: &'static str = "emacs";
+    pub const REPL_SHARED_HISTORY: bool = false;
+    pub const AUTH_PROVIDERS: &'static [&'static str] = &["token", "password"];
+    pub const AUTH_KEYRING_SERVICE: &'static str = "hubuum-cli";
     pub const API_VERSION: &'static str = "v1";
+    pub const HTTP_CONNECT_TIMEOUT: u64 = 10;
+    pub const HTTP_REQUEST_TIMEOUT: u64 = 30;
+    pub const HTTP_RETRIES: u32 = 3;
+    pub const HTTP_RETRY_BACKOFF: u64 = 250;
+    pub const HTTP_RETRY_BACKOFF_MAX: u64 = 5000;
+    pub const LOG_MAX_SIZE: u64 = 10_485_760; // 10 MB
+    pub const LOG_KEEP: usize = 5;
+    pub const LOG_FORMAT: &'static str = "text";
+    pub const FETCH_ENABLED: bool = true;
+    pub const FETCH_EXPAND_ENV: bool = false;
+    pub const FETCH_TIMEOUT: u64 = 10;
+    pub const FETCH_MAX_SIZE: u64 = 1_048_576; // 1 MB
+    pub const FETCH_CONTENT_TYPES: &'static [&'static str] = &[
+        "text/plain",
+        "text/csv",
+        "text/yaml",
+        "application/json",
+        "application/yaml",
+        "application/x-yaml",
+    ];
+    pub const OFFLINE_MAX_AGE: u64 = 604_800; // 7 days
+    pub const OFFLINE_MAX_SIZE: u64 = 10_485_760; // 10 MB
     pub const PROTOCOL: Protocol = Protocol::Https;
 }
@@ -1,6 +1,11 @@
 use hubuum_client::ApiError;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
 use thiserror::Error;
 
+use crate::i18n::{tr, tr_args};
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Command not found: {0}")]
@@ -80,4 +85,272 @@ pub enum AppError {
 
     #[error("Error parsing JSONPath: {0}")]
     JsonPathError(String),
+
+    #[error("CSV error: {0}")]
+    CsvError(#[from] csv::Error),
+
+    #[error("Authentication error: {0}")]
+    AuthError(String),
+
+    #[error("Not found in the history: {0}")]
+    HistoryEventNotFound(String),
+
+    #[error("Not available offline: {0}")]
+    Offline(String),
+
+    #[error("Clipboard error: {0}")]
+    ClipboardError(String),
+}
+
+/// A constraint the request broke, e.g. a data field failing the schema of the class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub field: Option<String>,
+    pub constraint: String,
+}
+
+/// The body of an error response from the API. Most errors are JSON with a message, and
+/// validation errors list the constraints that failed.
+#[derive(Debug, Clone, Default)]
+pub struct ApiErrorBody {
+    pub message: String,
+    pub violations: Vec<Violation>,
+    pub permission: Option<String>,
+}
+
+// Permission names as used by the server, e.g. ReadCollection or UpdateObject.
+static PERMISSION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(?:Read|Update|Delete|Create|Delegate)(?:Collection|ClassRelation|ObjectRelation|Class|Object)\b")
+        .expect("Invalid permission regex")
+});
+
+// The first string among `keys` in a JSON object.
+fn first_string(object: &serde_json::Map<String, Value>, keys: &[&str]) -> Option<String> {
+    keys.iter()
+        .find_map(|key| object.get(*key).and_then(Value::as_str))
+        .map(str::to_string)
+}
+
+// JSON pointers such as `/os/family` read better as `os.family`.
+fn field_name(field: &str) -> String {
+    field.trim_start_matches('/').replace('/', ".")
+}
+
+impl ApiErrorBody {
+    pub fn parse(body: &str) -> Self {
+        let permission_in = |text: &str| PERMISSION.find(text).map(|m| m.as_str().to_string());
+
+        let Ok(Value::Object(object)) = serde_json::from_str::<Value>(body) else {
+            return ApiErrorBody {
+                message: body.trim().to_string(),
+                violations: Vec::new(),
+                permission: permission_in(body),
+            };
+        };
+
+        let message = first_string(&object, &["message", "error", "detail"])
+            .unwrap_or_else(|| body.trim().to_string());
+
+        let violations = ["errors", "details", "violations"]
+            .iter()
+            .filter_map(|key| object.get(*key).and_then(Value::as_array))
+            .flatten()
+            .filter_map(|item| match item {
+                Value::String(constraint) => Some(Violation {
+                    field: None,
+                    constraint: constraint.clone(),
+                }),
+                Value::Object(item) => Some(Violation {
+                    field: first_string(
+                        item,
+                        &["field", "path", "instance_path", "instancePath", "property"],
+                    )
+                    .map(|field| field_name(&field)),
+                    constraint: first_string(item, &["message", "constraint", "keyword", "error"])?,
+                }),
+                _ => None,
+            })
+            .collect();
+
+        let permission = first_string(&object, &["permission", "required_permission"])
+            .or_else(|| permission_in(&message));
+
+        ApiErrorBody {
+            message,
+            violations,
+            permission,
+        }
+    }
+
+    /// The message, followed by one indented line per violated constraint.
+    pub fn render(&self) -> String {
+        let mut rendered = self.message.clone();
+        for violation in &self.violations {
+            rendered.push_str("\n  - ");
+            if let Some(field) = &violation.field {
+                rendered.push_str(&format!("{}: ", field));
+            }
+            rendered.push_str(&violation.constraint);
+        }
+        rendered
+    }
+}
+
+impl AppError {
+    /// Whether the server could not be reached at all, as opposed to answering with an
+    /// error.
+    pub fn is_connection_error(&self) -> bool {
+        let mut source = std::error::Error::source(self);
+        while let Some(err) = source {
+            if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+                return err.is_connect() || err.is_timeout();
+            }
+            source = err.source();
+        }
+        false
+    }
+
+    /// Actionable guidance for common API errors, based on the status and body of the
+    /// error and the command line that caused it.
+    pub fn guidance(&self, parts: &[String]) -> Option<String> {
+        let AppError::ApiError(ApiError::HttpWithBody { status, message }) = self else {
+            return None;
+        };
+
+        // The status may be displayed as "409" or "409 Conflict".
+        let status = status
+            .to_string()
+            .split_whitespace()
+            .next()
+            .and_then(|s| s.parse::<u16>().ok())?;
+        let body = ApiErrorBody::parse(message);
+        let message = message.to_lowercase();
+
+        // The first word of the command line is the scope, e.g. "object" or "class".
+        let entity = parts.first().map(String::as_str).unwrap_or("entity");
+        let class = option_value(parts, "c", "class");
+
+        let guidance = match status {
+            401 => tr("Your session has expired or the token is invalid, log in again"),
+            403 => match &body.permission {
+                Some(permission) => tr_args(
+                    "You lack the {permission} permission, see 'permissions show' and ask an administrator of the namespace to grant it to one of your groups",
+                    &[("permission", permission)],
+                ),
+                None => tr("You do not have permission to do this, see 'permissions show' and ask an administrator of the namespace for access"),
+            },
+            404 => tr_args(
+                "The {entity} was not found, check the name with '{entity} list'",
+                &[("entity", &entity)],
+            ),
+            // Only objects and namespaces can be renamed.
+            409 => match (entity, class) {
+                ("object", Some(class)) => tr_args(
+                    "An object with this name exists in class {class}, pick another name or rename the existing one with 'object modify --rename'",
+                    &[("class", &class)],
+                ),
+                ("object" | "namespace", _) => tr_args(
+                    "A {entity} with this name already exists, pick another name or rename the existing one with '{entity} modify --rename'",
+                    &[("entity", &entity)],
+                ),
+                _ => tr_args(
+                    "A {entity} with this name already exists, pick another name",
+                    &[("entity", &entity)],
+                ),
+            },
+            400 | 422 if message.contains("schema") || !body.violations.is_empty() => match class {
+                Some(class) => tr_args(
+                    "The data does not validate against the schema of the class, see 'class info {class}'",
+                    &[("class", &class)],
+                ),
+                None => tr("The data does not validate against the schema of the class, see 'class info'"),
+            },
+            500..=599 => tr("The server failed to handle the request, try again later or check the server logs"),
+            _ => return None,
+        };
+
+        Some(guidance)
+    }
+}
+
+fn option_value(parts: &[String], short: &str, long: &str) -> Option<String> {
+    let short = format!("-{}", short);
+    let long = format!("--{}", long);
+    parts
+        .iter()
+        .position(|p| *p == short || *p == long)
+        .and_then(|i| parts.get(i + 1))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_text_body() {
+        let body = ApiErrorBody::parse("  Missing permission ReadCollection\n");
+        assert_eq!(body.message, "Missing permission ReadCollection");
+        assert!(body.violations.is_empty());
+        assert_eq!(body.permission.as_deref(), Some("ReadCollection"));
+    }
+
+    #[test]
+    fn test_json_message_keys() {
+        assert_eq!(
+            ApiErrorBody::parse(r#"{"message": "Gone"}"#).message,
+            "Gone"
+        );
+        assert_eq!(ApiErrorBody::parse(r#"{"error": "Gone"}"#).message, "Gone");
+        assert_eq!(ApiErrorBody::parse(r#"{"detail": "Gone"}"#).message, "Gone");
+        assert_eq!(
+            ApiErrorBody::parse(r#"{"code": 7}"#).message,
+            r#"{"code": 7}"#
+        );
+    }
+
+    #[test]
+    fn test_violations() {
+        let body = ApiErrorBody::parse(
+            r#"{
+                "message": "Validation failed",
+                "errors": [
+                    "Name is too long",
+                    {"instance_path": "/os/family", "message": "is not one of the allowed values"},
+                    {"field": "port"},
+                    42
+                ]
+            }"#,
+        );
+        assert_eq!(
+            body.violations,
+            vec![
+                Violation {
+                    field: None,
+                    constraint: "Name is too long".to_string(),
+                },
+                Violation {
+                    field: Some("os.family".to_string()),
+                    constraint: "is not one of the allowed values".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            body.render(),
+            "Validation failed\n  - Name is too long\n  - os.family: is not one of the allowed values"
+        );
+    }
+
+    #[test]
+    fn test_permission() {
+        let explicit =
+            ApiErrorBody::parse(r#"{"message": "Forbidden", "permission": "UpdateObject"}"#);
+        assert_eq!(explicit.permission.as_deref(), Some("UpdateObject"));
+
+        let in_message = ApiErrorBody::parse(r#"{"message": "User lacks DeleteClass"}"#);
+        assert_eq!(in_message.permission.as_deref(), Some("DeleteClass"));
+
+        let none = ApiErrorBody::parse(r#"{"message": "Forbidden"}"#);
+        assert_eq!(none.permission, None);
+    }
 }
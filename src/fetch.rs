@@ -0,0 +1,110 @@
+use std::io::Read;
+use std::time::Duration;
+
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
+
+use crate::config::{get_config, FetchConfig};
+use crate::errors::AppError;
+
+fn fetch_error(url: &str, message: impl std::fmt::Display) -> AppError {
+    AppError::HttpError(format!("{}: {}", url, message))
+}
+
+/// Fetch the value of an option given as an http:// or https:// URL, with the headers,
+/// timeout, size limit and content types of the `fetch` configuration. With fetching
+/// disabled, the URL is the value.
+pub fn fetch_value(url: &str) -> Result<String, AppError> {
+    let config = get_config()?.fetch;
+    if !config.enabled {
+        return Ok(url.to_string());
+    }
+
+    let mut request = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(config.timeout))
+        .build()
+        .map_err(|e| AppError::HttpError(e.to_string()))?
+        .get(url);
+    if sends_credentials(&config, url) {
+        if let Some(token) = &config.bearer_token {
+            request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+        }
+        for (name, value) in &config.headers {
+            request = request.header(name, value);
+        }
+    }
+
+    let response = request
+        .send()
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| fetch_error(url, e))?;
+
+    check_content_type(&config, response.headers().get(CONTENT_TYPE))
+        .map_err(|e| fetch_error(url, e))?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > config.max_size)
+    {
+        return Err(fetch_error(url, too_large(&config)));
+    }
+
+    // The length is not always known up front, so read at most one byte past the limit.
+    let mut body = Vec::new();
+    response
+        .take(config.max_size + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| fetch_error(url, e))?;
+    if body.len() as u64 > config.max_size {
+        return Err(fetch_error(url, too_large(&config)));
+    }
+
+    String::from_utf8(body).map_err(|_| fetch_error(url, "The response is not UTF-8 text"))
+}
+
+// The bearer token and headers are credentials, so they only go to the hosts listed in
+// fetch.credential_hosts, and never over plain http.
+fn sends_credentials(config: &FetchConfig, url: &str) -> bool {
+    let Ok(url) = reqwest::Url::parse(url) else {
+        return false;
+    };
+    url.scheme() == "https"
+        && url.host_str().is_some_and(|host| {
+            config
+                .credential_hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host))
+        })
+}
+
+fn too_large(config: &FetchConfig) -> String {
+    format!(
+        "The response is larger than fetch.max_size ({} bytes)",
+        config.max_size
+    )
+}
+
+// Parameters like `; charset=utf-8` are ignored.
+fn check_content_type(
+    config: &FetchConfig,
+    content_type: Option<&reqwest::header::HeaderValue>,
+) -> Result<(), String> {
+    if config.content_types.is_empty() {
+        return Ok(());
+    }
+    let content_type = content_type
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(|value| value.trim().to_lowercase())
+        .unwrap_or_default();
+    if config
+        .content_types
+        .iter()
+        .any(|allowed| allowed.eq_ignore_ascii_case(&content_type))
+    {
+        return Ok(());
+    }
+    Err(format!(
+        "Content type '{}' is not in fetch.content_types ({})",
+        content_type,
+        config.content_types.join(", ")
+    ))
+}
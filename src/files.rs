@@ -1,5 +1,6 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use crate::config::get_config;
 use crate::{errors::AppError, models::TokenEntry};
 
 fn ensure_root_dir() -> Result<PathBuf, AppError> {
@@ -14,6 +15,36 @@ fn ensure_root_dir() -> Result<PathBuf, AppError> {
     Ok(root_dir)
 }
 
+/// A path with a leading `~` replaced by the home directory, as the shell would.
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix('~'), dirs::home_dir()) {
+        (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+            format!("{}{}", home.display(), rest)
+        }
+        _ => path.to_string(),
+    }
+}
+
+/// The name of the `n`th rotated copy of a file, e.g. `log.txt.1` for the newest.
+pub fn rotated_path(path: &Path, n: usize) -> PathBuf {
+    PathBuf::from(format!("{}.{}", path.display(), n))
+}
+
+/// Move `path` to `path.1`, shifting older copies up to `path.{keep}` and removing the
+/// oldest. With `keep` at 0 the file is removed.
+pub fn rotate_file(path: &Path, keep: usize) -> std::io::Result<()> {
+    if keep == 0 {
+        return std::fs::remove_file(path);
+    }
+    for n in (1..keep).rev() {
+        let from = rotated_path(path, n);
+        if from.exists() {
+            std::fs::rename(&from, rotated_path(path, n + 1))?;
+        }
+    }
+    std::fs::rename(path, rotated_path(path, 1))
+}
+
 pub fn get_system_config_path() -> PathBuf {
     if cfg!(target_os = "windows") {
         PathBuf::from(r"C:\ProgramData\hubuum_cli\config.toml")
@@ -38,8 +69,80 @@ fn ensure_file_exists(file: &str) -> Result<PathBuf, AppError> {
     Ok(fqfile)
 }
 
+fn ensure_dir_exists(dir: &str) -> Result<PathBuf, AppError> {
+    let fqdir = ensure_root_dir()?.join(dir);
+    if !fqdir.exists() {
+        log::debug!("Creating directory: {:?}", fqdir);
+        std::fs::create_dir_all(&fqdir)?;
+    }
+    Ok(fqdir)
+}
+
+// `name` made safe for use in a file name.
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '@' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// The hostname of the server in use, made safe for use in a file name.
+fn server_file_name() -> Result<String, AppError> {
+    Ok(safe_file_name(&get_config()?.server.hostname))
+}
+
+/// A file name for `key`, hashed with FNV-1a, which unlike the std hasher is stable between
+/// releases, so file names survive upgrades. Store the key in the file as well, so a
+/// collision can be told from a hit.
+pub fn hashed_file_name(key: &str) -> String {
+    let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}.json", hash)
+}
+
+/// The readline history for the server in use, so commands against different servers do
+/// not mix. With `repl.shared_history` all servers share one history.
 pub fn get_history_file() -> Result<PathBuf, AppError> {
-    ensure_file_exists("history.txt")
+    if get_config()?.repl.shared_history {
+        return ensure_file_exists("history.txt");
+    }
+    let file = format!("history-{}.txt", server_file_name()?);
+    // The first time a server gets its own history, it starts from the shared history
+    // that earlier versions kept, so the commands typed before are not lost.
+    let path = ensure_root_dir()?.join(&file);
+    let shared = ensure_root_dir()?.join("history.txt");
+    if !path.exists() && shared.exists() {
+        log::debug!("Copying {:?} to {:?}", shared, path);
+        std::fs::copy(&shared, &path)?;
+    }
+    ensure_file_exists(&file)
+}
+
+/// The directory of the responses saved for `--offline` use, one per user and server, as
+/// users may see different things.
+pub fn get_offline_dir() -> Result<PathBuf, AppError> {
+    let config = get_config()?;
+    ensure_dir_exists(&format!(
+        "offline/{}",
+        safe_file_name(&format!(
+            "{}@{}:{}",
+            config.server.username, config.server.hostname, config.server.port
+        ))
+    ))
+}
+
+pub fn get_command_log_file() -> Result<PathBuf, AppError> {
+    ensure_file_exists("command_log.jsonl")
+}
+
+pub fn get_audit_file() -> Result<PathBuf, AppError> {
+    ensure_file_exists("audit.jsonl")
 }
 
 pub fn get_token_file() -> Result<PathBuf, AppError> {
@@ -50,20 +153,44 @@ pub fn get_log_file() -> Result<PathBuf, AppError> {
     ensure_file_exists("log.txt")
 }
 
+pub fn get_template_dir() -> Result<PathBuf, AppError> {
+    ensure_dir_exists("templates")
+}
+
+pub fn get_snapshot_dir() -> Result<PathBuf, AppError> {
+    ensure_dir_exists("snapshots")
+}
+
+pub fn get_locale_dir() -> Result<PathBuf, AppError> {
+    ensure_dir_exists("locales")
+}
+
+pub fn get_response_cache_dir() -> Result<PathBuf, AppError> {
+    ensure_dir_exists("cache")
+}
+
+pub fn get_transcript_dir() -> Result<PathBuf, AppError> {
+    ensure_dir_exists("transcripts")
+}
+
 pub fn get_token_from_tokenfile(
     hostname: &str,
     username: &str,
 ) -> Result<Option<String>, AppError> {
+    Ok(get_token_entry_from_tokenfile(hostname, username)?.map(|entry| entry.token))
+}
+
+pub fn get_token_entry_from_tokenfile(
+    hostname: &str,
+    username: &str,
+) -> Result<Option<TokenEntry>, AppError> {
     let token_file_path = get_token_file()?;
     let token_file_content = std::fs::read_to_string(token_file_path)?;
     let token_entries: Vec<TokenEntry> = serde_json::from_str(&token_file_content)?;
 
-    for token_entry in &token_entries {
-        if token_entry.hostname == hostname && token_entry.username == username {
-            return Ok(Some(token_entry.token.clone()));
-        }
-    }
-    Ok(None)
+    Ok(token_entries
+        .into_iter()
+        .find(|entry| entry.hostname == hostname && entry.username == username))
 }
 
 pub fn write_token_to_tokenfile(token_entry: TokenEntry) -> Result<(), AppError> {
@@ -83,3 +210,20 @@ pub fn write_token_to_tokenfile(token_entry: TokenEntry) -> Result<(), AppError>
 
     Ok(())
 }
+
+/// Remove the token entries matching `predicate` from the token file.
+pub fn remove_tokens_from_tokenfile<F>(predicate: F) -> Result<(), AppError>
+where
+    F: Fn(&TokenEntry) -> bool,
+{
+    let token_file_path = get_token_file()?;
+    let mut token_entries: Vec<TokenEntry> =
+        serde_json::from_str(&std::fs::read_to_string(&token_file_path)?)?;
+
+    token_entries.retain(|entry| !predicate(entry));
+
+    let token_file_content = serde_json::to_string(&token_entries)?;
+    std::fs::write(token_file_path, token_file_content)?;
+
+    Ok(())
+}
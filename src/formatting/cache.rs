@@ -0,0 +1,52 @@
+use serde::Serialize;
+
+use super::{append_key_value, OutputFormatterWithPadding};
+use crate::errors::AppError;
+use crate::i18n::tr_args;
+use crate::lookup_cache::LookupCacheStats;
+
+// The state of the caches kept during the session.
+#[derive(Debug, Clone, Serialize)]
+pub struct FormattedCacheStats {
+    pub enabled: bool,
+    pub ttl_seconds: u64,
+    pub lookups: Vec<LookupCacheStats>,
+    pub completion_entries: usize,
+    pub disk_entries: usize,
+    pub disk_bytes: u64,
+}
+
+impl OutputFormatterWithPadding for FormattedCacheStats {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
+        append_key_value("Enabled", self.enabled, padding)?;
+        append_key_value("Time to live", format!("{} s", self.ttl_seconds), padding)?;
+        for stats in &self.lookups {
+            append_key_value(
+                &stats.kind,
+                tr_args(
+                    "{entries} cached, {hits} hits, {misses} misses",
+                    &[
+                        ("entries", &stats.entries),
+                        ("hits", &stats.hits),
+                        ("misses", &stats.misses),
+                    ],
+                ),
+                padding,
+            )?;
+        }
+        append_key_value(
+            "Completion",
+            tr_args("{entries} cached", &[("entries", &self.completion_entries)]),
+            padding,
+        )?;
+        append_key_value(
+            "Responses on disk",
+            tr_args(
+                "{entries} cached, {bytes} bytes",
+                &[("entries", &self.disk_entries), ("bytes", &self.disk_bytes)],
+            ),
+            padding,
+        )?;
+        Ok(())
+    }
+}
@@ -1,10 +1,38 @@
 use hubuum_client::Class;
+use serde::Serialize;
+use tabled::Tabled;
 
 use super::{append_key_value, append_some_key_value, OutputFormatterWithPadding};
 use crate::errors::AppError;
 
+// A class with the number of objects in it, for `class list --with-counts`.
+#[derive(Debug, Serialize, Tabled)]
+pub struct FormattedClassWithCount {
+    pub id: i32,
+    #[tabled(rename = "Name")]
+    pub name: String,
+    #[tabled(rename = "Description")]
+    pub description: String,
+    #[tabled(rename = "Namespace")]
+    pub namespace: String,
+    #[tabled(rename = "Objects")]
+    pub objects: usize,
+}
+
+impl FormattedClassWithCount {
+    pub fn new(class: &Class, objects: usize) -> Self {
+        Self {
+            id: class.id,
+            name: class.name.clone(),
+            description: class.description.clone(),
+            namespace: class.namespace.name.clone(),
+            objects,
+        }
+    }
+}
+
 impl OutputFormatterWithPadding for Class {
-    fn format(&self, padding: usize) -> Result<(), AppError> {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
         append_key_value("Name", &self.name, padding)?;
         append_key_value("Description", &self.description, padding)?;
         append_key_value("Namespace", &self.namespace.name, padding)?;
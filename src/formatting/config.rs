@@ -0,0 +1,22 @@
+use std::collections::BTreeMap;
+
+use super::{append_key_value, OutputFormatterWithPadding};
+use crate::config::AppConfig;
+use crate::errors::AppError;
+use crate::plan::flatten;
+
+impl OutputFormatterWithPadding for AppConfig {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
+        let mut values = BTreeMap::new();
+        flatten("", &serde_json::to_value(self)?, &mut values);
+
+        for (key, value) in values {
+            match value {
+                serde_json::Value::String(value) => append_key_value(key, value, padding)?,
+                serde_json::Value::Null => append_key_value(key, "<none>", padding)?,
+                value => append_key_value(key, value, padding)?,
+            }
+        }
+        Ok(())
+    }
+}
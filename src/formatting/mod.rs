@@ -1,21 +1,50 @@
+use serde::Serialize;
 use std::fmt::Display;
 use tabled::{settings::object::Columns, settings::Remove, settings::Style, Table, Tabled};
 
 use crate::errors::AppError;
-use crate::output::append_line;
+use crate::i18n::tr;
+use crate::models::OutputFormat;
+use crate::output::{append_json, append_json_line, append_line, append_yaml, get_format};
 
+mod cache;
 mod class;
+mod config;
 mod group;
 mod namespace;
 mod object;
+mod permission;
+mod plan;
 mod relations;
+mod server;
+mod snapshot;
+mod template;
+mod token;
 mod user;
 
-pub use object::FormattedObject;
+pub use cache::FormattedCacheStats;
+pub use class::FormattedClassWithCount;
+pub use namespace::FormattedNamespaceStats;
+pub use object::{format_object_comparison, FormattedObject};
+pub use permission::FormattedPermissions;
 pub use relations::{FormattedClassRelation, FormattedObjectRelation};
+pub use server::{ClassObjectCount, FormattedServerInfo, FormattedServerStats, FormattedVersion};
+pub use snapshot::FormattedObjectSnapshot;
+pub use template::{FormattedClassTemplate, FormattedObjectTemplate};
+pub use token::{mask_token, FormattedToken};
+pub use user::FormattedWhoami;
 
-pub trait OutputFormatterWithPadding {
-    fn format(&self, padding: usize) -> Result<(), AppError>;
+pub trait OutputFormatterWithPadding: Serialize {
+    /// Format the entity using the output format requested by the user.
+    fn format(&self, padding: usize) -> Result<(), AppError> {
+        match get_format()? {
+            OutputFormat::Text => self.format_text(padding),
+            format => append_serialized(self, format),
+        }
+    }
+
+    /// Format the entity as padded key-value lines.
+    fn format_text(&self, padding: usize) -> Result<(), AppError>;
 }
 
 pub trait OutputFormatter {
@@ -24,9 +53,19 @@ pub trait OutputFormatter {
 
 impl<T> OutputFormatter for Vec<T>
 where
-    T: Tabled,
+    T: Tabled + Serialize,
 {
     fn format(&self) -> Result<(), AppError> {
+        let format = get_format()?;
+        if format == OutputFormat::Jsonl {
+            for item in self {
+                append_json_line(item)?;
+            }
+            return Ok(());
+        } else if format != OutputFormat::Text {
+            return append_serialized(self, format);
+        }
+
         let mut table = Table::new(self);
         // This should be customizable by the user, including the ability to disable columns
         table
@@ -40,6 +79,56 @@ where
     }
 }
 
+/// Output the number of entities matched by a command.
+pub fn format_count(count: usize) -> Result<(), AppError> {
+    match get_format()? {
+        OutputFormat::Text => append_line(count),
+        format => append_serialized(&serde_json::json!({ "count": count }), format),
+    }
+}
+
+/// Output a piece of object data. As text, a string is printed without quotes so it can be
+/// used in scripts, and objects and arrays are flattened to one line per value.
+pub fn format_data(data: &serde_json::Value) -> Result<(), AppError> {
+    match (get_format()?, data) {
+        (OutputFormat::Text, serde_json::Value::String(string)) => append_line(string),
+        (OutputFormat::Text, serde_json::Value::Object(_) | serde_json::Value::Array(_)) => {
+            let flattener = smooth_json::Flattener {
+                ..Default::default()
+            };
+            let serde_json::Value::Object(map) = flattener.flatten(data) else {
+                return append_line(data);
+            };
+            let sorted_map = map
+                .into_iter()
+                .collect::<std::collections::BTreeMap<_, _>>();
+            let padding = sorted_map
+                .keys()
+                .map(|k| k.len())
+                .max()
+                .map_or(15, |len| len.max(15));
+            for (key, value) in sorted_map {
+                crate::output::append_key_value(key, value, padding)?;
+            }
+            Ok(())
+        }
+        (OutputFormat::Text, data) => append_line(data),
+        (format, data) => append_serialized(data, format),
+    }
+}
+
+fn append_serialized<T>(value: &T, format: OutputFormat) -> Result<(), AppError>
+where
+    T: Serialize + ?Sized,
+{
+    match format {
+        OutputFormat::Json => append_json(value),
+        OutputFormat::Jsonl => append_json_line(value),
+        OutputFormat::Yaml => append_yaml(value),
+        OutputFormat::Text => Err(AppError::FormatError),
+    }
+}
+
 fn pad_key_value<K, V>(key: K, value: V, padding: usize) -> String
 where
     K: Display,
@@ -53,7 +142,7 @@ where
     K: Display,
     V: Display,
 {
-    append_line(pad_key_value(key, value, padding))
+    append_line(pad_key_value(tr(&key.to_string()), value, padding))
 }
 
 fn append_some_key_value<K, V>(key: K, value: &Option<V>, padding: usize) -> Result<(), AppError>
@@ -67,3 +156,57 @@ where
         append_key_value(key, "<none>", padding)
     }
 }
+
+/// The width of the terminal, or 80 columns when the output is not a terminal.
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(terminal_size::Width(width), _)| width as usize)
+        .unwrap_or(80)
+}
+
+/// The words of a text, for wrapping.
+pub fn words(text: &str) -> Vec<String> {
+    text.split_whitespace().map(str::to_string).collect()
+}
+
+/// Wrap a text into lines of at most `width` characters, breaking between words.
+pub fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    wrap(&words(text), width)
+}
+
+/// Wrap the parts after `prefix`, with the lines after the first indented to line up with
+/// the first part. Parts are never broken, so e.g. `[--name <name>]` stays together.
+pub fn hanging(prefix: &str, parts: &[String], width: usize) -> Vec<String> {
+    let indent = prefix.chars().count();
+    // Very narrow terminals get long lines rather than a word per line.
+    let available = width.saturating_sub(indent).max(30);
+    wrap(parts, available)
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if i == 0 {
+                format!("{}{}", prefix, line)
+            } else {
+                format!("{}{}", " ".repeat(indent), line)
+            }
+        })
+        .collect()
+}
+
+fn wrap(parts: &[String], width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for part in parts {
+        if !line.is_empty() && line.chars().count() + 1 + part.chars().count() > width {
+            lines.push(std::mem::take(&mut line));
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(part);
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
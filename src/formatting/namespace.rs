@@ -1,10 +1,12 @@
 use hubuum_client::Namespace;
+use serde::Serialize;
+use tabled::Tabled;
 
 use super::{append_key_value, OutputFormatterWithPadding};
 use crate::errors::AppError;
 
 impl OutputFormatterWithPadding for Namespace {
-    fn format(&self, padding: usize) -> Result<(), AppError> {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
         append_key_value("Name", &self.name, padding)?;
         append_key_value("Description", &self.description, padding)?;
         append_key_value("Created", self.created_at, padding)?;
@@ -12,3 +14,26 @@ impl OutputFormatterWithPadding for Namespace {
         Ok(())
     }
 }
+
+// Class and object counts for a namespace.
+#[derive(Debug, Serialize, Tabled)]
+pub struct FormattedNamespaceStats {
+    pub id: i32,
+    #[tabled(rename = "Name")]
+    pub name: String,
+    #[tabled(rename = "Classes")]
+    pub classes: usize,
+    #[tabled(rename = "Objects")]
+    pub objects: usize,
+}
+
+impl FormattedNamespaceStats {
+    pub fn new(namespace: &Namespace, classes: usize, objects: usize) -> Self {
+        Self {
+            id: namespace.id,
+            name: namespace.name.clone(),
+            classes,
+            objects,
+        }
+    }
+}
@@ -1,16 +1,19 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use hubuum_client::{
     resources::{tabled_display, tabled_display_option},
     Class, Namespace, Object,
 };
-use tabled::Tabled;
+use serde::Serialize;
+use tabled::{builder::Builder, settings::Style, Tabled};
 
 use super::{append_key_value, OutputFormatterWithPadding};
 use crate::errors::AppError;
+use crate::i18n::tr;
+use crate::output::append_line;
 
 // A wrapper for objects that can be outputted where class_ids and namespace_ids are replaced with their names.
-#[derive(Debug, Tabled)]
+#[derive(Debug, Serialize, Tabled)]
 pub struct FormattedObject {
     pub id: i32,
     #[tabled(rename = "Name")]
@@ -61,7 +64,7 @@ impl FormattedObject {
 }
 
 impl OutputFormatterWithPadding for FormattedObject {
-    fn format(&self, padding: usize) -> Result<(), AppError> {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
         append_key_value("Name", &self.name, padding)?;
         append_key_value("Description", &self.description, padding)?;
         append_key_value("Namespace", &self.namespace, padding)?;
@@ -81,3 +84,74 @@ impl OutputFormatterWithPadding for FormattedObject {
         Ok(())
     }
 }
+
+/// Show objects side by side, one column per object and one row per field and data key,
+/// with the rows where the objects differ marked with `*`.
+pub fn format_object_comparison(objects: &[FormattedObject]) -> Result<(), AppError> {
+    let flattener = smooth_json::Flattener {
+        ..Default::default()
+    };
+    let data = objects
+        .iter()
+        .map(
+            |object| match object.data.as_ref().map(|d| flattener.flatten(d)) {
+                Some(serde_json::Value::Object(map)) => map.into_iter().collect(),
+                _ => BTreeMap::new(),
+            },
+        )
+        .collect::<Vec<BTreeMap<String, serde_json::Value>>>();
+
+    let mut rows: Vec<(String, Vec<String>)> = vec![
+        (
+            tr("Description"),
+            objects.iter().map(|o| o.description.clone()).collect(),
+        ),
+        (
+            tr("Namespace"),
+            objects.iter().map(|o| o.namespace.clone()).collect(),
+        ),
+        (
+            tr("Created"),
+            objects.iter().map(|o| o.created_at.to_string()).collect(),
+        ),
+        (
+            tr("Updated"),
+            objects.iter().map(|o| o.updated_at.to_string()).collect(),
+        ),
+    ];
+    let keys = data
+        .iter()
+        .flat_map(|map| map.keys().cloned())
+        .collect::<BTreeSet<_>>();
+    for key in keys {
+        let values = data
+            .iter()
+            .map(|map| match map.get(&key) {
+                Some(serde_json::Value::String(s)) => s.clone(),
+                Some(value) => value.to_string(),
+                None => String::new(),
+            })
+            .collect();
+        rows.push((key, values));
+    }
+
+    let mut builder = Builder::default();
+    builder
+        .push_record(std::iter::once(String::new()).chain(objects.iter().map(|o| o.name.clone())));
+    for (field, values) in rows {
+        let differs = values.iter().any(|value| value != &values[0]);
+        let field = if differs {
+            format!("* {}", field)
+        } else {
+            format!("  {}", field)
+        };
+        builder.push_record(std::iter::once(field).chain(values));
+    }
+
+    let mut table = builder.build();
+    table.with(Style::modern_rounded());
+    for line in table.to_string().lines() {
+        append_line(line)?;
+    }
+    Ok(())
+}
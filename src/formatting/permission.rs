@@ -0,0 +1,74 @@
+use serde::Serialize;
+use serde_json::Value;
+use tabled::Tabled;
+
+use crate::backup::permission_names;
+
+// The actions a permission may grant, with the letter shown for it in the matrix.
+const ACTIONS: [(&str, char); 5] = [
+    ("create", 'C'),
+    ("read", 'R'),
+    ("update", 'U'),
+    ("delete", 'D'),
+    ("delegate", 'G'),
+];
+
+// What a group may do in a namespace, with a column per kind of entity holding a letter
+// for every action the server knows for it, or `-` where it is not granted.
+#[derive(Debug, Serialize, Tabled)]
+pub struct FormattedPermissions {
+    pub id: i32,
+    #[tabled(rename = "Namespace")]
+    pub namespace: String,
+    #[tabled(rename = "Group")]
+    pub group: String,
+    #[tabled(rename = "On namespace")]
+    #[serde(skip)]
+    pub collection: String,
+    #[tabled(rename = "Classes")]
+    #[serde(skip)]
+    pub classes: String,
+    #[tabled(rename = "Objects")]
+    #[serde(skip)]
+    pub objects: String,
+    #[tabled(rename = "Class relations")]
+    #[serde(skip)]
+    pub class_relations: String,
+    #[tabled(rename = "Object relations")]
+    #[serde(skip)]
+    pub object_relations: String,
+    #[tabled(skip)]
+    pub permissions: Vec<String>,
+}
+
+impl FormattedPermissions {
+    /// A row of the matrix from a permission entry as the server returns it, with a flag
+    /// like `has_read_object` per permission.
+    pub fn new(id: i32, namespace: &str, group: &str, entry: &Value) -> Self {
+        Self {
+            id,
+            namespace: namespace.to_string(),
+            group: group.to_string(),
+            collection: actions(entry, "namespace"),
+            classes: actions(entry, "class"),
+            objects: actions(entry, "object"),
+            class_relations: actions(entry, "class_relation"),
+            object_relations: actions(entry, "object_relation"),
+            permissions: permission_names(entry),
+        }
+    }
+}
+
+fn actions(entry: &Value, kind: &str) -> String {
+    ACTIONS
+        .iter()
+        .filter_map(|(action, letter)| {
+            entry
+                .get(format!("has_{}_{}", action, kind))
+                .map(|granted| match granted.as_bool() {
+                    Some(true) => *letter,
+                    _ => '-',
+                })
+        })
+        .collect()
+}
@@ -0,0 +1,52 @@
+use serde_json::Value;
+
+use super::OutputFormatterWithPadding;
+use crate::errors::AppError;
+use crate::i18n::tr_args;
+use crate::output::append_line;
+use crate::plan::{Plan, PlanAction};
+
+fn display(value: &Option<Value>) -> String {
+    match value {
+        Some(Value::String(s)) => format!("{:?}", s),
+        Some(value) => value.to_string(),
+        None => "(none)".to_string(),
+    }
+}
+
+impl OutputFormatterWithPadding for Plan {
+    fn format_text(&self, _padding: usize) -> Result<(), AppError> {
+        for item in &self.items {
+            let (symbol, verb) = match item.action {
+                PlanAction::Create => ("+", "create"),
+                PlanAction::Update => ("~", "update"),
+                PlanAction::Delete => ("-", "delete"),
+            };
+            append_line(format!("{} {} {} {}", symbol, verb, item.kind, item.name))?;
+
+            for change in &item.changes {
+                let line = match item.action {
+                    PlanAction::Update => format!(
+                        "{} = {} -> {}",
+                        change.field,
+                        display(&change.old),
+                        display(&change.new)
+                    ),
+                    _ => format!("{} = {}", change.field, display(&change.new)),
+                };
+                append_line(format!("    {} {}", symbol, line))?;
+            }
+        }
+
+        let (create, update, delete) = self.counts();
+        append_line(tr_args(
+            "Plan: {create} to create, {update} to update, {delete} to delete.",
+            &[
+                ("create", &create),
+                ("update", &update),
+                ("delete", &delete),
+            ],
+        ))?;
+        Ok(())
+    }
+}
@@ -6,10 +6,11 @@ use crate::errors::AppError;
 use std::collections::HashMap;
 
 use hubuum_client::{resources::tabled_display, Class};
+use serde::Serialize;
 use tabled::Tabled;
 
 // A wrapper for classrelations that can be outputted where class_ids are replaced with their names
-#[derive(Debug, Tabled)]
+#[derive(Debug, Serialize, Tabled)]
 pub struct FormattedClassRelation {
     pub id: i32,
     #[tabled(rename = "FromClass")]
@@ -22,7 +23,7 @@ pub struct FormattedClassRelation {
     pub updated_at: chrono::NaiveDateTime,
 }
 
-#[derive(Debug, Tabled)]
+#[derive(Debug, Serialize, Tabled)]
 pub struct FormattedObjectRelation {
     pub id: i32,
     /*    #[tabled(rename = "FromClass")]
@@ -72,7 +73,7 @@ impl FormattedClassRelation {
 }
 
 impl OutputFormatterWithPadding for FormattedClassRelation {
-    fn format(&self, padding: usize) -> Result<(), AppError> {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
         append_key_value("ClassFrom", &self.from_class, padding)?;
         append_key_value("ClassTo", &self.to_class, padding)?;
         append_key_value("Created", self.created_at, padding)?;
@@ -149,7 +150,7 @@ impl FormattedObjectRelation {
 }
 
 impl OutputFormatterWithPadding for FormattedObjectRelation {
-    fn format(&self, padding: usize) -> Result<(), AppError> {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
         //        append_key_value("ClassFrom", &self.from_class, padding)?;
         //        append_key_value("ClassTo", &self.to_class, padding)?;
         append_key_value("ObjectFrom", &self.from_object, padding)?;
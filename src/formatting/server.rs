@@ -0,0 +1,84 @@
+use serde::Serialize;
+
+use super::{append_key_value, append_some_key_value, OutputFormatterWithPadding};
+use crate::errors::AppError;
+use crate::i18n::tr_args;
+use crate::output::append_line;
+
+// The number of objects in a class.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClassObjectCount {
+    pub class: String,
+    pub objects: usize,
+}
+
+// Instance-wide counts, with the classes holding the most objects.
+#[derive(Debug, Clone, Serialize)]
+pub struct FormattedServerStats {
+    pub classes: usize,
+    pub objects: usize,
+    pub namespaces: usize,
+    pub users: usize,
+    pub groups: usize,
+    pub top_classes: Vec<ClassObjectCount>,
+}
+
+impl OutputFormatterWithPadding for FormattedServerStats {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
+        append_key_value("Classes", self.classes, padding)?;
+        append_key_value("Objects", self.objects, padding)?;
+        append_key_value("Namespaces", self.namespaces, padding)?;
+        append_key_value("Users", self.users, padding)?;
+        append_key_value("Groups", self.groups, padding)?;
+
+        if !self.top_classes.is_empty() {
+            append_line("")?;
+            append_line(tr_args(
+                "Objects per class (top {count})",
+                &[("count", &self.top_classes.len())],
+            ))?;
+            for count in &self.top_classes {
+                append_key_value(&count.class, count.objects, padding)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// The server we are connected to, and how it answers.
+#[derive(Debug, Clone, Serialize)]
+pub struct FormattedServerInfo {
+    pub server: String,
+    pub version: Option<String>,
+    pub api_versions: Vec<String>,
+    pub client_api_version: String,
+    pub latency_ms: u128,
+}
+
+impl OutputFormatterWithPadding for FormattedServerInfo {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
+        append_key_value("Server", &self.server, padding)?;
+        append_some_key_value("Version", &self.version, padding)?;
+        append_key_value("API versions", self.api_versions.join(", "), padding)?;
+        append_key_value("Client API version", &self.client_api_version, padding)?;
+        append_key_value("Latency", format!("{} ms", self.latency_ms), padding)?;
+        Ok(())
+    }
+}
+
+// The versions of the CLI, the client library and the server.
+#[derive(Debug, Clone, Serialize)]
+pub struct FormattedVersion {
+    pub cli: String,
+    pub client: String,
+    pub server: Option<String>,
+}
+
+impl OutputFormatterWithPadding for FormattedVersion {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
+        append_key_value("hubuum-cli", &self.cli, padding)?;
+        append_key_value("hubuum_client", &self.client, padding)?;
+        append_some_key_value("Server", &self.server, padding)?;
+        Ok(())
+    }
+}
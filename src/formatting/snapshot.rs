@@ -0,0 +1,45 @@
+use hubuum_client::resources::{tabled_display, tabled_display_option};
+use serde::Serialize;
+use tabled::Tabled;
+
+use crate::snapshots::ObjectSnapshot;
+
+// A wrapper for object snapshots, the id is the position of the snapshot in the listing,
+// oldest first, and the version counts from 1.
+#[derive(Debug, Serialize, Tabled)]
+pub struct FormattedObjectSnapshot {
+    #[serde(skip)]
+    pub id: usize,
+    #[tabled(rename = "Version")]
+    pub version: usize,
+    #[tabled(rename = "Snapshot")]
+    pub snapshot: String,
+    #[tabled(display_with = "tabled_display", rename = "Taken")]
+    pub taken_at: chrono::NaiveDateTime,
+    #[tabled(rename = "Name")]
+    pub name: String,
+    #[tabled(rename = "Namespace")]
+    pub namespace: String,
+    #[tabled(rename = "Description")]
+    pub description: String,
+    #[tabled(display_with = "tabled_display_option", rename = "Data")]
+    pub data: Option<serde_json::Value>,
+    #[tabled(rename = "Taken before")]
+    pub reason: String,
+}
+
+impl FormattedObjectSnapshot {
+    pub fn new(snapshot: &ObjectSnapshot, id: usize) -> Self {
+        Self {
+            id,
+            version: id + 1,
+            snapshot: snapshot.id(),
+            taken_at: snapshot.taken_at,
+            name: snapshot.object.name.clone(),
+            namespace: snapshot.namespace.clone(),
+            description: snapshot.object.description.clone(),
+            data: snapshot.object.data.clone(),
+            reason: snapshot.reason.clone().unwrap_or_default(),
+        }
+    }
+}
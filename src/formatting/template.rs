@@ -0,0 +1,71 @@
+use serde::Serialize;
+use tabled::Tabled;
+
+use crate::templates::{ClassTemplate, ObjectTemplate};
+
+// A wrapper for class templates, the id is the position of the template in the listing.
+#[derive(Debug, Serialize, Tabled)]
+pub struct FormattedClassTemplate {
+    #[serde(skip)]
+    pub id: usize,
+    #[tabled(rename = "Name")]
+    pub name: String,
+    #[tabled(rename = "Description")]
+    pub description: String,
+    #[tabled(rename = "Source")]
+    pub source: String,
+    #[tabled(rename = "Properties")]
+    pub properties: String,
+    #[tabled(skip)]
+    pub json_schema: serde_json::Value,
+}
+
+impl FormattedClassTemplate {
+    pub fn new(template: &ClassTemplate, id: usize) -> Self {
+        let source = if template.builtin { "builtin" } else { "user" };
+
+        Self {
+            id,
+            name: template.name.clone(),
+            description: template.description.clone(),
+            source: source.to_string(),
+            properties: template.properties().join(", "),
+            json_schema: template.json_schema.clone(),
+        }
+    }
+}
+
+// A wrapper for object templates, the id is the position of the template in the listing.
+#[derive(Debug, Serialize, Tabled)]
+pub struct FormattedObjectTemplate {
+    #[serde(skip)]
+    pub id: usize,
+    #[tabled(rename = "Name")]
+    pub name: String,
+    #[tabled(rename = "Class")]
+    pub class: String,
+    #[tabled(rename = "Description")]
+    pub description: String,
+    #[tabled(rename = "Data keys")]
+    pub keys: String,
+    #[tabled(skip)]
+    pub data: Option<serde_json::Value>,
+}
+
+impl FormattedObjectTemplate {
+    pub fn new(template: &ObjectTemplate, id: usize) -> Self {
+        Self {
+            id,
+            name: template.name.clone(),
+            class: template.class.clone(),
+            description: template.description.clone(),
+            keys: template
+                .data
+                .as_ref()
+                .and_then(|d| d.as_object())
+                .map(|d| d.keys().cloned().collect::<Vec<_>>().join(", "))
+                .unwrap_or_default(),
+            data: template.data.clone(),
+        }
+    }
+}
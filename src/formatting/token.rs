@@ -0,0 +1,48 @@
+use serde::Serialize;
+use serde_json::Value;
+use tabled::Tabled;
+
+// A wrapper for API tokens, the id is the position of the token in the listing.
+#[derive(Debug, Serialize, Tabled)]
+pub struct FormattedToken {
+    // Not #[tabled(skip)], as tables drop their first column, which hides it.
+    #[serde(skip)]
+    pub id: usize,
+    #[tabled(rename = "Token")]
+    pub token: String,
+    #[tabled(rename = "Current")]
+    pub current: bool,
+    #[tabled(rename = "Issued")]
+    pub issued: String,
+    #[tabled(rename = "Last used")]
+    pub last_used: String,
+}
+
+// Only show the start of tokens, that is enough to revoke them.
+pub fn mask_token(token: &str) -> String {
+    format!("{}...", token.chars().take(8).collect::<String>())
+}
+
+fn field(token: &Value, names: &[&str]) -> String {
+    names
+        .iter()
+        .find_map(|name| token.get(*name))
+        .map(|value| match value {
+            Value::String(s) => s.clone(),
+            value => value.to_string(),
+        })
+        .unwrap_or_else(|| "<unknown>".to_string())
+}
+
+impl FormattedToken {
+    pub fn new(token: &Value, current_token: &str, id: usize) -> Self {
+        let value = token.get("token").and_then(|t| t.as_str()).unwrap_or("");
+        Self {
+            id,
+            token: mask_token(value),
+            current: value == current_token,
+            issued: field(token, &["issued", "issued_at", "created_at"]),
+            last_used: field(token, &["last_used", "last_used_at"]),
+        }
+    }
+}
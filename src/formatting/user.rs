@@ -1,10 +1,11 @@
 use hubuum_client::User;
+use serde::Serialize;
 
 use super::{append_key_value, append_some_key_value, OutputFormatterWithPadding};
 use crate::errors::AppError;
 
 impl OutputFormatterWithPadding for User {
-    fn format(&self, padding: usize) -> Result<(), AppError> {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
         append_key_value("Username", &self.username, padding)?;
         append_some_key_value("Email", &self.email, padding)?;
         append_key_value("Created", self.created_at, padding)?;
@@ -12,3 +13,26 @@ impl OutputFormatterWithPadding for User {
         Ok(())
     }
 }
+
+// The identity and session of the logged in user.
+#[derive(Debug, Serialize)]
+pub struct FormattedWhoami {
+    pub username: String,
+    pub id: i32,
+    pub email: Option<String>,
+    pub groups: Vec<String>,
+    pub server: String,
+    pub token_issued_at: Option<chrono::NaiveDateTime>,
+}
+
+impl OutputFormatterWithPadding for FormattedWhoami {
+    fn format_text(&self, padding: usize) -> Result<(), AppError> {
+        append_key_value("Username", &self.username, padding)?;
+        append_key_value("User ID", self.id, padding)?;
+        append_some_key_value("Email", &self.email, padding)?;
+        append_key_value("Groups", self.groups.join(", "), padding)?;
+        append_key_value("Server", &self.server, padding)?;
+        append_some_key_value("Token issued", &self.token_issued_at, padding)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,100 @@
+use crate::config::get_config;
+
+/// Whether completion falls back to fuzzy matching (`completion.fuzzy`).
+pub fn fuzzy_enabled() -> bool {
+    get_config().is_ok_and(|config| config.completion.fuzzy)
+}
+
+/// Score how well `pattern` matches `candidate`, fzf-style: the characters of the pattern
+/// must appear in order (ignoring case), and matches that are consecutive or start a word
+/// score higher. Returns `None` if the pattern does not match.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let candidate = candidate.to_lowercase().chars().collect::<Vec<_>>();
+
+    let mut score = 0;
+    let mut next = 0;
+    let mut last_match: Option<usize> = None;
+    for (i, c) in candidate.iter().enumerate() {
+        if next == pattern.len() {
+            break;
+        }
+        if *c != pattern[next] {
+            continue;
+        }
+
+        score += 1;
+        if last_match.is_some_and(|last| last + 1 == i) {
+            score += 5;
+        }
+        if i == 0 || matches!(candidate[i - 1], ' ' | '-' | '_' | '.' | '/') {
+            score += 8;
+        }
+        // Gaps before the first match count against the candidate.
+        if last_match.is_none() {
+            score -= i.min(10) as i64;
+        }
+        last_match = Some(i);
+        next += 1;
+    }
+
+    (next == pattern.len()).then_some(score)
+}
+
+/// The candidates matching `pattern`, best match first.
+pub fn fuzzy_filter<I>(pattern: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut scored = candidates
+        .into_iter()
+        .filter_map(|candidate| fuzzy_score(pattern, &candidate).map(|score| (score, candidate)))
+        .collect::<Vec<_>>();
+    scored.sort_by(|(a_score, a), (b_score, b)| b_score.cmp(a_score).then(a.cmp(b)));
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// The Levenshtein distance between two strings, counted in characters.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// The candidates closest to a mistyped `word`, for "did you mean" suggestions. Candidates
+/// further away than a third of the word (but at least two edits) are not suggested.
+pub fn closest_matches<I>(word: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = String>,
+{
+    let length = word.chars().count();
+    let limit = (length / 3).max(2).min(length.saturating_sub(1));
+
+    let scored = candidates
+        .into_iter()
+        .map(|candidate| (levenshtein(word, &candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .collect::<Vec<_>>();
+
+    let Some(best) = scored.iter().map(|(distance, _)| *distance).min() else {
+        return Vec::new();
+    };
+    let mut closest = scored
+        .into_iter()
+        .filter(|(distance, _)| *distance == best)
+        .map(|(_, candidate)| candidate)
+        .collect::<Vec<_>>();
+    closest.sort();
+    closest.dedup();
+    closest
+}
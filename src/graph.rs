@@ -0,0 +1,89 @@
+// Relation graphs rendered for Graphviz (`dot -Tsvg`) or Mermaid, for documenting data
+// models. Relations in hubuum have no direction, so the edges are undirected.
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::errors::AppError;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphFormat {
+    #[default]
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = AppError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "dot" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            _ => Err(AppError::ParseError(format!(
+                "Invalid graph format '{}', expected dot or mermaid",
+                s
+            ))),
+        }
+    }
+}
+
+/// Nodes are keyed by an id that is safe to use unquoted in both formats, e.g. `c12` for
+/// the class with id 12, and shown with their label.
+#[derive(Debug, Default)]
+pub struct Graph {
+    nodes: BTreeMap<String, String>,
+    edges: BTreeSet<(String, String)>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, id: &str, label: &str) {
+        self.nodes.insert(id.to_string(), label.to_string());
+    }
+
+    /// Add an edge between two nodes, once no matter which way round it is given.
+    pub fn add_edge(&mut self, a: &str, b: &str) {
+        let edge = if a <= b { (a, b) } else { (b, a) };
+        self.edges.insert((edge.0.to_string(), edge.1.to_string()));
+    }
+
+    pub fn render(&self, format: GraphFormat) -> Vec<String> {
+        match format {
+            GraphFormat::Dot => self.render_dot(),
+            GraphFormat::Mermaid => self.render_mermaid(),
+        }
+    }
+
+    fn render_dot(&self) -> Vec<String> {
+        let mut lines = vec!["graph hubuum {".to_string()];
+        for (id, label) in &self.nodes {
+            lines.push(format!(
+                "    {} [label=\"{}\"];",
+                id,
+                label.replace('\\', "\\\\").replace('"', "\\\"")
+            ));
+        }
+        for (a, b) in &self.edges {
+            lines.push(format!("    {} -- {};", a, b));
+        }
+        lines.push("}".to_string());
+        lines
+    }
+
+    fn render_mermaid(&self) -> Vec<String> {
+        let mut lines = vec!["graph LR".to_string()];
+        for (id, label) in &self.nodes {
+            lines.push(format!("    {}[\"{}\"]", id, label.replace('"', "#quot;")));
+        }
+        for (a, b) in &self.edges {
+            lines.push(format!("    {} --- {}", a, b));
+        }
+        lines
+    }
+}
@@ -0,0 +1,121 @@
+use colored::Colorize;
+
+use crate::commandlist::CommandList;
+use crate::session;
+
+// Split a line into words and the whitespace between them, keeping quoted strings
+// (including their whitespace) as one word.
+fn segments(line: &str) -> Vec<&str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut quote = None;
+    let mut in_space = false;
+
+    for (i, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => {
+                if in_space && i > start {
+                    segments.push(&line[start..i]);
+                    start = i;
+                }
+                in_space = false;
+                quote = Some(c);
+            }
+            None => {
+                let space = c.is_whitespace();
+                if space != in_space && i > start {
+                    segments.push(&line[start..i]);
+                    start = i;
+                }
+                in_space = space;
+            }
+        }
+    }
+    if start < line.len() {
+        segments.push(&line[start..]);
+    }
+    segments
+}
+
+fn is_negative_number(word: &str) -> bool {
+    word.len() > 1 && word[1..].parse::<f64>().is_ok()
+}
+
+/// Color a command line: scopes and commands as they are typed, known options and quoted
+/// strings, and unknown commands or options in red.
+pub fn highlight_line(cli: &CommandList, line: &str) -> String {
+    // Everything after a `|` is an output filter, which is left as typed.
+    let (command_line, filter) = match line.find('|') {
+        Some(index) => line.split_at(index),
+        None => (line, ""),
+    };
+
+    let mut scope = cli;
+    let mut command = None;
+    // After `--`, everything is a positional, even if it starts with a dash.
+    let mut end_of_options = false;
+    let mut highlighted = String::with_capacity(line.len() * 2);
+
+    for segment in segments(command_line) {
+        if segment.trim().is_empty() {
+            highlighted.push_str(segment);
+            continue;
+        }
+
+        let colored = match command {
+            None => {
+                if scope.get_scope(segment).is_none()
+                    && scope.get_command(segment).is_none()
+                    && std::ptr::eq(scope, cli)
+                    && session::context().class.is_some()
+                {
+                    // Object commands can be given without the scope while a class is in use.
+                    if let Some(objects) = cli.get_scope("object") {
+                        scope = objects;
+                    }
+                }
+
+                if let Some(next) = scope.get_scope(segment) {
+                    scope = next;
+                    segment.cyan().to_string()
+                } else if let Some(cmd) = scope.get_command(segment) {
+                    command = Some(cmd);
+                    segment.green().bold().to_string()
+                } else {
+                    segment.red().to_string()
+                }
+            }
+            Some(cmd) => {
+                if segment.starts_with('"') || segment.starts_with('\'') {
+                    segment.magenta().to_string()
+                } else if segment == "--" && !end_of_options {
+                    end_of_options = true;
+                    segment.yellow().to_string()
+                } else if segment.starts_with('-')
+                    && !end_of_options
+                    && !is_negative_number(segment)
+                {
+                    // `--option=value` is checked by its option part. The options of a
+                    // command include the global ones, see `GLOBAL_OPTIONS`.
+                    let option = segment.split_once('=').map_or(segment, |(o, _)| o);
+                    let known = cmd.options().iter().any(|opt| {
+                        opt.short.as_deref() == Some(option) || opt.long.as_deref() == Some(option)
+                    });
+                    if known {
+                        segment.yellow().to_string()
+                    } else {
+                        segment.red().to_string()
+                    }
+                } else {
+                    segment.to_string()
+                }
+            }
+        };
+        highlighted.push_str(&colored);
+    }
+
+    highlighted.push_str(filter);
+    highlighted
+}
@@ -0,0 +1,263 @@
+use std::io::{BufRead, Write};
+
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::{build_repl_commands, CliCommandInfo};
+use crate::errors::AppError;
+use crate::files::{get_command_log_file, get_history_file};
+use crate::output::REDACTED;
+use crate::tokenizer::join_command;
+
+// What a line needs to know about the command it runs, before it is written to disk.
+struct CommandSummary {
+    // The full name, e.g. `["object", "data", "set"]`.
+    path: Vec<String>,
+    mutating: bool,
+    // The short and long names of the options whose values are secrets.
+    secrets: Vec<String>,
+}
+
+static COMMANDS: Lazy<Vec<CommandSummary>> = Lazy::new(|| {
+    build_repl_commands()
+        .all_commands()
+        .into_iter()
+        .map(|(path, command)| CommandSummary {
+            path: path.split(' ').map(str::to_string).collect(),
+            mutating: command.is_mutating(),
+            secrets: command
+                .options()
+                .into_iter()
+                .filter(|option| option.secret)
+                .flat_map(|option| [option.short, option.long])
+                .flatten()
+                .collect(),
+        })
+        .collect()
+});
+
+// The command a line runs. With a class in use, object commands are given without
+// `object`, so a line that starts with no other command is also tried as one.
+fn find_command(line: &str) -> Option<&'static CommandSummary> {
+    let words = shlex::split(line)
+        .unwrap_or_default()
+        .into_iter()
+        .take_while(|part| !part.starts_with('-'))
+        .collect::<Vec<_>>();
+    let first = words.first()?;
+
+    let words = match COMMANDS.iter().any(|command| &command.path[0] == first) {
+        true => words,
+        false => std::iter::once("object".to_string()).chain(words).collect(),
+    };
+    COMMANDS
+        .iter()
+        .find(|command| words.starts_with(&command.path))
+}
+
+/// A command run in the CLI, as recorded in the command log.
+///
+/// Unlike the readline history, the command log knows when each command ran and whether
+/// it succeeded, which is what `history export` selects on.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CommandLogEntry {
+    pub at: NaiveDateTime,
+    pub line: String,
+    pub success: bool,
+}
+
+impl CommandLogEntry {
+    /// Whether the command changes something on the server.
+    pub fn is_mutating(&self) -> bool {
+        mutating_command(&self.line).is_some()
+    }
+}
+
+/// The full name of the command if it changes something on the server, e.g.
+/// `["object", "create"]`.
+pub fn mutating_command(line: &str) -> Option<Vec<String>> {
+    find_command(line)
+        .filter(|command| command.mutating)
+        .map(|command| command.path.clone())
+}
+
+/// The line with the values of secret options, like the password of `user modify`,
+/// replaced by [`REDACTED`], so it can be written to disk.
+pub fn redact_secrets(line: &str) -> String {
+    let Some(command) = find_command(line).filter(|command| !command.secrets.is_empty()) else {
+        return line.to_string();
+    };
+    let Some(parts) = shlex::split(line) else {
+        return line.to_string();
+    };
+
+    let mut redacted = Vec::with_capacity(parts.len());
+    let mut changed = false;
+    let mut secret_next = false;
+    for part in parts {
+        if std::mem::take(&mut secret_next) {
+            redacted.push(REDACTED.to_string());
+            changed = true;
+            continue;
+        }
+        match part.split_once('=') {
+            Some((option, _)) if command.secrets.iter().any(|s| s == option) => {
+                redacted.push(format!("{}={}", option, REDACTED));
+                changed = true;
+            }
+            _ => {
+                secret_next = command.secrets.contains(&part);
+                redacted.push(part);
+            }
+        }
+    }
+    match changed {
+        true => join_command(&redacted),
+        false => line.to_string(),
+    }
+}
+
+/// Append a command to the command log.
+pub fn record_command(line: &str, success: bool) -> Result<(), AppError> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(());
+    }
+
+    let entry = CommandLogEntry {
+        at: Local::now().naive_local(),
+        line: redact_secrets(line),
+        success,
+    };
+
+    let mut file = std::fs::OpenOptions::new()
+        .append(true)
+        .open(get_command_log_file()?)?;
+    writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+    Ok(())
+}
+
+/// Read the command log, oldest first. Lines that can not be parsed are skipped.
+pub fn command_log() -> Result<Vec<CommandLogEntry>, AppError> {
+    let file = std::fs::File::open(get_command_log_file()?)?;
+    let mut entries = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        if let Ok(entry) = serde_json::from_str(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Parse a point in time given as `<n> <unit> ago` (e.g. "1 hour ago", "30 minutes ago"),
+/// as a date (`2024-05-01`) or as a local date and time (`2024-05-01 14:00`).
+pub fn parse_since(since: &str) -> Result<NaiveDateTime, AppError> {
+    let since = since.trim();
+    let invalid = || {
+        AppError::ParseError(format!(
+            "Invalid time '{}', expected e.g. '1 hour ago' or '2024-05-01 14:00'",
+            since
+        ))
+    };
+
+    if let Some(relative) = since.strip_suffix("ago") {
+        let mut parts = relative.split_whitespace();
+        let amount = parts
+            .next()
+            .and_then(|n| n.parse::<i64>().ok())
+            .ok_or_else(invalid)?;
+        let unit = parts.next().ok_or_else(invalid)?;
+        if parts.next().is_some() {
+            return Err(invalid());
+        }
+
+        let duration = match unit.trim_end_matches('s') {
+            "second" | "sec" => Duration::seconds(amount),
+            "minute" | "min" => Duration::minutes(amount),
+            "hour" => Duration::hours(amount),
+            "day" => Duration::days(amount),
+            "week" => Duration::weeks(amount),
+            _ => return Err(invalid()),
+        };
+        return Ok(Local::now().naive_local() - duration);
+    }
+
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"] {
+        if let Ok(at) = NaiveDateTime::parse_from_str(since, format) {
+            return Ok(at);
+        }
+    }
+
+    NaiveDate::parse_from_str(since, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .ok_or_else(invalid)
+}
+
+/// The readline history, oldest first. Entry `n` (counting from 1) is what `history` shows
+/// as `n` and what `!n` runs.
+pub fn readline_history() -> Result<Vec<String>, AppError> {
+    let content = std::fs::read_to_string(get_history_file()?)?;
+    Ok(content
+        .lines()
+        // The first line of the file is the version of the format, e.g. `#V2`.
+        .filter(|line| !line.starts_with("#V"))
+        .filter(|line| !line.is_empty())
+        .map(unescape_history_line)
+        .collect())
+}
+
+// Entries are stored one per line, with `\` and newlines escaped by a backslash.
+fn unescape_history_line(line: &str) -> String {
+    let mut entry = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            entry.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => entry.push('\n'),
+            Some(escaped) => entry.push(escaped),
+            None => entry.push('\\'),
+        }
+    }
+    entry
+}
+
+/// Expand a history reference at the start of a line: `!!` is the previous command, `!n`
+/// command `n`, `!-n` the command `n` back and `!text` the last command starting with
+/// `text`. The rest of the line is kept, so `!! | grep web` works. Lines without a
+/// reference are returned as they are.
+pub fn expand_history(line: &str) -> Result<String, AppError> {
+    let trimmed = line.trim_start();
+    if !trimmed.starts_with('!') || trimmed == "!" {
+        return Ok(line.to_string());
+    }
+
+    let (event, rest) =
+        trimmed.split_at(trimmed.find(char::is_whitespace).unwrap_or(trimmed.len()));
+    let reference = &event[1..];
+    let history = readline_history()?;
+
+    let found = if reference == "!" {
+        history.last()
+    } else if let Ok(index) = reference.parse::<i64>() {
+        let index = if index < 0 {
+            history.len() as i64 + index
+        } else {
+            index - 1
+        };
+        usize::try_from(index).ok().and_then(|i| history.get(i))
+    } else {
+        history
+            .iter()
+            .rev()
+            .find(|entry| entry.starts_with(reference))
+    };
+
+    found
+        .map(|entry| format!("{}{}", entry, rest))
+        .ok_or_else(|| AppError::HistoryEventNotFound(event.to_string()))
+}
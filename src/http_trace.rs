@@ -0,0 +1,110 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use chrono::{Local, NaiveDateTime};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use serde_json::Value;
+
+// How many requests are kept, and how much of each body.
+const TRACE_KEEP: usize = 50;
+const BODY_MAX_CHARS: usize = 1000;
+
+// Keys whose values never go into the trace.
+const SECRET_KEYS: &[&str] = &["password", "token", "secret", "authorization"];
+
+/// A request to the server and its response, as shown by `trace last`. Only requests the
+/// CLI sends itself are traced, those sent by the client library are not visible to us.
+#[derive(Debug, Clone, Serialize)]
+pub struct HttpExchange {
+    pub at: NaiveDateTime,
+    pub method: String,
+    pub url: String,
+    pub status: Option<u16>,
+    pub elapsed_ms: u128,
+    pub request_body: Option<String>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+}
+
+static TRACE: Lazy<Mutex<VecDeque<HttpExchange>>> =
+    Lazy::new(|| Mutex::new(VecDeque::with_capacity(TRACE_KEEP)));
+
+fn redact_value(value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object.iter_mut() {
+                let key = key.to_lowercase();
+                if SECRET_KEYS.iter().any(|secret| key.contains(secret)) {
+                    *value = Value::String("***".to_string());
+                } else {
+                    redact_value(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_value),
+        _ => {}
+    }
+}
+
+/// A body fit for the trace: secrets in JSON replaced by `***`, and cut short if long.
+/// Bodies that are not JSON can't be searched for secrets, and are left out.
+pub fn redact_body(body: &str) -> Option<String> {
+    if body.trim().is_empty() {
+        return None;
+    }
+    let Ok(mut value) = serde_json::from_str::<Value>(body) else {
+        return Some(format!("<{} bytes, not JSON>", body.len()));
+    };
+    redact_value(&mut value);
+
+    let body = value.to_string();
+    if body.chars().count() <= BODY_MAX_CHARS {
+        return Some(body);
+    }
+    Some(format!(
+        "{}... ({} bytes)",
+        body.chars().take(BODY_MAX_CHARS).collect::<String>(),
+        body.len()
+    ))
+}
+
+impl HttpExchange {
+    /// A request about to be sent, to be completed with the response.
+    pub fn new(method: &str, url: &str, request_body: Option<&str>) -> Self {
+        HttpExchange {
+            at: Local::now().naive_local(),
+            method: method.to_string(),
+            url: url.to_string(),
+            status: None,
+            elapsed_ms: 0,
+            request_body: request_body.and_then(redact_body),
+            response_body: None,
+            error: None,
+        }
+    }
+}
+
+/// Add a request to the trace, forgetting the oldest if full.
+pub fn record_exchange(exchange: HttpExchange) {
+    if let Ok(mut trace) = TRACE.lock() {
+        if trace.len() == TRACE_KEEP {
+            trace.pop_front();
+        }
+        trace.push_back(exchange);
+    }
+}
+
+/// The last `count` requests, oldest first.
+pub fn last_exchanges(count: usize) -> Vec<HttpExchange> {
+    TRACE
+        .lock()
+        .map(|trace| {
+            trace
+                .iter()
+                .skip(trace.len().saturating_sub(count))
+                .cloned()
+                .collect()
+        })
+        .unwrap_or_default()
+}
@@ -0,0 +1,396 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use crate::errors::AppError;
+use crate::files::get_locale_dir;
+
+static CATALOG: Lazy<RwLock<HashMap<String, String>>> = Lazy::new(|| RwLock::new(HashMap::new()));
+
+// Norwegian (bokmål) translations shipped with the CLI.
+const NB: &[(&str, &str)] = &[
+    ("Warning", "Advarsel"),
+    ("Error", "Feil"),
+    ("API Error", "API-feil"),
+    (
+        "Password for {username} @ {hostname}: ",
+        "Passord for {username} @ {hostname}: ",
+    ),
+    (
+        "Command not found: {command}",
+        "Fant ikke kommandoen: {command}",
+    ),
+    ("Name", "Navn"),
+    ("Description", "Beskrivelse"),
+    ("Namespace", "Navnerom"),
+    ("Class", "Klasse"),
+    ("Schema", "Skjema"),
+    ("Validate", "Valider"),
+    ("Created", "Opprettet"),
+    ("Updated", "Oppdatert"),
+    ("Username", "Brukernavn"),
+    ("Email", "E-post"),
+    ("Password", "Passord"),
+    ("Objects", "Objekter"),
+    ("No objects found", "Fant ingen objekter"),
+    ("No relations found", "Fant ingen relasjoner"),
+    (
+        "User '{username}' deleted",
+        "Brukeren '{username}' er slettet",
+    ),
+    (
+        "Namespace '{name}' deleted",
+        "Navnerommet '{name}' er slettet",
+    ),
+    (
+        "JSON data requested, but object has no data",
+        "JSON-data ble etterspurt, men objektet har ingen data",
+    ),
+    (
+        "The server does not support filtering on {conditions}, filtering locally",
+        "Serveren støtter ikke filtrering på {conditions}, filtrerer lokalt",
+    ),
+    ("{class} ({count} matches)", "{class} ({count} treff)"),
+    (
+        "{class}: new objects: {names}",
+        "{class}: nye objekter: {names}",
+    ),
+    (
+        "{class}: changed objects: {names}",
+        "{class}: endrede objekter: {names}",
+    ),
+    (
+        "{class}: deleted objects: {names}",
+        "{class}: slettede objekter: {names}",
+    ),
+    (
+        "Watching class {class} every {interval} seconds",
+        "Overvåker klassen {class} hvert {interval}. sekund",
+    ),
+    (
+        "Class {class} is not watched",
+        "Klassen {class} overvåkes ikke",
+    ),
+    ("No classes are watched", "Ingen klasser overvåkes"),
+    (
+        "Plan: {create} to create, {update} to update, {delete} to delete.",
+        "Plan: {create} opprettes, {update} oppdateres, {delete} slettes.",
+    ),
+    ("Apply this plan?", "Utfør denne planen?"),
+    ("Plan not applied", "Planen ble ikke utført"),
+    ("Hint", "Tips"),
+    ("Nothing to move", "Ingenting å flytte"),
+    ("User ID", "Bruker-ID"),
+    ("Groups", "Grupper"),
+    ("Server", "Server"),
+    ("Token issued", "Token utstedt"),
+    ("Current password: ", "Nåværende passord: "),
+    ("New password: ", "Nytt passord: "),
+    ("Repeat new password: ", "Gjenta nytt passord: "),
+    (
+        "The current password is not correct",
+        "Det nåværende passordet er feil",
+    ),
+    (
+        "The new password can not be empty",
+        "Det nye passordet kan ikke være tomt",
+    ),
+    ("The passwords do not match", "Passordene er ikke like"),
+    ("Password changed", "Passordet er endret"),
+    (
+        "The sandbox {namespace} is already active",
+        "Sandkassen {namespace} er allerede aktiv",
+    ),
+    (
+        "Temporary sandbox namespace",
+        "Midlertidig sandkasse-navnerom",
+    ),
+    (
+        "Started sandbox {namespace}, remove it with 'sandbox destroy'",
+        "Startet sandkassen {namespace}, fjern den med 'sandbox destroy'",
+    ),
+    ("No sandbox is active", "Ingen sandkasse er aktiv"),
+    (
+        "Destroyed sandbox {namespace}",
+        "Fjernet sandkassen {namespace}",
+    ),
+    (
+        "Exported {count} commands to {file}",
+        "Eksporterte {count} kommandoer til {file}",
+    ),
+    (
+        "Revoked all tokens and logged out, use 'login' to log in again",
+        "Trakk tilbake alle tokens og logget ut, bruk 'login' for å logge inn igjen",
+    ),
+    ("Revoked token {token}", "Trakk tilbake token {token}"),
+    ("Current", "Gjeldende"),
+    ("Issued", "Utstedt"),
+    ("Last used", "Sist brukt"),
+    (
+        "Login with {provider} failed: {error}",
+        "Innlogging med {provider} feilet: {error}",
+    ),
+    (
+        "Moved {moved} of {total} entities from {from} to {to}",
+        "Flyttet {moved} av {total} entiteter fra {from} til {to}",
+    ),
+    (
+        "Logged in as {username} @ {hostname}",
+        "Logget inn som {username} @ {hostname}",
+    ),
+    ("Logged out", "Logget ut"),
+    ("logged out", "logget ut"),
+    (
+        "Not logged in, use 'login' to log in",
+        "Ikke logget inn, bruk 'login' for å logge inn",
+    ),
+    ("Classes", "Klasser"),
+    ("Namespaces", "Navnerom"),
+    ("Users", "Brukere"),
+    (
+        "Objects per class (top {count})",
+        "Objekter per klasse (topp {count})",
+    ),
+    (
+        "{server} is up ({latency} ms)",
+        "{server} svarer ({latency} ms)",
+    ),
+    ("Version", "Versjon"),
+    ("API versions", "API-versjoner"),
+    ("Client API version", "Klientens API-versjon"),
+    ("Latency", "Svartid"),
+    ("Not logged in: {error}", "Ikke logget inn: {error}"),
+    ("Set {key} to {value}", "Satte {key} til {value}"),
+    ("Saved to {file}", "Lagret i {file}"),
+    (
+        "No problems found in {files}",
+        "Fant ingen problemer i {files}",
+    ),
+    (
+        "Nothing to migrate in {file}",
+        "Ingenting å migrere i {file}",
+    ),
+    ("Cache cleared", "Hurtigbufferen er tømt"),
+    (
+        "No commands mention '{keyword}'",
+        "Ingen kommandoer nevner '{keyword}'",
+    ),
+    ("Did you mean {suggestion}?", "Mente du {suggestion}?"),
+    ("Recording to {file}", "Tar opp til {file}"),
+    ("Stopped recording to {file}", "Stoppet opptak til {file}"),
+    ("Not recording", "Tar ikke opp"),
+    (
+        "Giving up after {attempts} attempts",
+        "Gir opp etter {attempts} forsøk",
+    ),
+    ("Enabled", "Aktivert"),
+    ("Time to live", "Levetid"),
+    ("Completion", "Fullføring"),
+    (
+        "{entries} cached, {hits} hits, {misses} misses",
+        "{entries} lagret, {hits} treff, {misses} bom",
+    ),
+    ("{entries} cached", "{entries} lagret"),
+    (
+        "{command} has not been run online",
+        "{command} har ikke blitt kjørt mot serveren",
+    ),
+    (
+        "Offline, showing the output from {time}",
+        "Frakoblet, viser utdata fra {time}",
+    ),
+    ("Responses on disk", "Svar på disk"),
+    (
+        "{entries} cached, {bytes} bytes",
+        "{entries} lagret, {bytes} byte",
+    ),
+    (
+        "Removed {count} cached responses",
+        "Fjernet {count} lagrede svar",
+    ),
+    (
+        "Interrupted after {count} objects",
+        "Avbrutt etter {count} objekter",
+    ),
+    (
+        "You lack the {permission} permission, see 'permissions show' and ask an administrator of the namespace to grant it to one of your groups",
+        "Du mangler tillatelsen {permission}, se 'permissions show' og be en administrator for navnerommet om å gi den til en av gruppene dine",
+    ),
+    ("Logging {filter}", "Logger {filter}"),
+    ("Nothing has been timed yet", "Ingenting er tidtatt ennå"),
+    ("Commands", "Kommandoer"),
+    ("Endpoints", "Endepunkter"),
+    ("Count", "Antall"),
+    ("Mean", "Snitt"),
+    ("Slowest", "Tregeste"),
+    (
+        "The log is copied to the terminal",
+        "Loggen kopieres til terminalen",
+    ),
+    (
+        "The server matches the manifest",
+        "Serveren samsvarer med manifestet",
+    ),
+    (
+        "{count} objects were already up to date",
+        "{count} objekter var allerede oppdatert",
+    ),
+    ("The server can't be reached", "Serveren kan ikke nås"),
+    (
+        "The server can't be reached, working offline until 'login' succeeds",
+        "Serveren kan ikke nås, jobber frakoblet til 'login' lykkes",
+    ),
+    (
+        "{created} objects will be created and {updated} updated",
+        "{created} objekter blir opprettet og {updated} oppdatert",
+    ),
+    (
+        "Made {done} of {total} changes",
+        "Gjorde {done} av {total} endringer",
+    ),
+    (
+        "Pruning would delete {count} objects along with their classes, add --cascade to allow it",
+        "Opprydding ville slettet {count} objekter sammen med klassene deres, legg til --cascade for å tillate det",
+    ),
+    (
+        "Saved {count} entities to {file}",
+        "Lagret {count} entiteter i {file}",
+    ),
+    (
+        "Restored the backup of {server} from {time}: {created} created, {updated} updated, {skipped} skipped, {failed} failed",
+        "Gjenopprettet sikkerhetskopien av {server} fra {time}: {created} opprettet, {updated} oppdatert, {skipped} hoppet over, {failed} feilet",
+    ),
+    (
+        "Imported {imported} of {total} objects",
+        "Importerte {imported} av {total} objekter",
+    ),
+    (
+        "--interactive needs a terminal",
+        "--interactive krever en terminal",
+    ),
+    ("optional", "valgfri"),
+    ("required", "påkrevd"),
+    ("Cancelled", "Avbrutt"),
+    ("Command: {command}", "Kommando: {command}"),
+    (
+        "Keys in the schema of {class}: {keys}",
+        "Nøkler i skjemaet til {class}: {keys}",
+    ),
+    (
+        "Enter the {count} required data keys of {class}?",
+        "Skrive inn de {count} påkrevde datanøklene til {class}?",
+    ),
+    ("A value is required", "En verdi er påkrevd"),
+    ("Expected a whole number", "Forventet et heltall"),
+    ("Expected a number", "Forventet et tall"),
+    ("Expected true or false", "Forventet true eller false"),
+    ("Expected one of {values}", "Forventet en av {values}"),
+    ("Copied to the clipboard", "Kopiert til utklippstavlen"),
+    ("Valid options: {options}", "Gyldige valg: {options}"),
+    ("Usage", "Bruk"),
+    ("Arguments", "Argumenter"),
+    ("Options", "Valg"),
+    ("Examples", "Eksempler"),
+    ("{option} can not be used with {other}", "{option} kan ikke brukes sammen med {other}"),
+    ("{option} requires {other}", "{option} krever {other}"),
+    ("Finished in {ms} ms", "Ferdig på {ms} ms"),
+    ("unknown", "ukjent"),
+    (
+        "The schemas have the same properties",
+        "Skjemaene har de samme egenskapene",
+    ),
+    (
+        "{count} objects would fail validation",
+        "{count} objekter ville feilet valideringen",
+    ),
+    ("Nothing to migrate", "Ingenting å migrere"),
+    (
+        "Change the data of {count} objects?",
+        "Endre dataene til {count} objekter?",
+    ),
+    (
+        "Migrated {migrated} of {total} objects",
+        "Migrerte {migrated} av {total} objekter",
+    ),
+    ("No permissions found", "Fant ingen tilganger"),
+    (
+        "C create, R read, U update, D delete, G delegate, - not granted",
+        "C opprette, R lese, U endre, D slette, G delegere, - ikke gitt",
+    ),
+    (
+        "{pattern} matches {count} objects, more than the limit of {max}",
+        "{pattern} treffer {count} objekter, mer enn grensen på {max}",
+    ),
+    (
+        "{pattern} matches {count} objects, continue?",
+        "{pattern} treffer {count} objekter, fortsette?",
+    ),
+    (
+        "{pattern} did not match any objects",
+        "{pattern} traff ingen objekter",
+    ),
+    ("Delete {count} objects?", "Slette {count} objekter?"),
+    ("No objects were deleted", "Ingen objekter ble slettet"),
+    ("changed after snapshot", "endret etter øyeblikksbilde"),
+    ("No changes found", "Ingen endringer funnet"),
+    (
+        "Deleted {deleted} of {total} objects",
+        "Slettet {deleted} av {total} objekter",
+    ),
+];
+
+fn builtin_catalog(language: &str) -> HashMap<String, String> {
+    let messages: &[(&str, &str)] = match language {
+        "nb" | "no" => NB,
+        _ => &[],
+    };
+
+    messages
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// Select the language used for user-facing messages.
+///
+/// The built-in catalog for the language (if any) is extended by `<language>.json` in the
+/// `locales` directory under the data directory, a flat JSON object mapping the English
+/// messages to their translations. English needs no catalog.
+pub fn set_language(language: &str) -> Result<(), AppError> {
+    let language = language.to_lowercase();
+    let mut catalog = builtin_catalog(&language);
+
+    let user_catalog = get_locale_dir()?.join(format!("{}.json", language));
+    if user_catalog.exists() {
+        let content = std::fs::read_to_string(&user_catalog)?;
+        let messages: HashMap<String, String> = serde_json::from_str(&content)?;
+        catalog.extend(messages);
+    }
+
+    if catalog.is_empty() && language != "en" {
+        log::warn!("No message catalog found for language '{}'", language);
+    }
+
+    *CATALOG.write().map_err(|_| AppError::LockError)? = catalog;
+    Ok(())
+}
+
+/// Translate a message, falling back to the message itself if there is no translation.
+pub fn tr(message: &str) -> String {
+    CATALOG
+        .read()
+        .ok()
+        .and_then(|catalog| catalog.get(message).cloned())
+        .unwrap_or_else(|| message.to_string())
+}
+
+/// Translate a message and replace its `{name}` placeholders with the given arguments.
+pub fn tr_args(message: &str, args: &[(&str, &dyn Display)]) -> String {
+    let mut translated = tr(message);
+    for (name, value) in args {
+        translated = translated.replace(&format!("{{{}}}", name), &value.to_string());
+    }
+    translated
+}
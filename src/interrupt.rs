@@ -0,0 +1,112 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{debug, warn};
+
+use crate::errors::AppError;
+use crate::i18n::tr;
+use crate::output::add_warning;
+
+// Set by Ctrl-C while a command runs. Readline handles Ctrl-C itself at the prompt.
+//
+// Commands run on the blocking client, so a request in flight can't be cancelled. Commands
+// marked `cancellable`, which only read, run on a thread of their own: Ctrl-C returns to
+// the prompt at once, and the command is left to finish its request in the background,
+// with its output dropped and its next check of the flag stopping it. Other commands,
+// which may change something, are waited for. Those that work through many entities
+// (deletes, imports, moves and migrations) check the flag between requests and stop with
+// a report of what they got done.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// How many questions are being asked, during which Ctrl-C is left to the question, as a
+// command in the background must not read the answers meant for the prompt.
+static PROMPTING: AtomicUsize = AtomicUsize::new(0);
+
+// How often a waiting prompt looks for Ctrl-C.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+thread_local! {
+    // Set on the thread of a cancellable command once the prompt stopped waiting for it.
+    static ABANDONED: RefCell<Option<Arc<AtomicBool>>> = const { RefCell::new(None) };
+}
+
+/// Catch Ctrl-C, so long-running commands can stop cleanly instead of the CLI exiting.
+/// Commands that don't check [`take_interrupt`] run to the end, so a second Ctrl-C before
+/// the first one is taken exits as usual.
+pub fn install_handler() {
+    let result = ctrlc::set_handler(|| {
+        if INTERRUPTED.swap(true, Ordering::SeqCst) {
+            std::process::exit(130);
+        }
+    });
+    if let Err(err) = result {
+        warn!("Failed to install the Ctrl-C handler: {}", err);
+    }
+}
+
+/// Whether Ctrl-C was pressed since the last call, clearing it. Always true on the thread
+/// of a command that was left running in the background, so it stops at its next check.
+pub fn take_interrupt() -> bool {
+    abandoned() || INTERRUPTED.swap(false, Ordering::SeqCst)
+}
+
+/// Whether this is the thread of a command that was left running in the background after
+/// Ctrl-C. Its output is dropped, so it doesn't end up in that of the commands after it.
+pub fn abandoned() -> bool {
+    ABANDONED.with(|flag| {
+        flag.borrow()
+            .as_ref()
+            .is_some_and(|flag| flag.load(Ordering::SeqCst))
+    })
+}
+
+/// Ask the user something with `f`. Meanwhile Ctrl-C doesn't leave the command behind, so
+/// it is not left reading from the terminal.
+pub fn while_prompting<T>(f: impl FnOnce() -> T) -> T {
+    PROMPTING.fetch_add(1, Ordering::SeqCst);
+    let result = f();
+    PROMPTING.fetch_sub(1, Ordering::SeqCst);
+    result
+}
+
+/// Run a cancellable command on a thread of its own, returning its result, or
+/// `AppError::Quiet` once Ctrl-C is pressed, leaving the command to finish its request in
+/// the background.
+pub fn run_cancellable<F>(f: F) -> Result<(), AppError>
+where
+    F: FnOnce() -> Result<(), AppError> + Send + 'static,
+{
+    let abandoned = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let flag = abandoned.clone();
+    std::thread::spawn(move || {
+        ABANDONED.with(|abandoned| *abandoned.borrow_mut() = Some(flag));
+        // Nobody is listening if the command was abandoned.
+        let _ = sender.send(f());
+    });
+
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(result) => return result,
+            Err(RecvTimeoutError::Disconnected) => {
+                return Err(AppError::CommandExecutionError(
+                    "The command thread panicked".to_string(),
+                ))
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                if PROMPTING.load(Ordering::SeqCst) > 0
+                    || !INTERRUPTED.swap(false, Ordering::SeqCst)
+                {
+                    continue;
+                }
+                debug!("Leaving the interrupted command to finish in the background");
+                abandoned.store(true, Ordering::SeqCst);
+                add_warning(tr("Cancelled"))?;
+                return Err(AppError::Quiet);
+            }
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use rustyline::{Cmd, EditMode, KeyCode, KeyEvent, Modifiers, Movement};
+
+use crate::errors::AppError;
+
+/// Parse `repl.edit_mode`, which is either `emacs` or `vi`.
+pub fn edit_mode(mode: &str) -> Result<EditMode, AppError> {
+    match mode.to_lowercase().as_str() {
+        "emacs" => Ok(EditMode::Emacs),
+        "vi" => Ok(EditMode::Vi),
+        other => Err(AppError::ConfigError(format!(
+            "Invalid edit mode '{}', use 'emacs' or 'vi'",
+            other
+        ))),
+    }
+}
+
+/// Parse a key such as `ctrl-r`, `alt-f`, `f5`, `tab` or `x`.
+pub fn parse_key(key: &str) -> Result<KeyEvent, AppError> {
+    let invalid = || AppError::ConfigError(format!("Invalid key '{}' in repl.bindings", key));
+    let lower = key.to_lowercase();
+
+    let (modifiers, name) = if let Some(name) = lower.strip_prefix("ctrl-") {
+        (Modifiers::CTRL, name)
+    } else if let Some(name) = lower.strip_prefix("alt-") {
+        (Modifiers::ALT, name)
+    } else {
+        (Modifiers::NONE, lower.as_str())
+    };
+
+    let code = match name {
+        "tab" => KeyCode::Tab,
+        "enter" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "backspace" => KeyCode::Backspace,
+        "delete" => KeyCode::Delete,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        name if name.len() > 1 && name.starts_with('f') => {
+            KeyCode::F(name[1..].parse().map_err(|_| invalid())?)
+        }
+        name => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if modifiers == Modifiers::NONE => return Ok(KeyEvent::from(c)),
+                (Some(c), None) if modifiers == Modifiers::CTRL => return Ok(KeyEvent::ctrl(c)),
+                (Some(c), None) => return Ok(KeyEvent::alt(c)),
+                _ => return Err(invalid()),
+            }
+        }
+    };
+    Ok(KeyEvent(code, modifiers))
+}
+
+/// Parse the name of an editing action, such as `history-search-backward`.
+pub fn parse_action(action: &str) -> Result<Cmd, AppError> {
+    let cmd = match action {
+        "accept-line" => Cmd::AcceptLine,
+        "complete" => Cmd::Complete,
+        "clear-screen" => Cmd::ClearScreen,
+        "history-search-backward" => Cmd::ReverseSearchHistory,
+        "history-search-forward" => Cmd::ForwardSearchHistory,
+        "history-prefix-search-backward" => Cmd::HistorySearchBackward,
+        "history-prefix-search-forward" => Cmd::HistorySearchForward,
+        "previous-history" => Cmd::PreviousHistory,
+        "next-history" => Cmd::NextHistory,
+        "beginning-of-line" => Cmd::Move(Movement::BeginningOfLine),
+        "end-of-line" => Cmd::Move(Movement::EndOfLine),
+        "kill-line" => Cmd::Kill(Movement::EndOfLine),
+        "kill-whole-line" => Cmd::Kill(Movement::WholeLine),
+        "undo" => Cmd::Undo(1),
+        "noop" => Cmd::Noop,
+        other => {
+            return Err(AppError::ConfigError(format!(
+                "Unknown action '{}' in repl.bindings",
+                other
+            )))
+        }
+    };
+    Ok(cmd)
+}
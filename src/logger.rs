@@ -1,13 +1,221 @@
-use chrono::Utc;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Instant;
+
 use log::trace;
+use once_cell::sync::{Lazy, OnceCell};
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, reload, EnvFilter, Layer};
+
+use crate::config::LogConfig;
+use crate::errors::AppError;
+use crate::files::{get_log_file, rotate_file};
+use crate::progress::{start_spinner, stop_spinner};
+use crate::timing::{record_timing, TimingKind};
+
+// Replaces the filter of a log destination. The handle types name the whole subscriber
+// stack, so they are hidden behind closures.
+type SetFilter = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
+static SET_FILE_FILTER: OnceCell<SetFilter> = OnceCell::new();
+static SET_TERMINAL_FILTER: OnceCell<SetFilter> = OnceCell::new();
+
+/// The filters in use for the log file and the terminal, `None` for the terminal when logs
+/// are not copied there.
+#[derive(Debug, Clone)]
+pub struct LogFilters {
+    pub file: String,
+    pub terminal: Option<String>,
+}
+
+static FILTERS: Lazy<Mutex<LogFilters>> = Lazy::new(|| {
+    Mutex::new(LogFilters {
+        file: startup_filter(),
+        terminal: None,
+    })
+});
+
+// The filter from RUST_LOG, which the log file starts with and `debug off` returns to.
+fn startup_filter() -> String {
+    std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_else(|_| "error".to_string())
+}
+
+// A log file that is rotated when it grows past `log.max_size`.
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    // The log of the previous run is rotated away, so every run starts a new log.
+    fn open(path: PathBuf, config: &LogConfig) -> std::io::Result<Self> {
+        if std::fs::metadata(&path).is_ok_and(|m| m.len() > 0) {
+            rotate_file(&path, config.keep)?;
+        }
+        Ok(RotatingFile {
+            file: File::create(&path)?,
+            path,
+            size: 0,
+            max_size: config.max_size,
+            keep: config.keep,
+        })
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            rotate_file(&self.path, self.keep)?;
+            self.file = File::create(&self.path)?;
+            self.size = 0;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Log to `log.file` (or the log file in the data directory), filtered by RUST_LOG, with a
+/// second destination on stderr that is off until `debug on --terminal`.
+pub fn init_logging(config: &LogConfig) -> Result<(), AppError> {
+    let path = match &config.file {
+        Some(file) => PathBuf::from(file),
+        None => get_log_file()?,
+    };
+    let file = Mutex::new(RotatingFile::open(path, config)?);
+
+    let (file_filter, file_handle) = reload::Layer::new(EnvFilter::new(startup_filter()));
+    let (terminal_filter, terminal_handle) = reload::Layer::new(EnvFilter::new("off"));
+
+    // JSON lines carry the span of the command they were logged in, and a line with the
+    // time spent when each command finishes.
+    let file_layer = match config.format.as_str() {
+        "text" => fmt::layer()
+            .with_writer(file)
+            .with_ansi(false)
+            .with_filter(file_filter)
+            .boxed(),
+        "json" => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_span_events(FmtSpan::CLOSE)
+            .with_writer(file)
+            .with_filter(file_filter)
+            .boxed(),
+        format => {
+            return Err(AppError::ConfigError(format!(
+                "Invalid log.format '{}', expected text or json",
+                format
+            )))
+        }
+    };
+
+    tracing_subscriber::registry()
+        .with(file_layer)
+        .with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(terminal_filter),
+        )
+        .init();
+
+    let _ = SET_FILE_FILTER.set(Box::new(move |filter| {
+        file_handle.reload(filter).map_err(|e| e.to_string())
+    }));
+    let _ = SET_TERMINAL_FILTER.set(Box::new(move |filter| {
+        terminal_handle.reload(filter).map_err(|e| e.to_string())
+    }));
+    Ok(())
+}
 
+fn set_filter(setter: &OnceCell<SetFilter>, directives: &str) -> Result<(), AppError> {
+    let filter = EnvFilter::try_new(directives)
+        .map_err(|e| AppError::ParseError(format!("Invalid log filter '{}': {}", directives, e)))?;
+    let setter = setter
+        .get()
+        .ok_or_else(|| AppError::CommandExecutionError("Logging is not set up".to_string()))?;
+    setter(filter).map_err(AppError::CommandExecutionError)?;
+    // The log crate drops records above its own maximum before the filters see them, and
+    // that maximum was set from RUST_LOG at startup.
+    log::set_max_level(log::LevelFilter::Trace);
+    Ok(())
+}
+
+/// Change what is logged, e.g. `trace` or `hubuum_client=trace`, and whether the same is
+/// copied to the terminal.
+pub fn set_log_filter(directives: &str, terminal: bool) -> Result<(), AppError> {
+    set_filter(&SET_FILE_FILTER, directives)?;
+    set_filter(
+        &SET_TERMINAL_FILTER,
+        if terminal { directives } else { "off" },
+    )?;
+
+    let mut filters = FILTERS.lock().map_err(|_| AppError::LockError)?;
+    filters.file = directives.to_string();
+    filters.terminal = terminal.then(|| directives.to_string());
+    Ok(())
+}
+
+/// Go back to the filter from RUST_LOG, and stop copying logs to the terminal.
+pub fn reset_log_filter() -> Result<(), AppError> {
+    set_log_filter(&startup_filter(), false)
+}
+
+pub fn log_filters() -> Result<LogFilters, AppError> {
+    Ok(FILTERS.lock().map_err(|_| AppError::LockError)?.clone())
+}
+
+/// Run `f` in a span for the command `label`, with an id that is unique for the run, so the
+/// log lines of one command can be found together.
+pub fn in_command_span<F, R>(label: &str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    static COMMAND_ID: AtomicU64 = AtomicU64::new(0);
+    let id = format!(
+        "{}-{}",
+        std::process::id(),
+        COMMAND_ID.fetch_add(1, Ordering::Relaxed) + 1
+    );
+    // At the error level, as a span is only entered when its level is enabled, and the
+    // default filter only lets errors through.
+    tracing::error_span!("command", id = %id, command = %label).in_scope(f)
+}
+
+/// Run `f`, logging how long it took and adding that to the timings of the command
+/// `label` shown by `stats timing`.
 pub fn with_timing<F, R>(label: &str, f: F) -> R
 where
     F: FnOnce() -> R,
 {
-    let start = Utc::now();
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    trace!("{}: {}ms", label, elapsed.as_millis());
+    record_timing(TimingKind::Command, label, elapsed);
+    result
+}
+
+/// Run `f` with a spinner showing `label` on stderr if it takes more than a moment.
+pub fn with_spinner<F, R>(label: &str, f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    start_spinner(label);
     let result = f();
-    let elapsed = Utc::now().signed_duration_since(start);
-    trace!("{}: {}ms", label, elapsed.num_milliseconds());
+    stop_spinner();
     result
 }
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use hubuum_client::{ApiError, Class, Namespace};
+use log::trace;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::config::get_config;
+
+struct Cache<K, V> {
+    entries: HashMap<K, (Instant, V)>,
+    hits: u64,
+    misses: u64,
+}
+
+impl<K: Eq + Hash, V: Clone> Cache<K, V> {
+    fn new() -> Self {
+        Cache {
+            entries: HashMap::new(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn get(&mut self, key: &K, ttl: Duration) -> Option<V> {
+        match self.entries.get(key) {
+            Some((at, value)) if at.elapsed() < ttl => {
+                self.hits += 1;
+                Some(value.clone())
+            }
+            _ => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    fn stats(&self, kind: &str) -> LookupCacheStats {
+        LookupCacheStats {
+            kind: kind.to_string(),
+            entries: self.entries.len(),
+            hits: self.hits,
+            misses: self.misses,
+        }
+    }
+}
+
+// Classes and namespaces looked up by name, so commands run one after another don't fetch
+// them again and again. Objects are not cached, as their data is what commands show and
+// change, and a cached copy would show stale data or undo changes made by others.
+struct LookupCache {
+    classes: Cache<String, Class>,
+    namespaces: Cache<String, Namespace>,
+}
+
+static LOOKUP_CACHE: Lazy<Mutex<LookupCache>> = Lazy::new(|| {
+    Mutex::new(LookupCache {
+        classes: Cache::new(),
+        namespaces: Cache::new(),
+    })
+});
+
+/// How the lookup cache for one kind of entity is doing.
+#[derive(Debug, Clone, Serialize)]
+pub struct LookupCacheStats {
+    pub kind: String,
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+// Look `key` up in the cache chosen by `select`, fetching and caching it on a miss. Honors
+// `cache.time` and `cache.disable`.
+fn lookup<K, V, S, F>(select: S, key: K, fetch: F) -> Result<V, ApiError>
+where
+    K: Eq + Hash,
+    V: Clone,
+    S: Fn(&mut LookupCache) -> &mut Cache<K, V>,
+    F: FnOnce() -> Result<V, ApiError>,
+{
+    let (ttl, disabled) = get_config()
+        .map(|config| (Duration::from_secs(config.cache.time), config.cache.disable))
+        .unwrap_or((Duration::ZERO, true));
+    if disabled {
+        return fetch();
+    }
+
+    if let Ok(mut cache) = LOOKUP_CACHE.lock() {
+        if let Some(value) = select(&mut cache).get(&key, ttl) {
+            return Ok(value);
+        }
+    }
+
+    // The lock is not held while fetching, so other threads can use the cache meanwhile.
+    let value = fetch()?;
+    if let Ok(mut cache) = LOOKUP_CACHE.lock() {
+        select(&mut cache)
+            .entries
+            .insert(key, (Instant::now(), value.clone()));
+    }
+    Ok(value)
+}
+
+pub fn cached_class<F>(name: &str, fetch: F) -> Result<Class, ApiError>
+where
+    F: FnOnce() -> Result<Class, ApiError>,
+{
+    lookup(|cache| &mut cache.classes, name.to_string(), fetch)
+}
+
+pub fn cached_namespace<F>(name: &str, fetch: F) -> Result<Namespace, ApiError>
+where
+    F: FnOnce() -> Result<Namespace, ApiError>,
+{
+    lookup(|cache| &mut cache.namespaces, name.to_string(), fetch)
+}
+
+/// Forget cached entities after the command `words` (e.g. `["class", "delete"]`) changed
+/// something. Commands outside the class and namespace scopes forget everything to be
+/// safe, except object commands which can't change either. Changes made by others are
+/// only seen once the entries expire after `cache.time`, or after `cache clear`, as the
+/// help of `cache stats` says.
+pub fn invalidate_for_command(words: &[String]) {
+    let Ok(mut cache) = LOOKUP_CACHE.lock() else {
+        return;
+    };
+    trace!("Invalidating the lookup cache after {:?}", words);
+
+    match words.first().map(String::as_str) {
+        Some("object") => {}
+        Some("namespace") => cache.namespaces.entries.clear(),
+        Some("class") => cache.classes.entries.clear(),
+        _ => clear_entries(&mut cache),
+    }
+}
+
+fn clear_entries(cache: &mut LookupCache) {
+    cache.classes.entries.clear();
+    cache.namespaces.entries.clear();
+}
+
+/// Empty the lookup cache.
+pub fn clear_lookup_cache() {
+    if let Ok(mut cache) = LOOKUP_CACHE.lock() {
+        clear_entries(&mut cache);
+    }
+}
+
+pub fn lookup_cache_stats() -> Vec<LookupCacheStats> {
+    match LOOKUP_CACHE.lock() {
+        Ok(cache) => vec![
+            cache.classes.stats("Classes"),
+            cache.namespaces.stats("Namespaces"),
+        ],
+        Err(_) => Vec::new(),
+    }
+}
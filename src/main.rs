@@ -1,34 +1,69 @@
-use std::str::FromStr;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::time::Instant;
 
 use config::AppConfig;
-use errors::AppError;
-use files::get_log_file;
-use hubuum_client::{ApiError, Authenticated, Credentials, SyncClient, Token, Unauthenticated};
-use log::{debug, trace};
-use logger::with_timing;
-use output::{add_error, add_warning, clear_filter, flush_output, set_filter};
+use errors::{ApiErrorBody, AppError};
+use hubuum_client::ApiError;
+use log::{debug, trace, warn};
+use logger::{in_command_span, with_spinner, with_timing};
+use models::OutputFormat;
+use output::{
+    add_error, add_notice, add_warning, append_line, append_lines, buffered_lines, clear_filter,
+    flush_output, get_verbosity, primary_output, set_filter, set_format, set_verbosity, Verbosity,
+};
 use rustyline::history::FileHistory;
 use rustyline::Editor;
-use tracing_subscriber::EnvFilter;
 
+mod api;
+mod audit;
+mod auth;
 mod autocomplete;
+mod backup;
 mod cli;
+mod clipboard;
 mod commandlist;
 mod commands;
+mod completions;
 mod config;
+mod config_check;
 mod defaults;
 mod errors;
+mod fetch;
 mod files;
 mod formatting;
+mod fuzzy;
+mod graph;
+mod highlight;
+mod history;
+mod http_trace;
+mod i18n;
+mod interrupt;
+mod keybindings;
 mod logger;
+mod lookup_cache;
+mod manifest;
 mod models;
+mod offline;
 mod output;
+mod plan;
+mod progress;
+mod prompt;
+mod recording;
+mod response_cache;
+mod sandbox;
+mod schema;
+mod session;
+mod snapshots;
+mod templates;
+mod timing;
 mod tokenizer;
+mod version;
+mod watcher;
+mod wizard;
 
 use crate::commandlist::CommandList;
 use crate::files::get_history_file;
-use crate::models::internal::TokenEntry;
+use crate::i18n::{tr, tr_args};
 
 fn process_filter(line: &str) -> Result<String, AppError> {
     let parts: Vec<&str> = line.split('|').collect();
@@ -47,18 +82,10 @@ fn process_filter(line: &str) -> Result<String, AppError> {
     }
 }
 
-fn prompt(config: &AppConfig) -> String {
-    format!(
-        "{}@{}:{} > ",
-        config.server.username, config.server.hostname, config.server.port
-    )
-}
-
 fn handle_command(
-    cli: &CommandList,
+    cli: &'static CommandList,
     line: &str,
     context: &mut Vec<String>,
-    client: &SyncClient<Authenticated>,
 ) -> Result<(), AppError> {
     let parts = shlex::split(line)
         .ok_or_else(|| AppError::ParseError("Parsing input failed".to_string()))?;
@@ -69,189 +96,635 @@ fn handle_command(
     let (command, cmd_name) = find_command(cli, &parts, context)?;
 
     if let Some(cmd) = command {
-        let command_string = format!("Command {:?}", parts.join(" "));
-        with_timing(&command_string, || {
-            execute_command(cmd, cmd_name, line, context, client)
+        let command_path = context
+            .iter()
+            .map(String::as_str)
+            .chain(cmd_name)
+            .collect::<Vec<_>>()
+            .join(" ");
+        let label = command_path.clone();
+        let cmd_name = cmd_name.map(str::to_string);
+        let line = line.to_string();
+        let context = context.clone();
+        let run = move || {
+            in_command_span(&command_path, || {
+                with_timing(&command_path, || {
+                    execute_command(cli, cmd, cmd_name.as_deref(), &line, &context)
+                })
+            })
+        };
+        // Only the command is shown, its options may carry secrets such as passwords.
+        with_spinner(&label, || {
+            // Ctrl-C is only caught in the REPL, scripts are stopped by it as usual.
+            if cmd.is_cancellable() && session::is_interactive() {
+                interrupt::run_cancellable(run)
+            } else {
+                run()
+            }
         })
     } else {
-        add_warning(format!("Command not found: {}", parts.join(" ")))
+        add_warning(tr_args(
+            "Command not found: {command}",
+            &[("command", &parts.join(" "))],
+        ))
     }
 }
 
+// The command is borrowed from the command list, and its name from the parts, so a
+// command found in the command list of the session outlives the line it was found in.
 #[allow(clippy::type_complexity)]
-fn find_command<'a>(
-    cli: &'a CommandList,
-    parts: &'a [String],
+fn find_command<'c, 'p>(
+    cli: &'c CommandList,
+    parts: &'p [String],
     context: &mut Vec<String>,
-) -> Result<(Option<&'a Box<dyn commands::CliCommand>>, Option<&'a str>), AppError> {
+) -> Result<(Option<&'c Box<dyn commands::CliCommand>>, Option<&'p str>), AppError> {
     let mut current_scope = cli;
     let mut command = None;
     let mut cmd_name = None;
 
-    for part in parts {
+    // With a class in use, object commands can be given without the `object` scope.
+    if let Some(first) = parts.first() {
+        if session::context().class.is_some()
+            && cli.get_scope(first).is_none()
+            && cli.get_command(first).is_none()
+        {
+            if let Some(scope) = cli.get_scope("object") {
+                context.push("object".to_string());
+                current_scope = scope;
+            }
+        }
+    }
+
+    let mut scope_name = None;
+    for (i, part) in parts.iter().enumerate() {
         if let Some(scope) = current_scope.get_scope(part) {
             context.push(part.to_string());
             current_scope = scope;
+            scope_name = Some(part.as_str());
         } else if let Some(cmd) = current_scope.get_command(part) {
             command = Some(cmd);
             cmd_name = Some(part.as_str());
             break;
+        } else if part.starts_with('-') && current_scope.get_default_command().is_some() {
+            // Options for the default command, e.g. `history --last 5`.
+            break;
         } else {
-            return Err(AppError::CommandNotFound(format!(
-                "Command not found: {}",
-                part
-            )));
+            return Err(command_not_found(
+                current_scope,
+                context,
+                part,
+                &parts[i + 1..],
+            ));
+        }
+    }
+
+    // The scope was given without a command, run its default command if it has one. The
+    // scope name stands in for the command name, so the tokenizer finds it.
+    if command.is_none() {
+        if let Some(cmd) = current_scope.get_default_command() {
+            command = Some(cmd);
+            cmd_name = scope_name;
+            context.pop();
         }
     }
 
     Ok((command, cmd_name))
 }
 
+// Suggest the command line with `part` replaced by the closest name in the scope, keeping
+// the words after it up to the first option, e.g. `objcet list` -> `object list`.
+fn command_not_found(
+    scope: &CommandList,
+    context: &[String],
+    part: &str,
+    rest: &[String],
+) -> AppError {
+    let suggestions = fuzzy::closest_matches(part, scope.names())
+        .into_iter()
+        .map(|name| {
+            let words = context
+                .iter()
+                .cloned()
+                .chain(std::iter::once(name))
+                .chain(rest.iter().take_while(|w| !w.starts_with('-')).cloned())
+                .collect::<Vec<_>>();
+            format!("`{}`", words.join(" "))
+        })
+        .collect::<Vec<_>>();
+
+    if suggestions.is_empty() {
+        return AppError::CommandNotFound(part.to_string());
+    }
+    AppError::CommandNotFound(format!(
+        "{}. {}",
+        part,
+        tr_args(
+            "Did you mean {suggestion}?",
+            &[("suggestion", &suggestions.join(" or "))]
+        )
+    ))
+}
+
 #[allow(clippy::borrowed_box)]
 fn execute_command(
+    cli: &CommandList,
     cmd: &Box<dyn commands::CliCommand>,
     cmd_name: Option<&str>,
     line: &str,
     context: &[String],
-    client: &SyncClient<Authenticated>,
 ) -> Result<(), AppError> {
     debug!("Executing command: {:?} {}", context, cmd_name.unwrap());
-    let tokens = tokenizer::CommandTokenizer::new(line, cmd_name.unwrap())?;
+    let mut tokens = tokenizer::CommandTokenizer::new(line, cmd_name.unwrap(), &cmd.options())?;
+    apply_context_defaults(cmd, cmd_name.unwrap(), &mut tokens)?;
+
+    let verbosity = verbosity_of(cmd.as_ref(), &tokens);
+    set_verbosity(verbosity)?;
+    if verbosity == Verbosity::Quiet {
+        progress::stop_spinner();
+    }
+    let started = Instant::now();
+    let started_at = chrono::Local::now().naive_local();
+    let result = run_command(cli, cmd, cmd_name, line, context, tokens);
+    // A command left running after Ctrl-C shows nothing more.
+    if verbosity >= Verbosity::Verbose && !interrupt::abandoned() {
+        show_diagnostics(started, started_at, verbosity);
+    }
+    result
+}
+
+// The verbosity asked for with `--quiet` or `-v`, `-vv` or `--verbose`.
+fn verbosity_of(cmd: &dyn commands::CliCommand, tokens: &tokenizer::CommandTokenizer) -> Verbosity {
+    let options = cmd.options();
+    let count = |name: &str| {
+        options
+            .iter()
+            .filter(|opt| opt.name == name)
+            .flat_map(|opt| [opt.short_without_dash(), opt.long_without_dashes()])
+            .flatten()
+            .map(|key| tokens.count(&key))
+            .sum::<usize>()
+    };
+    match (count("quiet"), count("verbose")) {
+        (0, 0) => Verbosity::Normal,
+        (_, 0) => Verbosity::Quiet,
+        (_, 1) => Verbosity::Verbose,
+        _ => Verbosity::VeryVerbose,
+    }
+}
+
+// Diagnostics go to stderr, so they are not part of the output of the command.
+fn show_diagnostics(started: Instant, started_at: chrono::NaiveDateTime, verbosity: Verbosity) {
+    if verbosity >= Verbosity::VeryVerbose {
+        for exchange in http_trace::last_exchanges(usize::MAX)
+            .into_iter()
+            .filter(|exchange| exchange.at >= started_at)
+        {
+            eprintln!(
+                "{} {} {} ({} ms)",
+                exchange.method,
+                exchange.url,
+                exchange
+                    .status
+                    .map_or_else(|| "-".to_string(), |status| status.to_string()),
+                exchange.elapsed_ms
+            );
+        }
+    }
+    eprintln!(
+        "{}",
+        tr_args(
+            "Finished in {ms} ms",
+            &[("ms", &started.elapsed().as_millis())]
+        )
+    );
+}
+
+#[allow(clippy::borrowed_box)]
+fn run_command(
+    cli: &CommandList,
+    cmd: &Box<dyn commands::CliCommand>,
+    cmd_name: Option<&str>,
+    line: &str,
+    context: &[String],
+    mut tokens: tokenizer::CommandTokenizer,
+) -> Result<(), AppError> {
+    if wizard::wants_wizard(cmd.as_ref(), &tokens) {
+        let command = context
+            .iter()
+            .cloned()
+            .chain(cmd_name.map(str::to_string))
+            .collect::<Vec<_>>();
+        wizard::run_wizard(cli, cmd.as_ref(), &command, &mut tokens)?;
+    }
     trace!("Tokens: {:?}", tokens);
 
     let options = tokens.get_options();
     if options.contains_key("help") || options.contains_key("h") {
         cmd.help(&cmd_name.unwrap().to_string(), context)
     } else {
-        cmd.execute(client, &tokens)
+        if cmd.has_own_format() {
+            set_format(OutputFormat::Text)?;
+        } else {
+            set_format(output_format(options)?)?;
+        }
+        let cmd_name = cmd_name.unwrap();
+        let key = offline::response_key(context, cmd_name, &tokens);
+        if offline::is_offline() {
+            return execute_offline(cmd, cmd_name, line, &key, &tokens);
+        }
+
+        match session::client() {
+            Some(client) => {
+                if let Err(err) = cmd.execute(&client, &tokens) {
+                    // Without a connection, read commands fall back to their saved output.
+                    if err.is_connection_error() && offline::is_read_command(cmd_name) {
+                        if let Some(response) = offline::saved_response(&key)? {
+                            add_warning(tr("The server can't be reached"))?;
+                            return show_saved_response(&response);
+                        }
+                    }
+                    return Err(err);
+                }
+                copy_if_asked(&tokens)?;
+                // Streamed output has been printed already, and is not saved.
+                let lines = buffered_lines()?;
+                if offline::is_read_command(cmd_name) && !lines.is_empty() {
+                    if let Err(err) = offline::save_response(&key, lines) {
+                        warn!("Failed to save the response for offline use: {}", err);
+                    }
+                }
+                Ok(())
+            }
+            None => {
+                cmd.execute_unauthenticated(&tokens)?;
+                copy_if_asked(&tokens)
+            }
+        }
+    }
+}
+
+// With `--copy`, put the output of the command on the clipboard.
+fn copy_if_asked(tokens: &tokenizer::CommandTokenizer) -> Result<(), AppError> {
+    if tokens.get_options().contains_key("copy") {
+        clipboard::copy_to_clipboard(&primary_output()?)?;
+        add_notice(tr("Copied to the clipboard"))?;
+    }
+    Ok(())
+}
+
+// With `--offline`, read commands show the output saved when they last ran against the
+// server, commands that change the server are refused, and local commands run as usual.
+#[allow(clippy::borrowed_box)]
+fn execute_offline(
+    cmd: &Box<dyn commands::CliCommand>,
+    cmd_name: &str,
+    line: &str,
+    key: &str,
+    tokens: &tokenizer::CommandTokenizer,
+) -> Result<(), AppError> {
+    if history::mutating_command(line).is_some() {
+        return Err(AppError::Offline(line.to_string()));
+    }
+    if !offline::is_read_command(cmd_name) {
+        return cmd.execute_unauthenticated(tokens);
+    }
+
+    let Some(response) = offline::saved_response(key)? else {
+        return Err(AppError::Offline(tr_args(
+            "{command} has not been run online",
+            &[("command", &key)],
+        )));
+    };
+    show_saved_response(&response)
+}
+
+fn show_saved_response(response: &offline::SavedResponse) -> Result<(), AppError> {
+    add_warning(tr_args(
+        "Offline, showing the output from {time}",
+        &[("time", &response.fetched_at.format("%Y-%m-%d %H:%M:%S"))],
+    ))?;
+    append_lines(&response.lines)
+}
+
+// Options default to the class and namespace in use, and creates go into the sandbox
+// namespace if one is active and no namespace was given.
+#[allow(clippy::borrowed_box)]
+fn apply_context_defaults(
+    cmd: &Box<dyn commands::CliCommand>,
+    cmd_name: &str,
+    tokens: &mut tokenizer::CommandTokenizer,
+) -> Result<(), AppError> {
+    let context = session::context();
+    let mut namespace = context.namespace;
+    if namespace.is_none() && matches!(cmd_name, "create" | "apply") {
+        namespace = sandbox::current_sandbox()?.map(|sandbox| sandbox.namespace);
+    }
+
+    set_context_defaults(
+        &cmd.options(),
+        context.class.as_deref(),
+        namespace.as_deref(),
+        tokens,
+    );
+    Ok(())
+}
+
+// Default the `--class` and `--namespace` options that opted in with `context` to the
+// class and namespace in use. Options without it, such as the target of a move, are left
+// alone.
+fn set_context_defaults(
+    options: &[commands::CliOption],
+    class: Option<&str>,
+    namespace: Option<&str>,
+    tokens: &mut tokenizer::CommandTokenizer,
+) {
+    for (long, value) in [("class", class), ("namespace", namespace)] {
+        let Some(value) = value else {
+            continue;
+        };
+        if let Some(option) = options
+            .iter()
+            .find(|o| o.context && o.long_without_dashes().as_deref() == Some(long))
+        {
+            tokens.set_default_option(option.short_without_dash().as_deref(), long, value);
+        }
     }
 }
 
-fn create_editor(cli: &CommandList) -> Result<Editor<&CommandList, FileHistory>, AppError> {
+fn output_format(options: &HashMap<String, String>) -> Result<OutputFormat, AppError> {
+    if options.contains_key("yaml") {
+        return Ok(OutputFormat::Yaml);
+    }
+
+    match options.get("format") {
+        Some(format) => format.parse().map_err(AppError::ParseError),
+        None => Ok(OutputFormat::Text),
+    }
+}
+
+// Print the changes noticed by `notify on` since the last prompt.
+fn print_notices() -> Result<(), AppError> {
+    for notice in watcher::take_notices()? {
+        append_line(format!("* {}", notice))?;
+    }
+    flush_output()
+}
+
+fn create_editor(
+    cli: &CommandList,
+    config: &AppConfig,
+) -> Result<Editor<&CommandList, FileHistory>, AppError> {
     let repl_config = rustyline::Config::builder()
         .history_ignore_space(true)
         .completion_type(rustyline::CompletionType::List)
+        .edit_mode(keybindings::edit_mode(&config.repl.edit_mode)?)
         .build();
 
     let mut rl = Editor::with_config(repl_config)?;
+    for (key, action) in &config.repl.bindings {
+        rl.bind_sequence(
+            keybindings::parse_key(key)?,
+            keybindings::parse_action(action)?,
+        );
+    }
     rl.set_helper(Some(cli));
     rl.load_history(&get_history_file()?)?;
     Ok(rl)
 }
 
-fn login(
-    client: hubuum_client::SyncClient<Unauthenticated>,
-    username: &str,
-    hostname: &str,
-) -> Result<SyncClient<Authenticated>, AppError> {
-    let token = files::get_token_from_tokenfile(hostname, username)?;
-    if let Some(token) = token {
-        debug!("Found existing token, testing validity...");
-        match client.clone().login_with_token(Token { token }) {
-            Ok(client) => return Ok(client.clone()),
-            Err(err) => {
-                add_warning(format!("Error logging in with existing token: {}", err))?;
-                flush_output()?;
-            }
+// Returns whether the command succeeded.
+fn process_line_as_command(cli: &'static CommandList, line: &str) -> Result<bool, AppError> {
+    let original_line = line;
+    recording::record_command(original_line)?;
+    let line = process_filter(line)?;
+    // Every command starts with the normal verbosity, until its options say otherwise.
+    set_verbosity(Verbosity::Normal)?;
+    let mut context = Vec::new();
+    let result = handle_command(cli, &line, &mut context);
+    let succeeded = result.is_ok();
+    let missing_options = matches!(result, Err(AppError::MissingOptions(_)));
+    if let Err(err) = history::record_command(original_line, result.is_ok()) {
+        warn!("Failed to record command in the command log: {}", err);
+    }
+    if let Err(err) = audit::record_audit(&line, result.as_ref().err()) {
+        warn!("Failed to record command in the audit log: {}", err);
+    }
+    // Even a failed command may have changed some of what it was asked to.
+    if let Some(words) = history::mutating_command(&line) {
+        lookup_cache::invalidate_for_command(&words);
+        autocomplete::clear_cache();
+        if let Err(err) = response_cache::purge_response_cache() {
+            warn!("Failed to purge the response cache: {}", err);
+        }
+        if let Err(err) = watcher::absorb_own_changes() {
+            warn!("Failed to update the watched classes: {}", err);
         }
     }
+    let guidance = result
+        .as_ref()
+        .err()
+        .and_then(|err| err.guidance(&shlex::split(&line).unwrap_or_default()));
 
-    let password =
-        rpassword::prompt_password(format!("Password for {} @ {}: ", username, hostname))?;
-    let client = client
-        .clone()
-        .login(Credentials::new(username.to_string(), password))?;
-    debug!("Logged in successfully, saving token...");
-    files::write_token_to_tokenfile(TokenEntry {
-        hostname: hostname.to_string(),
-        username: username.to_string(),
-        token: client.get_token().to_string(),
-    })?;
-    Ok(client)
-}
-
-fn process_line_as_command(
-    cli: &CommandList,
-    line: &str,
-    client: &SyncClient<Authenticated>,
-) -> Result<(), AppError> {
-    let line = process_filter(line)?;
-    let mut context = Vec::new();
-    match handle_command(&cli, &line, &mut context, &client) {
+    match result {
         Ok(_) => {}
         Err(AppError::Quiet) => {}
         Err(AppError::EntityNotFound(entity)) => add_warning(entity.to_string())?,
-        Err(AppError::ApiError(ApiError::HttpWithBody { status, message })) => {
-            add_error(format!("API Error: Status {} - {}", status, message))?
-        }
+        Err(AppError::ApiError(ApiError::HttpWithBody { status, message })) => add_error(format!(
+            "{}: Status {} - {}",
+            tr("API Error"),
+            status,
+            ApiErrorBody::parse(&message).render()
+        ))?,
 
-        Err(err @ AppError::ApiError(_)) => add_error(format!("API Error: {}", err))?,
+        Err(err @ AppError::ApiError(_)) => add_error(format!("{}: {}", tr("API Error"), err))?,
         Err(err) => add_error(err)?,
     }
-    flush_output()
+
+    // Hints are diagnostics, which --quiet leaves out.
+    let quiet = get_verbosity()? == Verbosity::Quiet;
+    if let Some(guidance) = guidance.filter(|_| !quiet) {
+        append_line(format!("{}: {}", tr("Hint"), guidance))?;
+    }
+    if missing_options {
+        if let Some(usage) = command_usage(cli, &line) {
+            append_line(format!("{}: {}", tr("Usage"), usage))?;
+        }
+    }
+    flush_output()?;
+    Ok(succeeded)
 }
 
-fn source_commands_from_file(
-    cli: &CommandList,
-    filename: &str,
-    client: &SyncClient<Authenticated>,
-) -> Result<(), AppError> {
+// The usage line of the command the line runs, if it names one.
+fn command_usage(cli: &CommandList, line: &str) -> Option<String> {
+    let parts = shlex::split(line)?;
+    let mut context = Vec::new();
+    let (Some(cmd), Some(cmd_name)) = find_command(cli, &parts, &mut context).ok()? else {
+        return None;
+    };
+    let command_path = context
+        .iter()
+        .map(String::as_str)
+        .chain(Some(cmd_name))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(cmd.usage(&command_path))
+}
+
+// Run every line of the file, returning whether they all succeeded.
+fn source_commands_from_file(cli: &'static CommandList, filename: &str) -> Result<bool, AppError> {
     use std::io::BufRead;
     let file = std::fs::File::open(filename)?;
     let reader = std::io::BufReader::new(file);
+    let mut succeeded = true;
     for line in reader.lines() {
         let line = line?;
-        process_line_as_command(cli, &line, client)?;
+        succeeded &= process_line_as_command(cli, &line)?;
     }
-    Ok(())
+    Ok(succeeded)
 }
 
-fn main() -> Result<(), AppError> {
-    let file = get_log_file()?;
-    let file = std::fs::File::create(file).expect("Failed to create log file");
-
-    // Set up the tracing subscriber
-    tracing_subscriber::fmt()
-        .with_writer(file)
-        .with_env_filter(EnvFilter::from_default_env())
-        .init();
+// Report the problems in the configuration files, and exit with an error if any are fatal.
+fn check_config(cli_config_path: Option<std::path::PathBuf>) -> ! {
+    let mut failed = false;
+    for file in config_check::config_files(cli_config_path) {
+        match config_check::validate_file(&file) {
+            Ok(problems) => {
+                for problem in problems {
+                    failed |= problem.severity == config_check::Severity::Error;
+                    eprintln!("{}", problem);
+                }
+            }
+            Err(err) => {
+                failed = true;
+                eprintln!("{}: {}", file.display(), err);
+            }
+        }
+    }
+    std::process::exit(if failed { 1 } else { 0 })
+}
 
+fn main() -> Result<(), AppError> {
     let matches = cli::build_cli().get_matches();
     let cli_config_path = cli::get_cli_config_path(&matches);
+    if matches.get_flag("check_config") {
+        check_config(cli_config_path);
+    }
+    if let Some(shell) = matches.get_one::<String>("completions") {
+        let script = completions::completion_script(
+            shell,
+            &cli::build_cli(),
+            &crate::commands::build_repl_commands(),
+        )?;
+        print!("{}", script);
+        return Ok(());
+    }
     let mut config = config::load_config(cli_config_path)?;
     cli::update_config_from_cli(&mut config, &matches);
+    logger::init_logging(&config.log)?;
+    config::set_config(config.clone())?;
+    i18n::set_language(&config.display.language)?;
 
-    let baseurl = hubuum_client::BaseUrl::from_str(&format!(
-        "{}://{}:{}",
-        config.server.protocol, config.server.hostname, config.server.port
-    ))?;
-    let client = hubuum_client::SyncClient::new(baseurl);
+    let client = api::new_client(&config)?;
 
-    let client = login(
-        client,
-        config.server.username.as_str(),
-        config.server.hostname.as_str(),
-    )?;
+    // Without a login we still start, so the configuration can be fixed and `login` retried.
+    if matches.get_flag("offline") {
+        offline::set_offline(true);
+    } else {
+        match auth::login(
+            client,
+            config.server.username.as_str(),
+            config.server.hostname.as_str(),
+            &auth::providers_from_config(&config.auth)?,
+        ) {
+            Ok(client) => {
+                version::warn_if_incompatible(&client)?;
+                session::set_client(client)?;
+            }
+            // Scripts can't log in later, so they fail right away.
+            Err(err) if matches.contains_id("command") || matches.contains_id("source") => {
+                add_error(tr_args("Not logged in: {error}", &[("error", &err)]))?;
+                flush_output()?;
+                std::process::exit(1);
+            }
+            Err(err) => {
+                add_warning(tr_args("Not logged in: {error}", &[("error", &err)]))?;
+                if err.is_connection_error() {
+                    offline::set_offline(true);
+                    add_warning(tr(
+                        "The server can't be reached, working offline until 'login' succeeds",
+                    ))?;
+                }
+                flush_output()?;
+            }
+        }
+    }
 
-    let cli = crate::commands::build_repl_commands(Arc::new(client.clone()));
-    let mut rl = create_editor(&cli)?;
+    // Cancellable commands run on threads of their own, which may outlive the line.
+    let cli: &'static CommandList = Box::leak(Box::new(crate::commands::build_repl_commands()));
+    let mut rl = create_editor(cli, &config)?;
 
+    // Scripts need to know when a command failed.
     if let Some(command) = matches.get_one::<String>("command") {
-        process_line_as_command(&cli, &command, &client)?;
+        if !process_line_as_command(cli, command)? {
+            std::process::exit(1);
+        }
         return Ok(());
     }
 
     if let Some(filename) = matches.get_one::<String>("source") {
-        source_commands_from_file(&cli, &filename, &client)?;
+        if !source_commands_from_file(cli, &filename)? {
+            std::process::exit(1);
+        }
         return Ok(());
     }
 
+    session::set_interactive(true);
+    autocomplete::start_prefetch();
+    interrupt::install_handler();
+    let mut last_succeeded = true;
+    let mut history_file = get_history_file()?;
     loop {
-        match rl.readline(&prompt(&config)) {
+        print_notices()?;
+        // `login` may have switched to another server, and with it to another history.
+        let current_history_file = get_history_file()?;
+        if current_history_file != history_file {
+            rl.clear_history()?;
+            rl.load_history(&current_history_file)?;
+            history_file = current_history_file;
+        }
+        // `login` may have replaced both the configuration and the client.
+        let prompt = prompt::render_prompt(
+            &config::get_config()?,
+            session::is_logged_in(),
+            last_succeeded,
+        )?;
+        match rl.readline(&prompt) {
             Ok(line) => {
-                rl.add_history_entry(line.as_str())?;
-                rl.save_history(&get_history_file()?)?;
-                process_line_as_command(&cli, &line, &client)?;
+                // `!!` and `!n` run a command from the history, and it is that command
+                // that goes into the history.
+                let line = match history::expand_history(&line) {
+                    Ok(expanded) if expanded != line => {
+                        append_line(&expanded)?;
+                        flush_output()?;
+                        expanded
+                    }
+                    Ok(line) => line,
+                    Err(err) => {
+                        add_error(err)?;
+                        flush_output()?;
+                        last_succeeded = false;
+                        continue;
+                    }
+                };
+                // Lines with secrets are left out, as the history is saved to disk and
+                // running them again with the secret redacted would do the wrong thing.
+                if history::redact_secrets(&line) == line {
+                    rl.add_history_entry(line.as_str())?;
+                    rl.save_history(&history_file)?;
+                }
+                // A Ctrl-C left over from the previous command must not stop this one.
+                interrupt::take_interrupt();
+                last_succeeded = process_line_as_command(cli, &line)?;
             }
             Err(rustyline::error::ReadlineError::Interrupted) => continue,
             Err(rustyline::error::ReadlineError::Eof) => break,
@@ -260,3 +733,63 @@ fn main() -> Result<(), AppError> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::CliOption;
+
+    fn option(short: &str, long: &str, context: bool) -> CliOption {
+        CliOption {
+            name: long.to_string(),
+            short: Some(format!("-{}", short)),
+            long: Some(format!("--{}", long)),
+            flag: false,
+            help: String::new(),
+            field_type: std::any::TypeId::of::<String>(),
+            field_type_help: "string".to_string(),
+            required: false,
+            autocomplete: None,
+            completes: None,
+            values: Vec::new(),
+            path: false,
+            secret: false,
+            context,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            default: None,
+        }
+    }
+
+    fn defaulted(line: &str, options: &[CliOption]) -> HashMap<String, String> {
+        let mut tokens = tokenizer::CommandTokenizer::new(line, "modify", options).unwrap();
+        set_context_defaults(options, Some("Host"), Some("prod"), &mut tokens);
+        tokens.get_options().clone()
+    }
+
+    #[test]
+    fn test_context_fills_opted_in_options() {
+        let options = [option("c", "class", true), option("N", "namespace", true)];
+        let tokens = defaulted("modify -n web01", &options);
+        assert_eq!(tokens.get("class").map(String::as_str), Some("Host"));
+        assert_eq!(tokens.get("namespace").map(String::as_str), Some("prod"));
+    }
+
+    #[test]
+    fn test_context_leaves_other_options_alone() {
+        // A namespace that moves the entity, like that of `object modify`.
+        let options = [option("c", "class", true), option("N", "namespace", false)];
+        let tokens = defaulted("modify -n web01", &options);
+        assert_eq!(tokens.get("class").map(String::as_str), Some("Host"));
+        assert_eq!(tokens.get("namespace"), None);
+    }
+
+    #[test]
+    fn test_context_does_not_override_given_options() {
+        let options = [option("c", "class", true), option("N", "namespace", true)];
+        let tokens = defaulted("modify -n web01 -c Router --namespace lab", &options);
+        assert_eq!(tokens.get("c").map(String::as_str), Some("Router"));
+        assert_eq!(tokens.get("class"), None);
+        assert_eq!(tokens.get("namespace").map(String::as_str), Some("lab"));
+    }
+}
@@ -0,0 +1,504 @@
+// Declarative manifests: a YAML file listing namespaces, classes and objects as they should
+// be. The manifest is compared with the server to get the changes that make the server match
+// it, which `apply` makes and `plan` shows.
+//
+// namespaces:
+//   - name: infra
+//     description: Infrastructure
+//     owner: admins
+// classes:
+//   - name: Host
+//     namespace: infra
+//     schema: { "type": "object" }
+// objects:
+//   - name: web01
+//     class: Host
+//     namespace: infra
+//     data: { "ip": "10.0.0.1" }
+//
+// Fields that are left out are not changed on the server.
+use std::collections::{HashMap, HashSet};
+
+use hubuum_client::{
+    Authenticated, Class, ClassPatch, ClassPost, Namespace, NamespacePatch, NamespacePost, Object,
+    ObjectPatch, ObjectPost, SyncClient,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::retry_api;
+use crate::errors::AppError;
+use crate::plan::Plan;
+use crate::snapshots::snapshot_before;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct NamespaceSpec {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// The group owning the namespace, only used when the namespace is created.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ClassSpec {
+    pub name: String,
+    pub namespace: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validate: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ObjectSpec {
+    pub name: String,
+    pub class: String,
+    pub namespace: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+#[derive(Debug, Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    #[serde(default)]
+    pub namespaces: Vec<NamespaceSpec>,
+    #[serde(default)]
+    pub classes: Vec<ClassSpec>,
+    #[serde(default)]
+    pub objects: Vec<ObjectSpec>,
+}
+
+impl Manifest {
+    pub fn from_file(path: &str) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path)?;
+        let manifest: Manifest = serde_yaml::from_str(&content)
+            .map_err(|err| AppError::ParseError(format!("{}: {}", path, err)))?;
+        manifest.check_duplicates()?;
+        Ok(manifest)
+    }
+
+    fn check_duplicates(&self) -> Result<(), AppError> {
+        let names = self
+            .namespaces
+            .iter()
+            .map(|n| format!("namespace {}", n.name))
+            .chain(self.classes.iter().map(|c| format!("class {}", c.name)))
+            .chain(
+                self.objects
+                    .iter()
+                    .map(|o| format!("object {}/{}", o.class, o.name)),
+            );
+
+        let mut seen = HashSet::new();
+        for name in names {
+            if !seen.insert(name.clone()) {
+                return Err(AppError::ParseError(format!(
+                    "The manifest lists {} more than once",
+                    name
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+// A change to make, with the names of namespaces and classes resolved when it is made, as
+// they may be created by an earlier change.
+#[derive(Debug)]
+enum Change {
+    CreateNamespace(NamespaceSpec),
+    UpdateNamespace(i32, NamespaceSpec),
+    CreateClass(ClassSpec),
+    UpdateClass(i32, ClassSpec),
+    CreateObject(ObjectSpec),
+    UpdateObject(Object, ObjectSpec),
+    DeleteObject(String, Object),
+    DeleteClass(i32, String),
+}
+
+impl Change {
+    fn label(&self) -> String {
+        match self {
+            Change::CreateNamespace(spec) | Change::UpdateNamespace(_, spec) => {
+                format!("namespace {}", spec.name)
+            }
+            Change::CreateClass(spec) | Change::UpdateClass(_, spec) => {
+                format!("class {}", spec.name)
+            }
+            Change::CreateObject(spec) | Change::UpdateObject(_, spec) => {
+                format!("object {}/{}", spec.class, spec.name)
+            }
+            Change::DeleteObject(class, object) => format!("object {}/{}", class, object.name),
+            Change::DeleteClass(_, name) => format!("class {}", name),
+        }
+    }
+}
+
+/// The changes that make the server match a manifest, and the plan showing them.
+#[derive(Debug)]
+pub struct ManifestChanges {
+    pub plan: Plan,
+    changes: Vec<Change>,
+    // Objects deleted by the server along with a pruned class.
+    cascaded: usize,
+}
+
+// The fields of `current` that are also in `wanted`, so fields left out of the manifest
+// are not shown as removed.
+fn only_fields_in(current: Value, wanted: &Value) -> Value {
+    match (current, wanted) {
+        (Value::Object(current), Value::Object(wanted)) => Value::Object(
+            current
+                .into_iter()
+                .filter(|(key, _)| wanted.contains_key(key))
+                .collect(),
+        ),
+        (current, _) => current,
+    }
+}
+
+fn namespace_view(namespace: &Namespace) -> NamespaceSpec {
+    NamespaceSpec {
+        name: namespace.name.clone(),
+        description: Some(namespace.description.clone()),
+        owner: None,
+    }
+}
+
+fn class_view(class: &Class) -> ClassSpec {
+    ClassSpec {
+        name: class.name.clone(),
+        namespace: class.namespace.name.clone(),
+        description: Some(class.description.clone()),
+        schema: class.json_schema.clone(),
+        validate: class.validate_schema,
+    }
+}
+
+fn object_view(object: &Object, class: &str, namespaces: &HashMap<i32, String>) -> ObjectSpec {
+    ObjectSpec {
+        name: object.name.clone(),
+        class: class.to_string(),
+        namespace: namespaces
+            .get(&object.namespace_id)
+            .cloned()
+            .unwrap_or_default(),
+        description: Some(object.description.clone()),
+        data: object.data.clone(),
+    }
+}
+
+/// Compare `manifest` with the server. With `prune`, entities the manifest manages but
+/// does not list are deleted: objects in its classes, and classes in its namespaces.
+pub fn manifest_changes(
+    client: &SyncClient<Authenticated>,
+    manifest: &Manifest,
+    prune: bool,
+) -> Result<ManifestChanges, AppError> {
+    let namespaces = retry_api(|| client.namespaces().find().execute())?;
+    let classes = retry_api(|| client.classes().find().execute())?;
+    let namespace_names = namespaces
+        .iter()
+        .map(|n| (n.id, n.name.clone()))
+        .collect::<HashMap<_, _>>();
+
+    let mut plan = Plan::new();
+    let mut changes = Vec::new();
+    let mut cascaded = 0;
+
+    for spec in &manifest.namespaces {
+        match namespaces.iter().find(|n| n.name == spec.name) {
+            Some(namespace) => {
+                // The owner is only used to create the namespace.
+                let wanted = serde_json::to_value(NamespaceSpec {
+                    owner: None,
+                    ..spec.clone()
+                })?;
+                let current =
+                    only_fields_in(serde_json::to_value(namespace_view(namespace))?, &wanted);
+                if current != wanted {
+                    plan.update("namespace", &spec.name, &current, &wanted)?;
+                    changes.push(Change::UpdateNamespace(namespace.id, spec.clone()));
+                }
+            }
+            None => {
+                if spec.owner.is_none() {
+                    return Err(AppError::ParseError(format!(
+                        "Namespace {} does not exist, and needs an owner to be created",
+                        spec.name
+                    )));
+                }
+                plan.create("namespace", &spec.name, spec)?;
+                changes.push(Change::CreateNamespace(spec.clone()));
+            }
+        }
+    }
+
+    for spec in &manifest.classes {
+        match classes.iter().find(|c| c.name == spec.name) {
+            Some(class) => {
+                let wanted = serde_json::to_value(spec)?;
+                let current = only_fields_in(serde_json::to_value(class_view(class))?, &wanted);
+                if current != wanted {
+                    plan.update("class", &spec.name, &current, &wanted)?;
+                    changes.push(Change::UpdateClass(class.id, spec.clone()));
+                }
+            }
+            None => {
+                plan.create("class", &spec.name, spec)?;
+                changes.push(Change::CreateClass(spec.clone()));
+            }
+        }
+    }
+
+    // The objects of each class in the manifest, fetched once per class.
+    let mut objects = HashMap::<String, Vec<Object>>::new();
+    let class_names = manifest
+        .classes
+        .iter()
+        .map(|c| &c.name)
+        .chain(manifest.objects.iter().map(|o| &o.class));
+    for name in class_names {
+        if objects.contains_key(name) {
+            continue;
+        }
+        let existing = match classes.iter().find(|c| &c.name == name) {
+            Some(class) => retry_api(|| client.objects(class.id).find().execute())?,
+            None => Vec::new(),
+        };
+        objects.insert(name.clone(), existing);
+    }
+
+    for spec in &manifest.objects {
+        let name = format!("{}/{}", spec.class, spec.name);
+        match objects[&spec.class].iter().find(|o| o.name == spec.name) {
+            Some(object) => {
+                let wanted = serde_json::to_value(spec)?;
+                let current = only_fields_in(
+                    serde_json::to_value(object_view(object, &spec.class, &namespace_names))?,
+                    &wanted,
+                );
+                if current != wanted {
+                    plan.update("object", &name, &current, &wanted)?;
+                    changes.push(Change::UpdateObject(object.clone(), spec.clone()));
+                }
+            }
+            None => {
+                plan.create("object", &name, spec)?;
+                changes.push(Change::CreateObject(spec.clone()));
+            }
+        }
+    }
+
+    if prune {
+        // Objects in the classes of the manifest that it does not list, and classes in its
+        // namespaces that it does not list. Objects go first, as they are in the classes.
+        let mut class_names = objects.keys().collect::<Vec<_>>();
+        class_names.sort();
+        for class_name in class_names {
+            for object in &objects[class_name] {
+                let listed = manifest
+                    .objects
+                    .iter()
+                    .any(|o| &o.class == class_name && o.name == object.name);
+                if !listed {
+                    plan.delete("object", &format!("{}/{}", class_name, object.name));
+                    changes.push(Change::DeleteObject(class_name.clone(), object.clone()));
+                }
+            }
+        }
+
+        for class in &classes {
+            let in_namespace = manifest
+                .namespaces
+                .iter()
+                .any(|n| n.name == class.namespace.name);
+            let listed = manifest.classes.iter().any(|c| c.name == class.name);
+            if in_namespace && !listed {
+                plan.delete("class", &class.name);
+                changes.push(Change::DeleteClass(class.id, class.name.clone()));
+                // The server deletes the objects of the class with it, so they are shown
+                // as well.
+                let mut objects = retry_api(|| client.objects(class.id).find().execute())?;
+                objects.sort_by(|a, b| a.name.cmp(&b.name));
+                for object in &objects {
+                    plan.delete("object", &format!("{}/{}", class.name, object.name));
+                }
+                cascaded += objects.len();
+            }
+        }
+    }
+
+    Ok(ManifestChanges {
+        plan,
+        changes,
+        cascaded,
+    })
+}
+
+// Namespace and class ids by name, including those created while applying.
+struct Resolver<'a> {
+    client: &'a SyncClient<Authenticated>,
+    namespaces: HashMap<String, i32>,
+    classes: HashMap<String, i32>,
+}
+
+impl<'a> Resolver<'a> {
+    fn new(client: &'a SyncClient<Authenticated>) -> Result<Self, AppError> {
+        Ok(Resolver {
+            client,
+            namespaces: client
+                .namespaces()
+                .find()
+                .execute()?
+                .into_iter()
+                .map(|n| (n.name, n.id))
+                .collect(),
+            classes: client
+                .classes()
+                .find()
+                .execute()?
+                .into_iter()
+                .map(|c| (c.name, c.id))
+                .collect(),
+        })
+    }
+
+    fn namespace(&self, name: &str) -> Result<i32, AppError> {
+        self.namespaces
+            .get(name)
+            .copied()
+            .ok_or_else(|| AppError::EntityNotFound(format!("namespace {}", name)))
+    }
+
+    fn class(&self, name: &str) -> Result<i32, AppError> {
+        self.classes
+            .get(name)
+            .copied()
+            .ok_or_else(|| AppError::EntityNotFound(format!("class {}", name)))
+    }
+
+    fn apply(&mut self, change: &Change) -> Result<(), AppError> {
+        let client = self.client;
+        match change {
+            Change::CreateNamespace(spec) => {
+                let owner = spec.owner.clone().unwrap_or_default();
+                let group = client
+                    .groups()
+                    .find()
+                    .add_filter_name_exact(owner)
+                    .execute_expecting_single_result()?;
+                let namespace = client.namespaces().create(NamespacePost {
+                    name: spec.name.clone(),
+                    description: spec.description.clone().unwrap_or_default(),
+                    group_id: group.id,
+                })?;
+                self.namespaces.insert(namespace.name, namespace.id);
+            }
+            Change::UpdateNamespace(id, spec) => {
+                let patch = NamespacePatch {
+                    description: spec.description.clone(),
+                    ..Default::default()
+                };
+                client.namespaces().update(*id, patch)?;
+            }
+            Change::CreateClass(spec) => {
+                let class = client.classes().create(ClassPost {
+                    name: spec.name.clone(),
+                    namespace_id: self.namespace(&spec.namespace)?,
+                    description: spec.description.clone().unwrap_or_default(),
+                    json_schema: spec.schema.clone(),
+                    validate_schema: spec.validate,
+                })?;
+                self.classes.insert(class.name, class.id);
+            }
+            Change::UpdateClass(id, spec) => {
+                let patch = ClassPatch {
+                    namespace_id: Some(self.namespace(&spec.namespace)?),
+                    description: spec.description.clone(),
+                    json_schema: spec.schema.clone(),
+                    validate_schema: spec.validate,
+                    ..Default::default()
+                };
+                client.classes().update(*id, patch)?;
+            }
+            Change::CreateObject(spec) => {
+                let class_id = self.class(&spec.class)?;
+                client.objects(class_id).create(ObjectPost {
+                    name: spec.name.clone(),
+                    hubuum_class_id: class_id,
+                    namespace_id: self.namespace(&spec.namespace)?,
+                    description: spec.description.clone().unwrap_or_default(),
+                    data: spec.data.clone(),
+                })?;
+            }
+            Change::UpdateObject(object, spec) => {
+                let patch = ObjectPatch {
+                    namespace_id: Some(self.namespace(&spec.namespace)?),
+                    description: spec.description.clone(),
+                    data: spec.data.clone(),
+                    ..Default::default()
+                };
+                snapshot_before(client, &spec.class, object, "apply")?;
+                client
+                    .objects(self.class(&spec.class)?)
+                    .update(object.id, patch)?;
+            }
+            Change::DeleteObject(class, object) => {
+                snapshot_before(client, class, object, "apply")?;
+                client.objects(object.hubuum_class_id).delete(object.id)?;
+            }
+            Change::DeleteClass(id, _) => {
+                client.classes().delete(*id)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ManifestChanges {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// The number of objects that are deleted along with the classes pruned.
+    pub fn cascaded(&self) -> usize {
+        self.cascaded
+    }
+
+    /// Make the changes in order, calling `done` after each. A change that fails is reported
+    /// in the returned list and does not stop the rest, though changes depending on it (e.g.
+    /// objects in a class that could not be created) will fail as well.
+    pub fn apply<F>(
+        &self,
+        client: &SyncClient<Authenticated>,
+        mut done: F,
+    ) -> Result<Vec<String>, AppError>
+    where
+        F: FnMut(),
+    {
+        let mut resolver = Resolver::new(client)?;
+        let mut failures = Vec::new();
+        for change in &self.changes {
+            if let Err(err) = resolver.apply(change) {
+                failures.push(format!("{}: {}", change.label(), err));
+            }
+            done();
+        }
+        Ok(failures)
+    }
+}
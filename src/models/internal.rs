@@ -4,8 +4,10 @@ use std::{fmt, str::FromStr};
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub enum Protocol {
+    #[serde(alias = "http")]
     Http,
     #[default]
+    #[serde(alias = "https")]
     Https,
 }
 
@@ -36,9 +38,134 @@ impl fmt::Display for Protocol {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json,
+    Jsonl,
+    Yaml,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" | "ndjson" => Ok(OutputFormat::Jsonl),
+            "yaml" => Ok(OutputFormat::Yaml),
+            _ => Err(format!(
+                "Invalid output format: {}. Use 'text', 'json', 'jsonl' or 'yaml'.",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Json => write!(f, "json"),
+            OutputFormat::Jsonl => write!(f, "jsonl"),
+            OutputFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+/// A duration given as a number with a unit, e.g. `30s`, `5m`, `2h` or `1d`. A number
+/// without a unit is in seconds. Durations are used as whole seconds, so shorter ones are
+/// refused rather than becoming zero.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct HumanDuration(pub std::time::Duration);
+
+impl HumanDuration {
+    pub fn as_secs(&self) -> u64 {
+        self.0.as_secs()
+    }
+}
+
+impl FromStr for HumanDuration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = split_unit(s);
+        let number = number
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid duration: {}. Use e.g. '30s', '5m' or '2h'.", s))?;
+        let seconds = match unit.as_str() {
+            "" | "s" => 1,
+            "m" => 60,
+            "h" => 60 * 60,
+            "d" => 24 * 60 * 60,
+            _ => {
+                return Err(format!(
+                    "Invalid duration unit: {}. Use s, m, h or d.",
+                    unit
+                ))
+            }
+        };
+        if number == 0 {
+            return Err(format!("Duration must be at least 1s: {}", s));
+        }
+        number
+            .checked_mul(seconds)
+            .map(|seconds| HumanDuration(std::time::Duration::from_secs(seconds)))
+            .ok_or_else(|| format!("Duration is too long: {}", s))
+    }
+}
+
+/// A size in bytes given as a number with a unit, e.g. `512KB`, `10MB` or `1GiB`. KB, MB
+/// and GB are powers of 1000, KiB, MiB and GiB powers of 1024. A number without a unit is
+/// in bytes.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (number, unit) = split_unit(s);
+        let number = number
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid size: {}. Use e.g. '512KB' or '10MB'.", s))?;
+        let multiplier: u64 = match unit.as_str() {
+            "" | "b" => 1,
+            "kb" => 1000,
+            "mb" => 1000 * 1000,
+            "gb" => 1000 * 1000 * 1000,
+            "kib" => 1 << 10,
+            "mib" => 1 << 20,
+            "gib" => 1 << 30,
+            _ => {
+                return Err(format!(
+                    "Invalid size unit: {}. Use B, KB, MB, GB, KiB, MiB or GiB.",
+                    unit
+                ))
+            }
+        };
+        number
+            .checked_mul(multiplier)
+            .map(ByteSize)
+            .ok_or_else(|| format!("Size is too large: {}", s))
+    }
+}
+
+// Split e.g. "10MB" into "10" and "mb".
+fn split_unit(s: &str) -> (&str, String) {
+    let s = s.trim();
+    let at = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    (&s[..at], s[at..].trim().to_lowercase())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TokenEntry {
     pub hostname: String,
     pub username: String,
     pub token: String,
+    // Missing for tokens saved by older versions.
+    #[serde(default)]
+    pub issued_at: Option<chrono::NaiveDateTime>,
 }
@@ -1,4 +1,4 @@
 pub mod internal;
 pub mod responses;
 
-pub use internal::{Protocol, TokenEntry};
+pub use internal::{ByteSize, HumanDuration, OutputFormat, Protocol, TokenEntry};
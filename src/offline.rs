@@ -0,0 +1,135 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, UNIX_EPOCH};
+
+use chrono::{Local, NaiveDateTime};
+use log::debug;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{get_config, OfflineConfig};
+use crate::errors::AppError;
+use crate::files::{get_offline_dir, hashed_file_name};
+use crate::tokenizer::CommandTokenizer;
+
+// Set by `--offline`, or when the server can't be reached at startup, after which nothing
+// is sent to the server until `login` succeeds.
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
+// The commands whose output is saved, so it can be shown again when offline.
+const READ_COMMANDS: &[&str] = &["info", "list"];
+
+/// The output of a read command, as last fetched from the server.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SavedResponse {
+    pub fetched_at: NaiveDateTime,
+    pub lines: Vec<String>,
+}
+
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+pub fn is_read_command(cmd_name: &str) -> bool {
+    READ_COMMANDS.contains(&cmd_name)
+}
+
+/// The key a response is saved under: the command path, the positionals and the options
+/// sorted by name, so `object list -c Host -n web` and `object list -n web -c Host` (or
+/// `list -n web` with `use class Host`) share a response.
+pub fn response_key(context: &[String], cmd_name: &str, tokens: &CommandTokenizer) -> String {
+    let mut options = tokens
+        .get_options()
+        .iter()
+        .map(|(key, value)| format!("--{}={}", key, value))
+        .collect::<Vec<_>>();
+    options.sort();
+
+    context
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once(cmd_name))
+        .chain(tokens.get_positionals().iter().map(String::as_str))
+        .chain(options.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// A saved response, in a file of its own named by the hash of its key.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedEntry {
+    key: String,
+    #[serde(flatten)]
+    response: SavedResponse,
+}
+
+fn entries() -> Result<Vec<(PathBuf, std::fs::Metadata)>, AppError> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(get_offline_dir()?)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            entries.push((entry.path(), metadata));
+        }
+    }
+    Ok(entries)
+}
+
+// Remove saved output older than `offline.max_age`, and then the oldest until the rest fit
+// in `offline.max_size` bytes.
+fn evict(config: &OfflineConfig) -> Result<(), AppError> {
+    let max_age = Duration::from_secs(config.max_age);
+    let mut entries = entries()?;
+    entries.sort_by_key(|(_, metadata)| metadata.modified().unwrap_or(UNIX_EPOCH));
+
+    let mut total = entries.iter().map(|(_, m)| m.len()).sum::<u64>();
+    for (path, metadata) in entries {
+        let expired = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > max_age);
+        if !expired && total <= config.max_size {
+            break;
+        }
+        debug!("Removing saved output {}", path.display());
+        std::fs::remove_file(&path)?;
+        total -= metadata.len();
+    }
+    Ok(())
+}
+
+/// Save the output of a read command for the user and server in use, replacing the
+/// previous one.
+pub fn save_response(key: &str, lines: Vec<String>) -> Result<(), AppError> {
+    let entry = SavedEntry {
+        key: key.to_string(),
+        response: SavedResponse {
+            fetched_at: Local::now().naive_local(),
+            lines,
+        },
+    };
+    let file = get_offline_dir()?.join(hashed_file_name(key));
+    std::fs::write(file, serde_json::to_string(&entry)?)?;
+    evict(&get_config()?.offline)
+}
+
+/// The saved output of a read command for the user and server in use, if it has been run
+/// within `offline.max_age`.
+pub fn saved_response(key: &str) -> Result<Option<SavedResponse>, AppError> {
+    let file = get_offline_dir()?.join(hashed_file_name(key));
+    let Ok(content) = std::fs::read_to_string(file) else {
+        return Ok(None);
+    };
+    let Ok(entry) = serde_json::from_str::<SavedEntry>(&content) else {
+        return Ok(None);
+    };
+    let max_age = chrono::Duration::seconds(get_config()?.offline.max_age as i64);
+    if entry.key != key || Local::now().naive_local() - entry.response.fetched_at > max_age {
+        return Ok(None);
+    }
+    Ok(Some(entry.response))
+}
@@ -9,14 +9,43 @@ use std::sync::Mutex;
 use log::debug;
 
 use crate::errors::AppError;
+use crate::i18n::tr;
+use crate::interrupt::abandoned;
+use crate::models::OutputFormat;
+use crate::progress::{stop_spinner, suspend};
+use crate::recording::record_output;
 
 static OUTPUT_BUFFER: Lazy<Mutex<OutputBuffer>> = Lazy::new(|| Mutex::new(OutputBuffer::new()));
 
+/// How much diagnostic output the running command shows, from `--quiet` and `-v`/`-vv`.
+/// The output of the command and its errors are always shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum Verbosity {
+    /// No warnings, hints, notices or progress.
+    Quiet,
+    #[default]
+    Normal,
+    /// Also how long the command took.
+    Verbose,
+    /// Also the requests the command sent.
+    VeryVerbose,
+}
+
+/// What secrets are replaced by when lines are written to disk.
+pub const REDACTED: &str = "********";
+
 pub struct OutputBuffer {
     lines: Vec<String>,
     filter: Option<(Regex, bool)>,
+    format: OutputFormat,
     warnings: Vec<String>,
     errors: Vec<String>,
+    // Shown on stderr after the output, so they do not end up in piped data.
+    notices: Vec<String>,
+    primary: Option<String>,
+    // Values shown to the user that are not written to the transcript.
+    secrets: Vec<String>,
+    verbosity: Verbosity,
 }
 
 impl OutputBuffer {
@@ -24,8 +53,13 @@ impl OutputBuffer {
         OutputBuffer {
             lines: Vec::new(),
             filter: None,
+            format: OutputFormat::default(),
             warnings: Vec::new(),
             errors: Vec::new(),
+            notices: Vec::new(),
+            primary: None,
+            secrets: Vec::new(),
+            verbosity: Verbosity::default(),
         }
     }
 
@@ -37,6 +71,10 @@ impl OutputBuffer {
         self.errors.push(message);
     }
 
+    fn add_notice(&mut self, message: String) {
+        self.notices.push(message);
+    }
+
     fn append_line(&mut self, line: String) {
         self.lines.push(line);
     }
@@ -52,16 +90,30 @@ impl OutputBuffer {
         self.filter = None;
     }
 
-    fn flush(&mut self) {
+    fn set_format(&mut self, format: OutputFormat) {
+        debug!("Setting output format: {}", format);
+        self.format = format;
+    }
+
+    // Print the buffer, returning what was printed (without colors) for the transcript.
+    fn flush(&mut self) -> Vec<String> {
         debug!("Flushing output buffer ({} lines)", self.lines.len());
+        let mut printed = Vec::new();
 
+        if self.verbosity == Verbosity::Quiet {
+            self.warnings.clear();
+        }
         for warning in &self.warnings {
-            println!("{}", format!("Warning: {}", warning).yellow());
+            let line = format!("{}: {}", tr("Warning"), warning);
+            println!("{}", line.yellow());
+            printed.push(line);
         }
         self.warnings.clear();
 
         for error in &self.errors {
-            println!("{}", format!("Error: {}", error).red());
+            let line = format!("{}: {}", tr("Error"), error);
+            println!("{}", line.red());
+            printed.push(line);
         }
         self.errors.clear();
 
@@ -74,14 +126,36 @@ impl OutputBuffer {
                 let matches = regex.is_match(line);
                 if matches != *invert {
                     println!("{}", line);
+                    printed.push(line.clone());
                 }
             }
         } else {
             for line in &self.lines {
                 println!("{}", line);
             }
+            printed.append(&mut self.lines);
         }
         self.lines.clear();
+
+        if self.verbosity == Verbosity::Quiet {
+            self.notices.clear();
+        }
+        for notice in &self.notices {
+            eprintln!("{}", notice);
+            printed.push(notice.clone());
+        }
+        self.notices.clear();
+
+        self.primary = None;
+        for secret in std::mem::take(&mut self.secrets)
+            .into_iter()
+            .filter(|secret| !secret.is_empty())
+        {
+            for line in printed.iter_mut() {
+                *line = line.replace(&secret, REDACTED);
+            }
+        }
+        printed
     }
 }
 
@@ -101,6 +175,9 @@ impl OutputBuffer {
 ///
 ///  - OutputError::LockError if the output buffer cannot be locked.
 pub fn add_warning<T: Display>(message: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     OUTPUT_BUFFER
         .lock()
         .map_err(|_| AppError::LockError)?
@@ -124,6 +201,9 @@ pub fn add_warning<T: Display>(message: T) -> Result<(), AppError> {
 ///
 ///  - OutputError::LockError if the output buffer cannot be locked.
 pub fn add_error<T: Display>(message: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     OUTPUT_BUFFER
         .lock()
         .map_err(|_| AppError::LockError)?
@@ -131,6 +211,19 @@ pub fn add_error<T: Display>(message: T) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Add a notice about what the command did, e.g. that its output was copied, to the
+/// output buffer. Notices are printed to stderr after the output, unless `--quiet`.
+pub fn add_notice<T: Display>(message: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
+    OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .add_notice(message.to_string());
+    Ok(())
+}
+
 /// Append a line to the output buffer.
 ///
 /// This function appends the provided line to the output buffer.
@@ -147,6 +240,9 @@ pub fn add_error<T: Display>(message: T) -> Result<(), AppError> {
 ///
 ///  - OutputError::LockError if the output buffer cannot be locked.
 pub fn append_line<T: Display>(line: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     OUTPUT_BUFFER
         .lock()
         .map_err(|_| AppError::LockError)?
@@ -170,8 +266,10 @@ pub fn append_line<T: Display>(line: T) -> Result<(), AppError> {
 /// ## Errors
 ///
 ///  - OutputError::LockError if the output buffer cannot be locked.
-#[allow(dead_code)]
 pub fn append_lines<T: Display>(lines: &[T]) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     let mut buffer = OUTPUT_BUFFER.lock().map_err(|_| AppError::LockError)?;
     for line in lines {
         buffer.append_line(line.to_string());
@@ -199,6 +297,9 @@ pub fn append_lines<T: Display>(lines: &[T]) -> Result<(), AppError> {
 ///  - OutputError::LockError if the output buffer cannot be locked.
 #[allow(dead_code)]
 pub fn append_debug<T: std::fmt::Debug>(value: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     let mut debug_output = String::new();
     write!(&mut debug_output, "{:#?}", value).map_err(|_| AppError::FormatError)?;
 
@@ -211,8 +312,10 @@ pub fn append_debug<T: std::fmt::Debug>(value: T) -> Result<(), AppError> {
     Ok(())
 }
 
-#[allow(dead_code)]
 pub fn append_json<T: Serialize>(value: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     let json_output = serde_json::to_string_pretty(&value).map_err(|_| AppError::FormatError)?;
 
     let mut output_buffer = OUTPUT_BUFFER.lock().map_err(|_| AppError::LockError)?;
@@ -224,6 +327,35 @@ pub fn append_json<T: Serialize>(value: T) -> Result<(), AppError> {
     Ok(())
 }
 
+/// Append a value to the output buffer as a single line of compact JSON.
+///
+/// This is used for the JSON Lines output format, where every entity is emitted as
+/// one JSON document per line.
+///
+/// ## Errors
+///
+///  - OutputError::FormatError if the value cannot be serialized.
+///  - OutputError::LockError if the output buffer cannot be locked.
+pub fn append_json_line<T: Serialize>(value: T) -> Result<(), AppError> {
+    let json_output = serde_json::to_string(&value).map_err(|_| AppError::FormatError)?;
+    append_line(json_output)
+}
+
+pub fn append_yaml<T: Serialize>(value: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
+    let yaml_output = serde_yaml::to_string(&value).map_err(|_| AppError::FormatError)?;
+
+    let mut output_buffer = OUTPUT_BUFFER.lock().map_err(|_| AppError::LockError)?;
+
+    for line in yaml_output.lines() {
+        output_buffer.append_line(line.to_string());
+    }
+
+    Ok(())
+}
+
 pub fn append_key_value<K: Display, V: Display>(
     key: K,
     value: V,
@@ -233,6 +365,53 @@ pub fn append_key_value<K: Display, V: Display>(
     append_line(line)
 }
 
+/// The lines in the output buffer, without warnings and errors, before any filter is
+/// applied.
+pub fn buffered_lines() -> Result<Vec<String>, AppError> {
+    Ok(OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .lines
+        .clone())
+}
+
+/// Mark a value as the primary output of the command, which `--copy` puts on the clipboard
+/// instead of the whole output, e.g. the generated password of a new user.
+pub fn set_primary_output<T: Display>(value: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
+    OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .primary = Some(value.to_string());
+    Ok(())
+}
+
+/// Mark a value in the output as a secret, e.g. a generated password. It is shown, but
+/// replaced by [`REDACTED`] in the transcript.
+pub fn mark_secret<T: Display>(value: T) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
+    OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .secrets
+        .push(value.to_string());
+    Ok(())
+}
+
+/// The primary output of the command if it set one, or else the lines in the output
+/// buffer, before any filter is applied.
+pub fn primary_output() -> Result<String, AppError> {
+    let buffer = OUTPUT_BUFFER.lock().map_err(|_| AppError::LockError)?;
+    Ok(buffer
+        .primary
+        .clone()
+        .unwrap_or_else(|| buffer.lines.join("\n")))
+}
+
 /// Flush the output buffer to stdout.
 ///
 /// This function flushes the output buffer to stdout, printing each line in the
@@ -249,11 +428,14 @@ pub fn append_key_value<K: Display, V: Display>(
 /// ## Errors
 ///  - OutputError::LockError if the output buffer cannot be locked.
 pub fn flush_output() -> Result<(), AppError> {
-    OUTPUT_BUFFER
-        .lock()
-        .map_err(|_| AppError::LockError)?
-        .flush();
-    Ok(())
+    if abandoned() {
+        return Ok(());
+    }
+    stop_spinner();
+    let mut buffer = OUTPUT_BUFFER.lock().map_err(|_| AppError::LockError)?;
+    let printed = suspend(|| buffer.flush());
+    drop(buffer);
+    record_output(&printed)
 }
 
 /// Set a filter on the output buffer.
@@ -262,6 +444,9 @@ pub fn flush_output() -> Result<(), AppError> {
 /// only lines matching the expression will be printed. If the invert flag is set,
 /// lines matching the pattern will be excluded from the output.
 pub fn set_filter(pattern: String, invert: bool) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     OUTPUT_BUFFER
         .lock()
         .map_err(|_| AppError::LockError)?
@@ -271,9 +456,55 @@ pub fn set_filter(pattern: String, invert: bool) -> Result<(), AppError> {
 
 /// Clear the filter on the output buffer.
 pub fn clear_filter() -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
     OUTPUT_BUFFER
         .lock()
         .map_err(|_| AppError::LockError)?
         .clear_filter();
     Ok(())
 }
+
+/// Set the output format used by the formatters.
+///
+/// The format is set for every command that is executed, and defaults to
+/// `OutputFormat::Text` unless the user asks for something else.
+pub fn set_format(format: OutputFormat) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
+    OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .set_format(format);
+    Ok(())
+}
+
+/// Get the output format currently in use.
+pub fn get_format() -> Result<OutputFormat, AppError> {
+    Ok(OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .format)
+}
+
+/// Set the verbosity of the command about to run.
+pub fn set_verbosity(verbosity: Verbosity) -> Result<(), AppError> {
+    if abandoned() {
+        return Ok(());
+    }
+    OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .verbosity = verbosity;
+    Ok(())
+}
+
+/// Get the verbosity of the running command.
+pub fn get_verbosity() -> Result<Verbosity, AppError> {
+    Ok(OUTPUT_BUFFER
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .verbosity)
+}
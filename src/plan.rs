@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+use std::io::IsTerminal;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::errors::AppError;
+use crate::interrupt::{abandoned, while_prompting};
+use crate::progress::stop_spinner;
+
+/// What a plan will do to a single entity.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum PlanAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// A change to a single field, fields in nested JSON are named by their dotted path.
+#[derive(Debug, Serialize, Clone)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct PlanItem {
+    pub action: PlanAction,
+    pub kind: String,
+    pub name: String,
+    pub changes: Vec<FieldChange>,
+}
+
+/// A terraform-style list of the changes a command will make, shown to the user before
+/// anything is executed against the server.
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct Plan {
+    pub items: Vec<PlanItem>,
+}
+
+impl Plan {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn create<T: Serialize>(
+        &mut self,
+        kind: &str,
+        name: &str,
+        new: &T,
+    ) -> Result<(), AppError> {
+        let changes = diff(&Value::Null, &serde_json::to_value(new)?);
+        self.push(PlanAction::Create, kind, name, changes);
+        Ok(())
+    }
+
+    /// Add an update, unless the old and new values are identical.
+    pub fn update<O: Serialize, N: Serialize>(
+        &mut self,
+        kind: &str,
+        name: &str,
+        old: &O,
+        new: &N,
+    ) -> Result<(), AppError> {
+        let changes = diff(&serde_json::to_value(old)?, &serde_json::to_value(new)?);
+        if !changes.is_empty() {
+            self.push(PlanAction::Update, kind, name, changes);
+        }
+        Ok(())
+    }
+
+    pub fn delete(&mut self, kind: &str, name: &str) {
+        self.push(PlanAction::Delete, kind, name, Vec::new());
+    }
+
+    fn push(&mut self, action: PlanAction, kind: &str, name: &str, changes: Vec<FieldChange>) {
+        self.items.push(PlanItem {
+            action,
+            kind: kind.to_string(),
+            name: name.to_string(),
+            changes,
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The number of creates, updates and deletes in the plan.
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let count = |action| self.items.iter().filter(|i| i.action == action).count();
+        (
+            count(PlanAction::Create),
+            count(PlanAction::Update),
+            count(PlanAction::Delete),
+        )
+    }
+}
+
+/// Flatten a JSON value into dotted paths, so nested changes are shown per field.
+pub fn flatten(prefix: &str, value: &Value, out: &mut BTreeMap<String, Value>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&path, value, out);
+            }
+        }
+        Value::Null if prefix.is_empty() => {}
+        value => {
+            out.insert(prefix.to_string(), value.clone());
+        }
+    }
+}
+
+fn diff(old: &Value, new: &Value) -> Vec<FieldChange> {
+    let (mut old_fields, mut new_fields) = (BTreeMap::new(), BTreeMap::new());
+    flatten("", old, &mut old_fields);
+    flatten("", new, &mut new_fields);
+
+    let mut fields = old_fields
+        .keys()
+        .chain(new_fields.keys())
+        .collect::<Vec<_>>();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter(|field| old_fields.get(*field) != new_fields.get(*field))
+        .map(|field| FieldChange {
+            field: field.clone(),
+            old: old_fields.get(field).cloned(),
+            new: new_fields.get(field).cloned(),
+        })
+        .collect()
+}
+
+/// Ask the user to confirm before continuing. Without a terminal to ask on, the answer
+/// is no, so scripts have to confirm explicitly (e.g. with `--yes`).
+pub fn confirm(question: &str) -> Result<bool, AppError> {
+    // A command left running after Ctrl-C must not read what is typed at the prompt.
+    if !std::io::stdin().is_terminal() || abandoned() {
+        return Ok(false);
+    }
+
+    stop_spinner();
+    let mut stdout = std::io::stdout();
+    std::io::Write::write_all(&mut stdout, format!("{} [y/N] ", question).as_bytes())?;
+    std::io::Write::flush(&mut stdout)?;
+
+    let mut answer = String::new();
+    while_prompting(|| std::io::stdin().read_line(&mut answer))?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
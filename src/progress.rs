@@ -0,0 +1,90 @@
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use once_cell::sync::Lazy;
+
+use crate::output::{get_verbosity, Verbosity};
+
+// All progress bars are drawn through this, on stderr, so output can be printed without
+// drawing over them. Bars are hidden when stderr is not a terminal.
+static PROGRESS: Lazy<MultiProgress> =
+    Lazy::new(|| MultiProgress::with_draw_target(ProgressDrawTarget::stderr()));
+
+/// A progress bar for commands working through many entities, e.g. bulk imports, moves and
+/// validation. Finish it with [`finish_progress`].
+pub fn progress_bar(len: usize, message: &str) -> ProgressBar {
+    // The bar shows that the command is working, so the spinner is not needed.
+    stop_spinner();
+    if get_verbosity().is_ok_and(|verbosity| verbosity == Verbosity::Quiet) {
+        return ProgressBar::hidden();
+    }
+    let progress = PROGRESS.add(ProgressBar::new(len as u64));
+    if let Ok(style) = ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len}") {
+        progress.set_style(style.progress_chars("=> "));
+    }
+    progress.set_message(message.to_string());
+    progress
+}
+
+/// Remove a finished progress bar from the screen.
+pub fn finish_progress(progress: &ProgressBar) {
+    progress.finish_and_clear();
+    PROGRESS.remove(progress);
+}
+
+/// Run `f` with the progress bars hidden, so what it prints is not drawn over. The bars
+/// are drawn again afterwards.
+pub fn suspend<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    PROGRESS.suspend(f)
+}
+
+// Commands slower than this show a spinner, so the prompt does not seem to hang.
+const SPINNER_DELAY: Duration = Duration::from_millis(300);
+
+struct Spinner {
+    bar: ProgressBar,
+    // Dropping this tells the thread waiting to show the spinner that it is not needed.
+    _cancel: mpsc::Sender<()>,
+}
+
+static SPINNER: Lazy<Mutex<Option<Spinner>>> = Lazy::new(|| Mutex::new(None));
+
+/// Show a spinner with `label` if the work started now takes longer than a moment, until
+/// [`stop_spinner`] is called or output is flushed.
+pub fn start_spinner(label: &str) {
+    let bar = ProgressBar::new_spinner();
+    bar.set_message(label.to_string());
+    let (cancel, cancelled) = mpsc::channel::<()>();
+
+    let shown = bar.clone();
+    thread::spawn(move || {
+        if cancelled.recv_timeout(SPINNER_DELAY) == Err(RecvTimeoutError::Timeout)
+            && !shown.is_finished()
+        {
+            let shown = PROGRESS.add(shown);
+            shown.enable_steady_tick(Duration::from_millis(100));
+        }
+    });
+
+    if let Ok(mut spinner) = SPINNER.lock() {
+        if let Some(previous) = spinner.replace(Spinner {
+            bar,
+            _cancel: cancel,
+        }) {
+            finish_progress(&previous.bar);
+        }
+    }
+}
+
+/// Remove the spinner, if any. Anything about to print or prompt calls this first.
+pub fn stop_spinner() {
+    if let Some(spinner) = SPINNER.lock().ok().and_then(|mut spinner| spinner.take()) {
+        finish_progress(&spinner.bar);
+    }
+}
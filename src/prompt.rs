@@ -0,0 +1,69 @@
+use crate::config::AppConfig;
+use crate::errors::AppError;
+use crate::i18n::tr;
+use crate::sandbox::current_sandbox;
+use crate::session;
+
+// Color placeholders, rendered as ANSI escape codes.
+const COLORS: &[(&str, &str)] = &[
+    ("red", "\x1b[31m"),
+    ("green", "\x1b[32m"),
+    ("yellow", "\x1b[33m"),
+    ("blue", "\x1b[34m"),
+    ("magenta", "\x1b[35m"),
+    ("cyan", "\x1b[36m"),
+    ("bold", "\x1b[1m"),
+    ("reset", "\x1b[0m"),
+];
+
+// What the prompt shows about the session, besides the configuration.
+fn context() -> Result<String, AppError> {
+    let mut parts = Vec::new();
+    let context = session::context();
+    if let Some(namespace) = context.namespace {
+        parts.push(format!("ns:{}", namespace));
+    }
+    if let Some(class) = context.class {
+        parts.push(format!("class:{}", class));
+    }
+    if let Some(sandbox) = current_sandbox()? {
+        parts.push(format!("sandbox:{}", sandbox.namespace));
+    }
+
+    if parts.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!("[{}]", parts.join(" ")))
+}
+
+/// Render the prompt from `display.prompt`.
+///
+/// The template can use `{username}`, `{host}`, `{port}`, `{profile}` (the name of the
+/// configuration file given with `--config`, or `default`), `{context}`, `{status}` (the
+/// outcome of the last command), and the colors `{red}`, `{green}`, `{yellow}`, `{blue}`,
+/// `{magenta}`, `{cyan}`, `{bold}` and `{reset}`.
+pub fn render_prompt(
+    config: &AppConfig,
+    logged_in: bool,
+    last_succeeded: bool,
+) -> Result<String, AppError> {
+    let status = if last_succeeded { "✓" } else { "✗" };
+    let mut prompt = config
+        .display
+        .prompt
+        .replace("{username}", &config.server.username)
+        .replace("{host}", &config.server.hostname)
+        .replace("{port}", &config.server.port.to_string())
+        .replace("{profile}", &config.profile)
+        .replace("{context}", &context()?)
+        .replace("{status}", status);
+
+    for (name, code) in COLORS {
+        prompt = prompt.replace(&format!("{{{}}}", name), code);
+    }
+
+    if !logged_in {
+        prompt = format!("({}) {}", tr("logged out"), prompt);
+    }
+    Ok(prompt)
+}
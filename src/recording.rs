@@ -0,0 +1,79 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::Local;
+use once_cell::sync::Lazy;
+
+use crate::errors::AppError;
+use crate::history::redact_secrets;
+
+/// A transcript of the session being written to a file, started with `record start`.
+struct Recording {
+    path: PathBuf,
+    file: File,
+}
+
+static RECORDING: Lazy<Mutex<Option<Recording>>> = Lazy::new(|| Mutex::new(None));
+
+fn timestamp() -> String {
+    Local::now().format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Start writing the commands and their output to `path`, replacing any recording in
+/// progress. The file is appended to if it exists.
+pub fn start_recording(path: &Path) -> Result<(), AppError> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "# Transcript started at {}", timestamp())?;
+
+    *RECORDING.lock().map_err(|_| AppError::LockError)? = Some(Recording {
+        path: path.to_path_buf(),
+        file,
+    });
+    Ok(())
+}
+
+/// Stop the recording, returning the file it was written to.
+pub fn stop_recording() -> Result<Option<PathBuf>, AppError> {
+    let Some(mut recording) = RECORDING.lock().map_err(|_| AppError::LockError)?.take() else {
+        return Ok(None);
+    };
+    writeln!(recording.file, "# Transcript stopped at {}", timestamp())?;
+    Ok(Some(recording.path))
+}
+
+/// The file being recorded to, if any.
+pub fn recording_path() -> Result<Option<PathBuf>, AppError> {
+    Ok(RECORDING
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .as_ref()
+        .map(|recording| recording.path.clone()))
+}
+
+/// Record a command line, if recording.
+pub fn record_command(line: &str) -> Result<(), AppError> {
+    if let Some(recording) = RECORDING.lock().map_err(|_| AppError::LockError)?.as_mut() {
+        writeln!(
+            recording.file,
+            "[{}] > {}",
+            timestamp(),
+            redact_secrets(line)
+        )?;
+    }
+    Ok(())
+}
+
+/// Record output as it was shown, if recording.
+pub fn record_output(lines: &[String]) -> Result<(), AppError> {
+    if let Some(recording) = RECORDING.lock().map_err(|_| AppError::LockError)?.as_mut() {
+        for line in lines {
+            writeln!(recording.file, "{}", line)?;
+        }
+    }
+    Ok(())
+}
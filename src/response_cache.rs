@@ -0,0 +1,142 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use log::{debug, trace, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::{get_config, AppConfig};
+use crate::errors::AppError;
+use crate::files::{get_response_cache_dir, hashed_file_name};
+
+/// A GET response saved in the cache directory, one file per endpoint and query.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedResponse {
+    key: String,
+    fetched_at: u64,
+    body: Value,
+}
+
+/// The number and total size of the responses in the cache directory.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiskCacheUsage {
+    pub entries: usize,
+    pub bytes: u64,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Responses are per user, as users may see different things.
+fn cache_key(config: &AppConfig, path: &str) -> String {
+    format!(
+        "{}@{}:{}{}",
+        config.server.username, config.server.hostname, config.server.port, path
+    )
+}
+
+fn entries() -> Result<Vec<(PathBuf, std::fs::Metadata)>, AppError> {
+    let mut entries = Vec::new();
+    for entry in std::fs::read_dir(get_response_cache_dir()?)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            entries.push((entry.path(), metadata));
+        }
+    }
+    Ok(entries)
+}
+
+/// The response for the API `path`, from the cache if it was fetched within `cache.time`
+/// seconds, otherwise from `fetch` and then cached. Honors `cache.disable`.
+pub fn cached_get<F>(path: &str, fetch: F) -> Result<Value, AppError>
+where
+    F: FnOnce() -> Result<Value, AppError>,
+{
+    let config = get_config()?;
+    if config.cache.disable {
+        return fetch();
+    }
+
+    let key = cache_key(&config, path);
+    let file = get_response_cache_dir()?.join(hashed_file_name(&key));
+    if let Some(body) = read_entry(&file, &key, config.cache.time) {
+        trace!("Response cache hit for {}", key);
+        return Ok(body);
+    }
+
+    let body = fetch()?;
+    let entry = CachedResponse {
+        key,
+        fetched_at: now(),
+        body,
+    };
+    match serde_json::to_string(&entry) {
+        Ok(content) => {
+            if let Err(err) = std::fs::write(&file, content) {
+                warn!("Failed to cache the response for {}: {}", path, err);
+            } else if let Err(err) = evict(config.cache.size) {
+                warn!("Failed to evict cached responses: {}", err);
+            }
+        }
+        Err(err) => warn!("Failed to cache the response for {}: {}", path, err),
+    }
+    Ok(entry.body)
+}
+
+// The cached body, if the file holds a fresh response for `key`. A hit marks the file as
+// recently used by touching it, which is what eviction goes by.
+fn read_entry(file: &Path, key: &str, ttl: u64) -> Option<Value> {
+    let content = std::fs::read_to_string(file).ok()?;
+    let entry: CachedResponse = serde_json::from_str(&content).ok()?;
+    if entry.key != key || now().saturating_sub(entry.fetched_at) >= ttl {
+        return None;
+    }
+
+    if let Ok(handle) = std::fs::File::options().write(true).open(file) {
+        let _ = handle.set_modified(SystemTime::now());
+    }
+    Some(entry.body)
+}
+
+// Remove the least recently used responses until the cache fits in `size` bytes.
+fn evict(size: i32) -> Result<(), AppError> {
+    let mut entries = entries()?;
+    let mut total = entries.iter().map(|(_, m)| m.len()).sum::<u64>();
+    let limit = size.max(0) as u64;
+    if total <= limit {
+        return Ok(());
+    }
+
+    entries.sort_by_key(|(_, metadata)| metadata.modified().unwrap_or(UNIX_EPOCH));
+    for (path, metadata) in entries {
+        if total <= limit {
+            break;
+        }
+        debug!("Evicting cached response {}", path.display());
+        std::fs::remove_file(&path)?;
+        total -= metadata.len();
+    }
+    Ok(())
+}
+
+/// Remove every cached response, returning how many there were.
+pub fn purge_response_cache() -> Result<usize, AppError> {
+    let entries = entries()?;
+    for (path, _) in &entries {
+        std::fs::remove_file(path)?;
+    }
+    Ok(entries.len())
+}
+
+pub fn disk_cache_usage() -> Result<DiskCacheUsage, AppError> {
+    let entries = entries()?;
+    Ok(DiskCacheUsage {
+        entries: entries.len(),
+        bytes: entries.iter().map(|(_, m)| m.len()).sum(),
+    })
+}
@@ -0,0 +1,23 @@
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+
+use crate::errors::AppError;
+
+/// A temporary namespace that create commands use by default, until it is destroyed.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    pub namespace: String,
+    pub namespace_id: i32,
+}
+
+static SANDBOX: Lazy<Mutex<Option<Sandbox>>> = Lazy::new(|| Mutex::new(None));
+
+pub fn set_sandbox(sandbox: Option<Sandbox>) -> Result<(), AppError> {
+    *SANDBOX.lock().map_err(|_| AppError::LockError)? = sandbox;
+    Ok(())
+}
+
+pub fn current_sandbox() -> Result<Option<Sandbox>, AppError> {
+    Ok(SANDBOX.lock().map_err(|_| AppError::LockError)?.clone())
+}
@@ -0,0 +1,406 @@
+use regex::Regex;
+use rustyline::error::ReadlineError;
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::errors::AppError;
+use crate::i18n::{tr, tr_args};
+use crate::output::{append_line, flush_output};
+use crate::plan::confirm;
+use crate::progress::stop_spinner;
+use crate::session;
+
+/// The type of a property in a class JSON schema, as far as it can be prompted for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropertyType {
+    String,
+    Integer,
+    Number,
+    Boolean,
+    Enum(Vec<Value>),
+    /// Objects, arrays and anything else, entered as JSON.
+    Json,
+}
+
+/// A top level property of a class JSON schema.
+#[derive(Debug, Clone)]
+pub struct SchemaProperty {
+    pub name: String,
+    pub property_type: PropertyType,
+    pub required: bool,
+    pub description: Option<String>,
+}
+
+impl SchemaProperty {
+    /// Parse an answer to the prompt for the property into a value of its type.
+    pub fn parse(&self, answer: &str) -> Result<Value, String> {
+        if answer.is_empty() {
+            return Err(tr("A value is required"));
+        }
+        match &self.property_type {
+            PropertyType::String => Ok(Value::String(answer.to_string())),
+            PropertyType::Integer => answer
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| tr("Expected a whole number")),
+            PropertyType::Number => answer
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .map(Value::from)
+                .ok_or_else(|| tr("Expected a number")),
+            PropertyType::Boolean => match answer.to_lowercase().as_str() {
+                "true" | "yes" | "y" => Ok(Value::Bool(true)),
+                "false" | "no" | "n" => Ok(Value::Bool(false)),
+                _ => Err(tr("Expected true or false")),
+            },
+            PropertyType::Enum(values) => values
+                .iter()
+                .find(|value| enum_label(value) == answer)
+                .cloned()
+                .ok_or_else(|| {
+                    tr_args(
+                        "Expected one of {values}",
+                        &[("values", &enum_labels(values))],
+                    )
+                }),
+            PropertyType::Json => serde_json::from_str(answer).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// A short description of the type, e.g. `integer` or `red/green/blue`.
+    pub fn type_label(&self) -> String {
+        match &self.property_type {
+            PropertyType::String => "string".to_string(),
+            PropertyType::Integer => "integer".to_string(),
+            PropertyType::Number => "number".to_string(),
+            PropertyType::Boolean => "true/false".to_string(),
+            PropertyType::Enum(values) => enum_labels(values),
+            PropertyType::Json => "JSON".to_string(),
+        }
+    }
+
+    fn prompt(&self) -> String {
+        let hint = self.type_label();
+        match &self.description {
+            Some(description) => format!("{} - {} [{}]: ", self.name, description, hint),
+            None => format!("{} [{}]: ", self.name, hint),
+        }
+    }
+}
+
+/// The top level properties of a JSON schema, in the order they are listed.
+pub fn schema_properties(schema: &Value) -> Vec<SchemaProperty> {
+    let required = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|keys| keys.iter().filter_map(Value::as_str).collect::<Vec<_>>())
+        .unwrap_or_default();
+    let Some(properties) = schema.get("properties").and_then(Value::as_object) else {
+        return Vec::new();
+    };
+
+    properties
+        .iter()
+        .map(|(name, property)| SchemaProperty {
+            name: name.clone(),
+            property_type: property_type(property),
+            required: required.contains(&name.as_str()),
+            description: property
+                .get("description")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+        })
+        .collect()
+}
+
+/// How the top level properties differ between two JSON schemas.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SchemaDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<PropertyChange>,
+}
+
+/// The changes to a property that both schemas have.
+#[derive(Debug, Clone, Serialize)]
+pub struct PropertyChange {
+    pub property: String,
+    pub changes: Vec<String>,
+}
+
+impl SchemaDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// Compare the top level properties of two JSON schemas.
+pub fn diff_schemas(before: &Value, after: &Value) -> SchemaDiff {
+    let old = schema_properties(before);
+    let new = schema_properties(after);
+    let definition = |schema: &Value, name: &str| {
+        schema
+            .get("properties")
+            .and_then(|properties| properties.get(name))
+            .cloned()
+    };
+
+    let mut diff = SchemaDiff {
+        removed: old
+            .iter()
+            .filter(|property| !new.iter().any(|p| p.name == property.name))
+            .map(|property| property.name.clone())
+            .collect(),
+        ..Default::default()
+    };
+    for property in &new {
+        let Some(previous) = old.iter().find(|p| p.name == property.name) else {
+            diff.added.push(property.name.clone());
+            continue;
+        };
+
+        let mut changes = Vec::new();
+        if previous.property_type != property.property_type {
+            changes.push(format!(
+                "type {} → {}",
+                previous.type_label(),
+                property.type_label()
+            ));
+        }
+        match (previous.required, property.required) {
+            (false, true) => changes.push("now required".to_string()),
+            (true, false) => changes.push("no longer required".to_string()),
+            _ => {}
+        }
+        if changes.is_empty()
+            && definition(before, &property.name) != definition(after, &property.name)
+        {
+            changes.push("definition changed".to_string());
+        }
+        if !changes.is_empty() {
+            diff.changed.push(PropertyChange {
+                property: property.name.clone(),
+                changes,
+            });
+        }
+    }
+    diff
+}
+
+/// Check data against a JSON schema, returning what is wrong with it, one problem per
+/// entry. Covers the keywords class schemas use in practice: `type`, `enum`, `const`,
+/// `required`, `properties`, `additionalProperties: false`, `items`, `minimum`,
+/// `maximum`, `minLength`, `maxLength` and `pattern`. Anything else is not checked.
+pub fn validate_data(schema: &Value, data: &Value) -> Vec<String> {
+    let mut problems = Vec::new();
+    validate_value(schema, data, "$", &mut problems);
+    problems
+}
+
+fn validate_value(schema: &Value, value: &Value, path: &str, problems: &mut Vec<String>) {
+    if schema == &Value::Bool(false) {
+        problems.push(format!("{}: not allowed", path));
+        return;
+    }
+
+    let kinds = match schema.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    if !kinds.is_empty() && !kinds.iter().any(|kind| has_type(value, kind)) {
+        problems.push(format!(
+            "{}: expected {}, got {}",
+            path,
+            kinds.join(" or "),
+            type_name(value)
+        ));
+        return;
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        if !values.contains(value) {
+            problems.push(format!("{}: expected one of {}", path, enum_labels(values)));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if expected != value {
+            problems.push(format!("{}: expected {}", path, expected));
+        }
+    }
+
+    match value {
+        Value::Object(map) => {
+            for key in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !map.contains_key(key) {
+                    problems.push(format!("{}.{}: is required", path, key));
+                }
+            }
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, value) in map {
+                match properties.and_then(|properties| properties.get(key)) {
+                    Some(property) => {
+                        validate_value(property, value, &format!("{}.{}", path, key), problems)
+                    }
+                    None if schema.get("additionalProperties") == Some(&Value::Bool(false)) => {
+                        problems.push(format!("{}.{}: not allowed", path, key))
+                    }
+                    None => {}
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(item_schema) = schema.get("items").filter(|items| items.is_object()) {
+                for (i, item) in items.iter().enumerate() {
+                    validate_value(item_schema, item, &format!("{}[{}]", path, i), problems);
+                }
+            }
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+                if number < minimum {
+                    problems.push(format!("{}: less than {}", path, minimum));
+                }
+            }
+            if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+                if number > maximum {
+                    problems.push(format!("{}: more than {}", path, maximum));
+                }
+            }
+        }
+        Value::String(string) => {
+            let length = string.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if length < min {
+                    problems.push(format!("{}: shorter than {} characters", path, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if length > max {
+                    problems.push(format!("{}: longer than {} characters", path, max));
+                }
+            }
+            if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                if Regex::new(pattern).is_ok_and(|regex| !regex.is_match(string)) {
+                    problems.push(format!("{}: does not match {}", path, pattern));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn has_type(value: &Value, kind: &str) -> bool {
+    match kind {
+        "integer" => value
+            .as_f64()
+            .is_some_and(|n| n.fract() == 0.0 && n.is_finite()),
+        kind => type_name(value) == kind || (kind == "number" && value.is_number()),
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn property_type(property: &Value) -> PropertyType {
+    if let Some(values) = property.get("enum").and_then(Value::as_array) {
+        return PropertyType::Enum(values.clone());
+    }
+    // With several types, e.g. ["string", "null"], the first one other than null is used.
+    let kind = match property.get("type") {
+        Some(Value::String(kind)) => Some(kind.as_str()),
+        Some(Value::Array(kinds)) => kinds
+            .iter()
+            .filter_map(Value::as_str)
+            .find(|kind| *kind != "null"),
+        _ => None,
+    };
+    match kind {
+        Some("string") => PropertyType::String,
+        Some("integer") => PropertyType::Integer,
+        Some("number") => PropertyType::Number,
+        Some("boolean") => PropertyType::Boolean,
+        _ => PropertyType::Json,
+    }
+}
+
+fn enum_label(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        value => value.to_string(),
+    }
+}
+
+fn enum_labels(values: &[Value]) -> String {
+    values.iter().map(enum_label).collect::<Vec<_>>().join("/")
+}
+
+/// Offer to prompt for the required properties of the schema that `data` does not have,
+/// and return the data with the answers added. Returns `None` if there is nothing to ask
+/// for, we are not at the REPL with a terminal to ask on, or the offer is declined.
+pub fn prompt_required_data(
+    class: &str,
+    schema: &Value,
+    data: Option<&Value>,
+) -> Result<Option<Value>, AppError> {
+    let mut data = match data {
+        Some(Value::Object(map)) => map.clone(),
+        _ => Map::new(),
+    };
+    let missing = schema_properties(schema)
+        .into_iter()
+        .filter(|property| property.required && !data.contains_key(&property.name))
+        .collect::<Vec<_>>();
+
+    if missing.is_empty() || !session::is_interactive() {
+        return Ok(None);
+    }
+    stop_spinner();
+    if !confirm(&tr_args(
+        "Enter the {count} required data keys of {class}?",
+        &[("count", &missing.len()), ("class", &class)],
+    ))? {
+        return Ok(None);
+    }
+
+    let mut editor = Editor::<(), DefaultHistory>::new()?;
+    for property in missing {
+        let value = loop {
+            let answer = match editor.readline(&property.prompt()) {
+                Ok(answer) => answer.trim().to_string(),
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
+                    append_line(tr("Cancelled"))?;
+                    return Err(AppError::Quiet);
+                }
+                Err(err) => return Err(err.into()),
+            };
+            match property.parse(&answer) {
+                Ok(value) => break value,
+                Err(err) => {
+                    append_line(err)?;
+                    flush_output()?;
+                }
+            }
+        };
+        data.insert(property.name, value);
+    }
+    Ok(Some(Value::Object(data)))
+}
@@ -0,0 +1,66 @@
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+use hubuum_client::{Authenticated, SyncClient};
+use once_cell::sync::Lazy;
+
+use crate::errors::AppError;
+
+// The client commands are run with. It is replaced by `login` and cleared by `logout`, and
+// is missing if the CLI started without being able to log in.
+static CLIENT: Lazy<RwLock<Option<Arc<SyncClient<Authenticated>>>>> =
+    Lazy::new(|| RwLock::new(None));
+
+pub fn set_client(client: SyncClient<Authenticated>) -> Result<(), AppError> {
+    *CLIENT.write().map_err(|_| AppError::LockError)? = Some(Arc::new(client));
+    Ok(())
+}
+
+pub fn clear_client() -> Result<(), AppError> {
+    *CLIENT.write().map_err(|_| AppError::LockError)? = None;
+    Ok(())
+}
+
+/// The client of the current login, if any.
+pub fn client() -> Option<Arc<SyncClient<Authenticated>>> {
+    CLIENT.read().ok().and_then(|client| client.clone())
+}
+
+pub fn is_logged_in() -> bool {
+    client().is_some()
+}
+
+/// The class and namespace entered with `use`, which commands default to.
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    pub class: Option<String>,
+    pub namespace: Option<String>,
+}
+
+static CONTEXT: Lazy<RwLock<Context>> = Lazy::new(|| RwLock::new(Context::default()));
+
+pub fn context() -> Context {
+    CONTEXT
+        .read()
+        .map(|context| context.clone())
+        .unwrap_or_default()
+}
+
+pub fn set_context(context: Context) -> Result<(), AppError> {
+    *CONTEXT.write().map_err(|_| AppError::LockError)? = context;
+    Ok(())
+}
+
+// Set when commands are read from the user at the REPL prompt, rather than from -c or
+// --source, which must not stop to ask questions.
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_interactive(interactive: bool) {
+    INTERACTIVE.store(interactive, Ordering::Relaxed);
+}
+
+/// Whether commands may prompt the user, i.e. we are at the REPL with a terminal.
+pub fn is_interactive() -> bool {
+    INTERACTIVE.load(Ordering::Relaxed) && std::io::stdin().is_terminal()
+}
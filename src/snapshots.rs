@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+
+use chrono::{NaiveDateTime, Utc};
+use hubuum_client::{Authenticated, Object, SyncClient};
+use serde::{Deserialize, Serialize};
+
+use crate::api::retry_api;
+use crate::errors::AppError;
+use crate::files::get_snapshot_dir;
+
+/// The number of automatic snapshots kept for each object.
+const AUTOMATIC_SNAPSHOTS_KEPT: usize = 10;
+
+/// A point-in-time copy of an object, stored as JSON under the data directory.
+///
+/// Snapshots are stored per class and object name, one file per snapshot, named after
+/// the time the snapshot was taken.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ObjectSnapshot {
+    pub taken_at: NaiveDateTime,
+    pub class: String,
+    pub namespace: String,
+    pub object: Object,
+    /// Why the CLI took the snapshot by itself, e.g. `delete`. Unset for snapshots taken
+    /// with `object snapshot`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl ObjectSnapshot {
+    pub fn new(class: &str, namespace: &str, object: &Object) -> Self {
+        ObjectSnapshot {
+            taken_at: Utc::now().naive_utc(),
+            class: class.to_string(),
+            namespace: namespace.to_string(),
+            object: object.clone(),
+            reason: None,
+        }
+    }
+
+    /// A snapshot taken automatically before the CLI changes or deletes the object.
+    pub fn before(reason: &str, class: &str, namespace: &str, object: &Object) -> Self {
+        ObjectSnapshot {
+            reason: Some(reason.to_string()),
+            ..Self::new(class, namespace, object)
+        }
+    }
+
+    /// The identifier of the snapshot, used to select it when restoring.
+    pub fn id(&self) -> String {
+        self.taken_at.format("%Y%m%dT%H%M%S%.3f").to_string()
+    }
+}
+
+// Class and object names are user controlled, so make sure they are safe as path components.
+// Empty names and names of only dots, such as `..`, would not name a directory of their own.
+fn sanitize(name: &str) -> String {
+    if name.chars().all(|c| c == '.') {
+        return "_".repeat(name.len().max(1));
+    }
+
+    name.chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn object_snapshot_dir(class: &str, object: &str) -> Result<PathBuf, AppError> {
+    let dir = get_snapshot_dir()?
+        .join(sanitize(class))
+        .join(sanitize(object));
+
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir)?;
+    }
+
+    Ok(dir)
+}
+
+/// Store a snapshot on disk.
+pub fn save_object_snapshot(snapshot: &ObjectSnapshot) -> Result<(), AppError> {
+    let path = object_snapshot_dir(&snapshot.class, &snapshot.object.name)?
+        .join(format!("{}.json", snapshot.id()));
+    log::debug!("Saving object snapshot: {:?}", path);
+    std::fs::write(path, serde_json::to_string_pretty(snapshot)?)?;
+    Ok(())
+}
+
+/// Remove the oldest automatic snapshots of an object, keeping the newest `keep`.
+/// Snapshots taken with `object snapshot` are never removed.
+pub fn prune_object_snapshots(class: &str, object: &str, keep: usize) -> Result<(), AppError> {
+    let automatic = object_snapshots(class, object)?
+        .into_iter()
+        .filter(|s| s.reason.is_some())
+        .collect::<Vec<_>>();
+    let dir = object_snapshot_dir(class, object)?;
+    for snapshot in &automatic[..automatic.len().saturating_sub(keep)] {
+        log::debug!("Removing object snapshot {}", snapshot.id());
+        std::fs::remove_file(dir.join(format!("{}.json", snapshot.id())))?;
+    }
+    Ok(())
+}
+
+/// All snapshots stored for an object, oldest first.
+pub fn object_snapshots(class: &str, object: &str) -> Result<Vec<ObjectSnapshot>, AppError> {
+    let mut snapshots = Vec::new();
+
+    for entry in std::fs::read_dir(object_snapshot_dir(class, object)?)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        snapshots.push(serde_json::from_str::<ObjectSnapshot>(&content)?);
+    }
+
+    snapshots.sort_by_key(|s| s.taken_at);
+    Ok(snapshots)
+}
+
+/// Save a snapshot of an object before the CLI changes or deletes it, so `object restore`
+/// can bring it back. `reason` says what is about to happen, e.g. `delete`. Only the newest
+/// `AUTOMATIC_SNAPSHOTS_KEPT` automatic snapshots of an object are kept.
+pub fn snapshot_before(
+    client: &SyncClient<Authenticated>,
+    class: &str,
+    object: &Object,
+    reason: &str,
+) -> Result<(), AppError> {
+    let namespace = retry_api(|| {
+        client
+            .namespaces()
+            .find()
+            .add_filter_id(object.namespace_id)
+            .execute_expecting_single_result()
+    })?;
+    save_object_snapshot(&ObjectSnapshot::before(
+        reason,
+        class,
+        &namespace.name,
+        object,
+    ))?;
+    prune_object_snapshots(class, &object.name, AUTOMATIC_SNAPSHOTS_KEPT)
+}
@@ -0,0 +1,366 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use hubuum_client::Object;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::errors::AppError;
+use crate::files::get_template_dir;
+use crate::output::add_warning;
+
+/// A canned class definition that can be applied to a namespace.
+///
+/// Built-in templates ship with the CLI, user-defined templates are read from JSON files
+/// in the `templates` directory under the data directory. A user-defined template with the
+/// same name as a built-in template replaces it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ClassTemplate {
+    pub name: String,
+    pub description: String,
+    pub json_schema: serde_json::Value,
+    #[serde(default = "default_validate_schema")]
+    pub validate_schema: bool,
+    #[serde(skip)]
+    pub builtin: bool,
+}
+
+fn default_validate_schema() -> bool {
+    true
+}
+
+impl ClassTemplate {
+    fn builtin(name: &str, description: &str, json_schema: serde_json::Value) -> Self {
+        ClassTemplate {
+            name: name.to_string(),
+            description: description.to_string(),
+            json_schema,
+            validate_schema: true,
+            builtin: true,
+        }
+    }
+
+    /// Add a top level property of a JSON schema type, e.g. `string` or `integer`,
+    /// replacing any property with the same name.
+    pub fn add_property(&mut self, name: &str, kind: &str) -> Result<(), AppError> {
+        if !SCHEMA_TYPES.contains(&kind) {
+            return Err(AppError::ParseError(format!(
+                "Unknown type '{}' for property '{}', expected one of: {}",
+                kind,
+                name,
+                SCHEMA_TYPES.join(", ")
+            )));
+        }
+        let schema = self.schema_object()?;
+        let properties = schema
+            .entry("properties")
+            .or_insert_with(|| json!({}))
+            .as_object_mut()
+            .ok_or_else(|| {
+                AppError::ParseError("The schema properties are not an object".into())
+            })?;
+        properties.insert(name.to_string(), json!({ "type": kind }));
+        Ok(())
+    }
+
+    /// Make a top level property required.
+    pub fn require_property(&mut self, name: &str) -> Result<(), AppError> {
+        if !self.properties().iter().any(|p| p == name) {
+            return Err(AppError::EntityNotFound(format!(
+                "Property '{}' in template '{}'",
+                name, self.name
+            )));
+        }
+        let required = self
+            .schema_object()?
+            .entry("required")
+            .or_insert_with(|| json!([]));
+        if let Some(required) = required.as_array_mut() {
+            if !required.iter().any(|r| r == name) {
+                required.push(json!(name));
+            }
+        }
+        Ok(())
+    }
+
+    fn schema_object(
+        &mut self,
+    ) -> Result<&mut serde_json::Map<String, serde_json::Value>, AppError> {
+        self.json_schema.as_object_mut().ok_or_else(|| {
+            AppError::ParseError(format!("The schema of '{}' is not an object", self.name))
+        })
+    }
+
+    /// The names of the top level properties defined by the schema, if any.
+    pub fn properties(&self) -> Vec<String> {
+        self.json_schema
+            .get("properties")
+            .and_then(|p| p.as_object())
+            .map(|p| p.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+// The types a property added to a template can have.
+const SCHEMA_TYPES: [&str; 6] = ["string", "integer", "number", "boolean", "object", "array"];
+
+fn builtin_templates() -> Vec<ClassTemplate> {
+    vec![
+        ClassTemplate::builtin(
+            "Host",
+            "Physical or virtual machines",
+            json!({
+                "$id": "https://hubuum.no/schemas/host.json",
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "Host",
+                "type": "object",
+                "properties": {
+                    "fqdn": { "type": "string" },
+                    "ip": { "type": "string" },
+                    "os": {
+                        "type": "object",
+                        "properties": {
+                            "family": { "type": "string" },
+                            "version": { "type": "string" }
+                        }
+                    },
+                    "role": { "type": "string" },
+                    "environment": {
+                        "type": "string",
+                        "enum": ["production", "staging", "test", "development"]
+                    }
+                },
+                "required": ["fqdn"]
+            }),
+        ),
+        ClassTemplate::builtin(
+            "Network",
+            "IP networks and VLANs",
+            json!({
+                "$id": "https://hubuum.no/schemas/network.json",
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "Network",
+                "type": "object",
+                "properties": {
+                    "cidr": { "type": "string" },
+                    "gateway": { "type": "string" },
+                    "vlan": { "type": "integer", "minimum": 1, "maximum": 4094 },
+                    "dns_servers": { "type": "array", "items": { "type": "string" } },
+                    "location": { "type": "string" }
+                },
+                "required": ["cidr"]
+            }),
+        ),
+        ClassTemplate::builtin(
+            "Service",
+            "Network services and applications",
+            json!({
+                "$id": "https://hubuum.no/schemas/service.json",
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "Service",
+                "type": "object",
+                "properties": {
+                    "port": { "type": "integer", "minimum": 1, "maximum": 65535 },
+                    "protocol": { "type": "string", "enum": ["tcp", "udp"] },
+                    "owner": { "type": "string" },
+                    "url": { "type": "string" }
+                },
+                "required": ["port", "protocol"]
+            }),
+        ),
+        ClassTemplate::builtin(
+            "Contract",
+            "Support and licensing contracts",
+            json!({
+                "$id": "https://hubuum.no/schemas/contract.json",
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "title": "Contract",
+                "type": "object",
+                "properties": {
+                    "vendor": { "type": "string" },
+                    "reference": { "type": "string" },
+                    "start_date": { "type": "string", "format": "date" },
+                    "end_date": { "type": "string", "format": "date" },
+                    "cost": { "type": "number", "minimum": 0 }
+                },
+                "required": ["vendor", "end_date"]
+            }),
+        ),
+    ]
+}
+
+fn user_templates() -> Result<Vec<ClassTemplate>, AppError> {
+    let mut templates = Vec::new();
+
+    for entry in std::fs::read_dir(get_template_dir()?)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        match serde_json::from_str::<ClassTemplate>(&content) {
+            Ok(template) => templates.push(template),
+            Err(err) => add_warning(format!(
+                "Ignoring invalid class template {}: {}",
+                path.display(),
+                err
+            ))?,
+        }
+    }
+
+    Ok(templates)
+}
+
+/// All available class templates, sorted by name.
+pub fn class_templates() -> Result<Vec<ClassTemplate>, AppError> {
+    let mut templates = BTreeMap::new();
+
+    for template in builtin_templates().into_iter().chain(user_templates()?) {
+        templates.insert(template.name.to_lowercase(), template);
+    }
+
+    Ok(templates.into_values().collect())
+}
+
+/// Find a class template by name, ignoring case.
+pub fn find_class_template(name: &str) -> Result<ClassTemplate, AppError> {
+    class_templates()?
+        .into_iter()
+        .find(|t| t.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| AppError::EntityNotFound(format!("Class template '{}'", name)))
+}
+
+/// Save a class template to the templates directory, where it replaces any template with
+/// the same name. Returns the path of the file.
+pub fn save_class_template(template: &ClassTemplate) -> Result<PathBuf, AppError> {
+    let path = get_template_dir()?.join(template_file_name(&template.name));
+    std::fs::write(&path, serde_json::to_string_pretty(template)?)?;
+    Ok(path)
+}
+
+// Template names are user controlled, so make sure they are safe as file names.
+fn template_file_name(name: &str) -> String {
+    let name = name
+        .to_lowercase()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    format!("{}.json", name)
+}
+
+/// The description and data of an object, saved to create similar objects from.
+///
+/// Object templates are stored as JSON files in the `objects` directory under the
+/// templates directory. Strings in them may hold the placeholders `{{name}}`, `{{class}}`
+/// and `{{namespace}}`, which are filled in when an object is created from the template.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ObjectTemplate {
+    pub name: String,
+    /// The class of the object the template was saved from.
+    pub class: String,
+    pub description: String,
+    #[serde(default)]
+    pub data: Option<Value>,
+}
+
+impl ObjectTemplate {
+    /// A template from an object, with the name of the object replaced by `{{name}}`
+    /// wherever it appears as a word, e.g. in `web01.example.com`.
+    pub fn from_object(name: &str, class: &str, object: &Object) -> Result<Self, AppError> {
+        let word = Regex::new(&format!(r"\b{}\b", regex::escape(&object.name)))?;
+        let placeholder = |s: &str| word.replace_all(s, "{{name}}").into_owned();
+        Ok(ObjectTemplate {
+            name: name.to_string(),
+            class: class.to_string(),
+            description: placeholder(&object.description),
+            data: object
+                .data
+                .as_ref()
+                .map(|data| map_strings(data, &placeholder)),
+        })
+    }
+
+    /// The description and data for a new object, with the placeholders filled in.
+    pub fn instantiate(&self, name: &str, class: &str, namespace: &str) -> (String, Option<Value>) {
+        let fill = |s: &str| {
+            s.replace("{{name}}", name)
+                .replace("{{class}}", class)
+                .replace("{{namespace}}", namespace)
+        };
+        (
+            fill(&self.description),
+            self.data.as_ref().map(|data| map_strings(data, &fill)),
+        )
+    }
+}
+
+// The value with every string in it, but not the keys, passed through `f`.
+fn map_strings(value: &Value, f: &dyn Fn(&str) -> String) -> Value {
+    match value {
+        Value::String(s) => Value::String(f(s)),
+        Value::Array(items) => Value::Array(items.iter().map(|v| map_strings(v, f)).collect()),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| (k.clone(), map_strings(v, f)))
+                .collect(),
+        ),
+        value => value.clone(),
+    }
+}
+
+fn object_template_dir() -> Result<PathBuf, AppError> {
+    let dir = get_template_dir()?.join("objects");
+    if !dir.exists() {
+        std::fs::create_dir_all(&dir)?;
+    }
+    Ok(dir)
+}
+
+/// All saved object templates, sorted by name.
+pub fn object_templates() -> Result<Vec<ObjectTemplate>, AppError> {
+    let mut templates = Vec::new();
+
+    for entry in std::fs::read_dir(object_template_dir()?)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        match serde_json::from_str::<ObjectTemplate>(&content) {
+            Ok(template) => templates.push(template),
+            Err(err) => add_warning(format!(
+                "Ignoring invalid object template {}: {}",
+                path.display(),
+                err
+            ))?,
+        }
+    }
+
+    templates.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(templates)
+}
+
+/// Find an object template by name, ignoring case.
+pub fn find_object_template(name: &str) -> Result<ObjectTemplate, AppError> {
+    object_templates()?
+        .into_iter()
+        .find(|t| t.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| AppError::EntityNotFound(format!("Object template '{}'", name)))
+}
+
+/// Save an object template, replacing any template with the same name. Returns the path
+/// of the file.
+pub fn save_object_template(template: &ObjectTemplate) -> Result<PathBuf, AppError> {
+    let path = object_template_dir()?.join(template_file_name(&template.name));
+    std::fs::write(&path, serde_json::to_string_pretty(template)?)?;
+    Ok(path)
+}
@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+// Only the latest samples of each command or endpoint are kept, so long sessions don't grow
+// without bounds.
+const SAMPLES_KEPT: usize = 1000;
+
+/// What was timed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimingKind {
+    Command,
+    Endpoint,
+}
+
+/// The latencies of a command or an endpoint during the session, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimingStats {
+    pub kind: TimingKind,
+    pub name: String,
+    pub count: usize,
+    pub mean_ms: f64,
+    pub p95_ms: u128,
+    pub max_ms: u128,
+}
+
+static TIMINGS: Lazy<Mutex<HashMap<(TimingKind, String), Vec<u128>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+pub fn record_timing(kind: TimingKind, name: &str, elapsed: Duration) {
+    if let Ok(mut timings) = TIMINGS.lock() {
+        let samples = timings.entry((kind, name.to_string())).or_default();
+        if samples.len() == SAMPLES_KEPT {
+            samples.remove(0);
+        }
+        samples.push(elapsed.as_millis());
+    }
+}
+
+/// An API path with the ids replaced, so `/api/v1/iam/users/3/groups` and
+/// `/api/v1/iam/users/4/groups` are timed as one endpoint.
+pub fn endpoint_name(method: &str, path: &str) -> String {
+    let path = path
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .split('/')
+        .map(|segment| {
+            if !segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit()) {
+                "{id}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/");
+    format!("{} {}", method, path)
+}
+
+/// The statistics of everything timed so far, slowest first.
+pub fn timing_stats() -> Vec<TimingStats> {
+    let Ok(timings) = TIMINGS.lock() else {
+        return Vec::new();
+    };
+
+    let mut stats = timings
+        .iter()
+        .map(|((kind, name), samples)| {
+            let mut sorted = samples.clone();
+            sorted.sort_unstable();
+            // The nearest-rank percentile.
+            let p95 = sorted[(sorted.len() * 95).div_ceil(100).max(1) - 1];
+            TimingStats {
+                kind: *kind,
+                name: name.clone(),
+                count: sorted.len(),
+                mean_ms: sorted.iter().sum::<u128>() as f64 / sorted.len() as f64,
+                p95_ms: p95,
+                max_ms: *sorted.last().unwrap_or(&0),
+            }
+        })
+        .collect::<Vec<_>>();
+    stats.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then(b.max_ms.cmp(&a.max_ms))
+            .then(a.name.cmp(&b.name))
+    });
+    stats
+}
+
+/// Forget the timings of the session.
+pub fn clear_timings() {
+    if let Ok(mut timings) = TIMINGS.lock() {
+        timings.clear();
+    }
+}
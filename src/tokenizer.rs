@@ -1,39 +1,91 @@
 use log::trace;
 
+use crate::clipboard::paste_from_clipboard;
+use crate::commands::CliOption;
+use crate::config::get_config;
 use crate::errors::AppError;
-use std::collections::HashMap;
+use crate::fetch::fetch_value;
+use std::collections::{HashMap, HashSet};
+use std::io::{IsTerminal, Read};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Debug)]
 pub struct CommandTokenizer {
     scopes: Vec<String>,
     command: String,
     options: HashMap<String, String>,
+    // Every value of each option, for options that may be repeated, e.g. `v` twice in
+    // `-vv`. `options` has the last value.
+    repeated: HashMap<String, Vec<String>>,
     positionals: Vec<String>,
 }
 
+// The short options of a command, and which of its options are flags.
+struct OptionKinds {
+    shorts: HashSet<String>,
+    flags: HashSet<String>,
+}
+
+impl OptionKinds {
+    fn new(options: &[CliOption]) -> Self {
+        let shorts = options
+            .iter()
+            .filter_map(CliOption::short_without_dash)
+            .collect();
+        let flags = options
+            .iter()
+            .filter(|opt| opt.flag)
+            .flat_map(|opt| [opt.short_without_dash(), opt.long_without_dashes()])
+            .flatten()
+            .collect();
+        OptionKinds { shorts, flags }
+    }
+
+    // `-jd` is `-j -d` if both are short options of the command, and all but the last are
+    // flags. A single option named `jd` is not split.
+    fn cluster(&self, key: &str) -> Option<Vec<String>> {
+        if key.chars().count() < 2 || self.shorts.contains(key) {
+            return None;
+        }
+        let keys = key.chars().map(String::from).collect::<Vec<_>>();
+        let (last, rest) = keys.split_last()?;
+        let valid = self.shorts.contains(last)
+            && rest
+                .iter()
+                .all(|k| self.shorts.contains(k) && self.flags.contains(k));
+        valid.then_some(keys)
+    }
+}
+
 impl CommandTokenizer {
-    pub fn new(input: &str, cmd_name: &str) -> Result<Self, AppError> {
+    /// Split a command line into scopes, the command, its options and positionals, using
+    /// the options of the command to know which are flags without a value.
+    pub fn new(input: &str, cmd_name: &str, options: &[CliOption]) -> Result<Self, AppError> {
+        let kinds = OptionKinds::new(options);
         let tokens = shlex::split(input).ok_or(AppError::InvalidInput)?;
         let mut tokenizer = CommandTokenizer {
             scopes: Vec::new(),
             command: String::new(),
             options: HashMap::new(),
+            repeated: HashMap::new(),
             positionals: Vec::new(),
         };
 
         trace!("Tokenizer generated: {:?}", tokens);
 
-        let mut iter = tokens.into_iter();
+        let mut iter = tokens.into_iter().peekable();
 
         // Parse scopes and command
         while let Some(token) = iter.next() {
             if token == cmd_name {
                 tokenizer.command.clone_from(&token)
-            } else if token.starts_with('-') {
+            } else if token == "--" {
+                tokenizer.positionals.extend(iter.by_ref());
+            } else if looks_like_option(&token) {
                 if tokenizer.command.is_empty() {
                     return Err(AppError::InvalidInput);
                 }
-                tokenizer.parse_options(token, &mut iter)?;
+                tokenizer.parse_options(token, &mut iter, &kinds)?;
                 break;
             } else if tokenizer.command.is_empty() {
                 tokenizer.scopes.push(token);
@@ -42,50 +94,79 @@ impl CommandTokenizer {
             }
         }
 
-        // Parse remaining options
+        // Parse remaining options. After `--`, everything is a positional, and so are
+        // negative numbers not following an option.
         while let Some(token) = iter.next() {
-            tokenizer.parse_options(token, &mut iter)?;
+            if token == "--" {
+                tokenizer.positionals.extend(iter.by_ref());
+            } else if looks_like_option(&token) {
+                tokenizer.parse_options(token, &mut iter, &kinds)?;
+            } else {
+                tokenizer.positionals.push(token);
+            }
         }
 
         Ok(tokenizer)
     }
 
+    // An option takes the next token as its value, unless it is a flag, or that token is
+    // another option or `--`, which leaves flags like `--plan --yes` without a value.
     fn parse_options(
         &mut self,
         key: String,
-        iter: &mut std::vec::IntoIter<String>,
+        iter: &mut std::iter::Peekable<std::vec::IntoIter<String>>,
+        kinds: &OptionKinds,
     ) -> Result<(), AppError> {
-        if let Some(stripped) = key.strip_prefix("--") {
-            let value = iter.next().unwrap_or("".to_string());
-            self.options.insert(
-                stripped.to_string(),
-                self.convert_file_and_http_values(&value)?,
-            );
-        } else if let Some(stripped) = key.strip_prefix('-') {
-            let value = iter.next().unwrap_or("".to_string());
-            self.options.insert(
-                stripped.to_string(),
-                self.convert_file_and_http_values(&value)?,
-            );
-        } else {
-            return Err(AppError::InvalidInput);
+        let (stripped, keys) = match key.strip_prefix("--") {
+            Some(stripped) => (stripped, None),
+            None => {
+                let stripped = key.strip_prefix('-').ok_or(AppError::InvalidInput)?;
+                (stripped, kinds.cluster(stripped))
+            }
+        };
+        let keys = keys.unwrap_or_else(|| vec![stripped.to_string()]);
+
+        for key in keys {
+            let value = if kinds.flags.contains(&key) {
+                String::new()
+            } else {
+                iter.next_if(|next| next != "--" && !looks_like_option(next))
+                    .unwrap_or_default()
+            };
+            let value = self.convert_file_and_http_values(&value)?;
+            self.repeated
+                .entry(key.clone())
+                .or_default()
+                .push(value.clone());
+            self.options.insert(key, value);
         }
         Ok(())
     }
 
     pub fn convert_file_and_http_values(&self, value: &String) -> Result<String, AppError> {
+        // A backslash in front of a value that would be read from somewhere else, e.g. `\-`,
+        // gives the value as it is.
+        if let Some(literal) = value.strip_prefix('\\') {
+            if is_expanded(literal) {
+                return Ok(literal.to_string());
+            }
+        }
+
         let val = if value.starts_with("http://") || value.starts_with("https://") {
-            reqwest::blocking::get(value)
-                .map_err(|e| AppError::HttpError(e.to_string()))?
-                .text()
-                .map_err(|e| AppError::HttpError(e.to_string()))?
-                .trim_end()
-                .to_string()
+            fetch_value(value)?.trim_end().to_string()
+        } else if value == "-" || value == "stdin://" {
+            read_stdin()?.trim_end().to_string()
+        } else if value == "paste://" {
+            paste_from_clipboard()?.trim_end().to_string()
         } else if let Some(stripped) = value.strip_prefix("file://") {
             std::fs::read_to_string(stripped)
                 .map_err(AppError::IoError)?
                 .trim_end()
                 .to_string()
+        } else if let Some(name) = value.strip_prefix("env://") {
+            env_var(name)?
+        } else if get_config()?.fetch.expand_env {
+            expand_env_vars(value)?
         } else {
             value.clone()
         };
@@ -106,11 +187,341 @@ impl CommandTokenizer {
         }
     }
 
+    /// Set an option, unless it was given by either its short or long name.
+    pub fn set_default_option(&mut self, short: Option<&str>, long: &str, value: &str) {
+        if self.options.contains_key(long) || short.is_some_and(|s| self.options.contains_key(s)) {
+            return;
+        }
+        self.options.insert(long.to_string(), value.to_string());
+    }
+
+    /// Set an option by its long name, e.g. from an answer in the wizard.
+    pub fn set_option(&mut self, long: &str, value: &str) {
+        self.options.insert(long.to_string(), value.to_string());
+    }
+
     pub fn get_options(&self) -> &HashMap<String, String> {
         &self.options
     }
 
+    /// How many times an option was given on the command line, by this name.
+    pub fn count(&self, key: &str) -> usize {
+        self.get_all_values(key).len()
+    }
+
+    /// Every value an option was given on the command line, by this name, in order.
+    pub fn get_all_values(&self, key: &str) -> &[String] {
+        self.repeated.get(key).map_or(&[], Vec::as_slice)
+    }
+
     pub fn get_positionals(&self) -> &[String] {
         &self.positionals
     }
 }
+
+// Tokens starting with a dash are options, except for `-` (stdin), `--` and negative
+// numbers like `-5` or `-0.5`.
+fn looks_like_option(token: &str) -> bool {
+    token.starts_with('-') && token != "-" && token != "--" && !is_negative_number(token)
+}
+
+fn is_negative_number(token: &str) -> bool {
+    token.strip_prefix('-').is_some_and(|number| {
+        number.parse::<f64>().is_ok()
+            && number.starts_with(|c: char| c.is_ascii_digit() || c == '.')
+    })
+}
+
+fn env_var(name: &str) -> Result<String, AppError> {
+    std::env::var(name)
+        .map_err(|_| AppError::ParseError(format!("Environment variable '{}' is not set", name)))
+}
+
+// Replace `${NAME}` with the value of the environment variable NAME, where `$${` is a
+// literal `${`. A bare `$` is left alone, as it starts JSONPath expressions and JSON schema
+// keywords. Only done with `fetch.expand_env` set, so unset variables are an error.
+fn expand_env_vars(value: &str) -> Result<String, AppError> {
+    let mut expanded = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            expanded.push_str(&rest[..start]);
+            expanded.push('{');
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&env_var(&rest[start + 2..start + end])?);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+// Whether an option value is read from somewhere else rather than used as it is.
+fn is_expanded(value: &str) -> bool {
+    value == "-"
+        || [
+            "http://", "https://", "stdin://", "paste://", "file://", "env://",
+        ]
+        .iter()
+        .any(|prefix| value.starts_with(prefix))
+}
+
+// Stdin can only be read to the end once, so a second option reading it would silently
+// get nothing.
+static STDIN_READ: AtomicBool = AtomicBool::new(false);
+
+// The value of an option given as `-` or `stdin://`, which is what was piped to the CLI.
+fn read_stdin() -> Result<String, AppError> {
+    let mut stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        return Err(AppError::ParseError(
+            "Nothing is piped to stdin to read an option value from".to_string(),
+        ));
+    }
+    if STDIN_READ.swap(true, Ordering::SeqCst) {
+        return Err(AppError::ParseError(
+            "Stdin has already been read by another option".to_string(),
+        ));
+    }
+    let mut value = String::new();
+    stdin.read_to_string(&mut value)?;
+    Ok(value)
+}
+
+/// Quote a single argument so that it is read back as one token by the tokenizer.
+///
+/// Note that quoting does not stop the tokenizer from expanding `-`, `file://`, `stdin://`,
+/// `paste://`, `env://` and `http(s)://` option values, or `${NAME}` in them with
+/// `fetch.expand_env` set. Such values are given as they are with a backslash in front,
+/// e.g. `\-`.
+pub fn quote_arg(arg: &str) -> String {
+    // shlex refuses to quote nul bytes, and they can never be typed at the prompt anyway.
+    let arg = arg.replace('\0', "");
+    shlex::try_quote(&arg)
+        .map(|quoted| quoted.into_owned())
+        .unwrap_or(arg)
+}
+
+/// Join arguments into a command line that can be copy-pasted or piped back into the CLI.
+pub fn join_command<I, S>(args: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    args.into_iter()
+        .map(|arg| quote_arg(arg.as_ref()))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(input: &str, command: &str) -> CommandTokenizer {
+        CommandTokenizer::new(input, command, &[]).expect("Failed to tokenize")
+    }
+
+    fn option(short: &str, long: &str, flag: bool) -> CliOption {
+        CliOption {
+            name: long.to_string(),
+            short: Some(format!("-{}", short)),
+            long: Some(format!("--{}", long)),
+            flag,
+            help: String::new(),
+            field_type: std::any::TypeId::of::<String>(),
+            field_type_help: "string".to_string(),
+            required: false,
+            autocomplete: None,
+            completes: None,
+            values: Vec::new(),
+            path: false,
+            secret: false,
+            context: false,
+            conflicts_with: Vec::new(),
+            requires: Vec::new(),
+            default: None,
+        }
+    }
+
+    fn info_options() -> Vec<CliOption> {
+        vec![
+            option("j", "json", true),
+            option("d", "data", true),
+            option("n", "name", false),
+            option("c", "class", false),
+        ]
+    }
+
+    #[test]
+    fn test_options_and_positionals() {
+        let tokens = tokenize("object info Host1 -c Host --name web", "info");
+        assert_eq!(tokens.get_scopes(), ["object"]);
+        assert_eq!(tokens.get_command().unwrap(), "info");
+        assert_eq!(tokens.get_positionals(), ["Host1"]);
+        assert_eq!(tokens.get_options()["c"], "Host");
+        assert_eq!(tokens.get_options()["name"], "web");
+    }
+
+    #[test]
+    fn test_negative_number_values() {
+        let tokens = tokenize("object list --limit -5 -o -0.5 --sort name", "list");
+        assert_eq!(tokens.get_options()["limit"], "-5");
+        assert_eq!(tokens.get_options()["o"], "-0.5");
+        assert_eq!(tokens.get_options()["sort"], "name");
+    }
+
+    #[test]
+    fn test_negative_number_positionals() {
+        let tokens = tokenize("history undo -3", "undo");
+        assert_eq!(tokens.get_positionals(), ["-3"]);
+        assert!(tokens.get_options().is_empty());
+
+        let tokens = tokenize("history undo -c Host -3", "undo");
+        assert_eq!(tokens.get_options()["c"], "-3");
+    }
+
+    #[test]
+    fn test_flags_without_values() {
+        let tokens = tokenize("relation import -f edges.csv --plan --yes", "import");
+        assert_eq!(tokens.get_options()["f"], "edges.csv");
+        assert_eq!(tokens.get_options()["plan"], "");
+        assert_eq!(tokens.get_options()["yes"], "");
+    }
+
+    #[test]
+    fn test_escaped_values() {
+        let tokens = tokenize(r"object info -c '\-' -n '\file://host'", "info");
+        assert_eq!(tokens.get_options()["c"], "-");
+        assert_eq!(tokens.get_options()["n"], "file://host");
+
+        let tokens = tokenize(r"object info -n '\web'", "info");
+        assert_eq!(tokens.get_options()["n"], r"\web");
+    }
+
+    #[test]
+    fn test_end_of_options() {
+        let tokens = tokenize("object info -c Host -- -n --weird", "info");
+        assert_eq!(tokens.get_options()["c"], "Host");
+        assert_eq!(tokens.get_positionals(), ["-n", "--weird"]);
+
+        let tokens = tokenize("object info -- -5 -x", "info");
+        assert!(tokens.get_options().is_empty());
+        assert_eq!(tokens.get_positionals(), ["-5", "-x"]);
+    }
+
+    #[test]
+    fn test_flag_before_end_of_options() {
+        let tokens = tokenize("object delete --force -- -dashed", "delete");
+        assert_eq!(tokens.get_options()["force"], "");
+        assert_eq!(tokens.get_positionals(), ["-dashed"]);
+    }
+
+    #[test]
+    fn test_is_negative_number() {
+        assert!(is_negative_number("-5"));
+        assert!(is_negative_number("-0.5"));
+        assert!(is_negative_number("-.5"));
+        assert!(!is_negative_number("-n"));
+        assert!(!is_negative_number("-inf"));
+        assert!(!is_negative_number("--5"));
+        assert!(!is_negative_number("5"));
+    }
+
+    #[test]
+    fn test_options_before_command_are_invalid() {
+        assert!(CommandTokenizer::new("object -c Host info", "info", &[]).is_err());
+    }
+
+    #[test]
+    fn test_flags_do_not_take_values() {
+        let options = info_options();
+        let tokens = CommandTokenizer::new("object info -j Host1 --data", "info", &options)
+            .expect("Failed to tokenize");
+        assert_eq!(tokens.get_options()["j"], "");
+        assert_eq!(tokens.get_options()["data"], "");
+        assert_eq!(tokens.get_positionals(), ["Host1"]);
+    }
+
+    #[test]
+    fn test_clustered_short_flags() {
+        let options = info_options();
+        let tokens = CommandTokenizer::new("object info -jd -c Host", "info", &options)
+            .expect("Failed to tokenize");
+        assert_eq!(tokens.get_options()["j"], "");
+        assert_eq!(tokens.get_options()["d"], "");
+        assert_eq!(tokens.get_options()["c"], "Host");
+    }
+
+    #[test]
+    fn test_cluster_ending_in_option_with_value() {
+        let options = info_options();
+        let tokens = CommandTokenizer::new("object info -jn Host1", "info", &options)
+            .expect("Failed to tokenize");
+        assert_eq!(tokens.get_options()["j"], "");
+        assert_eq!(tokens.get_options()["n"], "Host1");
+    }
+
+    #[test]
+    fn test_invalid_clusters_are_kept_whole() {
+        let options = info_options();
+        // -n takes a value, so it can not come before another option in a cluster.
+        let tokens = CommandTokenizer::new("object info -nj x", "info", &options)
+            .expect("Failed to tokenize");
+        assert_eq!(tokens.get_options()["nj"], "x");
+
+        let tokens =
+            CommandTokenizer::new("object info -jx", "info", &options).expect("Failed to tokenize");
+        assert_eq!(tokens.get_options()["jx"], "");
+    }
+
+    #[test]
+    fn test_repeated_flags_are_counted() {
+        let mut options = info_options();
+        options.push(option("v", "verbose", true));
+        let tokens = CommandTokenizer::new("object info -vv -c Host", "info", &options)
+            .expect("Failed to tokenize");
+        assert_eq!(tokens.count("v"), 2);
+        assert_eq!(tokens.count("verbose"), 0);
+        assert_eq!(tokens.get_options()["c"], "Host");
+
+        let tokens = CommandTokenizer::new("object info -jv --verbose", "info", &options)
+            .expect("Failed to tokenize");
+        assert_eq!(tokens.count("v") + tokens.count("verbose"), 2);
+    }
+
+    #[test]
+    fn test_repeated_option_values() {
+        let tokens = tokenize(
+            "object info -c Host --name web01 --name web02 -n web03",
+            "info",
+        );
+        assert_eq!(tokens.get_all_values("name"), ["web01", "web02"]);
+        assert_eq!(tokens.get_all_values("n"), ["web03"]);
+        assert_eq!(tokens.get_options()["name"], "web02");
+        assert!(tokens.get_all_values("data").is_empty());
+    }
+
+    #[test]
+    fn test_env_vars_are_left_alone_by_default() {
+        let tokens = tokenize("object list --description ${HUBUUM_CLI_TEST_UNSET}", "list");
+        assert_eq!(
+            tokens.get_options()["description"],
+            "${HUBUUM_CLI_TEST_UNSET}"
+        );
+    }
+
+    #[test]
+    fn test_expand_env_vars() {
+        let path = std::env::var("PATH").expect("PATH is set");
+        assert_eq!(expand_env_vars("a${PATH}b").unwrap(), format!("a{}b", path));
+        assert_eq!(expand_env_vars("$${PATH}").unwrap(), "${PATH}");
+        assert_eq!(expand_env_vars("$.data[0]").unwrap(), "$.data[0]");
+        assert!(expand_env_vars("${HUBUUM_CLI_TEST_UNSET}").is_err());
+    }
+}
@@ -0,0 +1,99 @@
+use std::cmp::Ordering;
+
+use hubuum_client::{Authenticated, SyncClient};
+use log::debug;
+
+use crate::api::server_version;
+use crate::errors::AppError;
+use crate::output::{add_warning, flush_output};
+
+pub const CLI_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const CLIENT_VERSION: &str = env!("HUBUUM_CLIENT_VERSION");
+
+// The server versions a CLI release works with. `cli` is matched against the leading parts
+// of the CLI version, so "0.1" matches 0.1.5 but not 0.10.0, and the server version must be at least `min_server` and below `below_server`.
+struct Compatibility {
+    cli: &'static str,
+    min_server: &'static str,
+    below_server: Option<&'static str>,
+}
+
+const COMPATIBILITY: &[Compatibility] = &[Compatibility {
+    cli: "0.1",
+    min_server: "0.0.1",
+    below_server: None,
+}];
+
+// The numeric parts of a dotted version, ignoring any pre-release or build suffix.
+fn version_parts(version: &str) -> Vec<u64> {
+    version
+        .trim_start_matches('v')
+        .split(['-', '+'])
+        .next()
+        .unwrap_or_default()
+        .split('.')
+        .map(|part| part.parse::<u64>().unwrap_or(0))
+        .collect()
+}
+
+// Whether `version` starts with the parts of `prefix`, e.g. 0.1.5 with 0.1.
+fn version_matches(version: &str, prefix: &str) -> bool {
+    version_parts(version).starts_with(&version_parts(prefix))
+}
+
+// Compare dotted versions numerically, ignoring any pre-release or build suffix.
+fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_parts(a), version_parts(b));
+    (0..a.len().max(b.len()))
+        .map(|i| {
+            a.get(i)
+                .copied()
+                .unwrap_or(0)
+                .cmp(&b.get(i).copied().unwrap_or(0))
+        })
+        .find(|o| *o != Ordering::Equal)
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Why this CLI does not work with the given server version, if it is a known-incompatible
+/// combination.
+pub fn incompatibility(server_version: &str) -> Option<String> {
+    let entry = COMPATIBILITY
+        .iter()
+        .find(|entry| version_matches(CLI_VERSION, entry.cli))?;
+
+    let too_old = compare_versions(server_version, entry.min_server) == Ordering::Less;
+    let too_new = entry
+        .below_server
+        .is_some_and(|below| compare_versions(server_version, below) != Ordering::Less);
+    if !too_old && !too_new {
+        return None;
+    }
+
+    let supported = match entry.below_server {
+        Some(below) => format!(">= {}, < {}", entry.min_server, below),
+        None => format!(">= {}", entry.min_server),
+    };
+    Some(format!(
+        "hubuum-cli {} does not support server version {} (supported: {})",
+        CLI_VERSION, server_version, supported
+    ))
+}
+
+/// Warn if the server is known not to work with this CLI. Called once at startup, and
+/// servers that don't report a version are not warned about.
+pub fn warn_if_incompatible(client: &SyncClient<Authenticated>) -> Result<(), AppError> {
+    let version = match server_version(client) {
+        Ok(version) => version,
+        Err(err) => {
+            debug!("Could not get the server version: {}", err);
+            return Ok(());
+        }
+    };
+
+    if let Some(problem) = version.version.as_deref().and_then(incompatibility) {
+        add_warning(problem)?;
+        flush_output()?;
+    }
+    Ok(())
+}
@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::NaiveDateTime;
+use hubuum_client::{Authenticated, Class, Object, SyncClient};
+use log::{debug, warn};
+use once_cell::sync::Lazy;
+
+use crate::errors::AppError;
+use crate::i18n::tr_args;
+use crate::session;
+
+/// A class being watched for changes, with the last seen update time of each object.
+#[derive(Debug, Clone)]
+struct Watch {
+    class: Class,
+    seen: HashMap<i32, (String, NaiveDateTime)>,
+}
+
+impl Watch {
+    fn new(class: Class, objects: &[Object]) -> Self {
+        Watch {
+            class,
+            seen: seen_objects(objects),
+        }
+    }
+}
+
+fn seen_objects(objects: &[Object]) -> HashMap<i32, (String, NaiveDateTime)> {
+    objects
+        .iter()
+        .map(|o| (o.id, (o.name.clone(), o.updated_at)))
+        .collect()
+}
+
+static WATCHES: Lazy<Mutex<HashMap<i32, Watch>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NOTICES: Lazy<Mutex<Vec<String>>> = Lazy::new(|| Mutex::new(Vec::new()));
+static POLL_INTERVAL: AtomicU64 = AtomicU64::new(30);
+static POLLER_STARTED: AtomicBool = AtomicBool::new(false);
+
+/// Start watching a class for new, changed and deleted objects.
+///
+/// The first watch starts a background thread that polls the server every
+/// `interval_seconds` (30 unless set). Changes are queued as notices, and printed
+/// between prompts.
+pub fn watch_class(
+    client: &SyncClient<Authenticated>,
+    class: Class,
+    interval_seconds: Option<u64>,
+) -> Result<(), AppError> {
+    let objects = client.objects(class.id).find().execute()?;
+    WATCHES
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .insert(class.id, Watch::new(class, &objects));
+
+    if let Some(interval) = interval_seconds {
+        POLL_INTERVAL.store(interval.max(1), Ordering::Relaxed);
+    }
+    if !POLLER_STARTED.swap(true, Ordering::SeqCst) {
+        std::thread::spawn(poll_loop);
+    }
+
+    Ok(())
+}
+
+/// Stop watching a class. Returns false if the class was not watched.
+pub fn unwatch_class(name: &str) -> Result<bool, AppError> {
+    let mut watches = WATCHES.lock().map_err(|_| AppError::LockError)?;
+    let before = watches.len();
+    watches.retain(|_, watch| watch.class.name != name);
+    Ok(watches.len() != before)
+}
+
+/// The names of the watched classes, sorted.
+pub fn watched_classes() -> Result<Vec<String>, AppError> {
+    let watches = WATCHES.lock().map_err(|_| AppError::LockError)?;
+    let mut names = watches
+        .values()
+        .map(|w| w.class.name.clone())
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
+
+/// The polling interval in seconds.
+pub fn poll_interval() -> u64 {
+    POLL_INTERVAL.load(Ordering::Relaxed)
+}
+
+/// Take the notices queued since the last call.
+pub fn take_notices() -> Result<Vec<String>, AppError> {
+    let mut notices = NOTICES.lock().map_err(|_| AppError::LockError)?;
+    Ok(std::mem::take(&mut *notices))
+}
+
+/// Take in the current objects of the watched classes without queueing notices, after a
+/// command of our own changed objects, so users are not told about their own changes. A
+/// change someone else made since the last poll goes unreported as well.
+pub fn absorb_own_changes() -> Result<(), AppError> {
+    if WATCHES.lock().map_err(|_| AppError::LockError)?.is_empty() {
+        return Ok(());
+    }
+    match session::client() {
+        Some(client) => poll(&client, false),
+        None => Ok(()),
+    }
+}
+
+fn poll_loop() {
+    loop {
+        std::thread::sleep(Duration::from_secs(poll_interval()));
+
+        // Look the client up on each poll, so a new login is used and a logout pauses us.
+        let Some(client) = session::client() else {
+            continue;
+        };
+        if poll(&client, true).is_err() {
+            return;
+        }
+    }
+}
+
+fn poll(client: &SyncClient<Authenticated>, report: bool) -> Result<(), AppError> {
+    // Clone the watches so we don't hold the lock while talking to the server.
+    let watches = WATCHES
+        .lock()
+        .map_err(|_| AppError::LockError)?
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+
+    for watch in watches {
+        match client.objects(watch.class.id).find().execute() {
+            Ok(objects) => record_changes(&watch, &objects, report),
+            Err(err) => warn!("Failed to poll class {}: {}", watch.class.name, err),
+        }
+    }
+    Ok(())
+}
+
+fn record_changes(watch: &Watch, objects: &[Object], report: bool) {
+    let current = seen_objects(objects);
+
+    let mut created = Vec::new();
+    let mut changed = Vec::new();
+    for (id, (name, updated_at)) in &current {
+        match watch.seen.get(id) {
+            None => created.push(name.clone()),
+            Some((_, seen_at)) if seen_at != updated_at => changed.push(name.clone()),
+            Some(_) => {}
+        }
+    }
+    let mut deleted = watch
+        .seen
+        .iter()
+        .filter(|(id, _)| !current.contains_key(id))
+        .map(|(_, (name, _))| name.clone())
+        .collect::<Vec<_>>();
+
+    // The class may have been unwatched while we were polling.
+    match WATCHES.lock() {
+        Ok(mut watches) => match watches.get_mut(&watch.class.id) {
+            Some(stored) => stored.seen = current,
+            None => return,
+        },
+        Err(_) => return,
+    }
+    if !report {
+        return;
+    }
+
+    let mut notices = Vec::new();
+    for (message, names) in [
+        ("{class}: new objects: {names}", &mut created),
+        ("{class}: changed objects: {names}", &mut changed),
+        ("{class}: deleted objects: {names}", &mut deleted),
+    ] {
+        if names.is_empty() {
+            continue;
+        }
+        names.sort();
+        notices.push(tr_args(
+            message,
+            &[("class", &watch.class.name), ("names", &names.join(", "))],
+        ));
+    }
+
+    if notices.is_empty() {
+        return;
+    }
+    debug!("Changes detected in class {}", watch.class.name);
+    if let Ok(mut queue) = NOTICES.lock() {
+        queue.extend(notices);
+    }
+}
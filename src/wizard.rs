@@ -0,0 +1,250 @@
+use std::io::IsTerminal;
+
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use serde_json::Value;
+
+use crate::commandlist::CommandList;
+use crate::commands::{CliCommand, CliOption, GLOBAL_OPTIONS};
+use crate::errors::AppError;
+use crate::i18n::{tr, tr_args};
+use crate::output::{append_line, flush_output};
+use crate::progress::stop_spinner;
+use crate::schema::schema_properties;
+use crate::session;
+use crate::tokenizer::{join_command, CommandTokenizer};
+
+/// Whether the command was asked to run with `--interactive`.
+pub fn wants_wizard(cmd: &dyn CliCommand, tokens: &CommandTokenizer) -> bool {
+    cmd.options()
+        .iter()
+        .find(|opt| opt.name == "interactive")
+        .is_some_and(|opt| given(opt, tokens))
+}
+
+/// Ask for each option of the command that was not given, with the completions the option
+/// has at the prompt, and add the answers to the tokens. The resulting command line is
+/// shown so that it can be scripted later.
+///
+/// Required options are asked for until they get a value, optional ones are skipped with
+/// an empty answer.
+pub fn run_wizard(
+    cli: &CommandList,
+    cmd: &dyn CliCommand,
+    command: &[String],
+    tokens: &mut CommandTokenizer,
+) -> Result<(), AppError> {
+    if !std::io::stdin().is_terminal() {
+        return Err(AppError::ParseError(tr("--interactive needs a terminal")));
+    }
+    stop_spinner();
+
+    let options = cmd
+        .options()
+        .into_iter()
+        // The options every command has are not worth asking for.
+        .filter(|opt| !GLOBAL_OPTIONS.contains(&opt.name.as_str()) && opt.name != "interactive")
+        .collect::<Vec<_>>();
+
+    // The command line so far, which the completions of later options may depend on.
+    let mut line = command.to_vec();
+    for opt in options.iter().filter(|opt| given(opt, tokens)) {
+        let (key, value) = given_value(opt, tokens);
+        line.push(format!("--{}", key));
+        if !opt.flag {
+            line.push(value);
+        }
+    }
+
+    let mut editor = Editor::<WizardHelper, DefaultHistory>::new()?;
+    for opt in options.iter().filter(|opt| !given(opt, tokens)) {
+        let Some(long) = opt.long_without_dashes() else {
+            continue;
+        };
+
+        if opt.name == "data" {
+            show_schema_keys(&line)?;
+        }
+
+        let prompt = match (opt.required, opt.flag) {
+            (_, true) => format!("{} [y/N]: ", opt.help),
+            (true, false) => format!("{}: ", opt.help),
+            (false, false) => format!("{} ({}): ", opt.help, tr("optional")),
+        };
+        editor.set_helper(Some(WizardHelper {
+            cli,
+            option: completer_of(opt),
+            line: line.clone(),
+        }));
+
+        let answer = loop {
+            let answer = match editor.readline(&prompt) {
+                Ok(answer) => answer.trim().to_string(),
+                Err(ReadlineError::Interrupted | ReadlineError::Eof) => {
+                    append_line(tr("Cancelled"))?;
+                    return Err(AppError::Quiet);
+                }
+                Err(err) => return Err(err.into()),
+            };
+            if !answer.is_empty() || !opt.required || opt.flag {
+                break answer;
+            }
+        };
+
+        if opt.flag {
+            if matches!(answer.to_lowercase().as_str(), "y" | "yes") {
+                tokens.set_option(&long, "");
+                line.push(format!("--{}", long));
+            }
+        } else if !answer.is_empty() {
+            let value = tokens.convert_file_and_http_values(&answer)?;
+            tokens.set_option(&long, &value);
+            line.push(format!("--{}", long));
+            line.push(answer);
+        }
+    }
+
+    append_line(tr_args(
+        "Command: {command}",
+        &[("command", &join_command(&line))],
+    ))?;
+    Ok(())
+}
+
+fn given(opt: &CliOption, tokens: &CommandTokenizer) -> bool {
+    let options = tokens.get_options();
+    opt.short_without_dash()
+        .is_some_and(|short| options.contains_key(&short))
+        || opt
+            .long_without_dashes()
+            .is_some_and(|long| options.contains_key(&long))
+}
+
+// The name the option was given by, and its value.
+fn given_value(opt: &CliOption, tokens: &CommandTokenizer) -> (String, String) {
+    let options = tokens.get_options();
+    [opt.long_without_dashes(), opt.short_without_dash()]
+        .into_iter()
+        .flatten()
+        .find_map(|key| options.get(&key).map(|value| (key.clone(), value.clone())))
+        .unwrap_or_default()
+}
+
+// Before asking for the data of an object, show the keys the schema of its class has.
+fn show_schema_keys(line: &[String]) -> Result<(), AppError> {
+    let Some(client) = session::client() else {
+        return Ok(());
+    };
+    let Some(class) = line
+        .iter()
+        .position(|word| word == "--class" || word == "-c")
+        .and_then(|i| line.get(i + 1))
+    else {
+        return Ok(());
+    };
+    let Ok(class) = client
+        .classes()
+        .find()
+        .add_filter_name_exact(class)
+        .execute_expecting_single_result()
+    else {
+        return Ok(());
+    };
+
+    let keys = class
+        .json_schema
+        .as_ref()
+        .map(schema_keys)
+        .unwrap_or_default();
+    if keys.is_empty() {
+        return Ok(());
+    }
+    append_line(tr_args(
+        "Keys in the schema of {class}: {keys}",
+        &[("class", &class.name), ("keys", &keys.join(", "))],
+    ))?;
+    flush_output()
+}
+
+// The top level properties of a JSON schema, with the required ones marked.
+fn schema_keys(schema: &Value) -> Vec<String> {
+    schema_properties(schema)
+        .into_iter()
+        .map(|property| {
+            if property.required {
+                format!("{} ({})", property.name, tr("required"))
+            } else {
+                property.name
+            }
+        })
+        .collect()
+}
+
+// How the answer for an option is completed.
+enum OptionCompleter {
+    None,
+    Values(Vec<String>),
+    Function(fn(&CommandList, &str, &[String]) -> Vec<String>),
+}
+
+fn completer_of(opt: &CliOption) -> OptionCompleter {
+    if let Some(function) = opt.autocomplete {
+        OptionCompleter::Function(function)
+    } else if opt.flag {
+        OptionCompleter::Values(vec!["yes".to_string(), "no".to_string()])
+    } else if !opt.values.is_empty() {
+        OptionCompleter::Values(opt.values.clone())
+    } else {
+        OptionCompleter::None
+    }
+}
+
+struct WizardHelper<'a> {
+    cli: &'a CommandList,
+    option: OptionCompleter,
+    line: Vec<String>,
+}
+
+impl Completer for WizardHelper<'_> {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let prefix = &line[..pos];
+        let candidates = match &self.option {
+            OptionCompleter::None => Vec::new(),
+            OptionCompleter::Values(values) => values
+                .iter()
+                .filter(|value| value.starts_with(prefix))
+                .cloned()
+                .collect(),
+            OptionCompleter::Function(function) => function(self.cli, prefix, &self.line),
+        };
+        Ok((
+            0,
+            candidates
+                .into_iter()
+                .map(|candidate| Pair {
+                    display: candidate.clone(),
+                    replacement: candidate,
+                })
+                .collect(),
+        ))
+    }
+}
+
+impl Hinter for WizardHelper<'_> {
+    type Hint = String;
+}
+impl Highlighter for WizardHelper<'_> {}
+impl Validator for WizardHelper<'_> {}
+impl Helper for WizardHelper<'_> {}